@@ -1,4 +1,4 @@
-use xcb::atoms_struct;
+use xcb::{atoms_struct, x};
 
 atoms_struct! {
     #[derive(Copy, Clone, Debug)]
@@ -26,12 +26,39 @@ atoms_struct! {
         // ===== EWMH per-window properties =====
         pub wm_window_type => b"_NET_WM_WINDOW_TYPE" only_if_exists = false,
         pub wm_window_type_dock => b"_NET_WM_WINDOW_TYPE_DOCK" only_if_exists = false,
+        pub wm_window_type_dialog => b"_NET_WM_WINDOW_TYPE_DIALOG" only_if_exists = false,
+        pub wm_window_type_utility => b"_NET_WM_WINDOW_TYPE_UTILITY" only_if_exists = false,
+        pub wm_window_type_splash => b"_NET_WM_WINDOW_TYPE_SPLASH" only_if_exists = false,
+        pub wm_window_type_toolbar => b"_NET_WM_WINDOW_TYPE_TOOLBAR" only_if_exists = false,
+        pub wm_window_type_menu => b"_NET_WM_WINDOW_TYPE_MENU" only_if_exists = false,
         pub wm_strut_partial => b"_NET_WM_STRUT_PARTIAL" only_if_exists = false,
         pub wm_state => b"_NET_WM_STATE" only_if_exists = false,
         pub wm_state_fullscreen => b"_NET_WM_STATE_FULLSCREEN" only_if_exists = false,
+        pub wm_state_modal => b"_NET_WM_STATE_MODAL" only_if_exists = false,
+        pub wm_state_demands_attention => b"_NET_WM_STATE_DEMANDS_ATTENTION" only_if_exists = false,
+        pub wm_state_hidden => b"_NET_WM_STATE_HIDDEN" only_if_exists = false,
         pub close_window => b"_NET_CLOSE_WINDOW" only_if_exists = false,
+        pub moveresize_window => b"_NET_MOVERESIZE_WINDOW" only_if_exists = false,
         pub wm_protocols => b"WM_PROTOCOLS" only_if_exists = false,
         pub wm_delete_window => b"WM_DELETE_WINDOW" only_if_exists = false,
         pub wm_desktop => b"_NET_WM_DESKTOP" only_if_exists = false,
+        pub wm_user_time => b"_NET_WM_USER_TIME" only_if_exists = false,
+        pub frame_extents => b"_NET_FRAME_EXTENTS" only_if_exists = false,
+
+        // ===== FerrisWM client messages =====
+        pub pull_window => b"_FERRISWM_PULL_WINDOW" only_if_exists = false,
+    }
+}
+
+impl Atoms {
+    /// Window types that are managed but floated instead of tiled.
+    pub const fn floating_window_types(&self) -> [x::Atom; 5] {
+        [
+            self.wm_window_type_dialog,
+            self.wm_window_type_utility,
+            self.wm_window_type_splash,
+            self.wm_window_type_toolbar,
+            self.wm_window_type_menu,
+        ]
     }
 }
@@ -1,22 +1,135 @@
-use crate::key_mapping::{ActionEvent, ActionMapping};
-use crate::layout::LayoutType;
-use std::option_env;
+use crate::key_mapping::{ActionEvent, ActionMapping, NamedActionMapping};
+use crate::layout::{LayoutType, Rect};
+use crate::options::{AttachMode, EmptyWorkspace, FocusOnSwitch, TileDirection};
+use crate::x11::XErrorKind;
+use std::{option_env, time::Duration};
 use xcb::x::ModMask;
 use xkbcommon::xkb;
 
 pub const NUM_WORKSPACES: usize = 10;
 pub const DEFAULT_BORDER_WIDTH: u32 = 1;
 pub const DEFAULT_WINDOW_GAP: u32 = 0;
+/// Margin between tiled windows and the screen edge. `None` uses the window gap;
+/// `Some(0)` keeps the gap between windows but tiles flush to the edges.
+pub const OUTER_GAP: Option<u32> = None;
+/// Pixels added/removed per window gap key press.
+pub const GAP_STEP: u32 = 5;
+/// Weight a new window starts with; `WEIGHT_STEP` nudges relative to it, so the
+/// default of 10 makes each key press roughly a 10% change.
+pub const DEFAULT_WEIGHT: u32 = 10;
+/// Weight added/removed per window weight key press.
+pub const WEIGHT_STEP: u32 = 1;
+/// Percent of the focused window's and its neighbor's combined weight moved
+/// between them per grow/shrink key press.
+pub const RESIZE_STEP_PERCENT: u32 = 5;
 pub const DEFAULT_DOCK_HEIGHT: u32 = 30;
 pub const DEFAULT_LAYOUT: LayoutType = LayoutType::HorizontalLayout;
+/// Per-workspace starting layouts as (workspace index, layout); other workspaces use `DEFAULT_LAYOUT`.
+pub const WORKSPACE_LAYOUTS: &[(usize, LayoutType)] = &[];
+/// Per-workspace window gaps as (workspace index, gap); other workspaces use the
+/// global gap.
+pub const WORKSPACE_GAPS: &[(usize, u32)] = &[];
+/// Per-workspace border widths as (workspace index, width); other workspaces use
+/// the global width.
+pub const WORKSPACE_BORDERS: &[(usize, u32)] = &[];
+/// Fixed `_NET_DESKTOP_NAMES` entries as (workspace index, name); these are never
+/// replaced by automatic names.
+pub const WORKSPACE_NAMES: &[(usize, &str)] = &[];
+/// Name other workspaces after the lowercased WM_CLASS of their focused (or
+/// first) window instead of their number.
+pub const AUTO_NAME_WORKSPACES: bool = false;
+pub const DEFAULT_NMASTER: usize = 1;
+/// Order in which `HorizontalLayout` places windows across the screen.
+pub const HORIZONTAL_DIRECTION: TileDirection = TileDirection::LeftToRight;
+/// Ignore EWMH client messages from clients that don't send a source indication.
+pub const EWMH_STRICT_SOURCE_INDICATION: bool = false;
+/// EWMH hints (atom names, e.g. "_NET_WORKAREA") never published, for apps that
+/// misbehave when they see them. They are also left out of `_NET_SUPPORTED`.
+pub const SUPPRESSED_HINTS: &[&str] = &[];
+/// X errors that are expected races and only logged at debug level, optionally
+/// restricted to the request that raised them.
+pub const BENIGN_X_ERRORS: &[(XErrorKind, Option<&str>)] = &[
+    (XErrorKind::Window, None),
+    (XErrorKind::Drawable, None),
+    (XErrorKind::Match, Some("x::ConfigureWindow")),
+];
+/// Commands spawned at startup, each split on whitespace into a program and its
+/// arguments. When empty, `~/.config/ferriswm/autostart.sh` is run instead.
+pub const AUTOSTART: &[&str] = &[];
+/// Command spawned once the user has been idle for `IDLE_TIMEOUT_SECS` (e.g. a screen
+/// locker). Requires the X SCREENSAVER extension; `None` disables the idle monitor.
+pub const LOCK_COMMAND: Option<&str> = None;
+pub const IDLE_TIMEOUT_SECS: u32 = 600;
+/// PNG tiled over the root window at startup; `None` leaves the background alone
+/// (e.g. for `feh` in autostart).
+pub const WALLPAPER_PATH: Option<&str> = None;
+/// Session file written to `$XDG_RUNTIME_DIR` on every change and reloaded on startup.
+/// The X server and display are appended, e.g. `ferriswm-local-0.state` for `:0`.
+pub const SESSION_FILE_STEM: &str = "ferriswm";
+/// WM_CLASS instance or class names of override-redirect windows that should still be
+/// managed (tiled) like normal windows.
+pub const MANAGE_OVERRIDE_REDIRECT_CLASSES: &[&str] = &[];
+/// Border widths for specific windows as (WM_CLASS instance or class, width); others
+/// use `DEFAULT_BORDER_WIDTH`.
+pub const BORDER_WIDTH_RULES: &[(&str, u32)] = &[];
+/// Tile dialog windows (that aren't transient for another window) instead of
+/// floating them. A dialog whose tile would be smaller than its `WM_NORMAL_HINTS`
+/// minimum size is floated at that size instead.
+pub const TILE_DIALOGS: bool = false;
+/// While a modal dialog is open, redirect any attempt to focus its owner to the dialog.
+pub const MODAL_STRICT_FOCUS: bool = false;
+/// Draw a border only around the focused window; the others get none and are
+/// re-tiled to fill the space.
+pub const ONLY_FOCUSED_BORDER: bool = false;
+/// Screen regions (e.g. a clock widget in the top-right corner) that tiled windows
+/// never cover. Each region acts as a strut on the monitor edge it touches.
+pub const RESERVED_REGIONS: &[Rect] = &[];
+/// Window focused after switching workspaces.
+pub const FOCUS_ON_SWITCH: FocusOnSwitch = FocusOnSwitch::LastFocused;
+/// What happens when the last window of the current workspace is closed or sent away.
+pub const EMPTY_WORKSPACE: EmptyWorkspace = EmptyWorkspace::Stay;
+/// Where a newly mapped window joins its workspace's window list.
+pub const ATTACH_MODE: AttachMode = AttachMode::Bottom;
+/// Directional focus past the screen edge wraps to the window at the opposite edge.
+pub const FOCUS_WRAP: bool = false;
+/// Scrolling on the bare root window (Button4/Button5) goes to the next/previous
+/// workspace. The scroll buttons are grabbed (synchronously, with any modifiers)
+/// on the root, so every wheel notch waits on a round-trip through the WM before
+/// it is replayed to the window under the pointer. If another client already
+/// holds that grab, the feature is turned off with a warning.
+pub const ROOT_SCROLL_SWITCHES_WORKSPACE: bool = false;
+/// Scrolling over a tiled window's border grows (Button4) or shrinks (Button5)
+/// its weight by `WEIGHT_STEP`. X can't grab just the border, so the scroll
+/// buttons are grabbed (synchronously, with any modifiers) on the whole window,
+/// or on its frame with `REPARENT_WINDOWS`. Every wheel notch over a managed
+/// window then waits on a round-trip through the WM before the client gets it.
+pub const BORDER_SCROLL_WEIGHT: bool = false;
+/// Wrap each managed window in a WM-created frame window.
+pub const REPARENT_WINDOWS: bool = false;
+/// Newly mapped floating windows follow the pointer until a click places them.
+pub const INTERACTIVE_PLACEMENT: bool = false;
+/// Briefly draw a newly focused window's border in this pixel value for this long
+/// before it settles to the focused color.
+pub const FOCUS_FLASH: Option<(u32, Duration)> = None;
 
 const TESTING: Option<&str> = option_env!("WM_TESTING");
-const MOD: ModMask = if TESTING.is_none() {
+/// Modifier used by the bindings below; Mod1 when built with `WM_TESTING` set.
+pub const MOD: ModMask = if TESTING.is_none() {
     ModMask::N4
 } else {
     ModMask::N1
 };
 const SHIFT: ModMask = ModMask::SHIFT;
+const CTRL: ModMask = ModMask::CONTROL;
+/// `owner_events` for key grabs. A grab the server rejects is retried once with
+/// the opposite value.
+pub const KEY_GRAB_OWNER_EVENTS: bool = false;
+/// Modifier held while dragging a floating window with the left button.
+pub const MOUSE_MODIFIER: ModMask = MOD;
+/// Distance in pixels at which a dragged window snaps to screen and window edges.
+pub const SNAP_THRESHOLD: u32 = 16;
+/// Pixels of a dragged window that always stay inside the usable area on each axis.
+pub const MIN_VISIBLE: u32 = 20;
 
 /// Usage: binding!(key, [modifiers], action)
 macro_rules! binding {
@@ -38,6 +151,7 @@ pub static ACTION_MAPPINGS: &[ActionMapping] = &[
     binding!(xkb::Keysym::space, [MOD], ActionEvent::Spawn("rofi -show drun")),
     binding!(xkb::Keysym::r, [MOD,SHIFT], ActionEvent::Spawn("pkill -x FerrisWM")), // Reload the WM
     binding!(xkb::Keysym::r, [MOD,SHIFT], ActionEvent::Spawn("pkill -x FerrisWM")), // Reload the WM
+    binding!(xkb::Keysym::e, [MOD, SHIFT], ActionEvent::Quit),
 
 
     // ==================== MULTIMEDIA BINDINGS ====================
@@ -50,18 +164,77 @@ pub static ACTION_MAPPINGS: &[ActionMapping] = &[
 
     // ==================== WINDOW MANAGEMENT ====================
     binding!(xkb::Keysym::q, [MOD], ActionEvent::Kill),
+    binding!(xkb::Keysym::q, [MOD, SHIFT], ActionEvent::KillUnderPointer),
     binding!(xkb::Keysym::f, [MOD], ActionEvent::ToggleFullscreen),
+    binding!(xkb::Keysym::f, [MOD, SHIFT], ActionEvent::ToggleFloating),
+    binding!(xkb::Keysym::t, [MOD], ActionEvent::ToggleTilingSkip),
     binding!(xkb::Keysym::v, [MOD], ActionEvent::CycleLayout),
+    binding!(xkb::Keysym::r, [MOD], ActionEvent::RotateLayout),
+    binding!(xkb::Keysym::z, [MOD], ActionEvent::Zoom),
+    binding!(xkb::Keysym::z, [MOD, SHIFT], ActionEvent::ZoomInPlace),
+    binding!(xkb::Keysym::Tab, [MOD, SHIFT], ActionEvent::SwapLastTwo),
+    binding!(xkb::Keysym::i, [MOD], ActionEvent::IncNMaster),
+    binding!(xkb::Keysym::d, [MOD], ActionEvent::DecNMaster),
+    binding!(xkb::Keysym::d, [MOD, SHIFT], ActionEvent::DumpState),
+    binding!(xkb::Keysym::b, [MOD], ActionEvent::ToggleBorders),
+    binding!(xkb::Keysym::b, [MOD, SHIFT], ActionEvent::ToggleDocks),
     binding!(xkb::Keysym::Left, [MOD], ActionEvent::PrevWindow),
     binding!(xkb::Keysym::Right, [MOD], ActionEvent::NextWindow),
     binding!(xkb::Keysym::Left, [MOD, SHIFT], ActionEvent::SwapLeft),
     binding!(xkb::Keysym::Right, [MOD, SHIFT], ActionEvent::SwapRight),
+    binding!(xkb::Keysym::Up, [MOD], ActionEvent::FocusStackPrev),
+    binding!(xkb::Keysym::Down, [MOD], ActionEvent::FocusStackNext),
+    binding!(xkb::Keysym::grave, [MOD], ActionEvent::CycleFloating),
+    binding!(xkb::Keysym::a, [MOD], ActionEvent::FocusMaster),
+    binding!(xkb::Keysym::a, [MOD, SHIFT], ActionEvent::FocusLastStack),
+    binding!(xkb::Keysym::m, [MOD], ActionEvent::Minimize),
+    binding!(xkb::Keysym::m, [MOD, SHIFT], ActionEvent::RestoreLastMinimized),
+    binding!(xkb::Keysym::comma, [MOD], ActionEvent::FocusMonitorPrev),
+    binding!(xkb::Keysym::period, [MOD], ActionEvent::FocusMonitorNext),
+    binding!(xkb::Keysym::comma, [MOD, SHIFT], ActionEvent::SendToMonitorPrev),
+    binding!(xkb::Keysym::period, [MOD, SHIFT], ActionEvent::SendToMonitorNext),
+    binding!(xkb::Keysym::period, [MOD, CTRL], ActionEvent::SpreadAcrossMonitors),
+    binding!(xkb::Keysym::g, [MOD, SHIFT], ActionEvent::GatherAll),
+    binding!(xkb::Keysym::h, [MOD], ActionEvent::FocusLeft),
+    binding!(xkb::Keysym::l, [MOD], ActionEvent::FocusRight),
+    binding!(xkb::Keysym::k, [MOD], ActionEvent::FocusUp),
+    binding!(xkb::Keysym::j, [MOD], ActionEvent::FocusDown),
+    binding!(xkb::Keysym::h, [MOD, CTRL], ActionEvent::PreselectLeft),
+    binding!(xkb::Keysym::l, [MOD, CTRL], ActionEvent::PreselectRight),
+    binding!(xkb::Keysym::k, [MOD, CTRL], ActionEvent::PreselectUp),
+    binding!(xkb::Keysym::j, [MOD, CTRL], ActionEvent::PreselectDown),
+    binding!(xkb::Keysym::h, [MOD, SHIFT], ActionEvent::MoveWindowLeft),
+    binding!(xkb::Keysym::l, [MOD, SHIFT], ActionEvent::MoveWindowRight),
+    binding!(xkb::Keysym::k, [MOD, SHIFT], ActionEvent::MoveWindowUp),
+    binding!(xkb::Keysym::j, [MOD, SHIFT], ActionEvent::MoveWindowDown),
+    binding!(xkb::Keysym::Up, [MOD, SHIFT], ActionEvent::MoveWindowUp),
+    binding!(xkb::Keysym::Down, [MOD, SHIFT], ActionEvent::MoveWindowDown),
+    binding!(xkb::Keysym::Up, [MOD, CTRL], ActionEvent::MaxVertical),
+    binding!(xkb::Keysym::Right, [MOD, CTRL], ActionEvent::MaxHorizontal),
+    binding!(xkb::Keysym::Left, [MOD, CTRL, SHIFT], ActionEvent::SnapHalfLeft),
+    binding!(xkb::Keysym::Right, [MOD, CTRL, SHIFT], ActionEvent::SnapHalfRight),
+    binding!(xkb::Keysym::Home, [MOD, SHIFT], ActionEvent::SnapTopLeft),
+    binding!(xkb::Keysym::Prior, [MOD, SHIFT], ActionEvent::SnapTopRight),
+    binding!(xkb::Keysym::End, [MOD, SHIFT], ActionEvent::SnapBottomLeft),
+    binding!(xkb::Keysym::Next, [MOD, SHIFT], ActionEvent::SnapBottomRight),
+    binding!(xkb::Keysym::c, [MOD], ActionEvent::CenterWindow),
+
+    // ==================== MARKS (MOD + SHIFT + F1-F3 to set, MOD + F1-F3 to jump) ====================
+    binding!(xkb::Keysym::F1, [MOD, SHIFT], ActionEvent::SetMark('1')),
+    binding!(xkb::Keysym::F2, [MOD, SHIFT], ActionEvent::SetMark('2')),
+    binding!(xkb::Keysym::F3, [MOD, SHIFT], ActionEvent::SetMark('3')),
+    binding!(xkb::Keysym::F1, [MOD], ActionEvent::JumpToMark('1')),
+    binding!(xkb::Keysym::F2, [MOD], ActionEvent::JumpToMark('2')),
+    binding!(xkb::Keysym::F3, [MOD], ActionEvent::JumpToMark('3')),
 
     // ==================== WINDOW SIZING ====================
-    binding!(xkb::Keysym::equal, [MOD], ActionEvent::IncreaseWindowWeight(1)),
-    binding!(xkb::Keysym::minus, [MOD], ActionEvent::DecreaseWindowWeight(1)),
-    binding!(xkb::Keysym::equal, [MOD, SHIFT], ActionEvent::IncreaseWindowGap(1)),
-    binding!(xkb::Keysym::minus, [MOD, SHIFT], ActionEvent::DecreaseWindowGap(1)),
+    binding!(xkb::Keysym::equal, [MOD], ActionEvent::IncreaseWindowWeight(WEIGHT_STEP)),
+    binding!(xkb::Keysym::minus, [MOD], ActionEvent::DecreaseWindowWeight(WEIGHT_STEP)),
+    binding!(xkb::Keysym::e, [MOD], ActionEvent::EqualizeWeights),
+    binding!(xkb::Keysym::equal, [MOD, CTRL], ActionEvent::GrowActive(RESIZE_STEP_PERCENT)),
+    binding!(xkb::Keysym::minus, [MOD, CTRL], ActionEvent::ShrinkActive(RESIZE_STEP_PERCENT)),
+    binding!(xkb::Keysym::equal, [MOD, SHIFT], ActionEvent::IncreaseWindowGap(GAP_STEP)),
+    binding!(xkb::Keysym::minus, [MOD, SHIFT], ActionEvent::DecreaseWindowGap(GAP_STEP)),
 
     // ==================== WORKSPACE NAVIGATION (MOD + 1-9, 0) ====================
     binding!(xkb::Keysym::_1, [MOD], ActionEvent::GoToWorkspace(0)),
@@ -87,3 +260,7 @@ pub static ACTION_MAPPINGS: &[ActionMapping] = &[
     binding!(xkb::Keysym::_9, [MOD, SHIFT], ActionEvent::SendToWorkspace(8)),
     binding!(xkb::Keysym::_0, [MOD, SHIFT], ActionEvent::SendToWorkspace(9)),
 ];
+
+/// Extra bindings with modifiers given by name ("Super", "Alt", "Ctrl", "Shift", "Hyper"),
+/// e.g. `NamedActionMapping { key: xkb::Keysym::t, modifiers: &["Super", "Ctrl"], action: ... }`.
+pub static NAMED_ACTION_MAPPINGS: &[NamedActionMapping] = &[];
@@ -2,6 +2,20 @@ use xcb::x::{self, ModMask, Window};
 
 pub type Effects = Vec<Effect>;
 
+impl Effect {
+    /// The property an effect writes, if it writes one.
+    pub const fn property_atom(&self) -> Option<x::Atom> {
+        match self {
+            Effect::SetCardinal32 { atom, .. }
+            | Effect::SetCardinal32List { atom, .. }
+            | Effect::SetAtomList { atom, .. }
+            | Effect::SetUtf8String { atom, .. }
+            | Effect::SetWindowProperty { atom, .. } => Some(*atom),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Effect {
     Map(Window),
@@ -22,6 +36,8 @@ pub enum Effect {
         h: u32,
     },
     Focus(Window),
+    /// Moves input focus to the root, so no client keeps it.
+    FocusRoot,
     Raise(Window),
     SetBorder {
         window: Window,
@@ -54,12 +70,48 @@ pub enum Effect {
         values: Vec<u32>,
     },
     KillClient(Window),
-    SendWmDelete(Window),
+    SendWmDelete {
+        window: Window,
+        time: u32,
+    },
     GrabKey {
         keycode: u8,
         modifiers: ModMask,
         grab_window: Window,
+        owner_events: bool,
+    },
+    UngrabKey {
+        keycode: u8,
+        modifiers: ModMask,
+        grab_window: Window,
     },
     GrabButton(Window),
-    SubscribeEnterNotify(Window),
+    /// Grabs the scroll buttons on a window's toplevel, for `BORDER_SCROLL_WEIGHT`.
+    GrabScroll(Window),
+    SetEventMask {
+        window: Window,
+        mask: x::EventMask,
+    },
+    CreateFrame(Window),
+    /// Adds a client to the WM's save-set, so the server reparents it back to
+    /// the root instead of destroying it along with its frame.
+    AddToSaveSet(Window),
+    /// Moves `window` into `parent`, its outer top-left corner at `x`, `y`.
+    Reparent {
+        window: Window,
+        parent: Window,
+        x: i32,
+        y: i32,
+    },
+    DestroyWindow(Window),
+    SetScreenSaver {
+        timeout: i16,
+        interval: i16,
+        prefer_blanking: x::Blanking,
+        allow_exposures: x::Exposures,
+    },
+    WarpPointer {
+        x: i32,
+        y: i32,
+    },
 }
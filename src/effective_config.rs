@@ -0,0 +1,104 @@
+use std::fmt;
+
+use xcb::x::ModMask;
+
+use crate::config::{
+    ACTION_MAPPINGS, BORDER_WIDTH_RULES, DEFAULT_BORDER_WIDTH, DEFAULT_LAYOUT, DEFAULT_NMASTER,
+    DEFAULT_WINDOW_GAP, MANAGE_OVERRIDE_REDIRECT_CLASSES, MOD, NAMED_ACTION_MAPPINGS,
+    NUM_WORKSPACES,
+};
+use crate::keyboard::format_modifiers;
+use crate::layout::LayoutType;
+
+/// The settings FerrisWM runs with, gathered in one place so they can be
+/// logged at startup.
+#[derive(Debug)]
+pub struct Config {
+    pub border_width: u32,
+    pub window_gap: u32,
+    pub workspaces: usize,
+    pub mod_key: ModMask,
+    pub layout: LayoutType,
+    pub nmaster: usize,
+    pub key_bindings: usize,
+    pub border_rules: usize,
+    pub override_redirect_rules: usize,
+}
+
+impl Config {
+    /// Resolves the values from `config.rs`.
+    pub fn resolve() -> Self {
+        Self {
+            border_width: DEFAULT_BORDER_WIDTH,
+            window_gap: DEFAULT_WINDOW_GAP,
+            workspaces: NUM_WORKSPACES,
+            mod_key: MOD,
+            layout: DEFAULT_LAYOUT,
+            nmaster: DEFAULT_NMASTER,
+            key_bindings: ACTION_MAPPINGS.len() + NAMED_ACTION_MAPPINGS.len(),
+            border_rules: BORDER_WIDTH_RULES.len(),
+            override_redirect_rules: MANAGE_OVERRIDE_REDIRECT_CLASSES.len(),
+        }
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "border={} gap={} workspaces={} mod={} layout={:?} nmaster={} \
+             bindings={} border_rules={} override_redirect_rules={}",
+            self.border_width,
+            self.window_gap,
+            self.workspaces,
+            format_modifiers(self.mod_key),
+            self.layout,
+            self.nmaster,
+            self.key_bindings,
+            self.border_rules,
+            self.override_redirect_rules,
+        )
+    }
+}
+
+#[cfg(test)]
+mod effective_config_tests {
+    use super::*;
+
+    #[test]
+    fn display_includes_overridden_values() {
+        let config = Config {
+            border_width: 3,
+            window_gap: 12,
+            workspaces: 4,
+            mod_key: ModMask::N1,
+            layout: LayoutType::MasterLayout,
+            ..Config::resolve()
+        };
+
+        let shown = config.to_string();
+
+        for expected in [
+            "border=3",
+            "gap=12",
+            "workspaces=4",
+            "mod=Mod1",
+            "layout=MasterLayout",
+        ] {
+            assert!(
+                shown.contains(expected),
+                "{expected:?} missing from {shown:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_counts_bindings() {
+        let config = Config::resolve();
+        assert_eq!(
+            config.key_bindings,
+            ACTION_MAPPINGS.len() + NAMED_ACTION_MAPPINGS.len()
+        );
+        assert_eq!(config.workspaces, NUM_WORKSPACES);
+    }
+}
@@ -9,24 +9,152 @@ use crate::{
     atoms::Atoms,
     config::NUM_WORKSPACES,
     effect::{Effect, Effects},
-    x11::X11,
+    layout::Rect,
+    x11::XConn,
 };
 
+/// `_NET_WM_DESKTOP` value for windows shown on every desktop.
+pub const ALL_DESKTOPS: u32 = 0xFFFFFFFF;
+
+/// Source indication sent by clients that predate EWMH 1.2.
+const SOURCE_INDICATION_LEGACY: u32 = 0;
+
+/// Whether a client message carrying the given EWMH source indication should be
+/// honored. In strict mode, messages from legacy clients (source 0) are ignored.
+pub const fn accepts_source_indication(source: u32, strict: bool) -> bool {
+    !(strict && source == SOURCE_INDICATION_LEGACY)
+}
+
+/// `_NET_MOVERESIZE_WINDOW` flag bits saying which of x, y, width and height are set.
+const MOVERESIZE_X: u32 = 1 << 8;
+const MOVERESIZE_Y: u32 = 1 << 9;
+const MOVERESIZE_WIDTH: u32 = 1 << 10;
+const MOVERESIZE_HEIGHT: u32 = 1 << 11;
+
+/// A decoded `_NET_MOVERESIZE_WINDOW` request. Fields the client left out of
+/// the flags are `None` and keep their current value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveResize {
+    pub gravity: u32,
+    pub source: u32,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub w: Option<u32>,
+    pub h: Option<u32>,
+}
+
+/// Decodes the data of a `_NET_MOVERESIZE_WINDOW` message: gravity in bits 0-7
+/// of the first word, the field flags in bits 8-11 and the source in 12-13.
+pub fn decode_moveresize(data: [u32; 5]) -> MoveResize {
+    let [flags, x, y, w, h] = data;
+    let set = |bit: u32| flags & bit != 0;
+    MoveResize {
+        gravity: flags & 0xff,
+        source: (flags >> 12) & 0x3,
+        x: set(MOVERESIZE_X).then_some(x as i32),
+        y: set(MOVERESIZE_Y).then_some(y as i32),
+        w: set(MOVERESIZE_WIDTH).then_some(w),
+        h: set(MOVERESIZE_HEIGHT).then_some(h),
+    }
+}
+
+impl MoveResize {
+    /// `current` with the requested fields replaced. The requested position is
+    /// that of the gravity's reference point on the window including its
+    /// `border`, so it is shifted back to the top-left corner; gravity 0 (the
+    /// window's own) and unknown values are treated as NorthWest.
+    pub fn apply(&self, current: Rect, border: u32) -> Rect {
+        let w = self.w.unwrap_or(current.w);
+        let h = self.h.unwrap_or(current.h);
+        let (outer_w, outer_h) = ((w + 2 * border) as i32, (h + 2 * border) as i32);
+        let (dx, dy) = match self.gravity {
+            2 => (outer_w / 2, 0),
+            3 => (outer_w, 0),
+            4 => (0, outer_h / 2),
+            5 => (outer_w / 2, outer_h / 2),
+            6 => (outer_w, outer_h / 2),
+            7 => (0, outer_h),
+            8 => (outer_w / 2, outer_h),
+            9 => (outer_w, outer_h),
+            // Static: the position is that of the client area, inside the border.
+            10 => (border as i32, border as i32),
+            _ => (0, 0),
+        };
+        Rect {
+            x: self.x.map_or(current.x, |x| x - dx),
+            y: self.y.map_or(current.y, |y| y - dy),
+            w,
+            h,
+        }
+    }
+}
+
+/// Whether a newly mapped window may take focus, given its `_NET_WM_USER_TIME`
+/// and the server time of the user's last key or button press (0 if there has
+/// been none). A user time of 0 means the client asked not to be focused; an
+/// older one means the user has moved on since the window was requested.
+pub const fn allows_focus_on_map(user_time: Option<u32>, last_interaction: u32) -> bool {
+    match user_time {
+        None => true,
+        Some(0) => false,
+        // X timestamps wrap around, so compare them as a signed difference.
+        Some(time) => last_interaction == 0 || time.wrapping_sub(last_interaction) as i32 >= 0,
+    }
+}
+
 pub struct EwmhManager {
     atoms: Atoms,
     root: Window,
     wm_check_window: Window,
+    /// Last value published for `_NET_ACTIVE_WINDOW`; `None` until the first sync.
+    published_active_window: Option<Option<Window>>,
+    /// Last value published for `_NET_DESKTOP_NAMES` through `desktop_names_effect`.
+    published_desktop_names: Option<Vec<String>>,
+    /// Hints never published, from `SUPPRESSED_HINTS`.
+    suppressed: Vec<x::Atom>,
+}
+
+/// `_NET_DESKTOP_NAMES` is a list of NUL-terminated UTF-8 strings.
+fn desktop_names_value(names: &[String]) -> String {
+    names.iter().map(|name| format!("{name}\0")).collect()
 }
 
 impl EwmhManager {
-    pub fn new(atoms: Atoms, root: Window, wm_check_window: Window) -> Self {
+    pub fn new(
+        atoms: Atoms,
+        root: Window,
+        wm_check_window: Window,
+        suppressed: Vec<x::Atom>,
+    ) -> Self {
         Self {
             atoms,
             root,
             wm_check_window,
+            published_active_window: None,
+            published_desktop_names: None,
+            suppressed,
         }
     }
 
+    /// Destroys the `_NET_SUPPORTING_WM_CHECK` window, which only lives as long
+    /// as the WM.
+    pub const fn destroy_check_window_effect(&self) -> Effect {
+        Effect::DestroyWindow(self.wm_check_window)
+    }
+
+    /// Drops effects that write a suppressed hint.
+    pub fn without_suppressed(&self, mut effects: Effects) -> Effects {
+        effects.retain(|effect| {
+            effect
+                .property_atom()
+                .is_none_or(|atom| !self.suppressed.contains(&atom))
+        });
+        effects
+    }
+
+    /// Hints that are fixed for the WM's lifetime, plus empty defaults for the
+    /// ones the first sync fills in. `_NET_DESKTOP_NAMES` is left to that sync so
+    /// it is built from `State::desktop_names` and written once.
     pub fn publish_hints(&self) -> Effects {
         let atoms = &self.atoms;
         let root = self.root;
@@ -49,22 +177,27 @@ impl EwmhManager {
             atoms.wm_pid,
             atoms.wm_window_type,
             atoms.wm_window_type_dock,
+            atoms.wm_window_type_dialog,
+            atoms.wm_window_type_utility,
+            atoms.wm_window_type_splash,
+            atoms.wm_window_type_toolbar,
+            atoms.wm_window_type_menu,
             atoms.wm_strut_partial,
             atoms.wm_state,
             atoms.wm_state_fullscreen,
+            atoms.wm_state_modal,
+            atoms.wm_state_demands_attention,
+            atoms.wm_state_hidden,
             atoms.wm_desktop,
+            atoms.wm_user_time,
+            atoms.frame_extents,
             atoms.close_window,
+            atoms.moveresize_window,
         ];
 
-        let mut desktop_names = String::new();
-        for i in 0..NUM_WORKSPACES {
-            desktop_names.push_str(&(i + 1).to_string());
-            desktop_names.push('\0');
-        }
-
         let viewport_zeros = vec![0u32; NUM_WORKSPACES * 2];
 
-        vec![
+        self.without_suppressed(vec![
             Effect::SetWindowProperty {
                 window: root,
                 atom: atoms.supporting_wm_check,
@@ -90,6 +223,7 @@ impl EwmhManager {
                 atom: atoms.supported,
                 values: supported_atoms
                     .iter()
+                    .filter(|atom| !self.suppressed.contains(atom))
                     .map(xcb::Xid::resource_id)
                     .collect::<Vec<_>>(),
             },
@@ -113,11 +247,6 @@ impl EwmhManager {
                 atom: atoms.desktop_viewport,
                 values: viewport_zeros,
             },
-            Effect::SetUtf8String {
-                window: root,
-                atom: atoms.desktop_names,
-                value: desktop_names,
-            },
             Effect::SetWindowProperty {
                 window: root,
                 atom: atoms.client_list,
@@ -133,7 +262,7 @@ impl EwmhManager {
                 atom: atoms.active_window,
                 values: vec![],
             },
-        ]
+        ])
     }
 
     pub fn desktop_geometry_effect(&self, width: u32, height: u32) -> Effect {
@@ -157,12 +286,33 @@ impl EwmhManager {
         }
     }
 
-    pub fn active_window_effect(&self, window: Option<Window>) -> Effect {
-        Effect::SetWindowProperty {
+    /// Publishes `window` as `_NET_ACTIVE_WINDOW`, or returns `None` if it is
+    /// already the published value, so pagers aren't woken for nothing.
+    pub fn active_window_effect(&mut self, window: Option<Window>) -> Option<Effect> {
+        if self.published_active_window == Some(window) {
+            return None;
+        }
+        self.published_active_window = Some(window);
+        Some(Effect::SetWindowProperty {
             window: self.root,
             atom: self.atoms.active_window,
             values: window.map(|w| vec![w.resource_id()]).unwrap_or_default(),
+        })
+    }
+
+    /// Publishes `_NET_DESKTOP_NAMES`, or returns `None` if the names haven't
+    /// changed since the last call.
+    pub fn desktop_names_effect(&mut self, names: Vec<String>) -> Option<Effect> {
+        if self.published_desktop_names.as_ref() == Some(&names) {
+            return None;
         }
+        let effect = Effect::SetUtf8String {
+            window: self.root,
+            atom: self.atoms.desktop_names,
+            value: desktop_names_value(&names),
+        };
+        self.published_desktop_names = Some(names);
+        Some(effect)
     }
 
     pub fn client_list_effects(&self, windows: &[x::Window]) -> Effects {
@@ -200,24 +350,134 @@ impl EwmhManager {
         }
     }
 
-    pub fn get_window_desktop(&self, x11: &X11, window: Window) -> Option<u32> {
+    pub fn frame_extents_effect(
+        &self,
+        window: Window,
+        left: u32,
+        right: u32,
+        top: u32,
+        bottom: u32,
+    ) -> Effect {
+        Effect::SetCardinal32List {
+            window,
+            atom: self.atoms.frame_extents,
+            values: vec![left, right, top, bottom],
+        }
+    }
+
+    pub fn get_window_desktop(&self, x11: &dyn XConn, window: Window) -> Option<u32> {
         x11.get_cardinal32(window, self.atoms.wm_desktop)
     }
 
-    pub fn get_current_desktop(&self, x11: &X11) -> Option<u32> {
+    pub fn get_current_desktop(&self, x11: &dyn XConn) -> Option<u32> {
         x11.get_cardinal32(self.root, self.atoms.current_desktop)
     }
 
-    pub fn window_fullscreen_state_effect(&self, window: Window, fullscreen: bool) -> Effect {
+    pub fn window_state_effect(&self, window: Window, fullscreen: bool, hidden: bool) -> Effect {
         let atoms = &self.atoms;
+        let values = [
+            (fullscreen, atoms.wm_state_fullscreen),
+            (hidden, atoms.wm_state_hidden),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, atom)| atom.resource_id())
+        .collect();
         Effect::SetAtomList {
             window,
             atom: atoms.wm_state,
-            values: if fullscreen {
-                vec![atoms.wm_state_fullscreen.resource_id()]
-            } else {
-                vec![]
-            },
+            values,
         }
     }
 }
+
+#[cfg(test)]
+mod ewmh_manager_tests {
+    use super::*;
+
+    #[test]
+    fn lenient_mode_accepts_every_source() {
+        assert!(accepts_source_indication(0, false));
+        assert!(accepts_source_indication(1, false));
+        assert!(accepts_source_indication(2, false));
+    }
+
+    #[test]
+    fn strict_mode_rejects_legacy_source() {
+        assert!(!accepts_source_indication(0, true));
+        assert!(accepts_source_indication(1, true));
+        assert!(accepts_source_indication(2, true));
+    }
+
+    const RECT: Rect = Rect {
+        x: 100,
+        y: 150,
+        w: 200,
+        h: 100,
+    };
+
+    #[test]
+    fn moveresize_decodes_only_flagged_fields() {
+        let request = decode_moveresize([
+            1 | MOVERESIZE_X | MOVERESIZE_WIDTH | (2 << 12),
+            40,
+            50,
+            300,
+            60,
+        ]);
+        assert_eq!(
+            request,
+            MoveResize {
+                gravity: 1,
+                source: 2,
+                x: Some(40),
+                y: None,
+                w: Some(300),
+                h: None,
+            }
+        );
+
+        let rect = request.apply(RECT, 1);
+        assert_eq!((rect.x, rect.y, rect.w, rect.h), (40, 150, 300, 100));
+    }
+
+    #[test]
+    fn moveresize_gravity_shifts_reference_point() {
+        let all = MOVERESIZE_X | MOVERESIZE_Y | MOVERESIZE_WIDTH | MOVERESIZE_HEIGHT;
+        let rect_for = |gravity: u32| {
+            let rect = decode_moveresize([gravity | all, 400, 300, 98, 48]).apply(RECT, 1);
+            (rect.x, rect.y, rect.w, rect.h)
+        };
+
+        assert_eq!(rect_for(0), (400, 300, 98, 48));
+        assert_eq!(rect_for(5), (350, 275, 98, 48));
+        assert_eq!(rect_for(9), (300, 250, 98, 48));
+        assert_eq!(rect_for(10), (399, 299, 98, 48));
+    }
+
+    #[test]
+    fn windows_without_user_time_take_focus() {
+        assert!(allows_focus_on_map(None, 0));
+        assert!(allows_focus_on_map(None, 5000));
+    }
+
+    #[test]
+    fn zero_user_time_never_takes_focus() {
+        assert!(!allows_focus_on_map(Some(0), 0));
+        assert!(!allows_focus_on_map(Some(0), 5000));
+    }
+
+    #[test]
+    fn user_time_is_compared_with_last_interaction() {
+        assert!(allows_focus_on_map(Some(5000), 5000));
+        assert!(allows_focus_on_map(Some(6000), 5000));
+        assert!(!allows_focus_on_map(Some(4000), 5000));
+        assert!(allows_focus_on_map(Some(4000), 0));
+    }
+
+    #[test]
+    fn user_time_comparison_survives_wraparound() {
+        assert!(allows_focus_on_map(Some(10), u32::MAX - 10));
+        assert!(!allows_focus_on_map(Some(u32::MAX - 10), 10));
+    }
+}
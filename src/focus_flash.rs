@@ -0,0 +1,88 @@
+use std::time::{Duration, Instant};
+
+use xcb::x::Window;
+
+/// Tracks the one window whose border is flashing after it got focus, and when
+/// its border should settle back to the focused color.
+#[derive(Debug)]
+pub struct FocusFlash {
+    duration: Duration,
+    pending: Option<(Window, Instant)>,
+}
+
+impl FocusFlash {
+    pub const fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            pending: None,
+        }
+    }
+
+    /// Starts flashing `window`, replacing any flash still in progress.
+    pub fn start(&mut self, window: Window, now: Instant) {
+        self.pending = Some((window, now + self.duration));
+    }
+
+    /// How long the event loop may block before the pending flash is due.
+    pub fn time_left(&self, now: Instant) -> Option<Duration> {
+        self.pending
+            .map(|(_, deadline)| deadline.saturating_duration_since(now))
+    }
+
+    /// The flashing window, once its deadline has passed. Each flash is handed
+    /// out only once.
+    pub fn take_due(&mut self, now: Instant) -> Option<Window> {
+        match self.pending {
+            Some((window, deadline)) if deadline <= now => {
+                self.pending = None;
+                Some(window)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod focus_flash_tests {
+    use super::*;
+    use xcb::XidNew;
+
+    const DURATION: Duration = Duration::from_millis(150);
+
+    #[test]
+    fn nothing_pending_before_start() {
+        let mut flash = FocusFlash::new(DURATION);
+        let now = Instant::now();
+
+        assert_eq!(flash.time_left(now), None);
+        assert_eq!(flash.take_due(now), None);
+    }
+
+    #[test]
+    fn settles_once_deadline_passes() {
+        let mut flash = FocusFlash::new(DURATION);
+        let now = Instant::now();
+        flash.start(Window::new(1), now);
+
+        assert_eq!(flash.time_left(now), Some(DURATION));
+        assert_eq!(flash.take_due(now + DURATION / 2), None);
+        assert_eq!(flash.time_left(now + DURATION * 2), Some(Duration::ZERO));
+        assert_eq!(flash.take_due(now + DURATION), Some(Window::new(1)));
+        assert_eq!(flash.take_due(now + DURATION * 2), None);
+        assert_eq!(flash.time_left(now + DURATION * 2), None);
+    }
+
+    #[test]
+    fn new_focus_replaces_pending_flash() {
+        let mut flash = FocusFlash::new(DURATION);
+        let now = Instant::now();
+        flash.start(Window::new(1), now);
+        flash.start(Window::new(2), now + DURATION / 2);
+
+        assert_eq!(flash.take_due(now + DURATION), None);
+        assert_eq!(
+            flash.take_due(now + DURATION + DURATION / 2),
+            Some(Window::new(2))
+        );
+    }
+}
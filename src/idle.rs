@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+/// Decides when the idle command should run: once per idle period, as soon as the
+/// user has been idle for at least `timeout`.
+#[derive(Debug)]
+pub struct IdleMonitor {
+    timeout: Duration,
+    fired: bool,
+}
+
+impl IdleMonitor {
+    pub const fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            fired: false,
+        }
+    }
+
+    /// Returns true when the command should be spawned for the given idle time.
+    /// Any idle time below the timeout means the user came back and re-arms the monitor.
+    pub fn should_fire(&mut self, idle: Duration) -> bool {
+        if idle < self.timeout {
+            self.fired = false;
+            return false;
+        }
+        if self.fired {
+            return false;
+        }
+        self.fired = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod idle_tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(300);
+
+    #[test]
+    fn does_not_fire_before_timeout() {
+        let mut monitor = IdleMonitor::new(TIMEOUT);
+        assert!(!monitor.should_fire(Duration::ZERO));
+        assert!(!monitor.should_fire(TIMEOUT - Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn fires_once_per_idle_period() {
+        let mut monitor = IdleMonitor::new(TIMEOUT);
+        assert!(monitor.should_fire(TIMEOUT));
+        assert!(!monitor.should_fire(TIMEOUT + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn activity_rearms_the_monitor() {
+        let mut monitor = IdleMonitor::new(TIMEOUT);
+        assert!(monitor.should_fire(TIMEOUT));
+        assert!(!monitor.should_fire(Duration::from_secs(1)));
+        assert!(monitor.should_fire(TIMEOUT + Duration::from_secs(1)));
+    }
+}
@@ -6,10 +6,20 @@ pub struct ActionMapping {
     pub action: ActionEvent,
 }
 
+/// A binding whose modifiers are spelled by name, e.g. `&["Super", "Shift"]`.
+/// See `keyboard::parse_modifier` for the accepted names.
+pub struct NamedActionMapping {
+    pub key: Keysym,
+    pub modifiers: &'static [&'static str],
+    pub action: ActionEvent,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum ActionEvent {
     Spawn(&'static str),
     Kill,
+    KillUnderPointer,
+    Quit,
     NextWindow,
     PrevWindow,
     IncreaseWindowWeight(u32),
@@ -17,9 +27,61 @@ pub enum ActionEvent {
     SwapLeft,
     SwapRight,
     GoToWorkspace(usize),
+    GoToNextWorkspace,
+    GoToPrevWorkspace,
     SendToWorkspace(usize),
     IncreaseWindowGap(u32),
     DecreaseWindowGap(u32),
     ToggleFullscreen,
     CycleLayout,
+    IncNMaster,
+    DecNMaster,
+    ToggleBorders,
+    ToggleDocks,
+    ToggleFloating,
+    ToggleTilingSkip,
+    MaxVertical,
+    MaxHorizontal,
+    SnapHalfLeft,
+    SnapHalfRight,
+    SnapTopLeft,
+    SnapTopRight,
+    SnapBottomLeft,
+    SnapBottomRight,
+    CenterWindow,
+    Zoom,
+    ZoomInPlace,
+    SwapLastTwo,
+    RotateLayout,
+    DumpState,
+    FocusStackNext,
+    FocusStackPrev,
+    CycleFloating,
+    FocusMaster,
+    FocusLastStack,
+    Minimize,
+    RestoreLastMinimized,
+    FocusMonitorNext,
+    FocusMonitorPrev,
+    SendToMonitorNext,
+    SendToMonitorPrev,
+    SpreadAcrossMonitors,
+    GatherAll,
+    EqualizeWeights,
+    GrowActive(u32),
+    ShrinkActive(u32),
+    FocusLeft,
+    FocusRight,
+    FocusUp,
+    FocusDown,
+    PreselectLeft,
+    PreselectRight,
+    PreselectUp,
+    PreselectDown,
+    MoveWindowLeft,
+    MoveWindowRight,
+    MoveWindowUp,
+    MoveWindowDown,
+    SetMark(char),
+    JumpToMark(char),
 }
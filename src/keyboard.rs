@@ -1,9 +1,11 @@
 use log::{info, warn};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
 use xcb::Connection;
 use xcb::x::{self, ModMask};
+use xkbcommon::xkb::{self, Keysym};
 
-use crate::config::ACTION_MAPPINGS;
+use crate::config::{ACTION_MAPPINGS, NAMED_ACTION_MAPPINGS};
 use crate::key_mapping::ActionEvent;
 
 pub fn fetch_keyboard_mapping(conn: &Connection) -> (Vec<u32>, usize) {
@@ -27,26 +29,293 @@ pub fn populate_key_bindings(
 ) -> HashMap<(u8, ModMask), ActionEvent> {
     let mut key_bindings = HashMap::new();
 
-    for mapping in ACTION_MAPPINGS {
-        let modifiers = mapping
-            .modifiers
-            .iter()
-            .copied()
-            .reduce(|acc, modkey| acc | modkey)
-            .unwrap_or(xcb::x::ModMask::empty());
+    let mappings = ACTION_MAPPINGS
+        .iter()
+        .map(|mapping| {
+            let modifiers = mapping
+                .modifiers
+                .iter()
+                .copied()
+                .reduce(|acc, modkey| acc | modkey)
+                .unwrap_or(xcb::x::ModMask::empty());
+            (mapping.key, modifiers, mapping.action)
+        })
+        .chain(NAMED_ACTION_MAPPINGS.iter().map(|mapping| {
+            (
+                mapping.key,
+                parse_modifiers(mapping.modifiers),
+                mapping.action,
+            )
+        }));
 
+    let mut resolved = Vec::new();
+    for (key, modifiers, action) in mappings {
         for (i, chunk) in keysyms.chunks(keysyms_per_keycode).enumerate() {
-            if chunk.contains(&mapping.key.raw()) {
+            if chunk.contains(&key.raw()) {
                 let keycode = conn.get_setup().min_keycode() + i as u8;
-                key_bindings.insert((keycode, modifiers), mapping.action);
+                resolved.push(((keycode, modifiers), action));
                 info!(
                     "Mapped key {:?} (keycode: {}) with modifiers {:?} to action: {:?}",
-                    mapping.key, keycode, modifiers, mapping.action
+                    key, keycode, modifiers, action
                 );
                 break;
             }
         }
     }
 
+    for conflict in validate_bindings(&resolved) {
+        warn!(
+            "Keycode {} with modifiers {:?} is bound to both {:?} and {:?}; only {:?} will run",
+            conflict.keycode, conflict.modifiers, conflict.first, conflict.second, conflict.second
+        );
+    }
+
+    key_bindings.extend(resolved);
     key_bindings
 }
+
+/// Two bindings resolved to the same keycode and modifiers. `second` comes
+/// later in the config and silently replaces `first` in the binding map.
+#[derive(Debug)]
+pub struct Conflict {
+    pub keycode: u8,
+    pub modifiers: ModMask,
+    pub first: ActionEvent,
+    pub second: ActionEvent,
+}
+
+/// Reports every binding that reuses the keycode and modifiers of an earlier
+/// one, in config order.
+pub fn validate_bindings(mappings: &[((u8, ModMask), ActionEvent)]) -> Vec<Conflict> {
+    let mut seen: HashMap<(u8, ModMask), ActionEvent> = HashMap::new();
+    let mut conflicts = Vec::new();
+    for &((keycode, modifiers), action) in mappings {
+        if let Some(first) = seen.insert((keycode, modifiers), action) {
+            conflicts.push(Conflict {
+                keycode,
+                modifiers,
+                first,
+                second: action,
+            });
+        }
+    }
+    conflicts
+}
+
+/// Changes needed to go from the grabs currently held to the wanted ones.
+#[derive(Debug, Default, PartialEq)]
+pub struct GrabDelta {
+    pub ungrab: Vec<(u8, ModMask)>,
+    pub grab: Vec<(u8, ModMask)>,
+}
+
+/// Computes which grabs to release and which to take so that `active` becomes
+/// `wanted`. Grabs present in both are left alone, so nothing is grabbed twice.
+/// Both lists are sorted to keep the order of X requests stable.
+pub fn grab_delta(active: &HashSet<(u8, ModMask)>, wanted: &HashSet<(u8, ModMask)>) -> GrabDelta {
+    let sorted = |keys: Vec<(u8, ModMask)>| {
+        let mut keys = keys;
+        keys.sort_by_key(|&(keycode, modifiers)| (keycode, modifiers.bits()));
+        keys
+    };
+    GrabDelta {
+        ungrab: sorted(active.difference(wanted).copied().collect()),
+        grab: sorted(wanted.difference(active).copied().collect()),
+    }
+}
+
+/// Maps a config modifier name to its X modifier mask. Names are case-insensitive.
+pub fn parse_modifier(name: &str) -> Option<ModMask> {
+    match name.to_ascii_lowercase().as_str() {
+        "super" | "mod4" => Some(ModMask::N4),
+        "alt" | "mod1" => Some(ModMask::N1),
+        "ctrl" | "control" => Some(ModMask::CONTROL),
+        "shift" => Some(ModMask::SHIFT),
+        "hyper" | "mod3" => Some(ModMask::N3),
+        _ => None,
+    }
+}
+
+/// Combines modifier names into a single mask, skipping unknown names with a warning.
+pub fn parse_modifiers(names: &[&str]) -> ModMask {
+    names
+        .iter()
+        .filter_map(|name| {
+            let modifier = parse_modifier(name);
+            if modifier.is_none() {
+                warn!("Ignoring unknown modifier name {name:?}");
+            }
+            modifier
+        })
+        .fold(ModMask::empty(), |acc, modifier| acc | modifier)
+}
+
+/// First keysym bound to `keycode` in a keyboard mapping fetched with
+/// `fetch_keyboard_mapping`.
+pub fn keysym_for_keycode(
+    keysyms: &[u32],
+    keysyms_per_keycode: usize,
+    min_keycode: u8,
+    keycode: u8,
+) -> Option<Keysym> {
+    let index = usize::from(keycode.checked_sub(min_keycode)?) * keysyms_per_keycode;
+    keysyms
+        .get(index)
+        .filter(|raw| **raw != 0)
+        .map(|raw| Keysym::new(*raw))
+}
+
+const MODIFIER_NAMES: [(ModMask, &str); 8] = [
+    (ModMask::N4, "Mod4"),
+    (ModMask::N1, "Mod1"),
+    (ModMask::N2, "Mod2"),
+    (ModMask::N3, "Mod3"),
+    (ModMask::N5, "Mod5"),
+    (ModMask::CONTROL, "Control"),
+    (ModMask::SHIFT, "Shift"),
+    (ModMask::LOCK, "Lock"),
+];
+
+/// Names the modifiers in a mask joined with `+`, e.g. `Mod4+Shift`.
+pub fn format_modifiers(modifiers: ModMask) -> String {
+    MODIFIER_NAMES
+        .iter()
+        .filter(|(modifier, _)| modifiers.contains(*modifier))
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// Describes a failed key grab as e.g. `Mod4+Shift+Return (keycode 36)`.
+pub fn format_grab_failure(
+    keysym: Option<Keysym>,
+    keycode: u8,
+    modifiers: ModMask,
+    error: &impl Debug,
+) -> String {
+    let mut binding = format_modifiers(modifiers);
+    if !binding.is_empty() {
+        binding.push('+');
+    }
+    match keysym {
+        Some(keysym) => binding.push_str(&xkb::keysym_get_name(keysym)),
+        None => binding.push('?'),
+    }
+
+    format!("Failed to grab {binding} (keycode {keycode}): {error:?}")
+}
+
+#[cfg(test)]
+mod keyboard_tests {
+    use super::*;
+
+    #[test]
+    fn format_grab_failure_names_key_and_modifiers() {
+        let message = format_grab_failure(
+            Some(Keysym::Return),
+            36,
+            ModMask::N4 | ModMask::SHIFT,
+            &"BadAccess",
+        );
+        assert_eq!(
+            message,
+            "Failed to grab Mod4+Shift+Return (keycode 36): \"BadAccess\""
+        );
+    }
+
+    #[test]
+    fn format_grab_failure_without_keysym() {
+        let message = format_grab_failure(None, 200, ModMask::empty(), &"BadAccess");
+        assert_eq!(message, "Failed to grab ? (keycode 200): \"BadAccess\"");
+    }
+
+    #[test]
+    fn validate_bindings_reports_duplicate_binding_with_both_actions() {
+        // Keycode 24 is q on a standard layout.
+        let mappings = [
+            ((24, ModMask::N1), ActionEvent::Kill),
+            ((24, ModMask::N1 | ModMask::SHIFT), ActionEvent::CycleLayout),
+            ((24, ModMask::N1), ActionEvent::Spawn("xterm")),
+        ];
+        let conflicts = validate_bindings(&mappings);
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!((conflict.keycode, conflict.modifiers), (24, ModMask::N1));
+        assert!(matches!(conflict.first, ActionEvent::Kill));
+        assert!(matches!(conflict.second, ActionEvent::Spawn("xterm")));
+    }
+
+    #[test]
+    fn validate_bindings_accepts_distinct_bindings() {
+        let mappings = [
+            ((24, ModMask::N1), ActionEvent::Kill),
+            ((25, ModMask::N1), ActionEvent::Kill),
+            ((24, ModMask::N4), ActionEvent::Kill),
+        ];
+        assert!(validate_bindings(&mappings).is_empty());
+    }
+
+    #[test]
+    fn grab_delta_only_touches_changed_grabs() {
+        let active = HashSet::from([(24, ModMask::N4), (36, ModMask::N4), (10, ModMask::N1)]);
+        let wanted = HashSet::from([(36, ModMask::N4), (24, ModMask::N4 | ModMask::SHIFT)]);
+        assert_eq!(
+            grab_delta(&active, &wanted),
+            GrabDelta {
+                ungrab: vec![(10, ModMask::N1), (24, ModMask::N4)],
+                grab: vec![(24, ModMask::N4 | ModMask::SHIFT)],
+            }
+        );
+    }
+
+    #[test]
+    fn grab_delta_of_identical_sets_is_empty() {
+        let keys = HashSet::from([(24, ModMask::N4), (36, ModMask::N4)]);
+        assert_eq!(grab_delta(&keys, &keys), GrabDelta::default());
+        assert_eq!(
+            grab_delta(&HashSet::new(), &keys).grab,
+            vec![(24, ModMask::N4), (36, ModMask::N4)]
+        );
+        assert_eq!(
+            grab_delta(&keys, &HashSet::new()).ungrab,
+            vec![(24, ModMask::N4), (36, ModMask::N4)]
+        );
+    }
+
+    #[test]
+    fn parse_modifiers_combines_names() {
+        assert_eq!(
+            parse_modifiers(&["Super", "Shift"]),
+            ModMask::N4 | ModMask::SHIFT
+        );
+        assert_eq!(
+            parse_modifiers(&["alt", "Ctrl", "Hyper"]),
+            ModMask::N1 | ModMask::CONTROL | ModMask::N3
+        );
+    }
+
+    #[test]
+    fn parse_modifiers_skips_unknown_names() {
+        assert_eq!(parse_modifier("Meta"), None);
+        assert_eq!(parse_modifiers(&["Meta", "Shift"]), ModMask::SHIFT);
+        assert_eq!(parse_modifiers(&[]), ModMask::empty());
+    }
+
+    #[test]
+    fn keysym_for_keycode_uses_first_column() {
+        // Two keysyms per keycode, keycodes start at 8.
+        let keysyms = [
+            Keysym::a.raw(),
+            Keysym::A.raw(),
+            Keysym::Return.raw(),
+            0,
+            0,
+            0,
+        ];
+        assert_eq!(keysym_for_keycode(&keysyms, 2, 8, 8), Some(Keysym::a));
+        assert_eq!(keysym_for_keycode(&keysyms, 2, 8, 9), Some(Keysym::Return));
+        assert_eq!(keysym_for_keycode(&keysyms, 2, 8, 10), None);
+        assert_eq!(keysym_for_keycode(&keysyms, 2, 8, 7), None);
+        assert_eq!(keysym_for_keycode(&keysyms, 2, 8, 50), None);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::layout::{Direction, Layout, Rect, Split, pad};
+
+/// Balanced binary space partition: each new window halves the largest region
+/// so far, across its longer side, so every window ends up with about the same
+/// area. Weights are ignored.
+///
+/// A window with a preselected split instead takes the given side of the cell
+/// of the window it was split from, leaving every other window where it was.
+pub struct BspLayout;
+
+/// Halves `cell` into its left and right (`across_width`) or top and bottom parts.
+fn halve(cell: Rect, across_width: bool) -> (Rect, Rect) {
+    if across_width {
+        let left_w = cell.w / 2;
+        (
+            Rect { w: left_w, ..cell },
+            Rect {
+                x: cell.x + left_w as i32,
+                w: cell.w - left_w,
+                ..cell
+            },
+        )
+    } else {
+        let top_h = cell.h / 2;
+        (
+            Rect { h: top_h, ..cell },
+            Rect {
+                y: cell.y + top_h as i32,
+                h: cell.h - top_h,
+                ..cell
+            },
+        )
+    }
+}
+
+/// Index of the largest cell, the first of several equally large ones.
+fn largest(cells: &[Rect]) -> usize {
+    cells
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, cell)| u64::from(cell.w) * u64::from(cell.h))
+        .map_or(0, |(index, _)| index)
+}
+
+impl BspLayout {
+    /// Cells for `count` windows without preselected splits.
+    fn balanced_cells(full: Rect, count: usize) -> Vec<Rect> {
+        let mut cells = vec![full];
+        // The first of several equally large cells is split, and the halves
+        // take its place in order, so windows read left to right, top to bottom.
+        while cells.len() < count {
+            let index = largest(&cells);
+            let cell = cells[index];
+            let (first, second) = halve(cell, cell.w >= cell.h);
+            cells[index] = first;
+            cells.insert(index + 1, second);
+        }
+        cells
+    }
+
+    fn full_cell(area: Rect, window_gap: u32) -> Rect {
+        Rect {
+            x: window_gap as i32,
+            y: window_gap as i32,
+            w: area.w - window_gap,
+            h: area.h - window_gap,
+        }
+    }
+
+    fn padded(cells: Vec<Rect>, border_width: u32, window_gap: u32) -> Vec<Rect> {
+        let total_border = border_width + (window_gap / 2);
+        cells
+            .into_iter()
+            .map(|cell| Rect {
+                w: pad(cell.w, total_border),
+                h: pad(cell.h, total_border),
+                ..cell
+            })
+            .collect()
+    }
+}
+
+impl Layout for BspLayout {
+    fn generate_layout(
+        &self,
+        area: Rect,
+        weights: &[u32],
+        border_width: u32,
+        window_gap: u32,
+        _nmaster: usize,
+    ) -> Vec<Rect> {
+        if weights.is_empty() {
+            return vec![];
+        }
+
+        let cells = Self::balanced_cells(Self::full_cell(area, window_gap), weights.len());
+        Self::padded(cells, border_width, window_gap)
+    }
+
+    fn generate_split_layout(
+        &self,
+        area: Rect,
+        weights: &[u32],
+        splits: &[Split],
+        border_width: u32,
+        window_gap: u32,
+        nmaster: usize,
+    ) -> Vec<Rect> {
+        let Some(first_split) = splits
+            .iter()
+            .take(weights.len())
+            .position(|split| split.is_some())
+        else {
+            return self.generate_layout(area, weights, border_width, window_gap, nmaster);
+        };
+
+        // Windows before the first preselection keep their balanced cells; each
+        // later one is carved out of an earlier window's cell.
+        let mut cells = Self::balanced_cells(Self::full_cell(area, window_gap), first_split.max(1));
+        for index in cells.len()..weights.len() {
+            let (target, (kept, taken)) = match splits.get(index).copied().flatten() {
+                Some((parent, direction)) if parent < index => {
+                    let cell = cells[parent];
+                    let across_width = matches!(direction, Direction::Left | Direction::Right);
+                    let (first, second) = halve(cell, across_width);
+                    let halves = match direction {
+                        Direction::Left | Direction::Up => (second, first),
+                        Direction::Right | Direction::Down => (first, second),
+                    };
+                    (parent, halves)
+                }
+                _ => {
+                    let target = largest(&cells);
+                    let cell = cells[target];
+                    (target, halve(cell, cell.w >= cell.h))
+                }
+            };
+            cells[target] = kept;
+            cells.push(taken);
+        }
+        Self::padded(cells, border_width, window_gap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(w: u32, h: u32) -> Rect {
+        Rect { x: 0, y: 0, w, h }
+    }
+
+    fn assert_no_overlap(rects: &[Rect]) {
+        for i in 0..rects.len() {
+            for j in (i + 1)..rects.len() {
+                let a = &rects[i];
+                let b = &rects[j];
+                let no_overlap = a.x + a.w as i32 <= b.x
+                    || b.x + b.w as i32 <= a.x
+                    || a.y + a.h as i32 <= b.y
+                    || b.y + b.h as i32 <= a.y;
+                assert!(
+                    no_overlap,
+                    "window {} ({:?}) overlaps window {} ({:?})",
+                    i, a, j, b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn empty_weights_returns_empty_vec() {
+        assert!(
+            BspLayout
+                .generate_layout(area(1000, 800), &[], 0, 0, 1)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn single_window_fills_area() {
+        let rects = BspLayout.generate_layout(area(1000, 800), &[1], 0, 0, 1);
+        assert_eq!(
+            (rects[0].x, rects[0].y, rects[0].w, rects[0].h),
+            (0, 0, 1000, 800)
+        );
+    }
+
+    #[test]
+    fn four_windows_have_equal_areas_and_do_not_overlap() {
+        let rects = BspLayout.generate_layout(area(1920, 1080), &[1, 1, 1, 1], 2, 6, 1);
+        assert_eq!(rects.len(), 4);
+        assert_no_overlap(&rects);
+
+        let areas: Vec<u64> = rects.iter().map(|r| r.w as u64 * r.h as u64).collect();
+        let (min, max) = (*areas.iter().min().unwrap(), *areas.iter().max().unwrap());
+        assert!(
+            max - min <= max / 100,
+            "areas {areas:?} should be within 1% of each other"
+        );
+    }
+
+    #[test]
+    fn four_windows_form_a_grid() {
+        let rects = BspLayout.generate_layout(area(1000, 800), &[1, 1, 1, 1], 0, 0, 1);
+        let cells: Vec<_> = rects.iter().map(|r| (r.x, r.y, r.w, r.h)).collect();
+        assert_eq!(
+            cells,
+            vec![
+                (0, 0, 500, 400),
+                (0, 400, 500, 400),
+                (500, 0, 500, 400),
+                (500, 400, 500, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn largest_region_is_split_next() {
+        // After the first split both halves are 500x800; the left one is split
+        // across its height, leaving the right half as the largest window.
+        let rects = BspLayout.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0, 1);
+        let cells: Vec<_> = rects.iter().map(|r| (r.x, r.y, r.w, r.h)).collect();
+        assert_eq!(
+            cells,
+            vec![(0, 0, 500, 400), (0, 400, 500, 400), (500, 0, 500, 800)]
+        );
+    }
+
+    #[test]
+    fn preselected_window_takes_its_side_of_the_parent_cell() {
+        let rects = BspLayout.generate_split_layout(
+            area(1000, 800),
+            &[1, 1, 1],
+            &[None, None, Some((1, Direction::Up))],
+            0,
+            0,
+            1,
+        );
+        let cells: Vec<_> = rects.iter().map(|r| (r.x, r.y, r.w, r.h)).collect();
+        assert_eq!(
+            cells,
+            vec![(0, 0, 500, 800), (500, 400, 500, 400), (500, 0, 500, 400)]
+        );
+
+        let rects = BspLayout.generate_split_layout(
+            area(1000, 800),
+            &[1, 1],
+            &[None, Some((0, Direction::Left))],
+            0,
+            0,
+            1,
+        );
+        let cells: Vec<_> = rects.iter().map(|r| (r.x, r.y, r.w, r.h)).collect();
+        assert_eq!(cells, vec![(500, 0, 500, 800), (0, 0, 500, 800)]);
+    }
+
+    #[test]
+    fn split_pointing_at_a_later_window_falls_back_to_balanced() {
+        let rects = BspLayout.generate_split_layout(
+            area(1000, 800),
+            &[1, 1, 1],
+            &[None, Some((2, Direction::Down)), None],
+            0,
+            0,
+            1,
+        );
+        assert_eq!(rects.len(), 3);
+        assert_no_overlap(&rects);
+        let total: u64 = rects.iter().map(|r| r.w as u64 * r.h as u64).sum();
+        assert_eq!(total, 1000 * 800);
+    }
+
+    #[test]
+    fn many_windows_stay_within_bounds_without_overlap() {
+        let a = area(1600, 900);
+        for n in 1..=9 {
+            let rects = BspLayout.generate_layout(a, &vec![1; n], 1, 4, 1);
+            assert_eq!(rects.len(), n);
+            assert_no_overlap(&rects);
+            for r in &rects {
+                assert!(r.x >= 0 && r.y >= 0);
+                assert!(r.x as u32 + r.w <= a.w && r.y as u32 + r.h <= a.h);
+            }
+        }
+    }
+}
@@ -1,6 +1,17 @@
-use crate::layout::{Layout, Rect, pad};
+use crate::{
+    layout::{Layout, Rect, pad},
+    options::TileDirection,
+};
 
-pub struct HorizontalLayout;
+pub struct HorizontalLayout {
+    direction: TileDirection,
+}
+
+impl HorizontalLayout {
+    pub const fn new(direction: TileDirection) -> Self {
+        Self { direction }
+    }
+}
 
 impl Layout for HorizontalLayout {
     fn generate_layout(
@@ -9,19 +20,22 @@ impl Layout for HorizontalLayout {
         weights: &[u32],
         border_width: u32,
         window_gap: u32,
+        _nmaster: usize,
     ) -> Vec<Rect> {
         let total_weights: u32 = weights.iter().sum();
         let total_border = border_width + window_gap;
         let inner_h = pad(area.h, total_border);
-        let partitions = area.w / total_weights;
-
         let mut cumulative = 0u32;
         let layout: Vec<Rect> = weights
             .iter()
             .map(|weight| {
                 let cell = (area.w * weight) / total_weights;
                 let inner_w = pad(cell, total_border);
-                let x = cumulative * partitions + window_gap;
+                let start = (area.w * cumulative) / total_weights;
+                let x = match self.direction {
+                    TileDirection::LeftToRight => start,
+                    TileDirection::RightToLeft => area.w - start - cell,
+                } + window_gap;
                 cumulative += weight;
                 Rect {
                     x: x as i32,
@@ -40,6 +54,9 @@ mod tests {
     use super::*;
     use crate::layout::Rect;
 
+    const LTR: HorizontalLayout = HorizontalLayout::new(TileDirection::LeftToRight);
+    const RTL: HorizontalLayout = HorizontalLayout::new(TileDirection::RightToLeft);
+
     fn area(w: u32, h: u32) -> Rect {
         Rect { x: 0, y: 0, w, h }
     }
@@ -48,7 +65,7 @@ mod tests {
 
     #[test]
     fn single_window_no_border_no_gap() {
-        let rects = HorizontalLayout.generate_layout(area(1000, 800), &[1], 0, 0);
+        let rects = LTR.generate_layout(area(1000, 800), &[1], 0, 0, 1);
         assert_eq!(rects.len(), 1);
         assert_eq!(rects[0].x, 0);
         assert_eq!(rects[0].y, 0);
@@ -63,7 +80,7 @@ mod tests {
         // cell = (1000 * 1) / 1 = 1000
         // inner_w = pad(1000, 6) = 1000 - 12 = 988
         // x = 0 * 1000 + 4 = 4
-        let rects = HorizontalLayout.generate_layout(area(1000, 800), &[1], 2, 4);
+        let rects = LTR.generate_layout(area(1000, 800), &[1], 2, 4, 1);
         assert_eq!(rects.len(), 1);
         assert_eq!(rects[0].x, 4);
         assert_eq!(rects[0].y, 4);
@@ -78,7 +95,7 @@ mod tests {
         // total_weights = 2, partitions = 1000/2 = 500
         // Window 0: cell=500, inner_w=500, x=0*500+0=0
         // Window 1: cell=500, inner_w=500, x=1*500+0=500
-        let rects = HorizontalLayout.generate_layout(area(1000, 800), &[1, 1], 0, 0);
+        let rects = LTR.generate_layout(area(1000, 800), &[1, 1], 0, 0, 1);
         assert_eq!(rects.len(), 2);
 
         assert_eq!(rects[0].x, 0);
@@ -99,7 +116,7 @@ mod tests {
         // Window 0: cell = 300, x = 0
         // Window 1: cell = 300, x = 1*300 = 300
         // Window 2: cell = 300, x = 2*300 = 600
-        let rects = HorizontalLayout.generate_layout(area(900, 600), &[1, 1, 1], 0, 0);
+        let rects = LTR.generate_layout(area(900, 600), &[1, 1, 1], 0, 0, 1);
         assert_eq!(rects.len(), 3);
 
         assert_eq!(rects[0].x, 0);
@@ -125,7 +142,7 @@ mod tests {
         // total_weights = 3, partitions = 900/3 = 300
         // Window 0: weight=2, cell=(900*2)/3=600, inner_w=600, x=0*300+0=0
         // Window 1: weight=1, cell=(900*1)/3=300, inner_w=300, x=2*300+0=600
-        let rects = HorizontalLayout.generate_layout(area(900, 600), &[2, 1], 0, 0);
+        let rects = LTR.generate_layout(area(900, 600), &[2, 1], 0, 0, 1);
         assert_eq!(rects.len(), 2);
 
         assert_eq!(rects[0].x, 0);
@@ -141,7 +158,7 @@ mod tests {
         // Window 0: weight=1, cell=250, x = 0*250 = 0
         // Window 1: weight=2, cell=500, x = 1*250 = 250
         // Window 2: weight=1, cell=250, x = 3*250 = 750
-        let rects = HorizontalLayout.generate_layout(area(1000, 600), &[1, 2, 1], 0, 0);
+        let rects = LTR.generate_layout(area(1000, 600), &[1, 2, 1], 0, 0, 1);
         assert_eq!(rects.len(), 3);
 
         assert_eq!(rects[0].x, 0);
@@ -159,7 +176,7 @@ mod tests {
         // total_weights = 4, partitions = 800/4 = 200
         // Window 0: weight=1, cell=(800*1)/4=200, x=0*200=0
         // Window 1: weight=3, cell=(800*3)/4=600, x=1*200=200
-        let rects = HorizontalLayout.generate_layout(area(800, 400), &[1, 3], 0, 0);
+        let rects = LTR.generate_layout(area(800, 400), &[1, 3], 0, 0, 1);
         assert_eq!(rects.len(), 2);
 
         assert_eq!(rects[0].x, 0);
@@ -178,7 +195,7 @@ mod tests {
         // total_weights = 2, partitions = 1000/2 = 500
         // Window 0: cell=500, inner_w=pad(500,10)=480, x=0*500+10=10
         // Window 1: cell=500, inner_w=480, x=1*500+10=510
-        let rects = HorizontalLayout.generate_layout(area(1000, 800), &[1, 1], 0, 10);
+        let rects = LTR.generate_layout(area(1000, 800), &[1, 1], 0, 10, 1);
         assert_eq!(rects.len(), 2);
 
         assert_eq!(rects[0].x, 10);
@@ -199,7 +216,7 @@ mod tests {
         // total_weights = 2, partitions = 1000/2 = 500
         // Window 0: cell=500, inner_w=pad(500,5)=490, x=0+0=0
         // Window 1: cell=500, inner_w=490, x=500+0=500
-        let rects = HorizontalLayout.generate_layout(area(1000, 600), &[1, 1], 5, 0);
+        let rects = LTR.generate_layout(area(1000, 600), &[1, 1], 5, 0, 1);
         assert_eq!(rects.len(), 2);
 
         assert_eq!(rects[0].x, 0);
@@ -220,7 +237,7 @@ mod tests {
         // Window 0: cell=300, inner_w=pad(300,6)=288, x=0*300+4=4
         // Window 1: cell=300, inner_w=288, x=1*300+4=304
         // Window 2: cell=300, inner_w=288, x=2*300+4=604
-        let rects = HorizontalLayout.generate_layout(area(900, 600), &[1, 1, 1], 2, 4);
+        let rects = LTR.generate_layout(area(900, 600), &[1, 1, 1], 2, 4, 1);
         assert_eq!(rects.len(), 3);
 
         assert_eq!(rects[0].x, 4);
@@ -239,7 +256,7 @@ mod tests {
 
     #[test]
     fn five_equal_windows() {
-        let rects = HorizontalLayout.generate_layout(area(1000, 500), &[1, 1, 1, 1, 1], 0, 0);
+        let rects = LTR.generate_layout(area(1000, 500), &[1, 1, 1, 1, 1], 0, 0, 1);
         assert_eq!(rects.len(), 5);
 
         // partitions = 1000/5 = 200, each cell = 200
@@ -255,7 +272,7 @@ mod tests {
 
     #[test]
     fn all_windows_share_same_y() {
-        let rects = HorizontalLayout.generate_layout(area(1200, 700), &[1, 2, 3, 1], 3, 6);
+        let rects = LTR.generate_layout(area(1200, 700), &[1, 2, 3, 1], 3, 6, 1);
         for r in &rects {
             assert_eq!(r.y, 6);
         }
@@ -263,7 +280,7 @@ mod tests {
 
     #[test]
     fn all_windows_share_same_height() {
-        let rects = HorizontalLayout.generate_layout(area(1200, 700), &[1, 2, 3, 1], 3, 6);
+        let rects = LTR.generate_layout(area(1200, 700), &[1, 2, 3, 1], 3, 6, 1);
         // total_border = 3 + 6 = 9, inner_h = pad(700, 9) = 700 - 18 = 682
         let expected_h = 682;
         for r in &rects {
@@ -275,7 +292,7 @@ mod tests {
 
     #[test]
     fn x_positions_are_increasing() {
-        let rects = HorizontalLayout.generate_layout(area(1600, 900), &[1, 1, 1, 1], 2, 8);
+        let rects = LTR.generate_layout(area(1600, 900), &[1, 1, 1, 1], 2, 8, 1);
         for i in 1..rects.len() {
             assert!(
                 rects[i].x > rects[i - 1].x,
@@ -293,8 +310,8 @@ mod tests {
     #[test]
     fn large_weight_values() {
         // weights = [100, 100], should behave like [1, 1]
-        let rects_big = HorizontalLayout.generate_layout(area(1000, 800), &[100, 100], 0, 0);
-        let rects_small = HorizontalLayout.generate_layout(area(1000, 800), &[1, 1], 0, 0);
+        let rects_big = LTR.generate_layout(area(1000, 800), &[100, 100], 0, 0, 1);
+        let rects_small = LTR.generate_layout(area(1000, 800), &[1, 1], 0, 0, 1);
 
         assert_eq!(rects_big.len(), rects_small.len());
         for (a, b) in rects_big.iter().zip(rects_small.iter()) {
@@ -305,6 +322,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn default_weights_of_ten_split_like_unit_weights() {
+        // area.w is not a multiple of total_weights = 30, so offsets must be
+        // computed from the cumulative weight rather than a truncated partition.
+        let rects_ten = LTR.generate_layout(area(1000, 800), &[10, 10, 10], 0, 0, 1);
+        let rects_one = LTR.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0, 1);
+
+        for (a, b) in rects_ten.iter().zip(rects_one.iter()) {
+            assert_eq!(a.x, b.x);
+            assert_eq!(a.w, b.w);
+        }
+        assert_eq!(rects_ten[1].x, 333);
+        assert_eq!(rects_ten[2].x, 666);
+    }
+
+    #[test]
+    fn single_step_nudge_from_default_weight_is_about_ten_percent() {
+        let even = LTR.generate_layout(area(1000, 800), &[10, 10], 0, 0, 1);
+        let nudged = LTR.generate_layout(area(1000, 800), &[11, 10], 0, 0, 1);
+
+        // 11/21 of the width instead of 10/20: the window grows by ~5% of the
+        // screen, i.e. ~10% of its own width.
+        assert_eq!(even[0].w, 500);
+        assert_eq!(nudged[0].w, 523);
+        assert_eq!(nudged[1].x, 523);
+    }
+
     // ── pad clamp edge case (very small cell) ───────────────────────
 
     #[test]
@@ -312,7 +356,7 @@ mod tests {
         // area.w=20, weights=[1], border_width=5, window_gap=5
         // total_border = 10, cell = 20, inner_w = pad(20, 10) = 0 → 1
         // inner_h = pad(20, 10) = 0 → 1
-        let rects = HorizontalLayout.generate_layout(area(20, 20), &[1], 5, 5);
+        let rects = LTR.generate_layout(area(20, 20), &[1], 5, 5, 1);
         assert_eq!(rects.len(), 1);
         assert_eq!(rects[0].w, 1);
         assert_eq!(rects[0].h, 1);
@@ -328,7 +372,7 @@ mod tests {
             w: 800,
             h: 600,
         };
-        let rects = HorizontalLayout.generate_layout(shifted_area, &[1, 1], 0, 0);
+        let rects = LTR.generate_layout(shifted_area, &[1, 1], 0, 0, 1);
         assert_eq!(rects.len(), 2);
         // The layout uses area.w and area.h, not area.x/area.y for sizing
         assert_eq!(rects[0].w, 400);
@@ -340,7 +384,7 @@ mod tests {
 
     #[test]
     fn heavier_weight_gets_wider_window() {
-        let rects = HorizontalLayout.generate_layout(area(1000, 500), &[1, 3], 0, 0);
+        let rects = LTR.generate_layout(area(1000, 500), &[1, 3], 0, 0, 1);
         assert!(
             rects[1].w > rects[0].w,
             "window with weight 3 (w={}) should be wider than weight 1 (w={})",
@@ -351,16 +395,46 @@ mod tests {
 
     #[test]
     fn equal_weights_produce_equal_widths() {
-        let rects = HorizontalLayout.generate_layout(area(900, 600), &[2, 2, 2], 0, 0);
+        let rects = LTR.generate_layout(area(900, 600), &[2, 2, 2], 0, 0, 1);
         assert_eq!(rects[0].w, rects[1].w);
         assert_eq!(rects[1].w, rects[2].w);
     }
 
-    // ── empty weights panics (division by zero) ─────────────────────
+    // ── right-to-left direction ─────────────────────────────────────
+
+    #[test]
+    fn rtl_places_first_window_at_right_edge() {
+        let rects = RTL.generate_layout(area(1000, 800), &[1, 1], 0, 0, 1);
+        assert_eq!(rects[0].x, 500);
+        assert_eq!(rects[0].x as u32 + rects[0].w, 1000);
+        assert_eq!(rects[1].x, 0);
+    }
+
+    #[test]
+    fn rtl_places_later_windows_to_the_left() {
+        let rects = RTL.generate_layout(area(900, 600), &[1, 2, 3], 0, 0, 1);
+        assert_eq!((rects[0].x, rects[0].w), (750, 150));
+        assert_eq!((rects[1].x, rects[1].w), (450, 300));
+        assert_eq!((rects[2].x, rects[2].w), (0, 450));
+    }
+
+    #[test]
+    fn rtl_mirrors_ltr_with_border_and_gap() {
+        let ltr = LTR.generate_layout(area(1000, 600), &[2, 1, 1], 2, 4, 1);
+        let rtl = RTL.generate_layout(area(1000, 600), &[2, 1, 1], 2, 4, 1);
+        for (l, r) in ltr.iter().zip(rtl.iter()) {
+            assert_eq!(r.w, l.w);
+            assert_eq!((r.y, r.h), (l.y, l.h));
+            // Each window, borders included, is reflected about the centre line.
+            assert_eq!(r.x + (r.w + 2 * 2) as i32 + l.x, 1000);
+        }
+    }
+
+    // ── empty weights ───────────────────────────────────────────────
 
     #[test]
-    #[should_panic]
-    fn empty_weights_panics() {
-        HorizontalLayout.generate_layout(area(1000, 800), &[], 0, 0);
+    fn empty_weights_returns_empty_vec() {
+        let rects = LTR.generate_layout(area(1000, 800), &[], 0, 0, 1);
+        assert!(rects.is_empty());
     }
 }
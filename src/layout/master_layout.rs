@@ -9,49 +9,89 @@ impl Layout for MasterLayout {
         weights: &[u32],
         border_width: u32,
         window_gap: u32,
+        nmaster: usize,
     ) -> Vec<Rect> {
+        if weights.is_empty() {
+            return vec![];
+        }
+
+        let nmaster = nmaster.clamp(1, weights.len());
         let total_border = border_width + (window_gap / 2);
-        let mut prev_x: u32 = window_gap;
-        let mut prev_y: u32 = window_gap;
-        let mut prev_h: u32 = area.h - window_gap;
-        let mut prev_w: u32 = area.w - window_gap;
-        let layout: Vec<Rect> = weights
-            .iter()
-            .enumerate()
-            .map(|(i, _weight)| {
-                if weights.len() - 1 == i {
-                    Rect {
-                        x: prev_x as i32,
-                        y: prev_y as i32,
-                        w: pad(prev_w, total_border),
-                        h: pad(prev_h, total_border),
-                    }
-                } else if i % 2 == 0 {
-                    let inner_w = prev_w / 2;
-                    let rect = Rect {
-                        x: prev_x as i32,
-                        y: prev_y as i32,
-                        w: pad(inner_w, total_border),
-                        h: pad(prev_h, total_border),
-                    };
-                    prev_x += inner_w;
-                    prev_w = inner_w;
-                    rect
+        let full_h = area.h - window_gap;
+        let full_w = area.w - window_gap;
+
+        // The master column takes the whole width when there is no stack.
+        // Otherwise it is sized by the master windows' average weight against
+        // the stack's, so equal weights split the screen in half however long
+        // the stack grows. The column itself is split evenly between the master
+        // windows.
+        let (masters, stack) = weights.split_at(nmaster);
+        let master_w = if stack.is_empty() {
+            full_w
+        } else {
+            let master_sum: u64 = masters.iter().map(|&w| u64::from(w)).sum();
+            let stack_sum: u64 = stack.iter().map(|&w| u64::from(w)).sum();
+            let master_share = master_sum * stack.len() as u64;
+            let stack_share = stack_sum * masters.len() as u64;
+            (u64::from(full_w) * master_share / (master_share + stack_share).max(1)) as u32
+        };
+        let master_cell_h = full_h / nmaster as u32;
+        let mut layout: Vec<Rect> = (0..nmaster)
+            .map(|i| {
+                let y = window_gap + i as u32 * master_cell_h;
+                let h = if i == nmaster - 1 {
+                    full_h - i as u32 * master_cell_h
                 } else {
-                    let inner_h = prev_h / 2;
-                    let rect = Rect {
-                        x: prev_x as i32,
-                        y: prev_y as i32,
-                        w: pad(prev_w, total_border),
-                        h: pad(inner_h, total_border),
-                    };
-                    prev_y += inner_h;
-                    prev_h = inner_h;
-                    rect
+                    master_cell_h
+                };
+                Rect {
+                    x: window_gap as i32,
+                    y: y as i32,
+                    w: pad(master_w, total_border),
+                    h: pad(h, total_border),
                 }
             })
             .collect();
 
+        // The stack dwindles in the right half, alternating vertical and
+        // horizontal splits.
+        let mut prev_x: u32 = window_gap + master_w;
+        let mut prev_y: u32 = window_gap;
+        let mut prev_h: u32 = full_h;
+        let mut prev_w: u32 = full_w - master_w;
+        layout.extend(stack.iter().enumerate().map(|(i, _weight)| {
+            if stack.len() - 1 == i {
+                Rect {
+                    x: prev_x as i32,
+                    y: prev_y as i32,
+                    w: pad(prev_w, total_border),
+                    h: pad(prev_h, total_border),
+                }
+            } else if i % 2 == 0 {
+                let inner_h = prev_h / 2;
+                let rect = Rect {
+                    x: prev_x as i32,
+                    y: prev_y as i32,
+                    w: pad(prev_w, total_border),
+                    h: pad(inner_h, total_border),
+                };
+                prev_y += inner_h;
+                prev_h = inner_h;
+                rect
+            } else {
+                let inner_w = prev_w / 2;
+                let rect = Rect {
+                    x: prev_x as i32,
+                    y: prev_y as i32,
+                    w: pad(inner_w, total_border),
+                    h: pad(prev_h, total_border),
+                };
+                prev_x += inner_w;
+                prev_w = inner_w;
+                rect
+            }
+        }));
+
         layout
     }
 }
@@ -69,13 +109,13 @@ mod tests {
 
     #[test]
     fn empty_weights_returns_empty_vec() {
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[], 0, 0, 1);
         assert!(rects.is_empty());
     }
 
     #[test]
     fn empty_weights_with_border_and_gap() {
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[], 5, 10);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[], 5, 10, 1);
         assert!(rects.is_empty());
     }
 
@@ -86,7 +126,7 @@ mod tests {
         // i=0, last window → takes full remaining space
         // prev_x=0, prev_y=0, prev_w=1000, prev_h=800
         // rect = {x:0, y:0, w:pad(1000,0)=1000, h:pad(800,0)=800}
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 0, 0, 1);
         assert_eq!(rects.len(), 1);
         assert_eq!(rects[0].x, 0);
         assert_eq!(rects[0].y, 0);
@@ -99,7 +139,7 @@ mod tests {
         // total_border = 0 + 10/2 = 5
         // prev_x=10, prev_y=10, prev_w=990, prev_h=790
         // i=0, last: rect = {x:10, y:10, w:pad(990,5)=980, h:pad(790,5)=780}
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 0, 10);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 0, 10, 1);
         assert_eq!(rects.len(), 1);
         assert_eq!(rects[0].x, 10);
         assert_eq!(rects[0].y, 10);
@@ -112,7 +152,7 @@ mod tests {
         // total_border = 3 + 0/2 = 3
         // prev_x=0, prev_y=0, prev_w=1000, prev_h=800
         // i=0, last: rect = {x:0, y:0, w:pad(1000,3)=994, h:pad(800,3)=794}
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 3, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 3, 0, 1);
         assert_eq!(rects.len(), 1);
         assert_eq!(rects[0].x, 0);
         assert_eq!(rects[0].y, 0);
@@ -125,7 +165,7 @@ mod tests {
         // total_border = 2 + 4/2 = 4
         // prev_x=4, prev_y=4, prev_w=896, prev_h=596
         // i=0, last: rect = {x:4, y:4, w:pad(896,4)=888, h:pad(596,4)=588}
-        let rects = MasterLayout.generate_layout(area(900, 600), &[1], 2, 4);
+        let rects = MasterLayout.generate_layout(area(900, 600), &[1], 2, 4, 1);
         assert_eq!(rects.len(), 1);
         assert_eq!(rects[0].x, 4);
         assert_eq!(rects[0].y, 4);
@@ -143,7 +183,7 @@ mod tests {
         //   prev_x=500, prev_w=500
         // i=1, last:
         //   rect={x:500,y:0,w:pad(500,0)=500,h:pad(800,0)=800}
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1], 0, 0, 1);
         assert_eq!(rects.len(), 2);
 
         assert_eq!(rects[0].x, 0);
@@ -166,7 +206,7 @@ mod tests {
         //   prev_x=505, prev_w=495
         // i=1, last:
         //   rect={x:505,y:10,w:pad(495,5)=485,h:pad(790,5)=780}
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1], 0, 10);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1], 0, 10, 1);
         assert_eq!(rects.len(), 2);
 
         assert_eq!(rects[0].x, 10);
@@ -191,7 +231,7 @@ mod tests {
         //   rect={x:500,y:0,w:500,h:400}, prev_y=400, prev_h=400
         // i=2, last:
         //   rect={x:500,y:400,w:500,h:400}
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0, 1);
         assert_eq!(rects.len(), 3);
 
         // Master window takes left half
@@ -225,7 +265,7 @@ mod tests {
         //   prev_y=302, prev_h=298
         // i=2, last:
         //   rect={x:452,y:302,w:pad(448,4)=440,h:pad(298,4)=290}
-        let rects = MasterLayout.generate_layout(area(900, 600), &[1, 1, 1], 2, 4);
+        let rects = MasterLayout.generate_layout(area(900, 600), &[1, 1, 1], 2, 4, 1);
         assert_eq!(rects.len(), 3);
 
         assert_eq!(rects[0].x, 4);
@@ -257,7 +297,7 @@ mod tests {
         //   rect={x:500,y:400,w:250,h:400}, prev_x=750, prev_w=250
         // i=3, last:
         //   rect={x:750,y:400,w:250,h:400}
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1, 1], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1, 1], 0, 0, 1);
         assert_eq!(rects.len(), 4);
 
         assert_eq!(rects[0].x, 0);
@@ -296,7 +336,7 @@ mod tests {
         //   rect={x:750,y:400,w:250,h:200}, prev_y=600, prev_h=200
         // i=4, last:
         //   rect={x:750,y:600,w:250,h:200}
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1, 1, 1], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1, 1, 1], 0, 0, 1);
         assert_eq!(rects.len(), 5);
 
         assert_eq!(rects[0].x, 0);
@@ -329,7 +369,7 @@ mod tests {
 
     #[test]
     fn master_window_has_largest_area() {
-        let rects = MasterLayout.generate_layout(area(1200, 800), &[1, 1, 1, 1], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1200, 800), &[1, 1, 1, 1], 0, 0, 1);
         let master_area = rects[0].w as u64 * rects[0].h as u64;
         for r in &rects[1..] {
             let window_area = r.w as u64 * r.h as u64;
@@ -342,15 +382,15 @@ mod tests {
         }
     }
 
-    // ── weights are ignored by MasterLayout ─────────────────────────
+    // ── weights size the master column ──────────────────────────────
 
     #[test]
-    fn weights_values_are_ignored() {
-        let rects_ones = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0);
-        let rects_mixed = MasterLayout.generate_layout(area(1000, 800), &[5, 10, 2], 0, 0);
+    fn uniformly_scaled_weights_give_same_layout() {
+        let rects_ones = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0, 1);
+        let rects_fives = MasterLayout.generate_layout(area(1000, 800), &[5, 5, 5], 0, 0, 1);
 
-        assert_eq!(rects_ones.len(), rects_mixed.len());
-        for (a, b) in rects_ones.iter().zip(rects_mixed.iter()) {
+        assert_eq!(rects_ones.len(), rects_fives.len());
+        for (a, b) in rects_ones.iter().zip(rects_fives.iter()) {
             assert_eq!(a.x, b.x);
             assert_eq!(a.y, b.y);
             assert_eq!(a.w, b.w);
@@ -358,11 +398,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn master_weight_widens_master_column() {
+        // master 3 vs stack 1 → master takes 3/4 of the width
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[3, 1], 0, 0, 1);
+        assert_eq!(
+            (rects[0].x, rects[0].y, rects[0].w, rects[0].h),
+            (0, 0, 750, 800)
+        );
+        assert_eq!(
+            (rects[1].x, rects[1].y, rects[1].w, rects[1].h),
+            (750, 0, 250, 800)
+        );
+    }
+
+    #[test]
+    fn master_weight_is_compared_to_average_stack_weight() {
+        // master 2 vs stack average (1+1)/2 = 1 → master takes 2/3 of the width
+        let rects = MasterLayout.generate_layout(area(900, 800), &[2, 1, 1], 0, 0, 1);
+        assert_eq!(rects[0].w, 600);
+        assert_eq!(rects[1].x, 600);
+        assert_eq!(rects[1].w, 300);
+        assert_eq!(rects[2].w, 300);
+    }
+
+    #[test]
+    fn equal_weights_split_in_half_whatever_the_stack_size() {
+        // Against the stack's total, master would shrink to 1/4 here.
+        let rects = MasterLayout.generate_layout(area(800, 800), &[1, 1, 1, 1], 0, 0, 1);
+        assert_eq!(rects[0].w, 400);
+        assert_eq!(rects[1].x, 400);
+    }
+
     // ── no overlapping windows ──────────────────────────────────────
 
     #[test]
     fn windows_do_not_overlap_three() {
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0, 1);
         for i in 0..rects.len() {
             for j in (i + 1)..rects.len() {
                 let a = &rects[i];
@@ -382,7 +454,7 @@ mod tests {
 
     #[test]
     fn windows_do_not_overlap_five() {
-        let rects = MasterLayout.generate_layout(area(1600, 900), &[1, 1, 1, 1, 1], 2, 6);
+        let rects = MasterLayout.generate_layout(area(1600, 900), &[1, 1, 1, 1, 1], 2, 6, 1);
         for i in 0..rects.len() {
             for j in (i + 1)..rects.len() {
                 let a = &rects[i];
@@ -405,7 +477,7 @@ mod tests {
     #[test]
     fn all_windows_within_bounds_no_gap() {
         let a = area(1000, 800);
-        let rects = MasterLayout.generate_layout(a, &[1, 1, 1, 1], 0, 0);
+        let rects = MasterLayout.generate_layout(a, &[1, 1, 1, 1], 0, 0, 1);
         for (i, r) in rects.iter().enumerate() {
             assert!(r.x >= 0, "window {} x={} out of bounds", i, r.x);
             assert!(r.y >= 0, "window {} y={} out of bounds", i, r.y);
@@ -430,14 +502,14 @@ mod tests {
 
     #[test]
     fn gap_offsets_first_window() {
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 0, 20);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 0, 20, 1);
         assert_eq!(rects[0].x, 20);
         assert_eq!(rects[0].y, 20);
     }
 
     #[test]
     fn gap_zero_no_offset() {
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 0, 0, 1);
         assert_eq!(rects[0].x, 0);
         assert_eq!(rects[0].y, 0);
     }
@@ -446,8 +518,8 @@ mod tests {
 
     #[test]
     fn border_reduces_dimensions() {
-        let rects_no_border = MasterLayout.generate_layout(area(1000, 800), &[1, 1], 0, 0);
-        let rects_with_border = MasterLayout.generate_layout(area(1000, 800), &[1, 1], 5, 0);
+        let rects_no_border = MasterLayout.generate_layout(area(1000, 800), &[1, 1], 0, 0, 1);
+        let rects_with_border = MasterLayout.generate_layout(area(1000, 800), &[1, 1], 5, 0, 1);
 
         // Same positions (no gap change), but smaller dimensions
         assert_eq!(rects_no_border[0].x, rects_with_border[0].x);
@@ -462,7 +534,7 @@ mod tests {
         // total_border = 0 + 7/2 = 3 (integer division)
         // prev_x=7, prev_y=7, prev_w=993, prev_h=793
         // i=0, last: rect={x:7,y:7,w:pad(993,3)=987,h:pad(793,3)=787}
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 0, 7);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1], 0, 7, 1);
         assert_eq!(rects.len(), 1);
         assert_eq!(rects[0].x, 7);
         assert_eq!(rects[0].y, 7);
@@ -478,7 +550,7 @@ mod tests {
         // total_border = 4 + 2 = 6
         // prev_x=4, prev_y=4, prev_w=16, prev_h=16
         // i=0, last: rect={x:4,y:4,w:pad(16,6)=4,h:pad(16,6)=4}
-        let rects = MasterLayout.generate_layout(area(20, 20), &[1], 4, 4);
+        let rects = MasterLayout.generate_layout(area(20, 20), &[1], 4, 4, 1);
         assert_eq!(rects.len(), 1);
         assert_eq!(rects[0].x, 4);
         assert_eq!(rects[0].y, 4);
@@ -492,7 +564,7 @@ mod tests {
         // total_border = 3 + 2 = 5
         // prev_x=4, prev_y=4, prev_w=10, prev_h=10
         // i=0, last: rect={x:4,y:4,w:pad(10,5)=0->1,h:pad(10,5)=0->1}
-        let rects = MasterLayout.generate_layout(area(14, 14), &[1], 3, 4);
+        let rects = MasterLayout.generate_layout(area(14, 14), &[1], 3, 4, 1);
         assert_eq!(rects.len(), 1);
         assert_eq!(rects[0].w, 1);
         assert_eq!(rects[0].h, 1);
@@ -504,7 +576,7 @@ mod tests {
     fn output_count_matches_weight_count() {
         for n in 1..=8 {
             let weights: Vec<u32> = vec![1; n];
-            let rects = MasterLayout.generate_layout(area(2000, 1500), &weights, 2, 4);
+            let rects = MasterLayout.generate_layout(area(2000, 1500), &weights, 2, 4, 1);
             assert_eq!(rects.len(), n, "expected {} rects, got {}", n, rects.len());
         }
     }
@@ -521,8 +593,8 @@ mod tests {
         };
         let origin = area(1000, 800);
 
-        let rects_shifted = MasterLayout.generate_layout(shifted, &[1, 1, 1], 0, 0);
-        let rects_origin = MasterLayout.generate_layout(origin, &[1, 1, 1], 0, 0);
+        let rects_shifted = MasterLayout.generate_layout(shifted, &[1, 1, 1], 0, 0, 1);
+        let rects_origin = MasterLayout.generate_layout(origin, &[1, 1, 1], 0, 0, 1);
 
         // Layout uses area.w and area.h only, not area.x/area.y
         for (a, b) in rects_shifted.iter().zip(rects_origin.iter()) {
@@ -537,7 +609,7 @@ mod tests {
 
     #[test]
     fn regions_shrink_with_more_windows() {
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1, 1, 1], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1, 1, 1], 0, 0, 1);
 
         // Each non-last window splits in half, so areas should not increase
         let areas: Vec<u64> = rects.iter().map(|r| r.w as u64 * r.h as u64).collect();
@@ -559,7 +631,7 @@ mod tests {
     fn even_index_splits_horizontally() {
         // With 3 windows: i=0 (even) does horizontal split
         // Window 0 should occupy the left half of the screen
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0, 1);
         // Window 0 width should be half the total
         assert_eq!(rects[0].w, 500);
         // Window 0 height should be full height
@@ -570,19 +642,77 @@ mod tests {
     fn odd_index_splits_vertically() {
         // With 4 windows: i=1 (odd) does vertical split
         // Window 1 should occupy the top half of the right side
-        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1, 1], 0, 0);
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1, 1], 0, 0, 1);
         // Window 1 height should be half the total
         assert_eq!(rects[1].h, 400);
         // Window 1 width should span the remaining horizontal space
         assert_eq!(rects[1].w, 500);
     }
 
+    // ── multiple master windows ─────────────────────────────────────
+
+    #[test]
+    fn nmaster_two_with_four_windows() {
+        // master_w = 500, master_cell_h = 400
+        // masters: {0,0,500,400}, {0,400,500,400}
+        // stack i=0, vertical split: {500,0,500,400}
+        // stack i=1, last: {500,400,500,400}
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1, 1], 0, 0, 2);
+        assert_eq!(rects.len(), 4);
+
+        // Two master cells share the left column
+        assert_eq!(
+            (rects[0].x, rects[0].y, rects[0].w, rects[0].h),
+            (0, 0, 500, 400)
+        );
+        assert_eq!(
+            (rects[1].x, rects[1].y, rects[1].w, rects[1].h),
+            (0, 400, 500, 400)
+        );
+
+        // Two stack cells share the right column
+        assert_eq!(
+            (rects[2].x, rects[2].y, rects[2].w, rects[2].h),
+            (500, 0, 500, 400)
+        );
+        assert_eq!(
+            (rects[3].x, rects[3].y, rects[3].w, rects[3].h),
+            (500, 400, 500, 400)
+        );
+    }
+
+    #[test]
+    fn nmaster_is_clamped_to_window_count() {
+        // nmaster larger than the window count puts every window in a
+        // full-width master column.
+        let rects = MasterLayout.generate_layout(area(1000, 800), &[1, 1], 0, 0, 5);
+        assert_eq!(rects.len(), 2);
+        assert_eq!(
+            (rects[0].x, rects[0].y, rects[0].w, rects[0].h),
+            (0, 0, 1000, 400)
+        );
+        assert_eq!(
+            (rects[1].x, rects[1].y, rects[1].w, rects[1].h),
+            (0, 400, 1000, 400)
+        );
+    }
+
+    #[test]
+    fn nmaster_zero_behaves_like_one() {
+        let rects_zero = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0, 0);
+        let rects_one = MasterLayout.generate_layout(area(1000, 800), &[1, 1, 1], 0, 0, 1);
+
+        for (a, b) in rects_zero.iter().zip(rects_one.iter()) {
+            assert_eq!((a.x, a.y, a.w, a.h), (b.x, b.y, b.w, b.h));
+        }
+    }
+
     // ── large number of windows ─────────────────────────────────────
 
     #[test]
     fn eight_windows_all_have_positive_dimensions() {
         let weights = vec![1u32; 8];
-        let rects = MasterLayout.generate_layout(area(1920, 1080), &weights, 1, 2);
+        let rects = MasterLayout.generate_layout(area(1920, 1080), &weights, 1, 2, 1);
         assert_eq!(rects.len(), 8);
         for (i, r) in rects.iter().enumerate() {
             assert!(r.w > 0, "window {} has zero width", i);
@@ -2,23 +2,43 @@ use indexmap::IndexMap;
 use log::{debug, error};
 
 use crate::{
-    config::DEFAULT_LAYOUT,
-    layout::{horizontal_layout::HorizontalLayout, master_layout::MasterLayout},
+    config::{DEFAULT_LAYOUT, HORIZONTAL_DIRECTION},
+    layout::{
+        bsp_layout::BspLayout, horizontal_layout::HorizontalLayout, master_layout::MasterLayout,
+    },
 };
 
+pub mod bsp_layout;
 pub mod horizontal_layout;
 pub mod master_layout;
 
 macro_rules! define_layouts {
-    ( $( $variant:ident => $ty:path ),+ $(,)? ) => {
+    ( $( $variant:ident => $layout:expr ),+ $(,)? ) => {
+        // Variant names double as the layout names users see, suffix included.
+        #[allow(clippy::enum_variant_names)]
         #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
         pub enum LayoutType {
             $( $variant ),+
         }
 
+        impl LayoutType {
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $( LayoutType::$variant => stringify!($variant) ),+
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $( stringify!($variant) => Some(LayoutType::$variant), )+
+                    _ => None,
+                }
+            }
+        }
+
         fn build_layout_map() -> IndexMap<LayoutType, Box<dyn Layout>> {
             let mut map: IndexMap<LayoutType, Box<dyn Layout>> = IndexMap::default();
-            $( map.insert(LayoutType::$variant, Box::new($ty)); )+
+            $( map.insert(LayoutType::$variant, Box::new($layout)); )+
             map
         }
     };
@@ -26,8 +46,9 @@ macro_rules! define_layouts {
 
 // DEFINE LAYOUTS HERE
 define_layouts! {
-    HorizontalLayout => HorizontalLayout,
+    HorizontalLayout => HorizontalLayout::new(HORIZONTAL_DIRECTION),
     MasterLayout => MasterLayout,
+    BspLayout => BspLayout,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -38,6 +59,238 @@ pub struct Rect {
     pub h: u32,
 }
 
+impl Rect {
+    const fn center(&self) -> (i32, i32) {
+        (self.x + (self.w / 2) as i32, self.y + (self.h / 2) as i32)
+    }
+
+    /// Swaps the axes: x with y and width with height.
+    pub const fn transposed(&self) -> Rect {
+        Rect {
+            x: self.y,
+            y: self.x,
+            w: self.h,
+            h: self.w,
+        }
+    }
+
+    pub const fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.w as i32 && y >= self.y && y < self.y + self.h as i32
+    }
+
+    const fn overlaps_horizontally(&self, other: &Rect) -> bool {
+        self.x < other.x + other.w as i32 && other.x < self.x + self.w as i32
+    }
+
+    const fn overlaps_vertically(&self, other: &Rect) -> bool {
+        self.y < other.y + other.h as i32 && other.y < self.y + self.h as i32
+    }
+}
+
+/// Shrinks `area` so it no longer overlaps any reserved region, treating each
+/// region as a strut on whichever edge of `area` it touches. When a region
+/// touches several edges, the edge that costs the least area is cut. Regions
+/// floating in the middle of `area` can't be expressed as a strut and are ignored.
+pub fn subtract_reserved(area: Rect, reserved: &[Rect]) -> Rect {
+    reserved.iter().fold(area, |area, region| {
+        if !area.overlaps_horizontally(region) || !area.overlaps_vertically(region) {
+            return area;
+        }
+
+        let (left, top) = (area.x, area.y);
+        let (right, bottom) = (area.x + area.w as i32, area.y + area.h as i32);
+        let (r_right, r_bottom) = (region.x + region.w as i32, region.y + region.h as i32);
+
+        let candidates = [
+            (region.y <= top).then(|| Rect {
+                y: r_bottom,
+                h: (bottom - r_bottom).max(0) as u32,
+                ..area
+            }),
+            (r_bottom >= bottom).then(|| Rect {
+                h: (region.y - top).max(0) as u32,
+                ..area
+            }),
+            (region.x <= left).then(|| Rect {
+                x: r_right,
+                w: (right - r_right).max(0) as u32,
+                ..area
+            }),
+            (r_right >= right).then(|| Rect {
+                w: (region.x - left).max(0) as u32,
+                ..area
+            }),
+        ];
+
+        candidates
+            .into_iter()
+            .flatten()
+            .max_by_key(|rect| u64::from(rect.w) * u64::from(rect.h))
+            .unwrap_or(area)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    /// The same direction with the axes swapped, as for a `Rect::transposed` area.
+    pub const fn transposed(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Up,
+            Direction::Up => Direction::Left,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Right,
+        }
+    }
+}
+
+/// A window preselected to open on one side of an earlier window: the index of
+/// that window among the tiled ones, and the side.
+pub type Split = Option<(usize, Direction)>;
+
+/// Index of the rect adjacent to `rects[from]` in `direction`: the closest one
+/// whose center lies that way and that shares some extent on the other axis.
+pub fn neighbor_in_direction(rects: &[Rect], from: usize, direction: Direction) -> Option<usize> {
+    aligned_candidates(rects, from, direction)
+        .filter(|(along, _, _)| *along > 0)
+        .min()
+        .map(|(_, _, i)| i)
+}
+
+/// Like `neighbor_in_direction`, but wraps around: with nothing that way, the rect
+/// farthest the other way (at the opposite edge) is returned instead.
+pub fn wrapping_neighbor_in_direction(
+    rects: &[Rect],
+    from: usize,
+    direction: Direction,
+) -> Option<usize> {
+    neighbor_in_direction(rects, from, direction).or_else(|| {
+        aligned_candidates(rects, from, direction)
+            .filter(|(along, _, _)| *along < 0)
+            .min()
+            .map(|(_, _, i)| i)
+    })
+}
+
+/// (distance along `direction`, distance across it, index) of every other rect
+/// sharing some extent with `rects[from]` on the axis across `direction`.
+fn aligned_candidates(
+    rects: &[Rect],
+    from: usize,
+    direction: Direction,
+) -> impl Iterator<Item = (i32, i32, usize)> + '_ {
+    let origin = rects.get(from);
+    rects
+        .iter()
+        .enumerate()
+        .filter(move |(i, _)| *i != from)
+        .filter_map(move |(i, rect)| {
+            let origin = origin?;
+            let (ox, oy) = origin.center();
+            let (cx, cy) = rect.center();
+            let (along, across, aligned) = match direction {
+                Direction::Left => (ox - cx, cy - oy, origin.overlaps_vertically(rect)),
+                Direction::Right => (cx - ox, cy - oy, origin.overlaps_vertically(rect)),
+                Direction::Up => (oy - cy, cx - ox, origin.overlaps_horizontally(rect)),
+                Direction::Down => (cy - oy, cx - ox, origin.overlaps_horizontally(rect)),
+            };
+            aligned.then_some((along, across.abs(), i))
+        })
+}
+
+/// A half or quarter of the usable area a floating window can be tiled into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapRegion {
+    HalfLeft,
+    HalfRight,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl SnapRegion {
+    /// The part of `area` this region covers. Odd sizes give the extra pixel to
+    /// the right/bottom part so that both halves together cover `area` exactly.
+    pub fn rect(self, area: Rect) -> Rect {
+        let (left_w, top_h) = (area.w / 2, area.h / 2);
+        let left = Rect { w: left_w, ..area };
+        let right = Rect {
+            x: area.x + left_w as i32,
+            w: area.w - left_w,
+            ..area
+        };
+        let top = |rect: Rect| Rect { h: top_h, ..rect };
+        let bottom = |rect: Rect| Rect {
+            y: area.y + top_h as i32,
+            h: area.h - top_h,
+            ..rect
+        };
+        match self {
+            SnapRegion::HalfLeft => left,
+            SnapRegion::HalfRight => right,
+            SnapRegion::TopLeft => top(left),
+            SnapRegion::TopRight => top(right),
+            SnapRegion::BottomLeft => bottom(left),
+            SnapRegion::BottomRight => bottom(right),
+        }
+    }
+}
+
+/// Moves `rect` so that at least `min_visible` pixels of it (or all of it, if
+/// smaller) stay inside `usable` on each axis.
+pub fn constrain_on_screen(rect: Rect, usable: Rect, min_visible: u32) -> Rect {
+    let clamp_axis = |pos: i32, len: u32, start: i32, span: u32| {
+        let visible = min_visible.min(len) as i32;
+        let lowest = start + visible - len as i32;
+        let highest = start + span as i32 - visible;
+        pos.min(highest).max(lowest)
+    };
+    Rect {
+        x: clamp_axis(rect.x, rect.w, usable.x, usable.w),
+        y: clamp_axis(rect.y, rect.h, usable.y, usable.h),
+        ..rect
+    }
+}
+
+/// Moves `rect` so that an edge within `threshold` pixels of a `screen` edge, or
+/// of the facing edge of one of `others`, lines up with it. Each axis snaps to its
+/// nearest candidate; other rects only count when they share extent on the other axis.
+pub fn snap(rect: Rect, others: &[Rect], screen: Rect, threshold: u32) -> Rect {
+    let (right, bottom) = (rect.x + rect.w as i32, rect.y + rect.h as i32);
+    let mut dx = vec![screen.x - rect.x, screen.x + screen.w as i32 - right];
+    let mut dy = vec![screen.y - rect.y, screen.y + screen.h as i32 - bottom];
+    for other in others {
+        if rect.overlaps_vertically(other) {
+            dx.push(other.x + other.w as i32 - rect.x);
+            dx.push(other.x - right);
+        }
+        if rect.overlaps_horizontally(other) {
+            dy.push(other.y + other.h as i32 - rect.y);
+            dy.push(other.y - bottom);
+        }
+    }
+
+    let nearest = |deltas: Vec<i32>| {
+        deltas
+            .into_iter()
+            .filter(|delta| delta.unsigned_abs() <= threshold)
+            .min_by_key(|delta| delta.abs())
+            .unwrap_or(0)
+    };
+    Rect {
+        x: rect.x + nearest(dx),
+        y: rect.y + nearest(dy),
+        ..rect
+    }
+}
+
 pub trait Layout {
     fn generate_layout(
         &self,
@@ -45,16 +298,51 @@ pub trait Layout {
         weights: &[u32],
         border_width: u32,
         window_gap: u32,
+        nmaster: usize,
     ) -> Vec<Rect>;
+
+    /// Like `generate_layout`, with `splits[i]` saying where window `i` was
+    /// preselected to open. Layouts without a notion of splits ignore it.
+    fn generate_split_layout(
+        &self,
+        area: Rect,
+        weights: &[u32],
+        _splits: &[Split],
+        border_width: u32,
+        window_gap: u32,
+        nmaster: usize,
+    ) -> Vec<Rect> {
+        self.generate_layout(area, weights, border_width, window_gap, nmaster)
+    }
 }
 
 pub(super) fn pad(dim: u32, border: u32) -> u32 {
     (dim - 2 * border).max(1)
 }
 
+/// Layouts leave `window_gap` between the edge of their area and the windows.
+/// Grows or shrinks `area` so that margin becomes `outer_gap` instead, leaving
+/// the gaps between windows alone.
+pub fn outer_gap_area(area: Rect, window_gap: u32, outer_gap: u32) -> Rect {
+    let grow = window_gap as i32 - outer_gap as i32;
+    Rect {
+        x: area.x - grow,
+        y: area.y - grow,
+        w: (area.w as i32 + 2 * grow).max(1) as u32,
+        h: (area.h as i32 + 2 * grow).max(1) as u32,
+    }
+}
+
+impl Default for LayoutType {
+    fn default() -> Self {
+        DEFAULT_LAYOUT
+    }
+}
+
 pub struct LayoutManager {
     layout_map: IndexMap<LayoutType, Box<dyn Layout>>,
-    current_layout: LayoutType,
+    /// Transpose every generated layout, e.g. turning side-by-side into stacked.
+    rotated: bool,
 }
 
 impl LayoutManager {
@@ -67,37 +355,87 @@ impl LayoutManager {
             )
         }
 
-        let current_layout = if map.contains_key(&DEFAULT_LAYOUT) {
-            DEFAULT_LAYOUT
-        } else {
+        if !map.contains_key(&DEFAULT_LAYOUT) {
             // This shouldn't be possible
             error!("Layout {DEFAULT_LAYOUT:?} not defined in LayoutType.");
-            map.get_index(0).map(|(key, _)| *key).unwrap()
-        };
+        }
 
         LayoutManager {
             layout_map: map,
-            current_layout,
+            rotated: false,
         }
     }
 
-    pub fn get_current_layout(&self) -> &dyn Layout {
+    pub fn get_layout(&self, layout: LayoutType) -> &dyn Layout {
         self.layout_map
-            .get(&self.current_layout)
+            .get(&layout)
+            .or_else(|| self.layout_map.get_index(0).map(|(_, layout)| layout))
             .map(|layout| layout.as_ref())
             .unwrap()
     }
 
-    pub fn cycle_layout(&mut self) {
-        if let Some(current_idx) = self.layout_map.get_index_of(&self.current_layout) {
-            let next_idx = (current_idx + 1) % self.layout_map.len();
-            if let Some(layout) = self.layout_map.get_index(next_idx).map(|(key, _)| *key) {
-                debug!("New layout activated: {layout:?}");
-                self.current_layout = layout
-            } else {
-                error!("Failed to cycle layout");
-            }
+    pub const fn toggle_rotation(&mut self) {
+        self.rotated = !self.rotated;
+    }
+
+    /// Generates `layout` for `area`. When rotated, the layout is generated for
+    /// the transposed area (and splits) and each rect is transposed back, so it
+    /// still fits.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate(
+        &self,
+        layout: LayoutType,
+        area: Rect,
+        weights: &[u32],
+        splits: &[Split],
+        border_width: u32,
+        window_gap: u32,
+        nmaster: usize,
+    ) -> Vec<Rect> {
+        let layout = self.get_layout(layout);
+        if !self.rotated {
+            return layout.generate_split_layout(
+                area,
+                weights,
+                splits,
+                border_width,
+                window_gap,
+                nmaster,
+            );
         }
+
+        let splits: Vec<Split> = splits
+            .iter()
+            .map(|split| split.map(|(parent, direction)| (parent, direction.transposed())))
+            .collect();
+        layout
+            .generate_split_layout(
+                area.transposed(),
+                weights,
+                &splits,
+                border_width,
+                window_gap,
+                nmaster,
+            )
+            .iter()
+            .map(Rect::transposed)
+            .collect()
+    }
+
+    pub fn next_layout(&self, layout: LayoutType) -> LayoutType {
+        let Some(current_idx) = self.layout_map.get_index_of(&layout) else {
+            error!("Failed to cycle layout");
+            return layout;
+        };
+
+        let next_idx = (current_idx + 1) % self.layout_map.len();
+        let next = self
+            .layout_map
+            .get_index(next_idx)
+            .map(|(key, _)| *key)
+            .unwrap_or(layout);
+        debug!("New layout activated: {next:?}");
+        next
     }
 }
 
@@ -158,6 +496,140 @@ mod pad_tests {
     }
 }
 
+#[cfg(test)]
+mod outer_gap_tests {
+    use super::*;
+
+    const AREA: Rect = Rect {
+        x: 0,
+        y: 0,
+        w: 1000,
+        h: 800,
+    };
+
+    fn absolute(area: Rect, rect: &Rect) -> (i32, i32, u32, u32) {
+        (rect.x + area.x, rect.y + area.y, rect.w, rect.h)
+    }
+
+    #[test]
+    fn equal_gaps_leave_area_unchanged() {
+        let area = outer_gap_area(AREA, 10, 10);
+        assert_eq!((area.x, area.y, area.w, area.h), (0, 0, 1000, 800));
+    }
+
+    #[test]
+    fn master_windows_touch_screen_edges_with_zero_outer_gap() {
+        let area = outer_gap_area(AREA, 10, 0);
+        let rects = MasterLayout.generate_layout(area, &[1, 1], 0, 10, 1);
+
+        let (x0, y0, w0, h0) = absolute(area, &rects[0]);
+        let (x1, y1, w1, h1) = absolute(area, &rects[1]);
+        assert_eq!((x0, y0), (0, 0));
+        assert_eq!(y0 + h0 as i32, 800);
+        assert_eq!(x1 - (x0 + w0 as i32), 10);
+        assert_eq!(x1 + w1 as i32, 1000);
+        assert_eq!((y1, h1), (0, 800));
+    }
+
+    #[test]
+    fn larger_outer_gap_shrinks_the_area() {
+        let area = outer_gap_area(AREA, 4, 20);
+        let rects = MasterLayout.generate_layout(area, &[1], 0, 4, 1);
+
+        assert_eq!(absolute(area, &rects[0]), (20, 20, 960, 760));
+    }
+}
+
+#[cfg(test)]
+mod reserved_tests {
+    use super::*;
+
+    const AREA: Rect = Rect {
+        x: 0,
+        y: 0,
+        w: 800,
+        h: 600,
+    };
+
+    fn assert_rect(rect: Rect, x: i32, y: i32, w: u32, h: u32) {
+        assert_eq!((rect.x, rect.y, rect.w, rect.h), (x, y, w, h));
+    }
+
+    #[test]
+    fn top_right_widget_cuts_top_strip() {
+        let widget = Rect {
+            x: 600,
+            y: 0,
+            w: 200,
+            h: 30,
+        };
+        assert_rect(subtract_reserved(AREA, &[widget]), 0, 30, 800, 570);
+    }
+
+    #[test]
+    fn tall_left_region_cuts_left_side() {
+        let panel = Rect {
+            x: 0,
+            y: 0,
+            w: 50,
+            h: 600,
+        };
+        assert_rect(subtract_reserved(AREA, &[panel]), 50, 0, 750, 600);
+    }
+
+    #[test]
+    fn bottom_region_cuts_bottom() {
+        let strip = Rect {
+            x: 100,
+            y: 580,
+            w: 100,
+            h: 20,
+        };
+        assert_rect(subtract_reserved(AREA, &[strip]), 0, 0, 800, 580);
+    }
+
+    #[test]
+    fn non_overlapping_and_floating_regions_are_ignored() {
+        let elsewhere = Rect {
+            x: 900,
+            y: 0,
+            w: 100,
+            h: 30,
+        };
+        let middle = Rect {
+            x: 300,
+            y: 200,
+            w: 100,
+            h: 100,
+        };
+        assert_rect(
+            subtract_reserved(AREA, &[elsewhere, middle]),
+            0,
+            0,
+            800,
+            600,
+        );
+    }
+
+    #[test]
+    fn regions_are_relative_to_offset_area() {
+        let area = Rect {
+            x: 800,
+            y: 0,
+            w: 800,
+            h: 600,
+        };
+        let widget = Rect {
+            x: 1400,
+            y: 0,
+            w: 200,
+            h: 30,
+        };
+        assert_rect(subtract_reserved(area, &[widget]), 800, 30, 800, 570);
+        assert_rect(subtract_reserved(AREA, &[widget]), 0, 0, 800, 600);
+    }
+}
+
 #[cfg(test)]
 mod rect_tests {
     use super::*;
@@ -199,6 +671,7 @@ mod rect_tests {
             w: 7,
             h: 8,
         };
+        #[allow(clippy::clone_on_copy)]
         let r2 = r1.clone();
         assert_eq!(r1.x, r2.x);
         assert_eq!(r1.y, r2.y);
@@ -233,6 +706,351 @@ mod rect_tests {
     }
 }
 
+#[cfg(test)]
+mod neighbor_tests {
+    use super::*;
+
+    fn grid() -> Vec<Rect> {
+        // 0 | 2
+        // --+--
+        // 1 | 3
+        vec![
+            Rect {
+                x: 0,
+                y: 0,
+                w: 500,
+                h: 400,
+            },
+            Rect {
+                x: 0,
+                y: 400,
+                w: 500,
+                h: 400,
+            },
+            Rect {
+                x: 500,
+                y: 0,
+                w: 500,
+                h: 400,
+            },
+            Rect {
+                x: 500,
+                y: 400,
+                w: 500,
+                h: 400,
+            },
+        ]
+    }
+
+    /// Neighbors of `rects[from]` to the left, right, up and down.
+    fn neighbors(rects: &[Rect], from: usize) -> [Option<usize>; 4] {
+        [
+            Direction::Left,
+            Direction::Right,
+            Direction::Up,
+            Direction::Down,
+        ]
+        .map(|direction| neighbor_in_direction(rects, from, direction))
+    }
+
+    #[test]
+    fn every_direction_in_grid() {
+        let rects = grid();
+        assert_eq!(neighbors(&rects, 0), [None, Some(2), None, Some(1)]);
+        assert_eq!(neighbors(&rects, 1), [None, Some(3), Some(0), None]);
+        assert_eq!(neighbors(&rects, 2), [Some(0), None, None, Some(3)]);
+        assert_eq!(neighbors(&rects, 3), [Some(1), None, Some(2), None]);
+    }
+
+    #[test]
+    fn every_direction_in_master_layout() {
+        // 0 | 1
+        //   +---
+        //   | 2|3
+        let rects = MasterLayout.generate_layout(
+            Rect {
+                x: 0,
+                y: 0,
+                w: 1000,
+                h: 800,
+            },
+            &[1, 1, 1, 1],
+            0,
+            0,
+            1,
+        );
+        // The closest center to the right of the master is the stack's 2.
+        assert_eq!(neighbors(&rects, 0), [None, Some(2), None, None]);
+        // 2 and 3 are equally close below 1; the earlier window wins.
+        assert_eq!(neighbors(&rects, 1), [Some(0), None, None, Some(2)]);
+        assert_eq!(neighbors(&rects, 2), [Some(0), Some(3), Some(1), None]);
+        assert_eq!(neighbors(&rects, 3), [Some(2), None, Some(1), None]);
+    }
+
+    #[test]
+    fn every_direction_in_bsp_layout() {
+        let rects = BspLayout.generate_layout(
+            Rect {
+                x: 0,
+                y: 0,
+                w: 1000,
+                h: 800,
+            },
+            &[1, 1, 1],
+            0,
+            0,
+            1,
+        );
+        // 0 | 2
+        // --+
+        // 1 |
+        assert_eq!(neighbors(&rects, 0), [None, Some(2), None, Some(1)]);
+        assert_eq!(neighbors(&rects, 1), [None, Some(2), Some(0), None]);
+        assert_eq!(neighbors(&rects, 2), [Some(0), None, None, None]);
+    }
+
+    #[test]
+    fn single_rect_has_no_neighbors() {
+        assert_eq!(neighbors(&grid()[..1], 0), [None; 4]);
+    }
+
+    #[test]
+    fn neighbors_in_grid() {
+        let rects = grid();
+        assert_eq!(neighbor_in_direction(&rects, 0, Direction::Right), Some(2));
+        assert_eq!(neighbor_in_direction(&rects, 0, Direction::Down), Some(1));
+        assert_eq!(neighbor_in_direction(&rects, 3, Direction::Left), Some(1));
+        assert_eq!(neighbor_in_direction(&rects, 3, Direction::Up), Some(2));
+    }
+
+    #[test]
+    fn no_neighbor_at_edge() {
+        let rects = grid();
+        assert_eq!(neighbor_in_direction(&rects, 0, Direction::Left), None);
+        assert_eq!(neighbor_in_direction(&rects, 0, Direction::Up), None);
+        assert_eq!(neighbor_in_direction(&rects, 3, Direction::Right), None);
+    }
+
+    #[test]
+    fn out_of_range_origin_has_no_neighbor() {
+        assert_eq!(neighbor_in_direction(&grid(), 9, Direction::Right), None);
+        assert_eq!(
+            wrapping_neighbor_in_direction(&grid(), 9, Direction::Right),
+            None
+        );
+    }
+
+    #[test]
+    fn wrapping_neighbor_wraps_to_opposite_edge() {
+        let rects = grid();
+        assert_eq!(
+            wrapping_neighbor_in_direction(&rects, 2, Direction::Right),
+            Some(0)
+        );
+        assert_eq!(
+            wrapping_neighbor_in_direction(&rects, 1, Direction::Down),
+            Some(0)
+        );
+        assert_eq!(
+            wrapping_neighbor_in_direction(&rects, 1, Direction::Left),
+            Some(3)
+        );
+        assert_eq!(
+            wrapping_neighbor_in_direction(&rects, 0, Direction::Right),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn wrapping_neighbor_picks_farthest_rect() {
+        let rects: Vec<Rect> = (0..3)
+            .map(|i| Rect {
+                x: i * 100,
+                y: 0,
+                w: 100,
+                h: 100,
+            })
+            .collect();
+        assert_eq!(
+            wrapping_neighbor_in_direction(&rects, 2, Direction::Right),
+            Some(0)
+        );
+        assert_eq!(
+            wrapping_neighbor_in_direction(&rects, 0, Direction::Left),
+            Some(2)
+        );
+        assert_eq!(
+            wrapping_neighbor_in_direction(&rects[..1], 0, Direction::Left),
+            None
+        );
+    }
+}
+
+#[cfg(test)]
+mod snap_tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect {
+        x: 0,
+        y: 0,
+        w: 800,
+        h: 600,
+    };
+
+    fn rect(x: i32, y: i32, w: u32, h: u32) -> Rect {
+        Rect { x, y, w, h }
+    }
+
+    fn position(rect: Rect) -> (i32, i32) {
+        (rect.x, rect.y)
+    }
+
+    #[test]
+    fn near_screen_edge_snaps_to_it() {
+        assert_eq!(
+            position(snap(rect(10, 200, 100, 100), &[], SCREEN, 16)),
+            (0, 200)
+        );
+        assert_eq!(
+            position(snap(rect(690, 495, 100, 100), &[], SCREEN, 16)),
+            (700, 500)
+        );
+    }
+
+    #[test]
+    fn beyond_threshold_stays_put() {
+        assert_eq!(
+            position(snap(rect(17, 200, 100, 100), &[], SCREEN, 16)),
+            (17, 200)
+        );
+    }
+
+    #[test]
+    fn near_other_right_edge_snaps_flush() {
+        let other = rect(100, 100, 200, 200);
+
+        let snapped = snap(rect(308, 150, 100, 100), &[other], SCREEN, 16);
+
+        assert_eq!(position(snapped), (300, 150));
+    }
+
+    #[test]
+    fn other_without_shared_extent_is_ignored() {
+        let other = rect(100, 400, 200, 100);
+
+        let snapped = snap(rect(308, 150, 100, 100), &[other], SCREEN, 16);
+
+        assert_eq!(position(snapped), (308, 150));
+    }
+
+    #[test]
+    fn nearest_candidate_wins() {
+        // Left edge is 12px from the screen edge, right edge 4px from the other's left.
+        let other = rect(216, 0, 100, 600);
+
+        let snapped = snap(rect(12, 200, 200, 100), &[other], SCREEN, 16);
+
+        assert_eq!(position(snapped), (16, 200));
+    }
+}
+
+#[cfg(test)]
+mod constrain_on_screen_tests {
+    use super::*;
+
+    const USABLE: Rect = Rect {
+        x: 0,
+        y: 30,
+        w: 800,
+        h: 570,
+    };
+
+    fn rect(x: i32, y: i32, w: u32, h: u32) -> Rect {
+        Rect { x, y, w, h }
+    }
+
+    fn position(rect: Rect) -> (i32, i32) {
+        (rect.x, rect.y)
+    }
+
+    #[test]
+    fn dragged_far_left_keeps_min_visible() {
+        let constrained = constrain_on_screen(rect(-500, 100, 300, 200), USABLE, 20);
+
+        assert_eq!(position(constrained), (-280, 100));
+    }
+
+    #[test]
+    fn dragged_past_bottom_right_keeps_min_visible() {
+        let constrained = constrain_on_screen(rect(900, 700, 300, 200), USABLE, 20);
+
+        assert_eq!(position(constrained), (780, 580));
+    }
+
+    #[test]
+    fn partly_visible_rect_is_untouched() {
+        let constrained = constrain_on_screen(rect(-250, 10, 300, 200), USABLE, 20);
+
+        assert_eq!(position(constrained), (-250, 10));
+    }
+
+    #[test]
+    fn rect_smaller_than_min_visible_stays_whole() {
+        let constrained = constrain_on_screen(rect(-50, 100, 10, 10), USABLE, 20);
+
+        assert_eq!(position(constrained), (0, 100));
+    }
+}
+
+#[cfg(test)]
+mod snap_region_tests {
+    use super::*;
+
+    const AREA: Rect = Rect {
+        x: 0,
+        y: 30,
+        w: 1920,
+        h: 1050,
+    };
+
+    fn parts(rect: Rect) -> (i32, i32, u32, u32) {
+        (rect.x, rect.y, rect.w, rect.h)
+    }
+
+    #[test]
+    fn halves_split_width_and_keep_full_height() {
+        assert_eq!(parts(SnapRegion::HalfLeft.rect(AREA)), (0, 30, 960, 1050));
+        assert_eq!(
+            parts(SnapRegion::HalfRight.rect(AREA)),
+            (960, 30, 960, 1050)
+        );
+    }
+
+    #[test]
+    fn quarters_split_both_axes() {
+        assert_eq!(parts(SnapRegion::TopLeft.rect(AREA)), (0, 30, 960, 525));
+        assert_eq!(parts(SnapRegion::TopRight.rect(AREA)), (960, 30, 960, 525));
+        assert_eq!(parts(SnapRegion::BottomLeft.rect(AREA)), (0, 555, 960, 525));
+        assert_eq!(
+            parts(SnapRegion::BottomRight.rect(AREA)),
+            (960, 555, 960, 525)
+        );
+    }
+
+    #[test]
+    fn odd_sizes_give_extra_pixel_to_right_and_bottom() {
+        let area = Rect {
+            x: 10,
+            y: 0,
+            w: 101,
+            h: 51,
+        };
+
+        assert_eq!(parts(SnapRegion::HalfRight.rect(area)), (60, 0, 51, 51));
+        assert_eq!(parts(SnapRegion::BottomLeft.rect(area)), (10, 25, 50, 26));
+    }
+}
+
 #[cfg(test)]
 mod layout_type_tests {
     use super::*;
@@ -253,6 +1071,7 @@ mod layout_type_tests {
     fn layout_type_clone_copy() {
         let a = LayoutType::HorizontalLayout;
         let b = a; // Copy
+        #[allow(clippy::clone_on_copy)]
         let c = a.clone();
         assert_eq!(a, b);
         assert_eq!(a, c);
@@ -302,9 +1121,13 @@ mod layout_manager_tests {
         // DEFAULT_LAYOUT is HorizontalLayout, which lays out windows side by side.
         // With 3 equal-weight windows all y values should be the same (horizontal tiling).
         let manager = LayoutManager::new();
-        let rects = manager
-            .get_current_layout()
-            .generate_layout(test_area(), &[1, 1, 1], 0, 0);
+        let rects = manager.get_layout(LayoutType::default()).generate_layout(
+            test_area(),
+            &[1, 1, 1],
+            0,
+            0,
+            1,
+        );
 
         assert_eq!(rects.len(), 3);
         // HorizontalLayout: all windows share the same y
@@ -316,15 +1139,17 @@ mod layout_manager_tests {
     }
 
     #[test]
-    fn cycle_layout_switches_to_master() {
-        let mut manager = LayoutManager::new();
-        manager.cycle_layout();
+    fn next_layout_switches_to_master() {
+        let manager = LayoutManager::new();
+        let next = manager.next_layout(LayoutType::HorizontalLayout);
+
+        assert_eq!(next, LayoutType::MasterLayout);
 
         // MasterLayout with 3 windows: first window takes the left half,
         // remaining two split the right half vertically.
         let rects = manager
-            .get_current_layout()
-            .generate_layout(test_area(), &[1, 1, 1], 0, 0);
+            .get_layout(next)
+            .generate_layout(test_area(), &[1, 1, 1], 0, 0, 1);
 
         assert_eq!(rects.len(), 3);
         // Window 0 is on the left half, windows 1 and 2 share the right half
@@ -334,65 +1159,41 @@ mod layout_manager_tests {
     }
 
     #[test]
-    fn cycle_layout_wraps_around() {
-        let mut manager = LayoutManager::new();
-
-        // We have 2 layouts: HorizontalLayout and MasterLayout.
-        // Cycling twice should return to the original.
-        let rects_before =
-            manager
-                .get_current_layout()
-                .generate_layout(test_area(), &[1, 1, 1], 0, 0);
+    fn next_layout_wraps_around() {
+        let manager = LayoutManager::new();
 
-        manager.cycle_layout(); // → MasterLayout
-        manager.cycle_layout(); // → back to HorizontalLayout
+        // We have 3 layouts: HorizontalLayout, MasterLayout and BspLayout.
+        // Cycling three times should return to the original.
+        let start = LayoutType::HorizontalLayout;
+        let next = manager.next_layout(start); // → MasterLayout
+        let next = manager.next_layout(next); // → BspLayout
+        let wrapped = manager.next_layout(next); // → back to HorizontalLayout
 
-        let rects_after =
-            manager
-                .get_current_layout()
-                .generate_layout(test_area(), &[1, 1, 1], 0, 0);
-
-        assert_eq!(rects_before.len(), rects_after.len());
-        for (a, b) in rects_before.iter().zip(rects_after.iter()) {
-            assert_eq!(a.x, b.x);
-            assert_eq!(a.y, b.y);
-            assert_eq!(a.w, b.w);
-            assert_eq!(a.h, b.h);
-        }
+        assert_eq!(wrapped, start);
     }
 
     #[test]
-    fn cycle_layout_multiple_full_cycles() {
-        let mut manager = LayoutManager::new();
+    fn next_layout_multiple_full_cycles() {
+        let manager = LayoutManager::new();
 
-        let rects_initial =
-            manager
-                .get_current_layout()
-                .generate_layout(test_area(), &[1, 1], 0, 0);
+        let start = LayoutType::default();
+        let mut layout = start;
 
         // Cycle through all layouts 3 full times (2 layouts × 3 = 6 cycles)
         for _ in 0..6 {
-            manager.cycle_layout();
+            layout = manager.next_layout(layout);
         }
 
-        let rects_final = manager
-            .get_current_layout()
-            .generate_layout(test_area(), &[1, 1], 0, 0);
-
-        for (a, b) in rects_initial.iter().zip(rects_final.iter()) {
-            assert_eq!(a.x, b.x);
-            assert_eq!(a.y, b.y);
-            assert_eq!(a.w, b.w);
-            assert_eq!(a.h, b.h);
-        }
+        assert_eq!(layout, start);
     }
 
     #[test]
-    fn get_current_layout_single_window() {
+    fn get_layout_single_window() {
         let manager = LayoutManager::new();
-        let rects = manager
-            .get_current_layout()
-            .generate_layout(test_area(), &[1], 0, 0);
+        let rects =
+            manager
+                .get_layout(LayoutType::default())
+                .generate_layout(test_area(), &[1], 0, 0, 1);
 
         assert_eq!(rects.len(), 1);
         // Single window should fill the area
@@ -405,9 +1206,13 @@ mod layout_manager_tests {
     #[test]
     fn layout_with_borders_and_gaps() {
         let manager = LayoutManager::new();
-        let rects = manager
-            .get_current_layout()
-            .generate_layout(test_area(), &[1, 1], 2, 4);
+        let rects = manager.get_layout(LayoutType::default()).generate_layout(
+            test_area(),
+            &[1, 1],
+            2,
+            4,
+            1,
+        );
 
         assert_eq!(rects.len(), 2);
         // With border_width=2 and window_gap=4, total_border=6
@@ -417,10 +1222,99 @@ mod layout_manager_tests {
     }
 
     #[test]
-    fn build_layout_map_contains_both_layouts() {
+    fn rotated_horizontal_layout_stacks_windows_vertically() {
+        let mut manager = LayoutManager::new();
+        manager.toggle_rotation();
+
+        let rects = manager.generate(
+            LayoutType::HorizontalLayout,
+            test_area(),
+            &[1, 1, 1],
+            &[],
+            0,
+            0,
+            1,
+        );
+
+        assert_eq!(rects.len(), 3);
+        for rect in &rects {
+            assert_eq!(rect.x, 0);
+            assert_eq!(rect.w, 900);
+            assert_eq!(rect.h, 200);
+        }
+        assert_eq!(rects[0].y, 0);
+        assert_eq!(rects[1].y, 200);
+        assert_eq!(rects[2].y, 400);
+    }
+
+    #[test]
+    fn rotated_layout_stays_within_area() {
+        let mut manager = LayoutManager::new();
+        manager.toggle_rotation();
+
+        let rects = manager.generate(
+            LayoutType::HorizontalLayout,
+            test_area(),
+            &[2, 1],
+            &[],
+            1,
+            5,
+            1,
+        );
+
+        for rect in &rects {
+            assert!(rect.x >= 0 && rect.y >= 0);
+            assert!(rect.x + rect.w as i32 <= 900);
+            assert!(rect.y + rect.h as i32 <= 600);
+        }
+        assert!(rects[1].y > rects[0].y);
+    }
+
+    #[test]
+    fn rotated_layout_keeps_preselected_side() {
+        let mut manager = LayoutManager::new();
+        manager.toggle_rotation();
+
+        let rects = manager.generate(
+            LayoutType::BspLayout,
+            test_area(),
+            &[1, 1],
+            &[None, Some((0, Direction::Right))],
+            0,
+            0,
+            1,
+        );
+
+        assert_eq!((rects[0].x, rects[0].w, rects[0].h), (0, 450, 600));
+        assert_eq!((rects[1].x, rects[1].y, rects[1].w), (450, 0, 450));
+    }
+
+    #[test]
+    fn toggling_rotation_twice_restores_layout() {
+        let mut manager = LayoutManager::new();
+        manager.toggle_rotation();
+        manager.toggle_rotation();
+
+        let rects = manager.generate(
+            LayoutType::HorizontalLayout,
+            test_area(),
+            &[1, 1],
+            &[],
+            0,
+            0,
+            1,
+        );
+
+        assert_eq!(rects[0].y, rects[1].y);
+        assert_eq!(rects[1].x, 450);
+    }
+
+    #[test]
+    fn build_layout_map_contains_all_layouts() {
         let map = build_layout_map();
-        assert_eq!(map.len(), 2);
+        assert_eq!(map.len(), 3);
         assert!(map.contains_key(&LayoutType::HorizontalLayout));
         assert!(map.contains_key(&LayoutType::MasterLayout));
+        assert!(map.contains_key(&LayoutType::BspLayout));
     }
 }
@@ -1,11 +1,18 @@
 mod atoms;
 mod config;
 mod effect;
+mod effective_config;
 mod ewmh_manager;
+mod focus_flash;
+mod idle;
 mod key_mapping;
 mod keyboard;
 mod layout;
+mod monitor;
+mod options;
+mod session;
 mod state;
+mod wallpaper;
 mod window_manager;
 mod workspace;
 mod x11;
@@ -16,7 +23,10 @@ fn main() {
     match window_manager::WindowManager::new() {
         Ok(mut wm) => {
             if let Err(e) = wm.run() {
-                log::error!("Window manager runtime error: {e:?}");
+                log::error!("{e}");
+                // `exit` skips destructors; release the WM's grabs first.
+                drop(wm);
+                std::process::exit(e.exit_code());
             }
         }
         Err(e) => {
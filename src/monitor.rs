@@ -0,0 +1,22 @@
+use crate::layout::Rect;
+
+/// A physical output and the workspace (a global index into its own set) it is
+/// currently showing.
+#[derive(Clone, Copy, Debug)]
+pub struct Monitor {
+    pub rect: Rect,
+    pub workspace: usize,
+}
+
+impl Monitor {
+    pub const fn new(rect: Rect, workspace: usize) -> Self {
+        Self { rect, workspace }
+    }
+
+    pub const fn center(&self) -> (i32, i32) {
+        (
+            self.rect.x + (self.rect.w / 2) as i32,
+            self.rect.y + (self.rect.h / 2) as i32,
+        )
+    }
+}
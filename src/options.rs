@@ -0,0 +1,46 @@
+/// Which window gets focus when a workspace is switched to.
+#[cfg_attr(not(test), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FocusOnSwitch {
+    /// The window that had focus when the workspace was last shown.
+    LastFocused,
+    /// The first mapped window in tiling order.
+    FirstWindow,
+    /// The window under the pointer, falling back to the last focused one.
+    UnderPointer,
+}
+
+/// Screen edge `HorizontalLayout` starts tiling from.
+#[cfg_attr(not(test), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileDirection {
+    /// The first window is on the left.
+    LeftToRight,
+    /// The first window is on the right.
+    RightToLeft,
+}
+
+/// What to do when the current workspace loses its last window.
+#[cfg_attr(not(test), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmptyWorkspace {
+    /// Stay on the empty workspace, with nothing focused.
+    Stay,
+    /// Go to the closest lower-numbered workspace (wrapping around) that still
+    /// has windows.
+    SwitchToPrevious,
+}
+
+/// Where a newly mapped window joins the window list, as dwm's attach patches.
+#[cfg_attr(not(test), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttachMode {
+    /// First in the list, becoming the master.
+    Top,
+    /// Last in the list.
+    Bottom,
+    /// Right after the focused window.
+    AfterFocused,
+    /// First in the stack, right after the master area.
+    BelowMaster,
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+/// What `State` writes to the session file: enough to put every window back on
+/// its workspace, in order, with its weight and floating flag after a restart.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// Global index of the current workspace.
+    pub current: usize,
+    /// Layout name of each workspace, by global index.
+    pub layouts: Vec<String>,
+    /// Managed windows in tiling order.
+    pub windows: Vec<SessionWindow>,
+}
+
+/// A managed window, identified by its X window id. Clients outlive a window
+/// manager restart, so the id normally still names the same window; WM_CLASS
+/// and title are kept to place windows whose client was restarted too.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionWindow {
+    pub id: u32,
+    pub class: Option<String>,
+    pub instance: Option<String>,
+    pub title: Option<String>,
+    pub workspace: usize,
+    pub weight: u32,
+    pub floating: bool,
+}
+
+impl SessionWindow {
+    /// Whether a window with this WM_CLASS and title is the one saved. Without
+    /// `exact` the title is ignored, for clients that change it as they run.
+    /// Windows with no WM_CLASS at all never match.
+    pub fn matches(
+        &self,
+        class: Option<&str>,
+        instance: Option<&str>,
+        title: Option<&str>,
+        exact: bool,
+    ) -> bool {
+        (self.class.is_some() || self.instance.is_some())
+            && self.class.as_deref() == class
+            && self.instance.as_deref() == instance
+            && (!exact || self.title.as_deref() == title)
+    }
+}
+
+/// Session file name for the X display `display` (as in `$DISPLAY`), so servers
+/// running side by side, local or remote, each get their own file.
+pub fn session_file_name(stem: &str, display: &str) -> String {
+    let (host, screen) = display.rsplit_once(':').unwrap_or(("", display));
+    let number = screen.split('.').next().unwrap_or_default();
+    let host = if host.is_empty() { "local" } else { host };
+    let sanitize = |part: &str| -> String {
+        part.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+    format!("{stem}-{}-{}.state", sanitize(host), sanitize(number))
+}
+
+#[cfg(test)]
+mod session_tests {
+    use super::*;
+
+    fn saved(title: Option<&str>) -> SessionWindow {
+        SessionWindow {
+            id: 1,
+            class: Some("Alacritty".to_string()),
+            instance: Some("alacritty".to_string()),
+            title: title.map(str::to_string),
+            workspace: 0,
+            weight: 1,
+            floating: false,
+        }
+    }
+
+    #[test]
+    fn file_name_includes_server_and_display() {
+        assert_eq!(
+            session_file_name("ferriswm", ":0"),
+            "ferriswm-local-0.state"
+        );
+        assert_eq!(
+            session_file_name("ferriswm", ":1.0"),
+            "ferriswm-local-1.state"
+        );
+        assert_eq!(
+            session_file_name("ferriswm", "remote.host:10.0"),
+            "ferriswm-remote.host-10.state"
+        );
+        assert_eq!(
+            session_file_name("ferriswm", "/tmp/launch/org.xquartz:0"),
+            "ferriswm-_tmp_launch_org.xquartz-0.state"
+        );
+    }
+
+    #[test]
+    fn exact_match_needs_the_title() {
+        let window = saved(Some("~"));
+        let (class, instance) = (Some("Alacritty"), Some("alacritty"));
+
+        assert!(window.matches(class, instance, Some("~"), true));
+        assert!(!window.matches(class, instance, Some("vim"), true));
+        assert!(window.matches(class, instance, Some("vim"), false));
+        assert!(!window.matches(Some("Firefox"), instance, Some("~"), false));
+    }
+
+    #[test]
+    fn windows_without_class_never_match() {
+        let window = SessionWindow {
+            class: None,
+            instance: None,
+            ..saved(None)
+        };
+        assert!(!window.matches(None, None, None, true));
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let session = Session {
+            current: 3,
+            layouts: vec!["MasterLayout".to_string()],
+            windows: vec![saved(Some("~"))],
+        };
+        let data = serde_json::to_string(&session).unwrap();
+        assert_eq!(serde_json::from_str::<Session>(&data).unwrap(), session);
+    }
+}
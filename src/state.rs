@@ -1,13 +1,30 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use log::warn;
-use xcb::{Xid, x::Window};
+use indexmap::IndexMap;
+
+use log::{info, warn};
+use xcb::{
+    Xid,
+    x::{EventMask, Window},
+};
 
 use crate::{
-    config::NUM_WORKSPACES,
+    config::{
+        ATTACH_MODE, AUTO_NAME_WORKSPACES, BORDER_SCROLL_WEIGHT, DEFAULT_NMASTER, EMPTY_WORKSPACE,
+        FOCUS_ON_SWITCH, FOCUS_WRAP, MIN_VISIBLE, MODAL_STRICT_FOCUS, NUM_WORKSPACES,
+        ONLY_FOCUSED_BORDER, OUTER_GAP, RESERVED_REGIONS, SNAP_THRESHOLD, WEIGHT_STEP,
+        WORKSPACE_BORDERS, WORKSPACE_GAPS, WORKSPACE_LAYOUTS, WORKSPACE_NAMES,
+    },
     effect::{Effect, Effects},
     key_mapping::ActionEvent,
-    layout::{LayoutManager, Rect},
+    layout::{
+        Direction, LayoutManager, LayoutType, Rect, SnapRegion, Split, constrain_on_screen,
+        neighbor_in_direction, outer_gap_area, snap, subtract_reserved,
+        wrapping_neighbor_in_direction,
+    },
+    monitor::Monitor,
+    options::{AttachMode, EmptyWorkspace, FocusOnSwitch},
+    session::{Session, SessionWindow},
     workspace::Workspace,
     x11::WindowType,
 };
@@ -20,33 +37,242 @@ pub struct ScreenConfig {
     pub normal_border_pixel: u32,
 }
 
+/// Read-only snapshot of a managed window and the state the WM tracks for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowInfo {
+    pub window: Window,
+    pub workspace: usize,
+    pub focused: bool,
+    pub floating: bool,
+    pub fullscreen: bool,
+    pub hidden: bool,
+    pub border_width: u32,
+}
+
+/// A move of the current workspace's focus, as recorded by `State` before any
+/// effects are produced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FocusChange {
+    previous: Option<Window>,
+    focused: Window,
+}
+
+/// Screen edge a dock is attached to. It decides which side of the usable area
+/// the dock's height is taken from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DockEdge {
+    Top,
+    Bottom,
+}
+
+impl DockEdge {
+    /// Edge nearest to a dock at `rect` on a screen `screen_height` pixels tall.
+    pub fn of(rect: Rect, screen_height: u32) -> Self {
+        if 2 * i64::from(rect.y) + i64::from(rect.h) < i64::from(screen_height) {
+            DockEdge::Top
+        } else {
+            DockEdge::Bottom
+        }
+    }
+}
+
+/// A floating window being moved with the pointer.
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    window: Window,
+    /// Pointer position relative to the window's origin when the drag started.
+    offset: (i32, i32),
+}
+
+/// Whether a point relative to a window's inside, as in a button event, lies on
+/// its border rather than in the `rect` the client draws.
+fn on_border(rect: Rect, x: i32, y: i32) -> bool {
+    x < 0 || y < 0 || x >= rect.w as i32 || y >= rect.h as i32
+}
+
+/// Whether a tile leaves a window at least its minimum client size.
+fn fits_min_size(tile: Rect, (min_w, min_h): (u32, u32)) -> bool {
+    tile.w >= min_w && tile.h >= min_h
+}
+
+fn initial_workspaces(
+    layouts: &[(usize, LayoutType)],
+    gaps: &[(usize, u32)],
+    borders: &[(usize, u32)],
+) -> [Workspace; NUM_WORKSPACES] {
+    let mut workspaces: [Workspace; NUM_WORKSPACES] = Default::default();
+    for &(workspace_id, layout) in layouts {
+        match workspaces.get_mut(workspace_id) {
+            Some(workspace) => workspace.set_layout(layout),
+            None => warn!("Ignoring layout {layout:?} for out of range workspace {workspace_id}"),
+        }
+    }
+    for &(workspace_id, gap) in gaps {
+        match workspaces.get_mut(workspace_id) {
+            Some(workspace) => workspace.set_gap(Some(gap)),
+            None => warn!("Ignoring gap {gap} for out of range workspace {workspace_id}"),
+        }
+    }
+    for &(workspace_id, width) in borders {
+        match workspaces.get_mut(workspace_id) {
+            Some(workspace) => workspace.set_border_width(Some(width)),
+            None => warn!("Ignoring border {width} for out of range workspace {workspace_id}"),
+        }
+    }
+    workspaces
+}
+
 pub struct State {
     layout_manager: LayoutManager,
 
-    workspaces: [Workspace; NUM_WORKSPACES],
-    window_to_workspace: HashMap<Window, usize>,
+    /// `NUM_WORKSPACES` workspaces per monitor, monitor `m`'s set starting at
+    /// `m * NUM_WORKSPACES`.
+    workspaces: Vec<Workspace>,
+    /// Managed windows in the order they were first managed.
+    window_to_workspace: IndexMap<Window, usize>,
     current_workspace: usize,
 
+    monitors: Vec<Monitor>,
+    active_monitor: usize,
+
     screen: ScreenConfig,
     border_width: u32,
     window_gap: u32,
+    /// Margin between tiled windows and the screen edge; `None` uses `window_gap`.
+    outer_gap: Option<u32>,
+    nmaster: usize,
+    borders_enabled: bool,
 
     dock_windows: Vec<Window>,
+    /// Edges of docks whose geometry is known; the others sit at the bottom.
+    dock_edges: HashMap<Window, DockEdge>,
     dock_height: u32,
+    docks_hidden: bool,
+
+    transient_owners: HashMap<Window, Window>,
+    /// Windows mapped with `_NET_WM_STATE_MODAL`; their owner, if any, is in `transient_owners`.
+    modal_windows: HashSet<Window>,
+    /// Windows on all desktops; they follow the active monitor's workspace.
+    sticky_windows: HashSet<Window>,
+    /// Mapped windows left out of tiling at whatever geometry they last had.
+    tiling_skipped: HashSet<Window>,
+    /// Last known titles of managed windows, kept current through PropertyNotify.
+    titles: HashMap<Window, String>,
+    /// WM_CLASS class names of managed windows, read when they are first managed.
+    window_classes: HashMap<Window, String>,
+    /// WM_CLASS instance names of managed windows, read along with the class.
+    window_instances: HashMap<Window, String>,
+    /// Names from `WORKSPACE_NAMES`, by workspace index.
+    workspace_names: HashMap<usize, &'static str>,
+    auto_name_workspaces: bool,
+    urgent_windows: HashSet<Window>,
+    /// Windows kept from taking focus when mapped. They stay urgent until they
+    /// are focused, whatever their `WM_HINTS` say.
+    focus_denied: HashSet<Window>,
+    /// Vim-style marks set with `SetMark`.
+    marks: HashMap<char, Window>,
+    /// Minimized windows, most recently minimized last. They stay managed and in
+    /// the client list but are unmapped and left out of the layout.
+    hidden_windows: Vec<Window>,
+    strict_modal: bool,
+    only_focused_border: bool,
+    border_scroll_weight: bool,
+    /// Side of a tiled window the next mapped window opens on.
+    preselection: Option<(Window, Direction)>,
+    reserved_regions: Vec<Rect>,
+    focus_on_switch: FocusOnSwitch,
+    empty_workspace: EmptyWorkspace,
+    attach_mode: AttachMode,
+    /// Whether directional focus wraps around at the screen edge.
+    focus_wrap: bool,
+    /// Last known pointer position in root coordinates.
+    pointer: Option<(i32, i32)>,
+    frames: HashMap<Window, Window>,
+    /// Screen rects of windows floated with `toggle_floating`.
+    floating_geometry: HashMap<Window, Rect>,
+    /// Last `Configure` effects sent for each window, so re-tiling skips windows
+    /// that have not moved. Dropped whenever the window may have been placed
+    /// some other way.
+    configured: HashMap<Window, Effects>,
+    /// (y, h) and (x, w) of floating windows before they were maximized along that axis.
+    pre_max_vertical: HashMap<Window, (i32, u32)>,
+    pre_max_horizontal: HashMap<Window, (i32, u32)>,
+    drag: Option<Drag>,
+    /// Newly mapped floating window following the pointer until a click places it.
+    placing: Option<Window>,
+    /// Per-window border widths (from `BORDER_WIDTH_RULES`) replacing `border_width`.
+    border_overrides: HashMap<Window, u32>,
+    /// Minimum client sizes of tiled dialogs; see `TILE_DIALOGS`.
+    min_sizes: HashMap<Window, (u32, u32)>,
+    /// Windows floated by `float_undersized_tiles`, which tile again once their
+    /// tile would be big enough.
+    undersized_floated: HashSet<Window>,
 }
 
 impl State {
     pub fn new(screen: ScreenConfig, border_width: u32, window_gap: u32, dock_height: u32) -> Self {
         Self {
             layout_manager: LayoutManager::new(),
-            workspaces: Default::default(),
+            workspaces: Vec::from(initial_workspaces(
+                WORKSPACE_LAYOUTS,
+                WORKSPACE_GAPS,
+                WORKSPACE_BORDERS,
+            )),
             window_to_workspace: Default::default(),
             current_workspace: 0,
+            monitors: vec![Monitor::new(
+                Rect {
+                    x: 0,
+                    y: 0,
+                    w: screen.width,
+                    h: screen.height,
+                },
+                0,
+            )],
+            active_monitor: 0,
             screen,
             border_width,
             window_gap,
+            outer_gap: OUTER_GAP,
+            nmaster: DEFAULT_NMASTER,
+            borders_enabled: true,
             dock_windows: Vec::new(),
+            dock_edges: HashMap::new(),
             dock_height,
+            docks_hidden: false,
+            transient_owners: HashMap::new(),
+            modal_windows: HashSet::new(),
+            sticky_windows: HashSet::new(),
+            tiling_skipped: HashSet::new(),
+            titles: HashMap::new(),
+            window_classes: HashMap::new(),
+            window_instances: HashMap::new(),
+            workspace_names: WORKSPACE_NAMES.iter().copied().collect(),
+            auto_name_workspaces: AUTO_NAME_WORKSPACES,
+            urgent_windows: HashSet::new(),
+            focus_denied: HashSet::new(),
+            marks: HashMap::new(),
+            hidden_windows: Vec::new(),
+            strict_modal: MODAL_STRICT_FOCUS,
+            only_focused_border: ONLY_FOCUSED_BORDER,
+            border_scroll_weight: BORDER_SCROLL_WEIGHT,
+            preselection: None,
+            reserved_regions: RESERVED_REGIONS.to_vec(),
+            focus_on_switch: FOCUS_ON_SWITCH,
+            empty_workspace: EMPTY_WORKSPACE,
+            attach_mode: ATTACH_MODE,
+            focus_wrap: FOCUS_WRAP,
+            pointer: None,
+            frames: HashMap::new(),
+            floating_geometry: HashMap::new(),
+            configured: HashMap::new(),
+            pre_max_vertical: HashMap::new(),
+            pre_max_horizontal: HashMap::new(),
+            drag: None,
+            placing: None,
+            border_overrides: HashMap::new(),
+            min_sizes: HashMap::new(),
+            undersized_floated: HashSet::new(),
         }
     }
 
@@ -54,19 +280,150 @@ impl State {
         self.screen
     }
 
+    /// Index of the current workspace within the focused monitor's set. This is
+    /// what EWMH desktop numbers refer to.
     pub const fn current_workspace_id(&self) -> usize {
+        self.current_workspace % NUM_WORKSPACES
+    }
+
+    /// Global index of the current workspace, which also tells monitors apart.
+    pub const fn current_global_workspace(&self) -> usize {
         self.current_workspace
     }
 
+    /// Replaces the single full-screen monitor with the given outputs, each
+    /// getting its own set of workspaces. Ignored if no outputs are given.
+    pub fn set_monitors(&mut self, rects: &[Rect]) {
+        if rects.is_empty() {
+            return;
+        }
+
+        self.monitors = rects
+            .iter()
+            .enumerate()
+            .map(|(monitor, rect)| Monitor::new(*rect, monitor * NUM_WORKSPACES))
+            .collect();
+        while self.workspaces.len() < self.monitors.len() * NUM_WORKSPACES {
+            self.workspaces.extend(initial_workspaces(
+                WORKSPACE_LAYOUTS,
+                WORKSPACE_GAPS,
+                WORKSPACE_BORDERS,
+            ));
+        }
+        self.active_monitor = 0;
+        self.current_workspace = 0;
+    }
+
+    /// Adopts a new root size. Monitors that covered the whole old screen are
+    /// stretched to the new one, then docks and visible workspaces are re-laid out.
+    pub fn resize_screen(&mut self, width: u32, height: u32) -> Effects {
+        let (old_width, old_height) = (self.screen.width, self.screen.height);
+        if (old_width, old_height) == (width, height) {
+            return vec![];
+        }
+        self.screen.width = width;
+        self.screen.height = height;
+        for monitor in &mut self.monitors {
+            let rect = monitor.rect;
+            if (rect.x, rect.y, rect.w, rect.h) == (0, 0, old_width, old_height) {
+                monitor.rect = Rect {
+                    w: width,
+                    h: height,
+                    ..rect
+                };
+            }
+        }
+
+        let mut effects = Vec::new();
+        if !self.docks_hidden {
+            effects.extend(self.configure_dock_windows());
+        }
+        effects.extend(self.configure_visible_workspaces());
+        effects
+    }
+
+    /// Maps an index within the focused monitor's workspace set to a global one.
+    const fn global_workspace(&self, workspace_id: usize) -> usize {
+        self.active_monitor * NUM_WORKSPACES + workspace_id
+    }
+
+    fn monitor_showing(&self, workspace_id: usize) -> Option<usize> {
+        let monitor = workspace_id / NUM_WORKSPACES;
+        self.monitors
+            .get(monitor)
+            .filter(|m| m.workspace == workspace_id)
+            .map(|_| monitor)
+    }
+
+    fn monitor_rect(&self, workspace_id: usize) -> Rect {
+        self.monitors
+            .get(workspace_id / NUM_WORKSPACES)
+            .unwrap_or(&self.monitors[self.active_monitor])
+            .rect
+    }
+
+    /// Area available to tile `workspace_id`: the rect of the monitor owning it,
+    /// minus the dock on each screen edge the monitor reaches and minus the
+    /// reserved regions.
+    fn workspace_area(&self, workspace_id: usize) -> Rect {
+        let mut area = self.monitor_rect(workspace_id);
+        if self.dock_on_edge(DockEdge::Top) && area.y == 0 {
+            area.y += self.dock_height as i32;
+            area.h = area.h.saturating_sub(self.dock_height);
+        }
+        if self.dock_on_edge(DockEdge::Bottom)
+            && area.y + area.h as i32 == self.screen.height as i32
+        {
+            area.h = area.h.saturating_sub(self.dock_height);
+        }
+        subtract_reserved(area, &self.reserved_regions)
+    }
+
+    pub fn set_dock_edge(&mut self, window: Window, edge: DockEdge) {
+        self.dock_edges.insert(window, edge);
+    }
+
+    fn dock_edge(&self, window: Window) -> DockEdge {
+        self.dock_edges
+            .get(&window)
+            .copied()
+            .unwrap_or(DockEdge::Bottom)
+    }
+
+    /// Whether a shown dock takes space from `edge`.
+    fn dock_on_edge(&self, edge: DockEdge) -> bool {
+        self.docks_reserve_space()
+            && self
+                .dock_windows
+                .iter()
+                .any(|window| self.dock_edge(*window) == edge)
+    }
+
     pub fn focused_window(&self) -> Option<Window> {
         self.current_workspace().get_focus_window()
     }
 
+    /// Docks only take space away from tiling while at least one is shown.
+    fn docks_reserve_space(&self) -> bool {
+        !self.docks_hidden && !self.dock_windows.is_empty()
+    }
+
     pub fn usable_screen_height(&self) -> u32 {
-        if !self.dock_windows.is_empty() {
-            return self.screen.height.saturating_sub(self.dock_height);
+        let docked_edges = [DockEdge::Top, DockEdge::Bottom]
+            .into_iter()
+            .filter(|edge| self.dock_on_edge(*edge))
+            .count() as u32;
+        self.screen
+            .height
+            .saturating_sub(docked_edges * self.dock_height)
+    }
+
+    /// Top of the usable area: below a top dock, if one is shown.
+    pub fn usable_screen_top(&self) -> u32 {
+        if self.dock_on_edge(DockEdge::Top) {
+            return self.dock_height;
         }
-        self.screen.height
+        0
     }
 
     pub fn window_workspace(&self, window: Window) -> Option<usize> {
@@ -80,14 +437,41 @@ impl State {
             .map(|fullscreen| window == fullscreen)
             .unwrap_or(false)
     }
+
+    pub fn is_window_floating(&self, window: Window) -> bool {
+        self.window_workspace(window)
+            .and_then(|workspace_id| self.get_workspace(workspace_id))
+            .is_some_and(|workspace| workspace.is_window_floating(&window))
+    }
+
+    /// Managed windows in map order across all workspaces, which is what pagers
+    /// show for `_NET_CLIENT_LIST`. Moving a window between workspaces keeps its place.
     pub fn managed_windows_sorted(&self) -> Vec<Window> {
-        let mut entries = self
-            .window_to_workspace
-            .iter()
-            .map(|(w, ws)| (*ws, w.resource_id(), *w))
-            .collect::<Vec<_>>();
-        entries.sort_by_key(|(ws, id, _w)| (*ws, *id));
-        entries.into_iter().map(|(_ws, _id, w)| w).collect()
+        self.window_to_workspace.keys().copied().collect()
+    }
+
+    pub fn window_infos(&self) -> Vec<WindowInfo> {
+        let focused = self.focused_window();
+        self.managed_windows_sorted()
+            .into_iter()
+            .filter_map(|window| {
+                let workspace = self.window_workspace(window)?;
+                let fullscreen = self.is_window_fullscreen(window);
+                Some(WindowInfo {
+                    window,
+                    workspace: workspace % NUM_WORKSPACES,
+                    focused: focused == Some(window),
+                    floating: self.is_window_floating(window),
+                    fullscreen,
+                    hidden: self.is_window_hidden(window),
+                    border_width: if fullscreen {
+                        0
+                    } else {
+                        self.window_border_width(window)
+                    },
+                })
+            })
+            .collect()
     }
 
     pub fn client_list_windows(&self) -> Vec<Window> {
@@ -100,6 +484,207 @@ impl State {
         out
     }
 
+    /// Border width of windows on `workspace_id`: its own if it overrides the
+    /// global one.
+    fn effective_border_width(&self, workspace_id: usize) -> u32 {
+        if !self.borders_enabled {
+            return 0;
+        }
+        self.get_workspace(workspace_id)
+            .and_then(Workspace::border_width)
+            .unwrap_or(self.border_width)
+    }
+
+    /// Window gap on `workspace_id`: its own if it overrides the global one.
+    fn effective_window_gap(&self, workspace_id: usize) -> u32 {
+        self.get_workspace(workspace_id)
+            .and_then(Workspace::gap)
+            .unwrap_or(self.window_gap)
+    }
+
+    /// Number of master windows on `workspace_id`: its own once it has been
+    /// changed there, else the global default.
+    fn effective_nmaster(&self, workspace_id: usize) -> usize {
+        self.get_workspace(workspace_id)
+            .and_then(Workspace::nmaster)
+            .unwrap_or(self.nmaster)
+    }
+
+    /// Border width for `window`: its override if it has one, else its workspace's width.
+    /// With `ONLY_FOCUSED_BORDER`, windows other than the focused one have none.
+    fn window_border_width(&self, window: Window) -> u32 {
+        if !self.borders_enabled {
+            return 0;
+        }
+        if self.only_focused_border && self.focused_window() != Some(window) {
+            return 0;
+        }
+        self.border_overrides
+            .get(&window)
+            .copied()
+            .unwrap_or_else(|| {
+                let workspace_id = self
+                    .window_workspace(window)
+                    .unwrap_or(self.current_workspace);
+                self.effective_border_width(workspace_id)
+            })
+    }
+
+    pub fn set_border_override(&mut self, window: Window, width: u32) {
+        self.border_overrides.insert(window, width);
+    }
+
+    /// Records the smallest tile `window` accepts before it is floated instead.
+    pub fn set_min_size(&mut self, window: Window, min_size: (u32, u32)) {
+        self.min_sizes.insert(window, min_size);
+    }
+
+    pub fn is_framed(&self, window: Window) -> bool {
+        self.frames.contains_key(&window)
+    }
+
+    /// The direct child of the root that shows `window`: its frame, or the
+    /// window itself when it isn't framed.
+    pub fn toplevel(&self, window: Window) -> Window {
+        self.frames.get(&window).copied().unwrap_or(window)
+    }
+
+    /// The managed client shown by `toplevel`, a direct child of the root: either
+    /// the client itself or the frame it was reparented into.
+    pub fn client_for_toplevel(&self, toplevel: Window) -> Option<Window> {
+        if self.window_workspace(toplevel).is_some() {
+            return Some(toplevel);
+        }
+        self.frames
+            .iter()
+            .find(|(_, frame)| **frame == toplevel)
+            .map(|(window, _)| *window)
+    }
+
+    /// Hands every framed client back to `root` and destroys its frame, so that
+    /// clients outlive the WM that framed them. Each client lands where its
+    /// frame was, which is also where it was on screen since it sits, border
+    /// and all, at the frame's top-left corner.
+    pub fn release_frames(&self, root: Window) -> Effects {
+        let mut frames: Vec<(Window, Window)> = self
+            .frames
+            .iter()
+            .map(|(window, frame)| (*window, *frame))
+            .collect();
+        frames.sort_by_key(|(window, _)| window.resource_id());
+        frames
+            .into_iter()
+            .flat_map(|(window, frame)| {
+                let (x, y) = self.root_position(window);
+                [
+                    Effect::Reparent {
+                        window,
+                        parent: root,
+                        x,
+                        y,
+                    },
+                    Effect::DestroyWindow(frame),
+                ]
+            })
+            .collect()
+    }
+
+    /// Top-left corner of `window`'s placement on the root: its fullscreen,
+    /// floating or tiled rect, or its monitor's corner when it isn't shown.
+    fn root_position(&self, window: Window) -> (i32, i32) {
+        let Some(workspace_id) = self.window_workspace(window) else {
+            return (0, 0);
+        };
+        let rect = if self.is_window_fullscreen(window) {
+            None
+        } else if self.is_window_floating(window) {
+            self.floating_geometry.get(&window).copied()
+        } else {
+            self.tiled_geometry(workspace_id)
+                .into_iter()
+                .find_map(|(tiled, rect)| (tiled == window).then_some(rect))
+        };
+        let rect = rect.unwrap_or_else(|| self.monitor_rect(workspace_id));
+        (rect.x, rect.y)
+    }
+
+    /// Starts tracking `frame` as the WM-created parent of `window`.
+    pub fn attach_frame(&mut self, window: Window, frame: Window) -> Effects {
+        self.frames.insert(window, frame);
+        self.configured.remove(&window);
+        vec![
+            Effect::CreateFrame(frame),
+            Effect::AddToSaveSet(window),
+            Effect::Reparent {
+                window,
+                parent: frame,
+                x: 0,
+                y: 0,
+            },
+        ]
+    }
+
+    fn map_effects(&self, window: Window) -> Effects {
+        match self.frames.get(&window) {
+            Some(&frame) => vec![Effect::Map(frame), Effect::Map(window)],
+            None => vec![Effect::Map(window)],
+        }
+    }
+
+    fn unmap_effects(&self, window: Window) -> Effects {
+        match self.frames.get(&window) {
+            Some(&frame) => vec![Effect::Unmap(window), Effect::Unmap(frame)],
+            None => vec![Effect::Unmap(window)],
+        }
+    }
+
+    fn raise_effect(&self, window: Window) -> Effect {
+        Effect::Raise(self.toplevel(window))
+    }
+
+    /// Places `window` at `rect`. Framed windows get the frame positioned at
+    /// `rect` (grown by the border) and the client pinned to its top-left corner.
+    /// Nothing is emitted when the window already sits there.
+    fn configure_effects(&mut self, window: Window, rect: Rect, border: u32) -> Effects {
+        let effects = self.placement_effects(window, rect, border);
+        if self.configured.get(&window) == Some(&effects) {
+            return vec![];
+        }
+        self.configured.insert(window, effects.clone());
+        effects
+    }
+
+    fn placement_effects(&self, window: Window, rect: Rect, border: u32) -> Effects {
+        match self.frames.get(&window) {
+            Some(&frame) => vec![
+                Effect::Configure {
+                    window: frame,
+                    x: rect.x,
+                    y: rect.y,
+                    w: rect.w + 2 * border,
+                    h: rect.h + 2 * border,
+                    border: 0,
+                },
+                Effect::Configure {
+                    window,
+                    x: 0,
+                    y: 0,
+                    w: rect.w,
+                    h: rect.h,
+                    border,
+                },
+            ],
+            None => vec![Effect::Configure {
+                window,
+                x: rect.x,
+                y: rect.y,
+                w: rect.w,
+                h: rect.h,
+                border,
+            }],
+        }
+    }
+
     fn current_workspace_mut(&mut self) -> &mut Workspace {
         self.workspaces
             .get_mut(self.current_workspace)
@@ -139,72 +724,207 @@ impl State {
     }
 
     fn cycle_layout(&mut self) -> Effects {
-        self.layout_manager.cycle_layout();
+        let next = self
+            .layout_manager
+            .next_layout(self.current_workspace().layout());
+        self.current_workspace_mut().set_layout(next);
         self.configure_windows(self.current_workspace)
     }
 
-    pub fn configure_windows(&self, workspace_id: usize) -> Effects {
+    pub fn rotate_layout(&mut self) -> Effects {
+        self.layout_manager.toggle_rotation();
+        self.configure_visible_workspaces()
+    }
+
+    /// Re-tiles the workspace shown on every monitor.
+    fn configure_visible_workspaces(&mut self) -> Effects {
+        let workspaces: Vec<usize> = self.monitors.iter().map(|m| m.workspace).collect();
+        workspaces
+            .into_iter()
+            .flat_map(|workspace_id| self.configure_windows(workspace_id))
+            .collect()
+    }
+
+    pub fn configure_windows(&mut self, workspace_id: usize) -> Effects {
+        self.float_undersized_tiles(workspace_id);
         let mut effects: Effects = vec![];
-        if let Some(current_workspace) = self.get_workspace(workspace_id) {
-            if let Some(fullscreen) = current_workspace.get_fullscreen_window()
-                && current_workspace.is_window_mapped(&fullscreen)
-            {
-                effects.push(Effect::Configure {
-                    window: fullscreen,
-                    x: 0,
-                    y: 0,
-                    w: self.screen.width,
-                    h: self.screen.height,
-                    border: 0,
-                });
-                effects.push(Effect::Raise(fullscreen));
-                return effects;
-            }
+        let Some(current_workspace) = self.get_workspace(workspace_id) else {
+            return effects;
+        };
+        if let Some(fullscreen) = current_workspace.get_fullscreen_window()
+            && current_workspace.is_window_mapped(&fullscreen)
+        {
+            let monitor_rect = self.monitor_rect(workspace_id);
+            effects.extend(self.configure_effects(fullscreen, monitor_rect, 0));
+            effects.push(self.raise_effect(fullscreen));
+            return effects;
+        }
 
-            let clients: Vec<_> = current_workspace
-                .iter_clients()
-                .filter(|client| client.is_mapped())
-                .collect();
-            if clients.is_empty() {
-                return effects;
-            }
+        let floating: Vec<(Window, Rect)> = current_workspace
+            .iter_windows()
+            .filter(|window| {
+                current_workspace.is_window_mapped(window)
+                    && current_workspace.is_window_floating(window)
+            })
+            .filter_map(|window| Some((*window, *self.floating_geometry.get(window)?)))
+            .collect();
+        for (window, rect) in self
+            .tiled_geometry(workspace_id)
+            .into_iter()
+            .chain(floating)
+        {
+            let border = self.window_border_width(window);
+            effects.extend(self.configure_effects(window, rect, border));
+        }
 
-            let weights: Vec<u32> = clients.iter().map(|client| client.size()).collect();
-            let area = Rect {
-                x: 0,
-                y: 0,
-                w: self.screen.width,
-                h: self.usable_screen_height(),
+        effects
+    }
+
+    /// Floats every tiled window of `workspace_id` whose tile is smaller than its
+    /// minimum size, centred on the workspace at that size. Windows floated by an
+    /// earlier pass are put back in the layout first, so they tile again once
+    /// their tile fits. Floating a window only makes the remaining tiles larger,
+    /// so one pass is enough.
+    fn float_undersized_tiles(&mut self, workspace_id: usize) {
+        if self.min_sizes.is_empty() {
+            return;
+        }
+
+        let previously: Vec<Window> = self
+            .get_workspace(workspace_id)
+            .into_iter()
+            .flat_map(Workspace::iter_windows)
+            .filter(|window| self.undersized_floated.contains(window))
+            .copied()
+            .collect();
+        for window in &previously {
+            self.set_client_floating(workspace_id, *window, false);
+        }
+
+        let area = self.workspace_area(workspace_id);
+        let undersized: Vec<(Window, (u32, u32))> = self
+            .tiled_geometry(workspace_id)
+            .into_iter()
+            .filter_map(|(window, tile)| {
+                let min_size = *self.min_sizes.get(&window)?;
+                (!fits_min_size(tile, min_size)).then_some((window, min_size))
+            })
+            .collect();
+        for &(window, (w, h)) in &undersized {
+            self.set_client_floating(workspace_id, window, true);
+            self.undersized_floated.insert(window);
+            // Already floating before this pass: keep wherever it was moved to.
+            if previously.contains(&window) {
+                continue;
+            }
+            let border = self.window_border_width(window);
+            let (outer_w, outer_h) = (w + 2 * border, h + 2 * border);
+            let rect = Rect {
+                x: area.x + (area.w.saturating_sub(outer_w) / 2) as i32,
+                y: area.y + (area.h.saturating_sub(outer_h) / 2) as i32,
+                w,
+                h,
             };
-            let layout = self.layout_manager.get_current_layout().generate_layout(
-                area,
-                &weights,
-                self.border_width,
-                self.window_gap,
-            );
-
-            effects = clients
+            self.floating_geometry.insert(window, rect);
+            info!("Floating {window:?}: its tile is smaller than {w}x{h}");
+        }
+        for window in previously {
+            if !undersized
                 .iter()
-                .zip(layout)
-                .map(|(client, rect)| Effect::Configure {
-                    window: client.window(),
-                    x: rect.x,
-                    y: rect.y,
-                    w: rect.w,
-                    h: rect.h,
-                    border: self.border_width,
-                })
-                .collect();
+                .any(|(undersized, _)| *undersized == window)
+            {
+                self.undersized_floated.remove(&window);
+                self.forget_floating_geometry(window);
+                info!("Tiling {window:?} again: its tile fits");
+            }
+        }
+    }
+
+    fn set_client_floating(&mut self, workspace_id: usize, window: Window, floating: bool) {
+        if let Some(client) = self
+            .get_workspace_mut(workspace_id)
+            .and_then(|workspace| workspace.get_client_mut(&window))
+        {
+            client.set_floating(floating);
         }
+    }
 
-        effects
+    /// Screen rects of the mapped, tiled windows of `workspace_id`, in tiling order.
+    fn tiled_geometry(&self, workspace_id: usize) -> Vec<(Window, Rect)> {
+        let Some(workspace) = self.get_workspace(workspace_id) else {
+            return vec![];
+        };
+
+        let clients: Vec<_> = workspace
+            .iter_clients()
+            .filter(|client| {
+                client.is_mapped()
+                    && !client.is_floating()
+                    && !self.tiling_skipped.contains(&client.window())
+            })
+            .collect();
+        if clients.is_empty() {
+            return vec![];
+        }
+
+        let weights: Vec<u32> = clients.iter().map(|client| client.size()).collect();
+        let splits: Vec<Split> = clients
+            .iter()
+            .map(|client| {
+                let (parent, direction) = client.split_from()?;
+                let parent = clients.iter().position(|c| c.window() == parent)?;
+                Some((parent, direction))
+            })
+            .collect();
+        let window_gap = self.effective_window_gap(workspace_id);
+        let border = self.effective_border_width(workspace_id);
+        let area = match self.outer_gap {
+            Some(outer_gap) => {
+                outer_gap_area(self.workspace_area(workspace_id), window_gap, outer_gap)
+            }
+            None => self.workspace_area(workspace_id),
+        };
+        let layout = self.layout_manager.generate(
+            workspace.layout(),
+            area,
+            &weights,
+            &splits,
+            border,
+            window_gap,
+            self.effective_nmaster(workspace_id),
+        );
+
+        // Layouts size every cell for the workspace's border; a window with its own
+        // border width gets the difference back so its outer size stays the same.
+        let border = border as i32;
+        clients
+            .iter()
+            .zip(layout)
+            .map(|(client, rect)| {
+                let extra = 2 * (border - self.window_border_width(client.window()) as i32);
+                let rect = Rect {
+                    x: rect.x + area.x,
+                    y: rect.y + area.y,
+                    w: (rect.w as i32 + extra).max(1) as u32,
+                    h: (rect.h as i32 + extra).max(1) as u32,
+                };
+                (client.window(), rect)
+            })
+            .collect()
+    }
+
+    pub fn current_geometry(&self) -> Vec<(Window, Rect)> {
+        self.tiled_geometry(self.current_workspace)
     }
 
     pub fn configure_dock_windows(&self) -> Effects {
         let mut effects = Vec::with_capacity(self.dock_windows.len());
-        let dock_y = (self.screen.height as i32) - (self.dock_height as i32);
 
         for &window in &self.dock_windows {
+            let dock_y = match self.dock_edge(window) {
+                DockEdge::Top => 0,
+                DockEdge::Bottom => (self.screen.height as i32) - (self.dock_height as i32),
+            };
             effects.push(Effect::ConfigurePositionSize {
                 window,
                 x: 0,
@@ -217,47 +937,413 @@ impl State {
         effects
     }
 
+    /// Border of the focused `window` drawn in `pixel`. Nothing for windows that
+    /// lost focus in the meantime or are shown without a border.
+    pub fn focus_border_effect(&self, window: Window, pixel: u32) -> Option<Effect> {
+        if self.focused_window() != Some(window) || self.is_window_fullscreen(window) {
+            return None;
+        }
+        let width = self.window_border_width(window);
+        (width > 0).then_some(Effect::SetBorder {
+            window,
+            pixel,
+            width,
+        })
+    }
+
     pub fn set_focus(&mut self, window: Window) -> Effects {
+        let Some(change) = self.transition_focus(window) else {
+            return vec![];
+        };
+        let mut effects = self.focus_change_effects(change);
+        // The border moved with the focus, so both windows' sizes change.
+        if self.only_focused_border && change.previous != Some(change.focused) {
+            effects.extend(self.configure_windows(self.current_workspace));
+        }
+        effects
+    }
+
+    /// Focus bookkeeping behind `set_focus`, without any effects: moves the
+    /// current workspace's focus to `window` (or to the modal dialog blocking it,
+    /// in strict mode). Returns `None` if the window can't take focus: it isn't a
+    /// mapped window of the current workspace, or a fullscreen window holds focus.
+    fn transition_focus(&mut self, window: Window) -> Option<FocusChange> {
         if let Some(fs) = self.current_workspace().get_fullscreen_window()
             && self.current_workspace().is_window_mapped(&fs)
         {
-            return vec![];
+            return None;
         }
 
-        let mut effects = Vec::new();
+        let window = match self.blocking_modal(window) {
+            Some(modal) if self.strict_modal => modal,
+            _ => window,
+        };
+
+        let previous = self.current_workspace().get_focus_window();
+        if !self.current_workspace_mut().set_focus(window) {
+            return None;
+        }
+        if self.focus_denied.remove(&window) {
+            self.urgent_windows.remove(&window);
+        }
+        Some(FocusChange {
+            previous,
+            focused: window,
+        })
+    }
 
+    /// Borders, input focus and stacking for a focus change. Re-focusing the
+    /// focused window still sends the focus, in case X lost it.
+    fn focus_change_effects(&self, change: FocusChange) -> Effects {
+        let FocusChange { previous, focused } = change;
         let fullscreen_window = self.current_workspace().get_fullscreen_window();
-        let previous_focus = self.current_workspace().get_focus_window();
-        if self.current_workspace_mut().set_focus(window) {
-            if let Some(previous_window) = previous_focus {
-                effects.push(Effect::SetBorder {
-                    window: previous_window,
-                    pixel: self.screen.normal_border_pixel,
-                    width: if fullscreen_window == Some(previous_window) {
-                        0
-                    } else {
-                        self.border_width
-                    },
-                });
+        let border_width = |window: Window| {
+            if fullscreen_window == Some(window) {
+                0
+            } else {
+                self.window_border_width(window)
             }
+        };
 
+        let mut effects = Vec::new();
+        if let Some(previous) = previous
+            && previous != focused
+        {
             effects.push(Effect::SetBorder {
-                window,
-                pixel: self.screen.focused_border_pixel,
-                width: if fullscreen_window == Some(window) {
-                    0
-                } else {
-                    self.border_width
-                },
+                window: previous,
+                pixel: self.screen.normal_border_pixel,
+                width: border_width(previous),
             });
-            effects.push(Effect::Focus(window));
-            if fullscreen_window == Some(window) {
-                effects.push(Effect::Raise(window));
-            }
+        }
+        effects.push(Effect::SetBorder {
+            window: focused,
+            pixel: self.screen.focused_border_pixel,
+            width: border_width(focused),
+        });
+        effects.push(Effect::Focus(focused));
+        if fullscreen_window == Some(focused) || self.is_window_floating(focused) {
+            effects.push(self.raise_effect(focused));
+        }
+        effects
+    }
+
+    /// Takes the focused window out of the tiling (it keeps its current geometry
+    /// and is not raised like a floating window), or tiles it again.
+    pub fn toggle_tiling_skip(&mut self) -> Effects {
+        let Some(focused) = self.focused_window() else {
+            return vec![];
+        };
+        if self.is_window_fullscreen(focused) {
+            return vec![];
+        }
+
+        if !self.tiling_skipped.remove(&focused) {
+            self.tiling_skipped.insert(focused);
+        }
+        self.configure_windows(self.current_workspace)
+    }
+
+    /// Floats the focused tiled window at its current tiled rect, or tiles the
+    /// focused floating window again.
+    pub fn toggle_floating(&mut self) -> Effects {
+        let Some(focused) = self.focused_window() else {
+            return vec![];
+        };
+        if self.is_window_fullscreen(focused) {
+            return vec![];
+        }
+
+        let floating = !self.is_window_floating(focused);
+        // From here on the user decides; a later pass starts over from the tile.
+        self.undersized_floated.remove(&focused);
+        if floating {
+            let Some((_, rect)) = self
+                .current_geometry()
+                .into_iter()
+                .find(|(window, _)| *window == focused)
+            else {
+                return vec![];
+            };
+            self.floating_geometry.insert(focused, rect);
+        } else {
+            self.forget_floating_geometry(focused);
+        }
+
+        if let Some(client) = self.current_workspace_mut().get_client_mut(&focused) {
+            client.set_floating(floating);
+        }
+
+        let mut effects = self.configure_windows(self.current_workspace);
+        if floating {
+            effects.push(self.raise_effect(focused));
         }
         effects
     }
 
+    /// Whether `window` floats without a floating rect of its own yet, as
+    /// windows that float natively (dialogs, transients, ...) do when mapped.
+    pub fn needs_floating_geometry(&self, window: Window) -> bool {
+        self.is_window_floating(window) && !self.floating_geometry.contains_key(&window)
+    }
+
+    /// Gives a floating `window` without a floating rect the `rect` it has on
+    /// screen, so moving, resizing and snapping it start from there.
+    pub fn seed_floating_geometry(&mut self, window: Window, rect: Rect) {
+        if self.needs_floating_geometry(window) {
+            self.floating_geometry.insert(window, rect);
+        }
+    }
+
+    fn forget_floating_geometry(&mut self, window: Window) {
+        self.floating_geometry.remove(&window);
+        self.pre_max_vertical.remove(&window);
+        self.pre_max_horizontal.remove(&window);
+    }
+
+    /// Stretches the focused floating window over the usable height (`vertical`)
+    /// or width of its workspace, border included, or restores the span it had
+    /// before. The other axis is left alone.
+    pub fn toggle_float_maximize(&mut self, vertical: bool) -> Effects {
+        let Some(window) = self.focused_window() else {
+            return vec![];
+        };
+        if !self.is_window_floating(window) {
+            return vec![];
+        }
+        let Some(&rect) = self.floating_geometry.get(&window) else {
+            return vec![];
+        };
+
+        let area = self.workspace_area(self.current_workspace);
+        let border = 2 * self.window_border_width(window);
+        let saved = if vertical {
+            &mut self.pre_max_vertical
+        } else {
+            &mut self.pre_max_horizontal
+        };
+        let rect = match (saved.remove(&window), vertical) {
+            (Some((y, h)), true) => Rect { y, h, ..rect },
+            (Some((x, w)), false) => Rect { x, w, ..rect },
+            (None, true) => {
+                saved.insert(window, (rect.y, rect.h));
+                Rect {
+                    y: area.y,
+                    h: area.h.saturating_sub(border).max(1),
+                    ..rect
+                }
+            }
+            (None, false) => {
+                saved.insert(window, (rect.x, rect.w));
+                Rect {
+                    x: area.x,
+                    w: area.w.saturating_sub(border).max(1),
+                    ..rect
+                }
+            }
+        };
+        self.floating_geometry.insert(window, rect);
+        self.configure_windows(self.current_workspace)
+    }
+
+    /// Moves and/or resizes a floating `window` as a client asked, e.g. through
+    /// `_NET_MOVERESIZE_WINDOW`. `resize` gets its current rect and border width.
+    /// Tiled, fullscreen and unknown windows are left alone.
+    pub fn move_resize_floating(
+        &mut self,
+        window: Window,
+        resize: impl FnOnce(Rect, u32) -> Rect,
+    ) -> Effects {
+        if !self.is_window_floating(window) || self.is_window_fullscreen(window) {
+            return vec![];
+        }
+        let Some(&rect) = self.floating_geometry.get(&window) else {
+            return vec![];
+        };
+
+        let border = self.window_border_width(window);
+        let rect = resize(rect, border);
+        self.pre_max_vertical.remove(&window);
+        self.pre_max_horizontal.remove(&window);
+        self.floating_geometry.insert(window, rect);
+        if self.frames.contains_key(&window) {
+            return self.configure_effects(window, rect, border);
+        }
+        // Re-sent on the next configure pass, whatever the cache held.
+        self.configured.remove(&window);
+        vec![Effect::ConfigurePositionSize {
+            window,
+            x: rect.x,
+            y: rect.y,
+            w: rect.w,
+            h: rect.h,
+        }]
+    }
+
+    /// Moves the focused floating window into `region` of the usable area, border
+    /// included. Any saved maximize state is dropped.
+    pub fn snap_floating(&mut self, region: SnapRegion) -> Effects {
+        let Some(window) = self.focused_window() else {
+            return vec![];
+        };
+        if !self.is_window_floating(window) || self.is_window_fullscreen(window) {
+            return vec![];
+        }
+
+        let border = 2 * self.window_border_width(window);
+        let target = region.rect(self.workspace_area(self.current_workspace));
+        let rect = Rect {
+            w: target.w.saturating_sub(border).max(1),
+            h: target.h.saturating_sub(border).max(1),
+            ..target
+        };
+        self.pre_max_vertical.remove(&window);
+        self.pre_max_horizontal.remove(&window);
+        self.floating_geometry.insert(window, rect);
+        self.configure_windows(self.current_workspace)
+    }
+
+    /// Moves the focused floating window to the middle of the usable area,
+    /// border included, keeping its size.
+    pub fn center_floating(&mut self) -> Effects {
+        let Some(window) = self.focused_window() else {
+            return vec![];
+        };
+        let area = self.workspace_area(self.current_workspace);
+        self.move_resize_floating(window, |rect, border| Rect {
+            x: area.x + (area.w as i32 - (rect.w + 2 * border) as i32) / 2,
+            y: area.y + (area.h as i32 - (rect.h + 2 * border) as i32) / 2,
+            ..rect
+        })
+    }
+
+    /// Starts dragging `window` if it is a floating window with a known rect,
+    /// grabbed at root position (`x`, `y`).
+    pub fn begin_drag(&mut self, window: Window, x: i32, y: i32) -> bool {
+        if !self.is_window_floating(window) || self.is_window_fullscreen(window) {
+            return false;
+        }
+        let Some(rect) = self.floating_geometry.get(&window) else {
+            return false;
+        };
+        self.drag = Some(Drag {
+            window,
+            offset: (x - rect.x, y - rect.y),
+        });
+        true
+    }
+
+    /// Follows the pointer with the dragged window, snapping it to the edges of the
+    /// usable area and of the other windows on its workspace.
+    pub fn drag_to(&mut self, x: i32, y: i32) -> Effects {
+        let Some(Drag { window, offset }) = self.drag else {
+            return vec![];
+        };
+        let Some(&rect) = self.floating_geometry.get(&window) else {
+            self.drag = None;
+            return vec![];
+        };
+
+        let outer = |window: Window, rect: Rect| {
+            let border = 2 * self.window_border_width(window);
+            Rect {
+                w: rect.w + border,
+                h: rect.h + border,
+                ..rect
+            }
+        };
+        let Some(workspace_id) = self.window_workspace(window) else {
+            return vec![];
+        };
+        let others: Vec<Rect> = self
+            .tiled_geometry(workspace_id)
+            .into_iter()
+            .chain(
+                self.floating_geometry
+                    .iter()
+                    .filter(|(other, _)| {
+                        **other != window && self.window_workspace(**other) == Some(workspace_id)
+                    })
+                    .map(|(other, rect)| (*other, *rect)),
+            )
+            .map(|(other, rect)| outer(other, rect))
+            .collect();
+
+        let moved = Rect {
+            x: x - offset.0,
+            y: y - offset.1,
+            ..rect
+        };
+        let usable = self.workspace_area(workspace_id);
+        let snapped = constrain_on_screen(
+            snap(outer(window, moved), &others, usable, SNAP_THRESHOLD),
+            usable,
+            MIN_VISIBLE,
+        );
+        let rect = Rect {
+            x: snapped.x,
+            y: snapped.y,
+            ..rect
+        };
+        self.floating_geometry.insert(window, rect);
+        self.configure_effects(window, rect, self.window_border_width(window))
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag = None;
+    }
+
+    /// Starts interactive placement of a newly mapped floating `window` of `size`,
+    /// centering it on `pointer`. Windows that already have a floating rect are
+    /// left where they are.
+    pub fn begin_placement(
+        &mut self,
+        window: Window,
+        size: (u32, u32),
+        pointer: (i32, i32),
+    ) -> Effects {
+        if !self.is_window_floating(window) || self.floating_geometry.contains_key(&window) {
+            return vec![];
+        }
+        self.floating_geometry.insert(
+            window,
+            Rect {
+                x: 0,
+                y: 0,
+                w: size.0,
+                h: size.1,
+            },
+        );
+        self.placing = Some(window);
+        self.place_at(pointer.0, pointer.1)
+    }
+
+    /// Keeps the window being placed centered on the pointer.
+    pub fn place_at(&mut self, x: i32, y: i32) -> Effects {
+        let Some(window) = self.placing else {
+            return vec![];
+        };
+        let Some(&rect) = self.floating_geometry.get(&window) else {
+            return vec![];
+        };
+        let border = self.window_border_width(window);
+        let rect = Rect {
+            x: x - ((rect.w + 2 * border) / 2) as i32,
+            y: y - ((rect.h + 2 * border) / 2) as i32,
+            ..rect
+        };
+        self.floating_geometry.insert(window, rect);
+        self.configure_effects(window, rect, border)
+    }
+
+    /// Leaves the window being placed where it is. Returns whether one was.
+    pub fn commit_placement(&mut self) -> bool {
+        self.placing.take().is_some()
+    }
+
+    pub const fn is_placing(&self) -> bool {
+        self.placing.is_some()
+    }
+
     pub fn toggle_fullscreen(&mut self) -> Effects {
         let Some(focused) = self.current_workspace().get_focus_window() else {
             return vec![];
@@ -272,7 +1358,14 @@ impl State {
             self.current_workspace_mut().clear_fullscreen();
         } else {
             self.current_workspace_mut().set_fullscreen(focused);
-            effects.push(Effect::Raise(focused));
+            // `set_focus` below is a no-op while the window is fullscreen, so
+            // drop its border here.
+            effects.push(Effect::SetBorder {
+                window: focused,
+                pixel: self.screen.focused_border_pixel,
+                width: 0,
+            });
+            effects.push(self.raise_effect(focused));
         }
 
         effects.extend(self.configure_windows(self.current_workspace));
@@ -280,21 +1373,20 @@ impl State {
         effects
     }
 
-    pub fn focus_window(&mut self, window: Window, desktop_hint: Option<usize>) -> Effects {
+    pub fn focus_window(&mut self, window: Window) -> Effects {
         let mut effects = Vec::new();
 
-        let workspace_id = self.window_workspace(window).or(desktop_hint);
+        let Some(workspace_id) = self.window_workspace(window) else {
+            return effects;
+        };
 
         if self.current_workspace().get_fullscreen_window().is_some() {
             return effects;
         } //We don't want our focus to be stolen if we are fullscreen
 
-        let Some(workspace_id) = workspace_id else {
-            return effects;
-        };
-
-        if workspace_id < NUM_WORKSPACES && workspace_id != self.current_workspace {
-            effects.extend(self.go_to_workspace(workspace_id));
+        if workspace_id != self.current_workspace {
+            effects.extend(self.focus_monitor(workspace_id / NUM_WORKSPACES));
+            effects.extend(self.go_to_workspace(workspace_id % NUM_WORKSPACES));
         }
 
         effects.extend(self.set_focus(window));
@@ -302,14 +1394,57 @@ impl State {
         effects
     }
 
+    /// Focuses `window` wherever it is, switching to its workspace first and
+    /// restoring it if it is minimized. The one entry point for requests to
+    /// activate a window, such as `_NET_ACTIVE_WINDOW`.
+    pub fn focus_window_by_id(&mut self, window: Window) -> Effects {
+        if self.is_window_hidden(window) {
+            self.unminimize(window)
+        } else {
+            self.focus_window(window)
+        }
+    }
+
+    /// Tags the focused window with `mark`, taking the mark from any other window.
+    pub fn set_mark(&mut self, mark: char) -> Effects {
+        if let Some(window) = self.focused_window() {
+            self.marks.insert(mark, window);
+        }
+        vec![]
+    }
+
+    /// Focuses the window tagged with `mark`, switching workspace if needed.
+    pub fn jump_to_mark(&mut self, mark: char) -> Effects {
+        match self.marks.get(&mark) {
+            Some(&window) => self.focus_window_by_id(window),
+            None => vec![],
+        }
+    }
+
+    /// Moves `window` from wherever it is to the current workspace and focuses it.
+    pub fn pull_window(&mut self, window: Window) -> Effects {
+        if self.window_workspace(window).is_none() {
+            return vec![];
+        }
+        let mut effects = self.move_window_to_global_workspace(window, self.current_workspace);
+        effects.extend(self.focus_window(window));
+        effects
+    }
+
     pub fn go_to_workspace(&mut self, new_workspace_id: usize) -> Effects {
         let mut effects: Effects = vec![];
 
-        if self.current_workspace == new_workspace_id || new_workspace_id >= NUM_WORKSPACES {
+        if new_workspace_id >= NUM_WORKSPACES {
+            return effects;
+        }
+
+        let new_workspace_id = self.global_workspace(new_workspace_id);
+        if self.current_workspace == new_workspace_id {
             return effects;
         }
 
         let old_workspace_id = self.current_workspace;
+        self.carry_sticky_windows(old_workspace_id, new_workspace_id);
         let old_windows: Vec<Window> = self
             .workspaces
             .get(old_workspace_id)
@@ -329,12 +1464,93 @@ impl State {
         }
 
         for win in old_windows {
-            effects.push(Effect::Unmap(win));
+            effects.extend(self.unmap_effects(win));
+        }
+
+        effects.extend(self.show_workspace(new_workspace_id));
+        effects
+    }
+
+    /// Windows of the current workspace that should be on screen: mapped as far
+    /// as `State` knows, and not minimized.
+    pub fn current_mapped_windows(&self) -> Vec<Window> {
+        let workspace = self.current_workspace();
+        workspace
+            .iter_windows()
+            .filter(|window| workspace.is_window_mapped(window))
+            .filter(|window| !self.hidden_windows.contains(window))
+            .copied()
+            .collect()
+    }
+
+    /// Fail-safe against windows left unmapped by mistake: maps again every
+    /// window in `unviewable` (as reported by the server) that should be on
+    /// screen, and re-tiles if any were found. Windows the client withdrew are
+    /// no longer mapped in `State` and are left alone.
+    pub fn reconcile_current_workspace_maps(&mut self, unviewable: &[Window]) -> Effects {
+        let unmapped: Vec<Window> = self
+            .current_mapped_windows()
+            .into_iter()
+            .filter(|window| unviewable.contains(window))
+            .collect();
+        if unmapped.is_empty() {
+            return vec![];
+        }
+
+        let mut effects = Vec::new();
+        for window in unmapped {
+            warn!("Window {window:?} was unmapped on the current workspace, mapping it again");
+            effects.extend(self.map_effects(window));
+        }
+        effects.extend(self.configure_windows(self.current_workspace));
+        effects
+    }
+
+    /// Moves the sticky windows of workspace `from` to `to` when the monitor
+    /// switches workspaces. Focusing another monitor leaves them where they are.
+    fn carry_sticky_windows(&mut self, from: usize, to: usize) {
+        let sticky: Vec<Window> = self
+            .workspaces
+            .get(from)
+            .into_iter()
+            .flat_map(Workspace::iter_windows)
+            .filter(|window| self.sticky_windows.contains(window))
+            .copied()
+            .collect();
+        for &window in &sticky {
+            self.carry_window(window, from, to);
+        }
+    }
+
+    /// Moves a sticky window along with the view, keeping it mapped and floating
+    /// as before.
+    fn carry_window(&mut self, window: Window, from: usize, to: usize) {
+        let floating = self.is_window_floating(window);
+        if let Some(source) = self.workspaces.get_mut(from) {
+            source.remove_client(window);
+        }
+        if let Some(target) = self.workspaces.get_mut(to) {
+            target.push_window(window);
+            if let Some(client) = target.get_client_mut(&window) {
+                client.set_floating(floating);
+            }
         }
+        self.window_to_workspace.insert(window, to);
+    }
 
-        self.current_workspace = new_workspace_id;
+    /// Makes `workspace_id` the one shown on the active monitor, mapping and
+    /// tiling its windows.
+    fn show_workspace(&mut self, workspace_id: usize) -> Effects {
+        let mut effects = Vec::new();
+        self.current_workspace = workspace_id;
+        self.monitors[self.active_monitor].workspace = workspace_id;
 
-        let new_windows: Vec<Window> = self.current_workspace().iter_windows().copied().collect();
+        let new_windows: Vec<Window> = self
+            .current_workspace()
+            .iter_windows()
+            .filter(|window| !self.hidden_windows.contains(window))
+            .copied()
+            .collect();
 
         {
             let new_ws = self.current_workspace_mut();
@@ -344,23 +1560,106 @@ impl State {
         }
 
         for win in new_windows {
-            effects.push(Effect::Map(win));
+            effects.extend(self.map_effects(win));
         }
 
         effects.extend(self.configure_windows(self.current_workspace));
-        if let Some(focus) = self.current_workspace().get_focus_window() {
+        if let Some(focus) = self.switch_focus_target(workspace_id) {
             effects.extend(self.set_focus(focus));
         }
 
         effects
     }
 
+    pub const fn set_pointer(&mut self, x: i32, y: i32) {
+        self.pointer = Some((x, y));
+    }
+
+    fn switch_focus_target(&self, workspace_id: usize) -> Option<Window> {
+        let workspace = self.get_workspace(workspace_id)?;
+        match self.focus_on_switch {
+            FocusOnSwitch::LastFocused => workspace.get_focus_window(),
+            FocusOnSwitch::FirstWindow => workspace
+                .iter_clients()
+                .find(|client| client.is_mapped())
+                .map(|client| client.window()),
+            FocusOnSwitch::UnderPointer => self
+                .pointer
+                .and_then(|(x, y)| {
+                    // Floating windows sit above the tiles, so they win.
+                    workspace
+                        .iter_windows()
+                        .filter(|window| workspace.is_window_mapped(window))
+                        .find_map(|window| {
+                            self.floating_geometry
+                                .get(window)
+                                .filter(|rect| rect.contains(x, y))
+                                .map(|_| *window)
+                        })
+                        .or_else(|| {
+                            self.tiled_geometry(workspace_id)
+                                .into_iter()
+                                .find(|(_, rect)| rect.contains(x, y))
+                                .map(|(window, _)| window)
+                        })
+                })
+                .or(workspace.get_focus_window()),
+        }
+    }
+
+    pub fn focus_monitor(&mut self, monitor: usize) -> Effects {
+        let mut effects = Vec::new();
+        if monitor == self.active_monitor || monitor >= self.monitors.len() {
+            return effects;
+        }
+
+        if let Some(previous) = self.focused_window() {
+            effects.push(Effect::SetBorder {
+                window: previous,
+                pixel: self.screen.normal_border_pixel,
+                width: if self.is_window_fullscreen(previous) {
+                    0
+                } else {
+                    self.window_border_width(previous)
+                },
+            });
+        }
+
+        self.active_monitor = monitor;
+        self.current_workspace = self.monitors[monitor].workspace;
+
+        // An empty workspace takes focus away from the previous monitor's window.
+        match self.focused_window() {
+            Some(focus) => effects.extend(self.set_focus(focus)),
+            None => effects.push(Effect::FocusRoot),
+        }
+
+        let (x, y) = self.monitors[monitor].center();
+        effects.push(Effect::WarpPointer { x, y });
+        effects
+    }
+
+    /// Goes to the next (`direction` > 0) or previous workspace of the active
+    /// monitor, wrapping around.
+    pub fn cycle_workspace(&mut self, direction: isize) -> Effects {
+        let next =
+            (self.current_workspace_id() as isize + direction).rem_euclid(NUM_WORKSPACES as isize);
+        self.go_to_workspace(next as usize)
+    }
+
+    pub fn cycle_monitor(&mut self, direction: isize) -> Effects {
+        let next =
+            (self.active_monitor as isize + direction).rem_euclid(self.monitors.len() as isize);
+        self.focus_monitor(next as usize)
+    }
+
     pub fn send_to_workspace(&mut self, workspace_id: usize) -> Effects {
         let mut effects = Vec::new();
         if workspace_id >= NUM_WORKSPACES || workspace_id == self.current_workspace_id() {
             return effects;
         }
 
+        let workspace_id = self.global_workspace(workspace_id);
         if let Some(window_to_send) = self.current_workspace_mut().removed_focused_window()
             && let Some(new_workspace) = self.workspaces.get_mut(workspace_id)
         {
@@ -369,11 +1668,11 @@ impl State {
             self.window_to_workspace
                 .insert(window_to_send, workspace_id);
 
-            effects.push(Effect::Unmap(window_to_send));
+            effects.extend(self.unmap_effects(window_to_send));
             effects.push(Effect::SetBorder {
                 window: window_to_send,
                 pixel: self.screen.normal_border_pixel,
-                width: self.border_width,
+                width: self.window_border_width(window_to_send),
             });
 
             effects.extend(self.configure_windows(self.current_workspace));
@@ -381,756 +1680,5123 @@ impl State {
             if let Some(focus) = self.current_workspace().get_focus_window() {
                 effects.extend(self.set_focus(focus));
             }
+            effects.extend(self.leave_empty_workspace());
         }
 
         effects
     }
 
-    pub fn increase_window_weight(&mut self, increment: u32) -> Effects {
-        if let Some(focused_win) = self.current_workspace_mut().get_focused_client_mut() {
-            focused_win.increase_window_size(increment);
-            return self.configure_windows(self.current_workspace);
+    /// Applies `EMPTY_WORKSPACE` once the current workspace may have lost its
+    /// last window.
+    fn leave_empty_workspace(&mut self) -> Effects {
+        if self.empty_workspace == EmptyWorkspace::Stay
+            || self.current_workspace().iter_windows().next().is_some()
+        {
+            return vec![];
         }
 
-        vec![]
-    }
-
-    pub fn decrease_window_weight(&mut self, increment: u32) -> Effects {
-        if let Some(focused_win) = self.current_workspace_mut().get_focused_client_mut() {
-            focused_win.decrease_window_size(increment);
-            return self.configure_windows(self.current_workspace);
+        let current = self.current_workspace_id();
+        let previous = (1..NUM_WORKSPACES)
+            .map(|offset| (current + NUM_WORKSPACES - offset) % NUM_WORKSPACES)
+            .find(|&workspace_id| {
+                self.get_workspace(self.global_workspace(workspace_id))
+                    .is_some_and(|workspace| workspace.iter_windows().next().is_some())
+            });
+        match previous {
+            Some(workspace_id) => self.go_to_workspace(workspace_id),
+            None => vec![],
         }
-        vec![]
-    }
-
-    pub fn increase_window_gap(&mut self, increment: u32) -> Effects {
-        self.window_gap += increment;
-        self.configure_windows(self.current_workspace)
     }
 
-    pub fn decrease_window_gap(&mut self, decrement: u32) -> Effects {
-        let new_gap = self.window_gap.saturating_sub(decrement);
-
-        if new_gap == self.window_gap {
-            return vec![];
+    /// Moves `window` to workspace `workspace_id` of the monitor it is on, which
+    /// need not be the active one.
+    pub fn move_window_to_workspace(&mut self, window: Window, workspace_id: usize) -> Effects {
+        if workspace_id >= NUM_WORKSPACES {
+            return Vec::new();
         }
-
-        self.window_gap = new_gap;
-        self.configure_windows(self.current_workspace)
+        let Some(current) = self.window_workspace(window) else {
+            return Vec::new();
+        };
+        let monitor = current / NUM_WORKSPACES;
+        self.move_window_to_global_workspace(window, monitor * NUM_WORKSPACES + workspace_id)
     }
 
-    pub fn shift_focus(&mut self, direction: isize) -> Effects {
-        let Some(next_focus) = self.current_workspace().next_mapped_window(direction) else {
-            warn!("Failed to retrieve next focus");
-            return vec![];
+    /// Moves the focused window to the workspace currently shown on the next
+    /// (`direction` > 0) or previous monitor. Focus stays on the active monitor.
+    pub fn send_to_monitor(&mut self, direction: isize) -> Effects {
+        if self.monitors.len() < 2 {
+            return Vec::new();
+        }
+        let Some(window) = self.focused_window() else {
+            return Vec::new();
         };
 
-        self.set_focus(next_focus)
+        let target =
+            (self.active_monitor as isize + direction).rem_euclid(self.monitors.len() as isize);
+        let workspace_id = self.monitors[target as usize].workspace;
+        self.move_window_to_global_workspace(window, workspace_id)
     }
 
-    pub fn swap_window(&mut self, direction: isize) -> Effects {
-        let current_workspace = self.current_workspace_mut();
-        if current_workspace.get_fullscreen_window().is_some() {
+    /// Deals the current workspace's tiled windows out round-robin to the
+    /// workspaces shown on each monitor, starting with the active one, so every
+    /// monitor ends up with an even share.
+    pub fn spread_across_monitors(&mut self) -> Effects {
+        let count = self.monitors.len();
+        if count < 2 {
             return vec![];
         }
-        let Some(next_window) = current_workspace.next_mapped_window(direction) else {
-            return vec![];
-        };
 
-        let Some(focus) = current_workspace.get_focus_window() else {
-            return vec![];
-        };
-
-        current_workspace.swap_windows(&focus, &next_window);
+        let windows: Vec<Window> = self
+            .current_workspace()
+            .iter_clients()
+            .filter(|client| client.is_mapped() && !client.is_floating())
+            .map(|client| client.window())
+            .filter(|window| !self.sticky_windows.contains(window))
+            .collect();
+        let targets: Vec<usize> = (0..count)
+            .map(|offset| self.monitors[(self.active_monitor + offset) % count].workspace)
+            .collect();
 
-        let mut effects = vec![];
-        effects.extend(self.configure_windows(self.current_workspace));
+        let mut effects = Vec::new();
+        for (index, window) in windows.into_iter().enumerate() {
+            effects.extend(self.move_window_to_global_workspace(window, targets[index % count]));
+        }
         effects
     }
 
-    pub fn on_map_request(&mut self, window: Window, window_type: WindowType) -> Effects {
-        match window_type {
-            WindowType::Unmanaged => vec![Effect::Map(window)],
-            WindowType::Dock => self.handle_map_request_dock(window),
-            WindowType::Managed => self.handle_map_request_managed(window),
-        }
-    }
+    /// Moves every window on another workspace, of any monitor, to the current
+    /// one. Sticky windows are already everywhere and minimized ones stay put.
+    pub fn gather_all(&mut self) -> Effects {
+        let windows: Vec<Window> = self
+            .window_to_workspace
+            .iter()
+            .filter(|(window, workspace_id)| {
+                **workspace_id != self.current_workspace
+                    && !self.sticky_windows.contains(window)
+                    && !self.hidden_windows.contains(window)
+            })
+            .map(|(window, _)| *window)
+            .collect();
 
-    fn handle_map_request_dock(&mut self, window: Window) -> Effects {
         let mut effects = Vec::new();
-
-        if !self
-            .dock_windows
-            .iter()
-            .any(|w| w.resource_id() == window.resource_id())
-        {
-            self.dock_windows.push(window);
+        for window in windows {
+            effects.extend(self.move_window_to_global_workspace(window, self.current_workspace));
         }
-
-        effects.push(Effect::Map(window));
-        effects.extend(self.configure_dock_windows());
-        effects.extend(self.configure_windows(self.current_workspace));
         effects
     }
 
-    fn handle_map_request_managed(&mut self, window: Window) -> Effects {
+    fn move_window_to_global_workspace(&mut self, window: Window, workspace_id: usize) -> Effects {
         let mut effects = Vec::new();
+        let Some(source_id) = self.window_workspace(window) else {
+            return effects;
+        };
+        if workspace_id == source_id {
+            return effects;
+        }
 
-        match self.current_workspace_mut().get_client_mut(&window) {
-            Some(client) => {
-                client.set_mapped(true);
-            }
-            None => {
-                self.current_workspace_mut().push_window(window);
-                self.window_to_workspace
-                    .insert(window, self.current_workspace);
+        let floating = self.is_window_floating(window);
+        let source_visible = self.monitor_showing(source_id).is_some();
+        let target_visible = self.monitor_showing(workspace_id).is_some();
+
+        if let Some(source) = self.workspaces.get_mut(source_id) {
+            source.remove_client(window);
+        }
+        if let Some(target) = self.workspaces.get_mut(workspace_id) {
+            target.push_window(window);
+            if let Some(client) = target.get_client_mut(&window) {
+                client.set_floating(floating);
             }
+            target.set_client_mapped(&window, target_visible);
         }
+        self.window_to_workspace.insert(window, workspace_id);
 
-        effects.push(Effect::Map(window));
-        effects.push(Effect::GrabButton(window));
-        effects.push(Effect::SubscribeEnterNotify(window));
+        match (source_visible, target_visible) {
+            (false, false) => return effects,
+            (true, false) => effects.extend(self.unmap_effects(window)),
+            (false, true) => effects.extend(self.map_effects(window)),
+            (true, true) => {}
+        }
 
-        if let Some(fs) = self.current_workspace().get_fullscreen_window()
-            && self.current_workspace().is_window_mapped(&fs)
-        {
-            effects.extend(self.configure_windows(self.current_workspace));
-        } else {
-            effects.extend(self.set_focus(window));
-            effects.extend(self.configure_windows(self.current_workspace));
+        effects.push(Effect::SetBorder {
+            window,
+            pixel: self.screen.normal_border_pixel,
+            width: self.window_border_width(window),
+        });
+
+        if source_visible {
+            effects.extend(self.configure_windows(source_id));
+        }
+        if target_visible {
+            effects.extend(self.configure_windows(workspace_id));
+        }
+
+        if let Some(focus) = self.current_workspace().get_focus_window() {
+            effects.extend(self.set_focus(focus));
         }
 
         effects
     }
 
-    pub fn on_destroy(&mut self, window: Window) -> Effects {
-        match self.tracked_window_type(window) {
-            WindowType::Dock => self.handle_destroy_event_dock(window),
-            WindowType::Managed => self.handle_destroy_event_managed(window),
-            WindowType::Unmanaged => vec![],
+    /// Grows (`grow`) or shrinks the weight of the tiled window shown by
+    /// `toplevel` by `WEIGHT_STEP` when a scroll at `x`,`y` (relative to the
+    /// inside of `toplevel`, as in the button event) lands on its border.
+    /// Returns `None` for scrolls that belong to the client.
+    pub fn scroll_border_weight(
+        &mut self,
+        toplevel: Window,
+        x: i32,
+        y: i32,
+        grow: bool,
+    ) -> Option<Effects> {
+        if !self.border_scroll_weight {
+            return None;
+        }
+        let window = self.client_for_toplevel(toplevel)?;
+        // A frame starts at the outer corner of the client's border.
+        let (x, y) = if window == toplevel {
+            (x, y)
+        } else {
+            let border = self.window_border_width(window) as i32;
+            (x - border, y - border)
+        };
+        let workspace_id = self.window_workspace(window)?;
+        let (_, rect) = self
+            .tiled_geometry(workspace_id)
+            .into_iter()
+            .find(|(tiled, _)| *tiled == window)?;
+        if !on_border(rect, x, y) {
+            return None;
         }
-    }
 
-    fn handle_destroy_event_dock(&mut self, window: Window) -> Effects {
-        let window_id = window.resource_id();
-        self.dock_windows.retain(|w| w.resource_id() != window_id);
+        let client = self
+            .get_workspace_mut(workspace_id)?
+            .get_client_mut(&window)?;
+        if grow {
+            client.increase_window_size(WEIGHT_STEP);
+        } else {
+            client.decrease_window_size(WEIGHT_STEP);
+        }
+        Some(self.configure_windows(workspace_id))
+    }
 
-        let mut effects = Vec::new();
-        if !self.dock_windows.is_empty() {
-            effects.extend(self.configure_dock_windows());
+    pub fn increase_window_weight(&mut self, increment: u32) -> Effects {
+        if let Some(focused_win) = self.current_workspace_mut().get_focused_client_mut() {
+            focused_win.increase_window_size(increment);
+            return self.configure_windows(self.current_workspace);
         }
 
-        effects.extend(self.configure_windows(self.current_workspace));
-        effects
+        vec![]
     }
 
-    fn handle_destroy_event_managed(&mut self, window: Window) -> Effects {
-        if let Some(workspace_id) = self.window_to_workspace.remove(&window)
-            && let Some(current_workspace) = self.workspaces.get_mut(workspace_id)
-        {
-            current_workspace.remove_client(window);
+    pub fn decrease_window_weight(&mut self, increment: u32) -> Effects {
+        if let Some(focused_win) = self.current_workspace_mut().get_focused_client_mut() {
+            focused_win.decrease_window_size(increment);
+            return self.configure_windows(self.current_workspace);
         }
+        vec![]
+    }
 
-        let mut effects = Vec::new();
-        effects.extend(self.configure_windows(self.current_workspace));
-        if let Some(focus) = self.current_workspace().get_focus_window() {
-            effects.extend(self.set_focus(focus));
-        }
-        effects
+    /// Widens the focused tiled window by `percent` of its and its neighbor's
+    /// combined weight, taking the difference from the neighbor only.
+    pub fn grow_active(&mut self, percent: u32) -> Effects {
+        self.shift_active_boundary(percent, true)
     }
 
-    pub fn on_unmap(&mut self, window: Window) -> Effects {
-        match self.tracked_window_type(window) {
-            WindowType::Dock => vec![],
-            WindowType::Managed => self.handle_unmap_event_managed(window),
-            WindowType::Unmanaged => vec![],
-        }
+    pub fn shrink_active(&mut self, percent: u32) -> Effects {
+        self.shift_active_boundary(percent, false)
     }
 
-    fn handle_unmap_event_managed(&mut self, window: Window) -> Effects {
-        let Some(workspace_id) = self.window_workspace(window) else {
+    /// Moves weight between the focused window and the next tiled window (the
+    /// previous one for the last window), so only the boundary between them moves.
+    fn shift_active_boundary(&mut self, percent: u32, grow: bool) -> Effects {
+        let Some(focused) = self.current_workspace().get_focus_window() else {
+            return vec![];
+        };
+        let tiled: Vec<Window> = self
+            .tiled_geometry(self.current_workspace)
+            .into_iter()
+            .map(|(window, _)| window)
+            .collect();
+        let Some(index) = tiled.iter().position(|window| *window == focused) else {
             return vec![];
         };
+        let neighbor = match tiled.get(index + 1) {
+            Some(next) => *next,
+            None if index > 0 => tiled[index - 1],
+            None => return vec![],
+        };
 
-        let mut changed = false;
-        if let Some(workspace) = self.workspaces.get_mut(workspace_id)
-            && let Some(client) = workspace.get_client_mut(&window)
-            && client.is_mapped()
-        {
-            workspace.set_client_mapped(&window, false);
-            changed = true;
+        let workspace = self.current_workspace_mut();
+        let pair: u32 = [focused, neighbor]
+            .iter()
+            .filter_map(|window| workspace.get_client_mut(window).map(|client| client.size()))
+            .sum();
+        let amount = (pair * percent / 100).max(1);
+        let (from, to) = if grow {
+            (neighbor, focused)
+        } else {
+            (focused, neighbor)
+        };
+        if !workspace.transfer_weight(&from, &to, amount) {
+            return vec![];
         }
+        self.configure_windows(self.current_workspace)
+    }
 
-        if workspace_id != self.current_workspace {
+    pub fn equalize_weights(&mut self) -> Effects {
+        if !self.current_workspace_mut().equalize_weights() {
             return vec![];
         }
+        self.configure_windows(self.current_workspace)
+    }
 
-        if !changed {
+    pub fn increase_window_gap(&mut self, increment: u32) -> Effects {
+        let gap = self.effective_window_gap(self.current_workspace) + increment;
+        self.set_current_window_gap(gap);
+        self.configure_windows(self.current_workspace)
+    }
+
+    pub fn decrease_window_gap(&mut self, decrement: u32) -> Effects {
+        let gap = self.effective_window_gap(self.current_workspace);
+        let new_gap = gap.saturating_sub(decrement);
+
+        if new_gap == gap {
             return vec![];
         }
 
-        let mut effects = Vec::new();
-        effects.extend(self.configure_windows(self.current_workspace));
-        effects
+        self.set_current_window_gap(new_gap);
+        self.configure_windows(self.current_workspace)
     }
 
-    pub fn apply_action(&mut self, action: ActionEvent) -> Effects {
-        match action {
-            ActionEvent::NextWindow => self.shift_focus(1),
-            ActionEvent::PrevWindow => self.shift_focus(-1),
-            ActionEvent::IncreaseWindowWeight(increment) => self.increase_window_weight(increment),
-            ActionEvent::DecreaseWindowWeight(increment) => self.decrease_window_weight(increment),
-            ActionEvent::SwapLeft => self.swap_window(-1),
-            ActionEvent::SwapRight => self.swap_window(1),
-            ActionEvent::GoToWorkspace(workspace_id) => self.go_to_workspace(workspace_id),
-            ActionEvent::SendToWorkspace(workspace_id) => self.send_to_workspace(workspace_id),
-            ActionEvent::IncreaseWindowGap(increment) => self.increase_window_gap(increment),
-            ActionEvent::DecreaseWindowGap(increment) => self.decrease_window_gap(increment),
-            ActionEvent::ToggleFullscreen => self.toggle_fullscreen(),
-            ActionEvent::CycleLayout => self.cycle_layout(),
-            _ => vec![],
+    /// Changes the gap of the current workspace if it has its own, else the
+    /// global one.
+    fn set_current_window_gap(&mut self, gap: u32) {
+        let workspace = self.current_workspace_mut();
+        if workspace.gap().is_some() {
+            workspace.set_gap(Some(gap));
+        } else {
+            self.window_gap = gap;
         }
     }
 
-    pub fn track_startup_dock(&mut self, window: Window) {
-        if !self
-            .dock_windows
-            .iter()
-            .any(|w| w.resource_id() == window.resource_id())
-        {
-            self.dock_windows.push(window);
+    pub fn increase_nmaster(&mut self) -> Effects {
+        let tiled = self
+            .current_workspace()
+            .iter_clients()
+            .filter(|client| client.is_mapped())
+            .count();
+        let nmaster = self.effective_nmaster(self.current_workspace);
+        let new_nmaster = (nmaster + 1).min(tiled.max(1));
+
+        if new_nmaster == nmaster {
+            return vec![];
         }
+
+        self.current_workspace_mut().set_nmaster(Some(new_nmaster));
+        self.configure_windows(self.current_workspace)
     }
 
-    pub fn track_startup_managed(&mut self, window: Window, workspace_id: usize) {
-        if let Some(ws) = self.get_workspace_mut(workspace_id) {
-            ws.push_window(window);
-            self.window_to_workspace.insert(window, workspace_id);
+    pub fn decrease_nmaster(&mut self) -> Effects {
+        let nmaster = self.effective_nmaster(self.current_workspace);
+        let new_nmaster = nmaster.saturating_sub(1).max(1);
+
+        if new_nmaster == nmaster {
+            return vec![];
         }
+
+        self.current_workspace_mut().set_nmaster(Some(new_nmaster));
+        self.configure_windows(self.current_workspace)
     }
 
-    pub fn startup_finalize(&mut self, current_desktop: Option<usize>) -> Effects {
-        let mut effects = Vec::new();
+    pub fn toggle_borders(&mut self) -> Effects {
+        self.borders_enabled = !self.borders_enabled;
 
-        // Set up button grabs and enter-notify subscriptions for all managed windows
-        for ws in &self.workspaces {
-            for window in ws.iter_windows() {
-                effects.push(Effect::GrabButton(*window));
-                effects.push(Effect::SubscribeEnterNotify(*window));
-            }
-        }
+        let focused = self.focused_window();
+        let mut effects: Effects = self
+            .managed_windows_sorted()
+            .into_iter()
+            .map(|window| Effect::SetBorder {
+                window,
+                pixel: if Some(window) == focused {
+                    self.screen.focused_border_pixel
+                } else {
+                    self.screen.normal_border_pixel
+                },
+                width: if self.is_window_fullscreen(window) {
+                    0
+                } else {
+                    self.window_border_width(window)
+                },
+            })
+            .collect();
 
-        if !self.dock_windows.is_empty() {
-            effects.extend(self.configure_dock_windows());
-        }
+        effects.extend(self.configure_windows(self.current_workspace));
+        effects
+    }
 
-        if let Some(workspace_id) = current_desktop {
-            self.current_workspace = (workspace_id + 1) % NUM_WORKSPACES;
-            effects.extend(self.go_to_workspace(workspace_id));
-            return effects;
+    /// Unmaps every dock and gives its space back to the tiled windows, or maps
+    /// them again and restores the reserved area.
+    pub fn toggle_docks(&mut self) -> Effects {
+        self.docks_hidden = !self.docks_hidden;
+
+        let mut effects: Effects = self
+            .dock_windows
+            .iter()
+            .map(|&window| {
+                if self.docks_hidden {
+                    Effect::Unmap(window)
+                } else {
+                    Effect::Map(window)
+                }
+            })
+            .collect();
+        if !self.docks_hidden {
+            effects.extend(self.configure_dock_windows());
         }
 
+        effects.extend(self.configure_visible_workspaces());
         effects
     }
-}
 
-#[cfg(test)]
-mod state_tests {
-    use xcb::XidNew;
+    pub fn shift_focus(&mut self, direction: isize) -> Effects {
+        let Some(next_focus) = self.current_workspace().next_mapped_window(direction) else {
+            warn!("Failed to retrieve next focus");
+            return vec![];
+        };
 
-    use super::*;
+        self.set_focus(next_focus)
+    }
 
-    fn make_state_with_windows(windows: &[(usize, u32, bool)], dock_height: u32) -> State {
-        let screen = ScreenConfig {
-            width: 800,
-            height: 600,
-            focused_border_pixel: 0,
-            normal_border_pixel: 1,
-        };
+    /// Mapped, tiled windows past the master area of the current workspace.
+    fn stack_windows(&self) -> Vec<Window> {
+        self.current_workspace()
+            .iter_clients()
+            .filter(|client| client.is_mapped() && !client.is_floating())
+            .map(|client| client.window())
+            .skip(self.effective_nmaster(self.current_workspace))
+            .collect()
+    }
 
-        let mut state = State::new(screen, 1, 0, dock_height);
+    pub fn focus_stack(&mut self, direction: isize) -> Effects {
+        let stack = self.stack_windows();
+        if stack.is_empty() {
+            return vec![];
+        }
 
-        for (workspace_id, window_id, mapped) in windows {
-            let window = Window::new(*window_id);
-            state.track_startup_managed(window, *workspace_id);
-            if !*mapped {
-                let workspace = state.get_workspace_mut(*workspace_id).unwrap();
-                workspace.set_client_mapped(&window, false);
-            }
+        let focused = self.focused_window();
+        let next_index = match focused.and_then(|f| stack.iter().position(|w| *w == f)) {
+            Some(index) => (index as isize + direction).rem_euclid(stack.len() as isize) as usize,
+            None if direction < 0 => stack.len() - 1,
+            None => 0,
+        };
+
+        let next_focus = stack[next_index];
+        if Some(next_focus) == focused {
+            return vec![];
         }
 
-        state
+        self.set_focus(next_focus)
     }
 
-    fn find_configure_height(effects: &[Effect], window: Window) -> Option<u32> {
-        effects.iter().find_map(|effect| match effect {
-            Effect::Configure { window: w, h, .. } if *w == window => Some(*h),
-            _ => None,
-        })
+    /// Focuses the first tiled window (`last` false), i.e. the master, or the last
+    /// one, i.e. the bottom of the stack.
+    pub fn focus_tiled_end(&mut self, last: bool) -> Effects {
+        let tiled = self.current_geometry();
+        let target = if last { tiled.last() } else { tiled.first() };
+        let Some(&(window, _)) = target else {
+            return vec![];
+        };
+        if self.focused_window() == Some(window) {
+            return vec![];
+        }
+        self.set_focus(window)
     }
 
-    fn make_state(num_of_clients_per_workspace: u32) -> State {
-        let screen = ScreenConfig {
-            width: 800,
-            height: 600,
-            focused_border_pixel: 0,
-            normal_border_pixel: 1,
+    /// Focuses and raises the mapped floating window after the focused one on the
+    /// current workspace, wrapping around. Tiled windows are skipped.
+    pub fn cycle_floating(&mut self) -> Effects {
+        let floating: Vec<Window> = self
+            .current_workspace()
+            .iter_clients()
+            .filter(|client| client.is_mapped() && client.is_floating())
+            .map(|client| client.window())
+            .collect();
+        if floating.is_empty() {
+            return vec![];
+        }
+
+        let focused = self.focused_window();
+        let next_focus = match focused.and_then(|f| floating.iter().position(|w| *w == f)) {
+            Some(index) => floating[(index + 1) % floating.len()],
+            None => floating[0],
         };
-        let mut state = State::new(screen, 1, 0, 25);
-        for i in 0..(num_of_clients_per_workspace * NUM_WORKSPACES as u32) {
-            let workspace_id: usize = (i as usize) / NUM_WORKSPACES;
-            let window = Window::new(i);
-            state.track_startup_managed(window, workspace_id);
-            if workspace_id > 0 {
-                let workspace = state.get_workspace_mut(workspace_id).unwrap();
-                workspace.set_client_mapped(&window, false);
-            }
+        if Some(next_focus) == focused {
+            return vec![self.raise_effect(next_focus)];
         }
 
-        state
+        self.set_focus(next_focus)
     }
 
-    #[test]
-    fn test_set_focus() {
-        let mut state = make_state(10);
+    /// The focused window and its neighbor in `direction` on screen, as found by
+    /// `search` over the current layout. `None` while a window is fullscreen.
+    fn spatial_neighbor(
+        &self,
+        direction: Direction,
+        search: fn(&[Rect], usize, Direction) -> Option<usize>,
+    ) -> Option<(Window, Window)> {
+        if self.current_workspace().get_fullscreen_window().is_some() {
+            return None;
+        }
+        let focused = self.focused_window()?;
+
+        let geometry = self.current_geometry();
+        let rects: Vec<Rect> = geometry.iter().map(|(_, rect)| *rect).collect();
+        let from = geometry.iter().position(|(window, _)| *window == focused)?;
+        search(&rects, from, direction).map(|i| (focused, geometry[i].0))
+    }
+
+    /// Makes the next mapped window open on the `direction` side of the focused
+    /// tiled window, splitting its cell in the BSP layout. Preselecting the same
+    /// side again cancels it.
+    pub fn preselect(&mut self, direction: Direction) -> Effects {
+        let Some(focused) = self.focused_window() else {
+            return vec![];
+        };
+        if self.is_window_floating(focused) {
+            return vec![];
+        }
+        self.preselection = if self.preselection == Some((focused, direction)) {
+            None
+        } else {
+            Some((focused, direction))
+        };
+        vec![]
+    }
+
+    /// Focuses the window next to the focused one in `direction` on screen,
+    /// wrapping to the opposite edge if `FOCUS_WRAP` is set.
+    pub fn focus_direction(&mut self, direction: Direction) -> Effects {
+        let search = if self.focus_wrap {
+            wrapping_neighbor_in_direction
+        } else {
+            neighbor_in_direction
+        };
+        match self.spatial_neighbor(direction, search) {
+            Some((_, neighbor)) => self.set_focus(neighbor),
+            None => vec![],
+        }
+    }
+
+    /// Swaps the focused window with its neighbor in `direction` on screen.
+    pub fn move_window(&mut self, direction: Direction) -> Effects {
+        let Some((focused, neighbor)) = self.spatial_neighbor(direction, neighbor_in_direction)
+        else {
+            return vec![];
+        };
+
+        self.current_workspace_mut()
+            .swap_windows(&focused, &neighbor);
+        self.configure_windows(self.current_workspace)
+    }
+
+    pub fn swap_window(&mut self, direction: isize) -> Effects {
+        let current_workspace = self.current_workspace_mut();
+        if current_workspace.get_fullscreen_window().is_some() {
+            return vec![];
+        }
+        let Some(next_window) = current_workspace.next_mapped_window(direction) else {
+            return vec![];
+        };
+
+        let Some(focus) = current_workspace.get_focus_window() else {
+            return vec![];
+        };
+
+        current_workspace.swap_windows(&focus, &next_window);
+
+        let mut effects = vec![];
+        effects.extend(self.configure_windows(self.current_workspace));
+        effects
+    }
+
+    /// Swaps the positions of the focused and previously focused windows; focus
+    /// stays on the same window.
+    pub fn swap_last_two(&mut self) -> Effects {
+        let current_workspace = self.current_workspace_mut();
+        if current_workspace.get_fullscreen_window().is_some() {
+            return vec![];
+        }
+        let (Some(focus), Some(previous)) = (
+            current_workspace.get_focus_window(),
+            current_workspace.get_previous_focus_window(),
+        ) else {
+            return vec![];
+        };
+
+        current_workspace.swap_windows(&focus, &previous);
+        self.configure_windows(self.current_workspace)
+    }
+
+    /// Promotes the focused tiled window to master. If it already is master, the
+    /// next tiled window (the previous master after an earlier zoom) takes its place.
+    pub fn zoom(&mut self) -> Effects {
+        if self.current_workspace().get_fullscreen_window().is_some() {
+            return vec![];
+        }
+        let Some(focus) = self.focused_window() else {
+            return vec![];
+        };
+
+        let tiled: Vec<Window> = self
+            .current_geometry()
+            .into_iter()
+            .map(|(window, _)| window)
+            .collect();
+        let promoted = match tiled.iter().position(|window| *window == focus) {
+            Some(0) => match tiled.get(1) {
+                Some(&next) => next,
+                None => return vec![],
+            },
+            Some(_) => focus,
+            None => return vec![],
+        };
+
+        self.current_workspace_mut()
+            .move_window_before(&promoted, &tiled[0]);
+
+        let mut effects = self.configure_windows(self.current_workspace);
+        effects.extend(self.set_focus(promoted));
+        effects
+    }
+
+    /// Exchanges the focused tiled window with the master (or, from the master,
+    /// with the next tiled window) without moving any other window, then keeps
+    /// focus on the same screen position: the window that moved into the spot
+    /// the focused window had is focused afterwards.
+    pub fn zoom_in_place(&mut self) -> Effects {
+        if self.current_workspace().get_fullscreen_window().is_some() {
+            return vec![];
+        }
+        let Some(focus) = self.focused_window() else {
+            return vec![];
+        };
+
+        let tiled: Vec<Window> = self
+            .current_geometry()
+            .into_iter()
+            .map(|(window, _)| window)
+            .collect();
+        let partner = match tiled.iter().position(|window| *window == focus) {
+            Some(0) => match tiled.get(1) {
+                Some(&next) => next,
+                None => return vec![],
+            },
+            Some(_) => tiled[0],
+            None => return vec![],
+        };
+
+        self.current_workspace_mut().swap_windows(&focus, &partner);
+
+        let mut effects = self.configure_windows(self.current_workspace);
+        effects.extend(self.set_focus(partner));
+        effects
+    }
+
+    pub fn on_map_request(&mut self, window: Window, window_type: WindowType) -> Effects {
+        match window_type {
+            WindowType::Unmanaged => vec![Effect::Map(window)],
+            WindowType::Dock => self.handle_map_request_dock(window),
+            WindowType::Managed => self.handle_map_request_managed(window, false, true),
+            WindowType::Floating => self.handle_map_request_managed(window, true, true),
+        }
+    }
+
+    /// Like `on_map_request`, but leaves focus where it is. Used when the
+    /// window's `_NET_WM_USER_TIME` says it shouldn't steal focus.
+    pub fn on_map_request_unfocused(&mut self, window: Window, window_type: WindowType) -> Effects {
+        match window_type {
+            WindowType::Managed => self.handle_map_request_managed(window, false, false),
+            WindowType::Floating => self.handle_map_request_managed(window, true, false),
+            _ => self.on_map_request(window, window_type),
+        }
+    }
+
+    /// Maps a window whose `WM_TRANSIENT_FOR` points at a managed owner. The
+    /// transient floats, and focus returns to the owner once it is destroyed.
+    pub fn on_map_request_transient(&mut self, window: Window, owner: Window) -> Effects {
+        if self.window_workspace(owner).is_none() {
+            return self.on_map_request(window, WindowType::Floating);
+        }
+
+        self.transient_owners.insert(window, owner);
+        self.handle_map_request_managed(window, true, true)
+    }
+
+    /// Maps a modal dialog: it floats and takes focus like a transient. In strict
+    /// mode its owner can't be focused until the dialog goes away.
+    pub fn on_map_request_modal(&mut self, window: Window, owner: Option<Window>) -> Effects {
+        self.modal_windows.insert(window);
+        match owner {
+            Some(owner) => self.on_map_request_transient(window, owner),
+            None => self.on_map_request(window, WindowType::Floating),
+        }
+    }
+
+    /// Mapped modal dialog on the current workspace that blocks `owner`.
+    fn blocking_modal(&self, owner: Window) -> Option<Window> {
+        self.transient_owners
+            .iter()
+            .find(|(window, window_owner)| {
+                **window_owner == owner
+                    && self.modal_windows.contains(window)
+                    && self.current_workspace().is_window_mapped(window)
+            })
+            .map(|(window, _)| *window)
+    }
+
+    fn handle_map_request_dock(&mut self, window: Window) -> Effects {
+        let mut effects = Vec::new();
+
+        if !self
+            .dock_windows
+            .iter()
+            .any(|w| w.resource_id() == window.resource_id())
+        {
+            self.dock_windows.push(window);
+        }
+
+        if self.docks_hidden {
+            return effects;
+        }
+
+        effects.push(Effect::Map(window));
+        effects.extend(self.configure_dock_windows());
+        effects.extend(self.configure_windows(self.current_workspace));
+        effects
+    }
+
+    /// Events the WM selects on every managed window: enter for focus and property
+    /// changes for titles/urgency. Structure events are not selected here since
+    /// the parent's `SUBSTRUCTURE_NOTIFY` already reports them.
+    const fn client_event_mask_effect(window: Window) -> Effect {
+        Effect::SetEventMask {
+            window,
+            mask: EventMask::ENTER_WINDOW.union(EventMask::PROPERTY_CHANGE),
+        }
+    }
+
+    /// Index in the current workspace's window list where `ATTACH_MODE` puts a
+    /// new window, or `None` to append it.
+    fn attach_index(&self) -> Option<usize> {
+        let workspace = self.current_workspace();
+        match self.attach_mode {
+            AttachMode::Top => Some(0),
+            AttachMode::Bottom => None,
+            AttachMode::AfterFocused => workspace
+                .get_focus_window()
+                .and_then(|focused| workspace.index_of_window(&focused))
+                .map(|index| index + 1),
+            AttachMode::BelowMaster => Some(self.effective_nmaster(self.current_workspace)),
+        }
+    }
+
+    fn handle_map_request_managed(
+        &mut self,
+        window: Window,
+        floating: bool,
+        focus: bool,
+    ) -> Effects {
+        let mut effects = Vec::new();
+        self.hidden_windows.retain(|hidden| *hidden != window);
+
+        match self.current_workspace_mut().get_client_mut(&window) {
+            Some(client) => {
+                client.set_mapped(true);
+                client.set_floating(floating);
+            }
+            None => {
+                let split_from = self.preselection.filter(|(anchor, _)| {
+                    !floating
+                        && self.current_workspace().is_window_mapped(anchor)
+                        && !self.current_workspace().is_window_floating(anchor)
+                });
+                // A preselected window goes last so it comes after the window it
+                // splits from.
+                let attach_index = match split_from {
+                    Some(_) => None,
+                    None => self.attach_index(),
+                };
+                self.current_workspace_mut().push_window(window);
+                if let Some(index) = attach_index {
+                    self.current_workspace_mut()
+                        .move_window_to_index(&window, index);
+                }
+                if let Some(client) = self.current_workspace_mut().get_client_mut(&window) {
+                    client.set_floating(floating);
+                    client.set_split_from(split_from);
+                }
+                if split_from.is_some() {
+                    self.preselection = None;
+                }
+                self.window_to_workspace
+                    .insert(window, self.current_workspace);
+            }
+        }
+
+        effects.extend(self.map_effects(window));
+        effects.push(Effect::GrabButton(window));
+        if self.border_scroll_weight {
+            effects.push(Effect::GrabScroll(self.toplevel(window)));
+        }
+        effects.push(Self::client_event_mask_effect(window));
+
+        if let Some(fs) = self.current_workspace().get_fullscreen_window()
+            && self.current_workspace().is_window_mapped(&fs)
+        {
+            effects.extend(self.configure_windows(self.current_workspace));
+        } else if focus {
+            effects.extend(self.set_focus(window));
+            effects.extend(self.configure_windows(self.current_workspace));
+        } else {
+            effects.push(Effect::SetBorder {
+                window,
+                pixel: self.screen.normal_border_pixel,
+                width: self.window_border_width(window),
+            });
+            effects.extend(self.configure_windows(self.current_workspace));
+        }
+
+        effects
+    }
+
+    pub fn on_destroy(&mut self, window: Window) -> Effects {
+        match self.tracked_window_type(window) {
+            WindowType::Dock => self.handle_destroy_event_dock(window),
+            WindowType::Managed | WindowType::Floating => self.handle_destroy_event_managed(window),
+            WindowType::Unmanaged => vec![],
+        }
+    }
+
+    fn handle_destroy_event_dock(&mut self, window: Window) -> Effects {
+        let window_id = window.resource_id();
+        self.dock_windows.retain(|w| w.resource_id() != window_id);
+        self.dock_edges.remove(&window);
+
+        let mut effects = Vec::new();
+        if !self.dock_windows.is_empty() {
+            effects.extend(self.configure_dock_windows());
+        }
+
+        effects.extend(self.configure_windows(self.current_workspace));
+        effects
+    }
+
+    fn handle_destroy_event_managed(&mut self, window: Window) -> Effects {
+        let source = self.window_to_workspace.shift_remove(&window);
+        if let Some(workspace_id) = source
+            && let Some(current_workspace) = self.workspaces.get_mut(workspace_id)
+        {
+            current_workspace.remove_client(window);
+        }
+
+        self.forget_floating_geometry(window);
+        self.configured.remove(&window);
+        if self.placing == Some(window) {
+            self.placing = None;
+        }
+        self.border_overrides.remove(&window);
+        self.min_sizes.remove(&window);
+        self.undersized_floated.remove(&window);
+        if self
+            .preselection
+            .is_some_and(|(anchor, _)| anchor == window)
+        {
+            self.preselection = None;
+        }
+        self.sticky_windows.remove(&window);
+        self.tiling_skipped.remove(&window);
+        self.titles.remove(&window);
+        self.window_classes.remove(&window);
+        self.window_instances.remove(&window);
+        self.urgent_windows.remove(&window);
+        self.focus_denied.remove(&window);
+        self.marks.retain(|_, marked| *marked != window);
+        self.hidden_windows.retain(|hidden| *hidden != window);
+        self.modal_windows.remove(&window);
+        let owner = self.transient_owners.remove(&window);
+        self.transient_owners
+            .retain(|_, transient_owner| *transient_owner != window);
+
+        let mut effects = Vec::new();
+        if let Some(frame) = self.frames.remove(&window) {
+            effects.push(Effect::DestroyWindow(frame));
+        }
+        effects.extend(self.configure_windows(self.current_workspace));
+        let owner_on_current_workspace =
+            owner.filter(|owner| self.window_workspace(*owner) == Some(self.current_workspace));
+        if let Some(focus) =
+            owner_on_current_workspace.or(self.current_workspace().get_focus_window())
+        {
+            effects.extend(self.set_focus(focus));
+        }
+        if source == Some(self.current_workspace) {
+            effects.extend(self.leave_empty_workspace());
+        }
+        effects
+    }
+
+    /// Stops managing a window that another client reparented away from the root
+    /// (or from its frame) and re-tiles what is left.
+    pub fn on_reparent(&mut self, window: Window, new_parent: Window, root: Window) -> Effects {
+        if self.window_workspace(window).is_none()
+            || new_parent == root
+            || self.frames.get(&window) == Some(&new_parent)
+        {
+            return vec![];
+        }
+
+        self.handle_destroy_event_managed(window)
+    }
+
+    pub fn on_unmap(&mut self, window: Window) -> Effects {
+        match self.tracked_window_type(window) {
+            WindowType::Dock => vec![],
+            WindowType::Managed | WindowType::Floating => self.handle_unmap_event_managed(window),
+            WindowType::Unmanaged => vec![],
+        }
+    }
+
+    fn handle_unmap_event_managed(&mut self, window: Window) -> Effects {
+        let Some(workspace_id) = self.window_workspace(window) else {
+            return vec![];
+        };
+        if self.placing == Some(window) {
+            self.placing = None;
+        }
+        // The client may move or resize itself before mapping again.
+        self.configured.remove(&window);
+
+        let mut changed = false;
+        if let Some(workspace) = self.workspaces.get_mut(workspace_id)
+            && let Some(client) = workspace.get_client_mut(&window)
+            && client.is_mapped()
+        {
+            workspace.set_client_mapped(&window, false);
+            changed = true;
+        }
+
+        if workspace_id != self.current_workspace {
+            return vec![];
+        }
+
+        if !changed {
+            return vec![];
+        }
+
+        let mut effects = Vec::new();
+        if let Some(&frame) = self.frames.get(&window) {
+            effects.push(Effect::Unmap(frame));
+        }
+        effects.extend(self.configure_windows(self.current_workspace));
+        effects
+    }
+
+    pub fn apply_action(&mut self, action: ActionEvent) -> Effects {
+        match action {
+            ActionEvent::NextWindow => self.shift_focus(1),
+            ActionEvent::PrevWindow => self.shift_focus(-1),
+            ActionEvent::IncreaseWindowWeight(increment) => self.increase_window_weight(increment),
+            ActionEvent::DecreaseWindowWeight(increment) => self.decrease_window_weight(increment),
+            ActionEvent::SwapLeft => self.swap_window(-1),
+            ActionEvent::SwapRight => self.swap_window(1),
+            ActionEvent::GoToWorkspace(workspace_id) => self.go_to_workspace(workspace_id),
+            ActionEvent::GoToNextWorkspace => self.cycle_workspace(1),
+            ActionEvent::GoToPrevWorkspace => self.cycle_workspace(-1),
+            ActionEvent::SendToWorkspace(workspace_id) => self.send_to_workspace(workspace_id),
+            ActionEvent::IncreaseWindowGap(increment) => self.increase_window_gap(increment),
+            ActionEvent::DecreaseWindowGap(increment) => self.decrease_window_gap(increment),
+            ActionEvent::ToggleFullscreen => self.toggle_fullscreen(),
+            ActionEvent::CycleLayout => self.cycle_layout(),
+            ActionEvent::IncNMaster => self.increase_nmaster(),
+            ActionEvent::DecNMaster => self.decrease_nmaster(),
+            ActionEvent::ToggleBorders => self.toggle_borders(),
+            ActionEvent::ToggleDocks => self.toggle_docks(),
+            ActionEvent::ToggleFloating => self.toggle_floating(),
+            ActionEvent::ToggleTilingSkip => self.toggle_tiling_skip(),
+            ActionEvent::MaxVertical => self.toggle_float_maximize(true),
+            ActionEvent::MaxHorizontal => self.toggle_float_maximize(false),
+            ActionEvent::SnapHalfLeft => self.snap_floating(SnapRegion::HalfLeft),
+            ActionEvent::SnapHalfRight => self.snap_floating(SnapRegion::HalfRight),
+            ActionEvent::SnapTopLeft => self.snap_floating(SnapRegion::TopLeft),
+            ActionEvent::SnapTopRight => self.snap_floating(SnapRegion::TopRight),
+            ActionEvent::SnapBottomLeft => self.snap_floating(SnapRegion::BottomLeft),
+            ActionEvent::SnapBottomRight => self.snap_floating(SnapRegion::BottomRight),
+            ActionEvent::CenterWindow => self.center_floating(),
+            ActionEvent::Zoom => self.zoom(),
+            ActionEvent::ZoomInPlace => self.zoom_in_place(),
+            ActionEvent::SwapLastTwo => self.swap_last_two(),
+            ActionEvent::RotateLayout => self.rotate_layout(),
+            ActionEvent::FocusStackNext => self.focus_stack(1),
+            ActionEvent::CycleFloating => self.cycle_floating(),
+            ActionEvent::FocusMaster => self.focus_tiled_end(false),
+            ActionEvent::FocusLastStack => self.focus_tiled_end(true),
+            ActionEvent::Minimize => self.minimize(),
+            ActionEvent::RestoreLastMinimized => self.restore_last_minimized(),
+            ActionEvent::FocusStackPrev => self.focus_stack(-1),
+            ActionEvent::FocusMonitorNext => self.cycle_monitor(1),
+            ActionEvent::FocusMonitorPrev => self.cycle_monitor(-1),
+            ActionEvent::SendToMonitorNext => self.send_to_monitor(1),
+            ActionEvent::SpreadAcrossMonitors => self.spread_across_monitors(),
+            ActionEvent::GatherAll => self.gather_all(),
+            ActionEvent::SendToMonitorPrev => self.send_to_monitor(-1),
+            ActionEvent::EqualizeWeights => self.equalize_weights(),
+            ActionEvent::GrowActive(percent) => self.grow_active(percent),
+            ActionEvent::ShrinkActive(percent) => self.shrink_active(percent),
+            ActionEvent::FocusLeft => self.focus_direction(Direction::Left),
+            ActionEvent::FocusRight => self.focus_direction(Direction::Right),
+            ActionEvent::FocusUp => self.focus_direction(Direction::Up),
+            ActionEvent::FocusDown => self.focus_direction(Direction::Down),
+            ActionEvent::PreselectLeft => self.preselect(Direction::Left),
+            ActionEvent::PreselectRight => self.preselect(Direction::Right),
+            ActionEvent::PreselectUp => self.preselect(Direction::Up),
+            ActionEvent::PreselectDown => self.preselect(Direction::Down),
+            ActionEvent::SetMark(mark) => self.set_mark(mark),
+            ActionEvent::JumpToMark(mark) => self.jump_to_mark(mark),
+            ActionEvent::MoveWindowLeft => self.move_window(Direction::Left),
+            ActionEvent::MoveWindowRight => self.move_window(Direction::Right),
+            ActionEvent::MoveWindowUp => self.move_window(Direction::Up),
+            ActionEvent::MoveWindowDown => self.move_window(Direction::Down),
+            _ => vec![],
+        }
+    }
+
+    pub fn track_startup_dock(&mut self, window: Window) {
+        if !self
+            .dock_windows
+            .iter()
+            .any(|w| w.resource_id() == window.resource_id())
+        {
+            self.dock_windows.push(window);
+        }
+    }
+
+    pub fn track_startup_managed(&mut self, window: Window, workspace_id: usize) {
+        if workspace_id >= NUM_WORKSPACES {
+            return;
+        }
+
+        let workspace_id = self.global_workspace(workspace_id);
+        if let Some(ws) = self.get_workspace_mut(workspace_id) {
+            ws.push_window(window);
+            self.window_to_workspace.insert(window, workspace_id);
+        }
+    }
+
+    pub fn track_startup_floating(&mut self, window: Window, workspace_id: usize) {
+        self.track_startup_managed(window, workspace_id);
+        if let Some(client) = self
+            .window_workspace(window)
+            .and_then(|workspace_id| self.get_workspace_mut(workspace_id))
+            .and_then(|ws| ws.get_client_mut(&window))
+        {
+            client.set_floating(true);
+        }
+    }
+
+    /// Tracks a window found at startup with `_NET_WM_DESKTOP` set to all desktops.
+    pub fn track_startup_sticky(&mut self, window: Window, floating: bool) {
+        let workspace_id = self.current_workspace_id();
+        if floating {
+            self.track_startup_floating(window, workspace_id);
+        } else {
+            self.track_startup_managed(window, workspace_id);
+        }
+        if self.window_workspace(window).is_some() {
+            self.sticky_windows.insert(window);
+        }
+    }
+
+    pub fn set_window_title(&mut self, window: Window, title: Option<String>) {
+        if self.window_workspace(window).is_none() {
+            return;
+        }
+        match title {
+            Some(title) => self.titles.insert(window, title),
+            None => self.titles.remove(&window),
+        };
+    }
+
+    pub fn set_window_class(&mut self, window: Window, class: Option<String>) {
+        if self.window_workspace(window).is_none() {
+            return;
+        }
+        match class {
+            Some(class) => self.window_classes.insert(window, class),
+            None => self.window_classes.remove(&window),
+        };
+    }
+
+    pub fn set_window_instance(&mut self, window: Window, instance: Option<String>) {
+        if self.window_workspace(window).is_none() {
+            return;
+        }
+        match instance {
+            Some(instance) => self.window_instances.insert(window, instance),
+            None => self.window_instances.remove(&window),
+        };
+    }
+
+    /// `_NET_DESKTOP_NAMES` for the focused monitor's workspaces: the configured
+    /// name, else (with auto naming) the class of the focused or first window,
+    /// else the workspace number.
+    pub fn desktop_names(&self) -> Vec<String> {
+        (0..NUM_WORKSPACES)
+            .map(|workspace_id| {
+                if let Some(name) = self.workspace_names.get(&workspace_id) {
+                    return (*name).to_string();
+                }
+                if self.auto_name_workspaces
+                    && let Some(class) = self.workspace_class(self.global_workspace(workspace_id))
+                {
+                    return class.to_lowercase();
+                }
+                (workspace_id + 1).to_string()
+            })
+            .collect()
+    }
+
+    fn workspace_class(&self, workspace_id: usize) -> Option<&str> {
+        let workspace = self.workspaces.get(workspace_id)?;
+        workspace
+            .get_focus_window()
+            .into_iter()
+            .chain(workspace.iter_windows().copied())
+            .find_map(|window| self.window_classes.get(&window))
+            .map(String::as_str)
+    }
+
+    pub fn set_window_urgent(&mut self, window: Window, urgent: bool) {
+        if self.window_workspace(window).is_none() {
+            return;
+        }
+        if urgent || self.focus_denied.contains(&window) {
+            self.urgent_windows.insert(window);
+        } else {
+            self.urgent_windows.remove(&window);
+        }
+    }
+
+    /// Marks `window`, just mapped without focus, as wanting attention until it
+    /// is focused.
+    pub fn deny_focus_on_map(&mut self, window: Window) {
+        if self.window_workspace(window).is_none() {
+            return;
+        }
+        self.focus_denied.insert(window);
+        self.urgent_windows.insert(window);
+    }
+
+    pub fn is_window_hidden(&self, window: Window) -> bool {
+        self.hidden_windows.contains(&window)
+    }
+
+    /// Unmaps the focused window and leaves it out of the layout until it is
+    /// restored; focus moves to the next mapped window.
+    pub fn minimize(&mut self) -> Effects {
+        let Some(window) = self.focused_window() else {
+            return vec![];
+        };
+        if self.current_workspace().get_fullscreen_window().is_some() {
+            return vec![];
+        }
+
+        let next_focus = self.current_workspace().next_mapped_window(1);
+        self.current_workspace_mut()
+            .set_client_mapped(&window, false);
+        self.hidden_windows.push(window);
+
+        let mut effects = self.unmap_effects(window);
+        effects.extend(self.configure_windows(self.current_workspace));
+        match next_focus {
+            Some(next_focus) => effects.extend(self.set_focus(next_focus)),
+            None => self.current_workspace_mut().clear_focus(),
+        }
+        effects
+    }
+
+    /// Maps a minimized window again, re-tiles its workspace and focuses it,
+    /// switching to its workspace if needed. The window keeps the place in the
+    /// tiling order it had before it was minimized.
+    pub fn unminimize(&mut self, window: Window) -> Effects {
+        let Some(workspace_id) = self.window_workspace(window) else {
+            return vec![];
+        };
+        if !self.is_window_hidden(window) {
+            return vec![];
+        }
+        self.hidden_windows.retain(|hidden| *hidden != window);
+
+        let mut effects = Vec::new();
+        if workspace_id == self.current_workspace {
+            self.current_workspace_mut()
+                .set_client_mapped(&window, true);
+            effects.extend(self.map_effects(window));
+            effects.extend(self.configure_windows(workspace_id));
+        }
+        effects.extend(self.focus_window(window));
+        effects
+    }
+
+    /// Restores the most recently minimized window of the current workspace.
+    pub fn restore_last_minimized(&mut self) -> Effects {
+        let current = self.current_workspace;
+        let Some(window) = self
+            .hidden_windows
+            .iter()
+            .rev()
+            .find(|window| self.window_workspace(**window) == Some(current))
+            .copied()
+        else {
+            return vec![];
+        };
+        self.unminimize(window)
+    }
+
+    pub fn is_window_sticky(&self, window: Window) -> bool {
+        self.sticky_windows.contains(&window)
+    }
+
+    /// Human-readable description of everything the WM tracks, for bug reports.
+    /// Only workspaces that hold windows or are shown on a monitor are listed.
+    pub fn dump(&self) -> String {
+        let mut out = format!(
+            "border_width={} borders_enabled={} window_gap={} nmaster={} docks={:?} docks_hidden={}\n",
+            self.border_width,
+            self.borders_enabled,
+            self.window_gap,
+            self.nmaster,
+            self.dock_windows
+                .iter()
+                .map(|w| w.resource_id())
+                .collect::<Vec<_>>(),
+            self.docks_hidden,
+        );
+
+        for (monitor_id, monitor) in self.monitors.iter().enumerate() {
+            out.push_str(&format!(
+                "monitor {monitor_id} {}x{}+{}+{} showing workspace {}{}\n",
+                monitor.rect.w,
+                monitor.rect.h,
+                monitor.rect.x,
+                monitor.rect.y,
+                monitor.workspace % NUM_WORKSPACES,
+                if monitor_id == self.active_monitor {
+                    " (active)"
+                } else {
+                    ""
+                },
+            ));
+        }
+
+        let infos = self.window_infos();
+        for (workspace_id, workspace) in self.workspaces.iter().enumerate() {
+            if workspace.iter_windows().next().is_none()
+                && self.monitor_showing(workspace_id).is_none()
+            {
+                continue;
+            }
+
+            out.push_str(&format!(
+                "monitor {} workspace {} layout={} focus={:?}\n",
+                workspace_id / NUM_WORKSPACES,
+                workspace_id % NUM_WORKSPACES,
+                workspace.layout().name(),
+                workspace.get_focus_window().map(|w| w.resource_id()),
+            ));
+            for client in workspace.iter_clients() {
+                let window = client.window();
+                let info = infos.iter().find(|info| info.window == window);
+                let mut flags = Vec::new();
+                if client.is_mapped() {
+                    flags.push("mapped");
+                }
+                if client.is_floating() {
+                    flags.push("floating");
+                }
+                if self.tiling_skipped.contains(&window) {
+                    flags.push("skipped");
+                }
+                if info.is_some_and(|info| info.fullscreen) {
+                    flags.push("fullscreen");
+                }
+                if info.is_some_and(|info| info.focused) {
+                    flags.push("focused");
+                }
+                if self.urgent_windows.contains(&window) {
+                    flags.push("urgent");
+                }
+                let title = self
+                    .titles
+                    .get(&window)
+                    .map(|title| format!(" title={title:?}"))
+                    .unwrap_or_default();
+                let line = format!(
+                    "  window {} weight={}{title} {}",
+                    window.resource_id(),
+                    client.size(),
+                    flags.join(" ")
+                );
+                out.push_str(line.trim_end());
+                out.push('\n');
+            }
+        }
+
+        out
+    }
+
+    /// Writes the session as JSON: the current workspace, every workspace's
+    /// layout and every managed window in tiling order with its id, WM_CLASS,
+    /// title, workspace, weight and floating flag. Workspace indices are global.
+    pub fn serialize(&self) -> String {
+        let session = Session {
+            current: self.current_workspace,
+            layouts: self
+                .workspaces
+                .iter()
+                .map(|workspace| workspace.layout().name().to_string())
+                .collect(),
+            windows: self
+                .workspaces
+                .iter()
+                .enumerate()
+                .flat_map(|(workspace_id, workspace)| {
+                    workspace.iter_clients().map(move |client| SessionWindow {
+                        id: client.window().resource_id(),
+                        class: self.window_classes.get(&client.window()).cloned(),
+                        instance: self.window_instances.get(&client.window()).cloned(),
+                        title: self.titles.get(&client.window()).cloned(),
+                        workspace: workspace_id,
+                        weight: client.size(),
+                        floating: client.is_floating(),
+                    })
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&session).unwrap_or_default()
+    }
+
+    /// Applies a session written by `serialize` to the windows already tracked
+    /// by the startup scan. Saved windows are matched on their window id; only
+    /// those whose id is gone fall back to WM_CLASS and title, then WM_CLASS
+    /// alone. Windows left unmatched stay where the scan put them. Returns the saved current workspace, local to the active
+    /// monitor, to hand to `startup_finalize`.
+    pub fn deserialize(&mut self, data: &str) -> Option<usize> {
+        let session: Session = match serde_json::from_str(data) {
+            Ok(session) => session,
+            Err(e) => {
+                warn!("Ignoring malformed session: {e}");
+                return None;
+            }
+        };
+
+        for (workspace_id, name) in session.layouts.iter().enumerate() {
+            if let Some(layout) = LayoutType::from_name(name)
+                && let Some(workspace) = self.get_workspace_mut(workspace_id)
+            {
+                workspace.set_layout(layout);
+            }
+        }
+
+        let mut unclaimed = self.managed_windows_sorted();
+        let live: HashSet<u32> = unclaimed
+            .iter()
+            .map(|window| window.resource_id())
+            .collect();
+        let mut matched: Vec<Option<Window>> = session
+            .windows
+            .iter()
+            .map(|saved| {
+                let position = unclaimed
+                    .iter()
+                    .position(|window| window.resource_id() == saved.id)?;
+                Some(unclaimed.remove(position))
+            })
+            .collect();
+        for exact in [true, false] {
+            for (saved, slot) in session.windows.iter().zip(matched.iter_mut()) {
+                if slot.is_some() || live.contains(&saved.id) {
+                    continue;
+                }
+                let position = unclaimed.iter().position(|window| {
+                    saved.matches(
+                        self.window_classes.get(window).map(String::as_str),
+                        self.window_instances.get(window).map(String::as_str),
+                        self.titles.get(window).map(String::as_str),
+                        exact,
+                    )
+                });
+                *slot = position.map(|position| unclaimed.remove(position));
+            }
+        }
+
+        for (saved, window) in session.windows.iter().zip(matched) {
+            if let Some(window) = window {
+                self.restore_window(window, saved.workspace, saved.weight, saved.floating);
+            }
+        }
+
+        Some(session.current)
+            .filter(|id| *id / NUM_WORKSPACES == self.active_monitor)
+            .map(|id| id % NUM_WORKSPACES)
+    }
+
+    fn restore_window(&mut self, window: Window, workspace_id: usize, weight: u32, floating: bool) {
+        let Some(source_id) = self.window_workspace(window) else {
+            return;
+        };
+        if workspace_id >= self.workspaces.len() {
+            return;
+        }
+
+        // Re-push even when the workspace is unchanged so the saved order is kept.
+        if let Some(source) = self.get_workspace_mut(source_id) {
+            source.remove_client(window);
+        }
+        if let Some(target) = self.get_workspace_mut(workspace_id) {
+            target.push_window(window);
+            if let Some(client) = target.get_client_mut(&window) {
+                client.set_window_size(weight);
+                client.set_floating(floating);
+            }
+        }
+        self.window_to_workspace.insert(window, workspace_id);
+    }
+
+    pub fn startup_finalize(&mut self, current_desktop: Option<usize>) -> Effects {
+        let mut effects = Vec::new();
+
+        // Set up button grabs and enter-notify subscriptions for all managed windows
+        for ws in &self.workspaces {
+            for window in ws.iter_windows() {
+                effects.push(Effect::GrabButton(*window));
+                if self.border_scroll_weight {
+                    effects.push(Effect::GrabScroll(self.toplevel(*window)));
+                }
+                effects.push(Self::client_event_mask_effect(*window));
+            }
+        }
+
+        if !self.dock_windows.is_empty() {
+            effects.extend(self.configure_dock_windows());
+        }
+
+        for monitor in 0..self.monitors.len() {
+            if monitor != self.active_monitor {
+                effects.extend(self.configure_windows(self.monitors[monitor].workspace));
+            }
+        }
+
+        if let Some(workspace_id) = current_desktop.filter(|id| *id < NUM_WORKSPACES) {
+            if workspace_id == self.current_workspace_id() {
+                effects.extend(self.show_workspace(self.current_workspace));
+            } else {
+                effects.extend(self.go_to_workspace(workspace_id));
+            }
+        }
+
+        effects
+    }
+}
+
+#[cfg(test)]
+mod state_tests {
+    use xcb::XidNew;
+
+    use super::*;
+    use crate::config::{DEFAULT_BORDER_WIDTH, DEFAULT_WEIGHT, GAP_STEP, WEIGHT_STEP};
+
+    fn make_state_with_windows(windows: &[(usize, u32, bool)], dock_height: u32) -> State {
+        let screen = ScreenConfig {
+            width: 800,
+            height: 600,
+            focused_border_pixel: 0,
+            normal_border_pixel: 1,
+        };
+
+        let mut state = State::new(screen, 1, 0, dock_height);
+
+        for (workspace_id, window_id, mapped) in windows {
+            let window = Window::new(*window_id);
+            state.track_startup_managed(window, *workspace_id);
+            if !*mapped {
+                let workspace = state.get_workspace_mut(*workspace_id).unwrap();
+                workspace.set_client_mapped(&window, false);
+            }
+        }
+
+        state
+    }
+
+    fn bound_action(matches: fn(&ActionEvent) -> bool) -> ActionEvent {
+        crate::config::ACTION_MAPPINGS
+            .iter()
+            .map(|mapping| mapping.action)
+            .find(matches)
+            .expect("action should be bound in config")
+    }
+
+    fn find_configure_height(effects: &[Effect], window: Window) -> Option<u32> {
+        effects.iter().find_map(|effect| match effect {
+            Effect::Configure { window: w, h, .. } if *w == window => Some(*h),
+            _ => None,
+        })
+    }
+
+    fn make_state(num_of_clients_per_workspace: u32) -> State {
+        let screen = ScreenConfig {
+            width: 800,
+            height: 600,
+            focused_border_pixel: 0,
+            normal_border_pixel: 1,
+        };
+        let mut state = State::new(screen, 1, 0, 25);
+        for i in 0..(num_of_clients_per_workspace * NUM_WORKSPACES as u32) {
+            let workspace_id: usize = (i as usize) / NUM_WORKSPACES;
+            let window = Window::new(i);
+            state.track_startup_managed(window, workspace_id);
+            if workspace_id > 0 {
+                let workspace = state.get_workspace_mut(workspace_id).unwrap();
+                workspace.set_client_mapped(&window, false);
+            }
+        }
+
+        state
+    }
+
+    #[test]
+    fn test_set_focus() {
+        let mut state = make_state(10);
         let window_to_focus = Window::new(6);
         let effects = state.set_focus(window_to_focus);
 
-        assert_eq!(state.focused_window().unwrap(), window_to_focus);
-        assert!(effects.contains(&Effect::SetBorder {
-            window: Window::new(0),
-            pixel: state.screen.normal_border_pixel,
-            width: state.border_width
+        assert_eq!(state.focused_window().unwrap(), window_to_focus);
+        assert!(effects.contains(&Effect::SetBorder {
+            window: Window::new(0),
+            pixel: state.screen.normal_border_pixel,
+            width: state.border_width
+        }));
+        assert!(effects.contains(&Effect::SetBorder {
+            window: window_to_focus,
+            pixel: state.screen.focused_border_pixel,
+            width: state.border_width
+        }));
+        assert!(effects.contains(&Effect::Focus(window_to_focus)));
+    }
+
+    #[test]
+    fn test_toggle_fullscreen() {
+        let mut state = make_state(10);
+        let window_to_fullsreen = Window::new(6);
+        let _ = state.set_focus(window_to_fullsreen);
+        let mut fullscreen_effects = state.toggle_fullscreen();
+
+        // Test that we succesfully toggled window to fullscreen
+        assert_eq!(state.focused_window().unwrap(), window_to_fullsreen);
+        assert_eq!(
+            state.current_workspace().get_fullscreen_window().unwrap(),
+            window_to_fullsreen
+        );
+        assert!(state.is_window_fullscreen(window_to_fullsreen));
+        assert!(fullscreen_effects.contains(&Effect::Raise(window_to_fullsreen)));
+        assert!(fullscreen_effects.contains(&Effect::Configure {
+            window: window_to_fullsreen,
+            x: 0,
+            y: 0,
+            w: 800,
+            h: 600,
+            border: 0
+        }));
+
+        fullscreen_effects = state.toggle_fullscreen();
+
+        assert_eq!(state.focused_window().unwrap(), window_to_fullsreen);
+        assert_eq!(state.current_workspace().get_fullscreen_window(), None);
+        assert!(!state.is_window_fullscreen(window_to_fullsreen));
+        assert!(fullscreen_effects.contains(&Effect::Focus(window_to_fullsreen)))
+    }
+
+    #[test]
+    fn test_toggle_fullscreen_drops_and_restores_border() {
+        let screen = ScreenConfig {
+            width: 800,
+            height: 600,
+            focused_border_pixel: 0,
+            normal_border_pixel: 1,
+        };
+        let mut state = State::new(screen, DEFAULT_BORDER_WIDTH, 0, 0);
+        let window = Window::new(1);
+        state.track_startup_managed(window, 0);
+        let _ = state.set_focus(window);
+
+        let effects = state.toggle_fullscreen();
+        assert!(effects.contains(&Effect::SetBorder {
+            window,
+            pixel: 0,
+            width: 0,
+        }));
+
+        let effects = state.toggle_fullscreen();
+        assert!(effects.contains(&Effect::SetBorder {
+            window,
+            pixel: 0,
+            width: DEFAULT_BORDER_WIDTH,
+        }));
+        assert!(effects.iter().any(|effect| matches!(
+            effect,
+            Effect::Configure { window: w, border, .. }
+                if *w == window && *border == DEFAULT_BORDER_WIDTH
+        )));
+    }
+
+    #[test]
+    fn test_toggle_fullscreen_and_switch_focus() {
+        let mut state = make_state(10);
+        let window_to_fullsreen = Window::new(6);
+        let window_to_focus = Window::new(2);
+        let _ = state.set_focus(window_to_fullsreen);
+        let _fullscreen_effects = state.toggle_fullscreen();
+        let focus_effects = state.set_focus(window_to_focus);
+        // We assert that our focus has not been stolen
+        assert!(focus_effects.is_empty());
+    }
+
+    #[test]
+    fn test_toggle_fullscreen_and_kill_window() {
+        let mut state = make_state(10);
+        let window_to_fullsreen = Window::new(6);
+        let expected_focus = Window::new(7);
+        let _ = state.set_focus(window_to_fullsreen);
+        let _fullscreen_effects = state.toggle_fullscreen();
+        let destroy_effects = state.on_destroy(window_to_fullsreen);
+
+        assert!(!state.is_window_fullscreen(window_to_fullsreen));
+        assert_eq!(state.focused_window().unwrap(), expected_focus);
+        assert!(destroy_effects.contains(&Effect::Focus(expected_focus)));
+        assert_eq!(
+            destroy_effects
+                .iter()
+                .filter(|effect| matches!(
+                    effect,
+                    Effect::Configure {
+                        window: _,
+                        x: _,
+                        y: _,
+                        w: _,
+                        h: _,
+                        border: _
+                    }
+                ))
+                .collect::<Vec<&Effect>>()
+                .len(),
+            9
+        )
+    }
+
+    #[test]
+    fn test_toggle_fullscreen_and_send_to_workspace() {
+        let mut state = make_state(10);
+        let window_to_fullsreen = Window::new(6);
+        let expected_focus = Window::new(7);
+        let _ = state.set_focus(window_to_fullsreen);
+        let _fullscreen_effects = state.toggle_fullscreen();
+        let workspace_effects = state.send_to_workspace(1);
+
+        assert!(!state.is_window_fullscreen(window_to_fullsreen));
+        assert_eq!(state.window_workspace(window_to_fullsreen).unwrap(), 1);
+        assert!(
+            state
+                .get_workspace(0)
+                .unwrap()
+                .index_of_window(&window_to_fullsreen)
+                .is_none()
+        );
+        assert!(workspace_effects.contains(&Effect::Unmap(window_to_fullsreen)));
+        assert!(workspace_effects.contains(&Effect::Focus(expected_focus)));
+        assert_eq!(
+            workspace_effects
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Configure { .. }))
+                .collect::<Vec<&Effect>>()
+                .len(),
+            9
+        )
+    }
+
+    #[test]
+    fn test_toggle_fullscreen_and_go_to_workspace() {
+        let mut state = make_state(10);
+        let window_to_fullsreen = Window::new(6);
+        let _ = state.set_focus(window_to_fullsreen);
+        let _fullscreen_effects = state.toggle_fullscreen();
+        let workspace_effects = state.go_to_workspace(1);
+
+        assert!(!state.is_window_fullscreen(window_to_fullsreen));
+        assert_eq!(state.current_workspace_id(), 1);
+        assert_eq!(
+            workspace_effects
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Configure { .. }))
+                .collect::<Vec<&Effect>>()
+                .len(),
+            10
+        );
+        assert_eq!(
+            workspace_effects
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Unmap(_)))
+                .collect::<Vec<&Effect>>()
+                .len(),
+            10
+        );
+        assert_eq!(
+            workspace_effects
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Map(_)))
+                .collect::<Vec<&Effect>>()
+                .len(),
+            10
+        )
+    }
+
+    #[test]
+    fn test_fullscreen_then_map_request_does_not_steal_focus() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let fullscreen_window = Window::new(1);
+        let _ = state.set_focus(fullscreen_window);
+        let _ = state.toggle_fullscreen();
+
+        let new_window = Window::new(2);
+        let effects = state.on_map_request(new_window, WindowType::Managed);
+
+        assert_eq!(state.focused_window(), Some(fullscreen_window));
+        assert!(state.is_window_fullscreen(fullscreen_window));
+        assert!(effects.contains(&Effect::Map(new_window)));
+        assert!(!effects.contains(&Effect::Focus(new_window)));
+        assert!(state.current_workspace().is_window_mapped(&new_window));
+    }
+
+    #[test]
+    fn test_unmap_current_workspace_window_reconfigures() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 25);
+        let focus_window = Window::new(1);
+        let other_window = Window::new(2);
+
+        let _ = state.set_focus(focus_window);
+        let effects = state.on_unmap(other_window);
+
+        assert_eq!(state.focused_window(), Some(focus_window));
+        assert!(!state.current_workspace().is_window_mapped(&other_window));
+        assert_eq!(
+            effects
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Configure { .. }))
+                .collect::<Vec<&Effect>>()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_dock_reduces_configured_height() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let window = Window::new(1);
+
+        let effects_no_dock = state.configure_windows(0);
+        let height_no_dock = find_configure_height(&effects_no_dock, window).unwrap();
+
+        state.track_startup_dock(Window::new(99));
+        let effects_with_dock = state.configure_windows(0);
+        let height_with_dock = find_configure_height(&effects_with_dock, window).unwrap();
+
+        assert_eq!(height_no_dock, 598);
+        assert_eq!(height_with_dock, 573);
+        assert!(height_with_dock < height_no_dock);
+    }
+
+    #[test]
+    fn test_focus_border_effect_only_for_focused_bordered_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        assert_eq!(
+            state.focus_border_effect(Window::new(1), 0xff0000),
+            Some(Effect::SetBorder {
+                window: Window::new(1),
+                pixel: 0xff0000,
+                width: 1,
+            })
+        );
+        assert_eq!(state.focus_border_effect(Window::new(2), 0xff0000), None);
+
+        let _ = state.toggle_fullscreen();
+        assert_eq!(state.focus_border_effect(Window::new(1), 0xff0000), None);
+    }
+
+    #[test]
+    fn test_resize_screen_updates_size_and_retiles() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        state.track_startup_dock(Window::new(99));
+        let _ = state.configure_windows(0);
+
+        let effects = state.resize_screen(1024, 768);
+
+        assert_eq!((state.screen().width, state.screen().height), (1024, 768));
+        assert!(effects.contains(&Effect::ConfigurePositionSize {
+            window: Window::new(99),
+            x: 0,
+            y: 743,
+            w: 1024,
+            h: 25,
+        }));
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(1),
+            x: 0,
+            y: 0,
+            w: 1022,
+            h: 741,
+            border: 1,
+        }));
+        assert!(state.resize_screen(1024, 768).is_empty());
+    }
+
+    #[test]
+    fn test_dock_edge_follows_dock_position() {
+        let bar = |y| Rect {
+            x: 0,
+            y,
+            w: 800,
+            h: 25,
+        };
+
+        assert_eq!(DockEdge::of(bar(0), 600), DockEdge::Top);
+        assert_eq!(DockEdge::of(bar(575), 600), DockEdge::Bottom);
+    }
+
+    #[test]
+    fn test_top_dock_moves_usable_area_down() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let dock = Window::new(99);
+        state.set_dock_edge(dock, DockEdge::Top);
+        let effects = state.on_map_request(dock, WindowType::Dock);
+
+        assert!(effects.contains(&Effect::ConfigurePositionSize {
+            window: dock,
+            x: 0,
+            y: 0,
+            w: 800,
+            h: 25,
+        }));
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(1),
+            x: 0,
+            y: 25,
+            w: 798,
+            h: 573,
+            border: 1,
+        }));
+        assert_eq!(state.usable_screen_top(), 25);
+        assert_eq!(state.usable_screen_height(), 575);
+    }
+
+    #[test]
+    fn test_bottom_dock_keeps_usable_origin() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let dock = Window::new(99);
+        state.set_dock_edge(dock, DockEdge::Bottom);
+        let effects = state.on_map_request(dock, WindowType::Dock);
+
+        assert!(effects.contains(&Effect::ConfigurePositionSize {
+            window: dock,
+            x: 0,
+            y: 575,
+            w: 800,
+            h: 25,
+        }));
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(1),
+            x: 0,
+            y: 0,
+            w: 798,
+            h: 573,
+            border: 1,
+        }));
+        assert_eq!(state.usable_screen_top(), 0);
+    }
+
+    #[test]
+    fn test_docks_on_both_edges_reserve_both() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        state.set_dock_edge(Window::new(98), DockEdge::Top);
+        state.track_startup_dock(Window::new(98));
+        state.track_startup_dock(Window::new(99));
+
+        let effects = state.configure_windows(0);
+
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(1),
+            x: 0,
+            y: 25,
+            w: 798,
+            h: 548,
+            border: 1,
+        }));
+        assert_eq!(state.usable_screen_height(), 550);
+    }
+
+    #[test]
+    fn test_managed_windows_sorted_in_map_order() {
+        let state = make_state_with_windows(&[(1, 3, false), (0, 2, true), (0, 1, true)], 25);
+        // Ensure all are tracked
+        assert_eq!(state.window_workspace(Window::new(1)), Some(0));
+        assert_eq!(state.window_workspace(Window::new(2)), Some(0));
+        assert_eq!(state.window_workspace(Window::new(3)), Some(1));
+
+        let sorted = state.managed_windows_sorted();
+        assert_eq!(sorted, vec![Window::new(3), Window::new(2), Window::new(1)]);
+    }
+
+    #[test]
+    fn test_managed_windows_sorted_ignores_ids() {
+        let mut state = make_state_with_windows(&[], 0);
+        let (a, b, c) = (Window::new(30), Window::new(10), Window::new(20));
+        let _ = state.on_map_request(a, WindowType::Managed);
+        let _ = state.on_map_request(b, WindowType::Managed);
+        let _ = state.go_to_workspace(2);
+        let _ = state.on_map_request(c, WindowType::Managed);
+
+        assert_eq!(state.managed_windows_sorted(), vec![a, b, c]);
+
+        // Moving a window keeps its place, destroying one closes the gap.
+        let _ = state.move_window_to_workspace(a, 2);
+        let _ = state.on_destroy(b);
+        assert_eq!(state.managed_windows_sorted(), vec![a, c]);
+    }
+
+    #[test]
+    fn test_client_list_includes_docks_after_managed() {
+        let mut state = make_state_with_windows(&[(0, 5, true), (0, 2, true)], 25);
+        state.track_startup_dock(Window::new(20));
+        state.track_startup_dock(Window::new(10));
+
+        let list = state.client_list_windows();
+        assert_eq!(
+            list,
+            vec![
+                Window::new(5),
+                Window::new(2),
+                Window::new(10),
+                Window::new(20)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_focus_window_switches_to_window_workspace() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 11, true)], 25);
+        let effects = state.focus_window(Window::new(11));
+
+        assert_eq!(state.current_workspace_id(), 1);
+        assert_eq!(state.focused_window(), Some(Window::new(11)));
+        assert!(effects.iter().any(|e| matches!(e, Effect::Map(_))));
+        assert!(
+            effects
+                .iter()
+                .any(|e| matches!(e, Effect::Configure { .. }))
+        );
+    }
+
+    #[test]
+    fn test_focus_window_by_id_switches_to_its_workspace() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (2, 21, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        let effects = state.focus_window_by_id(Window::new(21));
+
+        assert_eq!(state.current_workspace_id(), 2);
+        assert_eq!(state.focused_window(), Some(Window::new(21)));
+        assert!(effects.contains(&Effect::Unmap(Window::new(1))));
+        assert!(effects.contains(&Effect::Focus(Window::new(21))));
+    }
+
+    #[test]
+    fn test_focus_window_by_id_restores_minimized_window_elsewhere() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (2, 21, true), (2, 22, true)], 0);
+        let _ = state.go_to_workspace(2);
+        let _ = state.set_focus(Window::new(21));
+        let _ = state.minimize();
+        let _ = state.go_to_workspace(0);
+
+        let effects = state.focus_window_by_id(Window::new(21));
+
+        assert_eq!(state.current_workspace_id(), 2);
+        assert!(!state.is_window_hidden(Window::new(21)));
+        assert_eq!(state.focused_window(), Some(Window::new(21)));
+        assert!(effects.contains(&Effect::Map(Window::new(21))));
+        assert!(state.focus_window_by_id(Window::new(99)).is_empty());
+    }
+
+    #[test]
+    fn test_jump_to_mark_focuses_marked_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        let _ = state.apply_action(ActionEvent::SetMark('a'));
+        let _ = state.set_focus(Window::new(2));
+
+        let effects = state.apply_action(ActionEvent::JumpToMark('a'));
+
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+        assert!(effects.contains(&Effect::Focus(Window::new(1))));
+        assert!(state.apply_action(ActionEvent::JumpToMark('b')).is_empty());
+    }
+
+    #[test]
+    fn test_jump_to_mark_switches_workspace() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 11, true)], 0);
+        let _ = state.go_to_workspace(1);
+        let _ = state.set_focus(Window::new(11));
+        let _ = state.apply_action(ActionEvent::SetMark('x'));
+        let _ = state.go_to_workspace(0);
+
+        let _ = state.apply_action(ActionEvent::JumpToMark('x'));
+
+        assert_eq!(state.current_workspace_id(), 1);
+        assert_eq!(state.focused_window(), Some(Window::new(11)));
+    }
+
+    #[test]
+    fn test_marks_move_between_windows_and_are_dropped_on_destroy() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        let _ = state.set_mark('a');
+        let _ = state.set_mark('b');
+        let _ = state.set_focus(Window::new(2));
+        let _ = state.set_mark('b');
+
+        let _ = state.on_destroy(Window::new(1));
+
+        assert!(!state.marks.contains_key(&'a'));
+        assert_eq!(state.marks.get(&'b'), Some(&Window::new(2)));
+        assert!(state.jump_to_mark('a').is_empty());
+    }
+
+    #[test]
+    fn test_pull_window_moves_it_to_current_workspace_and_focuses_it() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (3, 31, false)], 25);
+
+        let effects = state.pull_window(Window::new(31));
+
+        assert_eq!(state.current_workspace_id(), 0);
+        assert_eq!(state.window_workspace(Window::new(31)), Some(0));
+        assert_eq!(state.focused_window(), Some(Window::new(31)));
+        assert!(
+            state
+                .get_workspace(3)
+                .unwrap()
+                .iter_windows()
+                .next()
+                .is_none()
+        );
+        assert!(effects.contains(&Effect::Map(Window::new(31))));
+        assert!(effects.contains(&Effect::Focus(Window::new(31))));
+    }
+
+    #[test]
+    fn test_pull_window_ignores_untracked_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+
+        assert!(state.pull_window(Window::new(99)).is_empty());
+    }
+
+    #[test]
+    fn test_startup_all_desktops_window_is_sticky() {
+        let mut state = make_state_with_windows(&[(2, 1, true)], 0);
+        state.track_startup_sticky(Window::new(9), false);
+        let _ = state.startup_finalize(Some(2));
+
+        assert!(state.is_window_sticky(Window::new(9)));
+        assert!(!state.is_window_sticky(Window::new(1)));
+        assert_eq!(state.window_workspace(Window::new(9)), Some(2));
+
+        let effects = state.go_to_workspace(4);
+        assert_eq!(state.window_workspace(Window::new(9)), Some(4));
+        assert!(state.current_workspace().is_window_mapped(&Window::new(9)));
+        assert!(!effects.contains(&Effect::Unmap(Window::new(9))));
+        assert!(effects.contains(&Effect::Unmap(Window::new(1))));
+    }
+
+    #[test]
+    fn test_cycle_workspace_wraps_around() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+
+        let _ = state.apply_action(ActionEvent::GoToNextWorkspace);
+        assert_eq!(state.current_workspace_id(), 1);
+
+        let _ = state.apply_action(ActionEvent::GoToPrevWorkspace);
+        let _ = state.apply_action(ActionEvent::GoToPrevWorkspace);
+        assert_eq!(state.current_workspace_id(), NUM_WORKSPACES - 1);
+    }
+
+    #[test]
+    fn test_go_to_workspace_invalid_or_same_is_noop() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let effects_same = state.go_to_workspace(0);
+        let effects_invalid = state.go_to_workspace(NUM_WORKSPACES + 1);
+
+        assert!(effects_same.is_empty());
+        assert!(effects_invalid.is_empty());
+        assert_eq!(state.current_workspace_id(), 0);
+    }
+
+    #[test]
+    fn test_send_to_workspace_invalid_or_same_is_noop() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let effects_same = state.send_to_workspace(0);
+        let effects_invalid = state.send_to_workspace(NUM_WORKSPACES + 1);
+
+        assert!(effects_same.is_empty());
+        assert!(effects_invalid.is_empty());
+        assert_eq!(state.window_workspace(Window::new(1)), Some(0));
+    }
+
+    #[test]
+    fn test_increase_decrease_window_gap_reconfigures() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 25);
+
+        let effects_increase = state.increase_window_gap(1);
+        assert_eq!(
+            effects_increase
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Configure { .. }))
+                .count(),
+            2
+        );
+
+        let effects_decrease = state.decrease_window_gap(1);
+        assert_eq!(
+            effects_decrease
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Configure { .. }))
+                .count(),
+            2
+        );
+
+        let effects_noop = state.decrease_window_gap(1000);
+        assert!(effects_noop.is_empty());
+    }
+
+    #[test]
+    fn test_increase_decrease_window_weight_reconfigures() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 25);
+        let _ = state.set_focus(Window::new(1));
+
+        let effects_inc = state.increase_window_weight(2);
+        assert_eq!(
+            effects_inc
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Configure { .. }))
+                .count(),
+            2
+        );
+
+        let effects_dec = state.decrease_window_weight(1);
+        assert_eq!(
+            effects_dec
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Configure { .. }))
+                .count(),
+            2
+        );
+    }
+
+    fn weights(state: &State) -> Vec<u32> {
+        state
+            .current_workspace()
+            .iter_clients()
+            .map(|client| client.size())
+            .collect()
+    }
+
+    #[test]
+    fn test_scroll_up_on_border_increments_that_windows_weight() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state.border_scroll_weight = true;
+        let _ = state.set_focus(Window::new(1));
+        let (_, rect) = state.tiled_geometry(0)[1];
+
+        // Bottom border of the unfocused window.
+        let effects = state
+            .scroll_border_weight(Window::new(2), 10, rect.h as i32, true)
+            .expect("scroll on the border is handled");
+
+        assert!(!effects.is_empty());
+        assert_eq!(
+            weights(&state),
+            vec![DEFAULT_WEIGHT, DEFAULT_WEIGHT + WEIGHT_STEP]
+        );
+        assert!(state.tiled_geometry(0)[1].1.w > rect.w);
+
+        let _ = state.scroll_border_weight(Window::new(2), -1, 10, false);
+        assert_eq!(weights(&state), vec![DEFAULT_WEIGHT, DEFAULT_WEIGHT]);
+    }
+
+    #[test]
+    fn test_scroll_on_frame_border_adjusts_the_framed_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        state.border_scroll_weight = true;
+        let (window, frame) = (Window::new(2), Window::new(50));
+        let _ = state.attach_frame(window, frame);
+        let effects = state.on_map_request(window, WindowType::Managed);
+        assert!(effects.contains(&Effect::GrabScroll(frame)));
+
+        // The frame's left edge is the client's left border; one pixel in is
+        // the client itself.
+        assert!(state.scroll_border_weight(frame, 1, 10, true).is_none());
+        assert!(state.scroll_border_weight(frame, 0, 10, true).is_some());
+        assert_eq!(
+            weights(&state),
+            vec![DEFAULT_WEIGHT, DEFAULT_WEIGHT + WEIGHT_STEP]
+        );
+    }
+
+    #[test]
+    fn test_scroll_inside_window_or_when_disabled_is_left_to_client() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        assert!(
+            state
+                .scroll_border_weight(Window::new(1), -1, 10, true)
+                .is_none()
+        );
+
+        state.border_scroll_weight = true;
+        assert!(
+            state
+                .scroll_border_weight(Window::new(1), 10, 10, true)
+                .is_none()
+        );
+        assert_eq!(weights(&state), vec![DEFAULT_WEIGHT, DEFAULT_WEIGHT]);
+    }
+
+    #[test]
+    fn test_map_request_managed_selects_property_but_not_structure_events() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let effects = state.on_map_request(Window::new(99), WindowType::Managed);
+
+        assert!(effects.iter().any(|e| matches!(
+            e,
+            Effect::SetEventMask { window, mask }
+                if *window == Window::new(99)
+                    && mask.contains(EventMask::PROPERTY_CHANGE)
+                    && !mask.contains(EventMask::STRUCTURE_NOTIFY)
+        )));
+    }
+
+    fn attached_order(mode: AttachMode) -> Vec<u32> {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        state.attach_mode = mode;
+        let _ = state.set_focus(Window::new(2));
+
+        let _ = state.on_map_request(Window::new(4), WindowType::Managed);
+
+        state
+            .current_workspace()
+            .iter_windows()
+            .map(|window| window.resource_id())
+            .collect()
+    }
+
+    #[test]
+    fn test_attach_bottom_appends_new_window() {
+        assert_eq!(attached_order(AttachMode::Bottom), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_attach_top_makes_new_window_master() {
+        assert_eq!(attached_order(AttachMode::Top), vec![4, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_attach_after_focused_inserts_next_to_focus() {
+        assert_eq!(attached_order(AttachMode::AfterFocused), vec![1, 2, 4, 3]);
+    }
+
+    #[test]
+    fn test_attach_below_master_heads_the_stack() {
+        assert_eq!(attached_order(AttachMode::BelowMaster), vec![1, 4, 2, 3]);
+    }
+
+    #[test]
+    fn test_attach_mode_ignored_for_preselected_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state.attach_mode = AttachMode::Top;
+        let _ = state.set_focus(Window::new(2));
+        let _ = state.preselect(Direction::Right);
+        let _ = state.on_map_request(Window::new(3), WindowType::Managed);
+
+        let order: Vec<u32> = state
+            .current_workspace()
+            .iter_windows()
+            .map(|window| window.resource_id())
+            .collect();
+        assert_eq!(order, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_map_request_unfocused_keeps_focus() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        state.set_focus(Window::new(1));
+        let effects = state.on_map_request_unfocused(Window::new(99), WindowType::Managed);
+
+        assert!(effects.contains(&Effect::Map(Window::new(99))));
+        assert!(!effects.contains(&Effect::Focus(Window::new(99))));
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+        assert_eq!(state.window_workspace(Window::new(99)), Some(0));
+    }
+
+    #[test]
+    fn test_map_request_unmanaged_is_simple_map() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let effects = state.on_map_request(Window::new(99), WindowType::Unmanaged);
+
+        assert_eq!(effects, vec![Effect::Map(Window::new(99))]);
+        assert!(state.window_workspace(Window::new(99)).is_none());
+    }
+
+    #[test]
+    fn test_dock_map_and_destroy_updates_layout() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let dock = Window::new(50);
+
+        let map_effects = state.on_map_request(dock, WindowType::Dock);
+        assert!(map_effects.contains(&Effect::Map(dock)));
+        assert!(!state.dock_windows.is_empty());
+
+        let destroy_effects = state.on_destroy(dock);
+        assert!(
+            !destroy_effects
+                .iter()
+                .any(|e| matches!(e, Effect::ConfigurePositionSize { .. }))
+        );
+        assert!(state.dock_windows.is_empty());
+    }
+
+    #[test]
+    fn test_on_unmap_ignored_for_dock_and_unmanaged() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let dock = Window::new(77);
+        state.track_startup_dock(dock);
+
+        let effects_dock = state.on_unmap(dock);
+        let effects_unmanaged = state.on_unmap(Window::new(88));
+
+        assert!(effects_dock.is_empty());
+        assert!(effects_unmanaged.is_empty());
+    }
+
+    #[test]
+    fn test_startup_finalize_switches_workspace_when_hint_provided() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 11, false)], 25);
+        let effects = state.startup_finalize(Some(1));
+
+        assert_eq!(state.current_workspace_id(), 1);
+        assert!(effects.iter().any(|e| matches!(e, Effect::Map(_))));
+        assert!(
+            effects
+                .iter()
+                .any(|e| matches!(e, Effect::Configure { .. }))
+        );
+    }
+
+    #[test]
+    fn test_shift_focus_wraps_and_skips_unmapped() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, false), (0, 3, true)], 25);
+
+        let _ = state.set_focus(Window::new(1));
+        let effects_forward = state.shift_focus(1);
+
+        assert_eq!(state.focused_window(), Some(Window::new(3)));
+        assert!(effects_forward.contains(&Effect::Focus(Window::new(3))));
+
+        let effects_backward = state.shift_focus(-1);
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+        assert!(effects_backward.contains(&Effect::Focus(Window::new(1))));
+    }
+
+    #[test]
+    fn test_shift_focus_noop_when_only_one_mapped() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, false)], 25);
+        let _ = state.set_focus(Window::new(1));
+
+        let effects = state.shift_focus(1);
+
+        assert!(effects.is_empty());
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+    }
+
+    #[test]
+    fn test_swap_window_swaps_with_next_mapped() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, false), (0, 3, true)], 25);
+        let _ = state.set_focus(Window::new(1));
+
+        let effects = state.swap_window(1);
+
+        let order: Vec<Window> = state.current_workspace().iter_windows().copied().collect();
+        assert_eq!(order, vec![Window::new(3), Window::new(2), Window::new(1)]);
+        assert_eq!(
+            effects
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Configure { .. }))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_swap_window_noop_when_no_other_mapped() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let _ = state.set_focus(Window::new(1));
+
+        let effects = state.swap_window(1);
+
+        assert!(effects.is_empty());
+        let order: Vec<Window> = state.current_workspace().iter_windows().copied().collect();
+        assert_eq!(order, vec![Window::new(1)]);
+    }
+
+    #[test]
+    fn test_increase_nmaster_clamped_to_window_count() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, false)], 25);
+
+        let effects = state.increase_nmaster();
+        assert_eq!(state.effective_nmaster(0), 2);
+        assert_eq!(
+            effects
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Configure { .. }))
+                .count(),
+            2
+        );
+
+        // Only two mapped windows, so nmaster cannot grow past 2
+        let effects_noop = state.increase_nmaster();
+        assert!(effects_noop.is_empty());
+        assert_eq!(state.effective_nmaster(0), 2);
+    }
+
+    #[test]
+    fn test_nmaster_is_per_workspace() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (1, 3, true)], 25);
+
+        let _ = state.increase_nmaster();
+        assert_eq!(state.effective_nmaster(0), 2);
+        // Other workspaces keep the global default
+        assert_eq!(state.effective_nmaster(1), 1);
+        assert_eq!(state.nmaster, 1);
+    }
+
+    #[test]
+    fn test_zero_outer_gap_keeps_inner_gap_between_master_and_stack() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state
+            .current_workspace_mut()
+            .set_layout(LayoutType::MasterLayout);
+        state.window_gap = 10;
+        state.outer_gap = Some(0);
+
+        let geometry = state.current_geometry();
+
+        let (master, stack) = (geometry[0].1, geometry[1].1);
+        assert_eq!((master.x, master.y), (0, 0));
+        // Outer sizes include the 1px border on each side.
+        assert_eq!(stack.x - (master.x + master.w as i32 + 2), 10);
+        assert_eq!(stack.x + stack.w as i32 + 2, 800);
+        assert_eq!(stack.y + stack.h as i32 + 2, 600);
+    }
+
+    #[test]
+    fn test_decrease_nmaster_never_below_one() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 25);
+        state
+            .current_workspace_mut()
+            .set_layout(LayoutType::MasterLayout);
+        let _ = state.increase_nmaster();
+
+        let effects = state.decrease_nmaster();
+        assert_eq!(state.effective_nmaster(0), 1);
+        assert!(!effects.is_empty());
+
+        let effects_noop = state.decrease_nmaster();
+        assert!(effects_noop.is_empty());
+        assert_eq!(state.effective_nmaster(0), 1);
+    }
+
+    #[test]
+    fn test_map_request_floating_is_not_tiled() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let dialog = Window::new(2);
+
+        let effects = state.on_map_request(dialog, WindowType::Floating);
+
+        assert!(state.is_window_floating(dialog));
+        assert_eq!(state.focused_window(), Some(dialog));
+        assert!(effects.contains(&Effect::Map(dialog)));
+        assert!(effects.contains(&Effect::Raise(dialog)));
+        assert!(
+            !effects
+                .iter()
+                .any(|e| matches!(e, Effect::Configure { window, .. } if *window == dialog))
+        );
+        // The tiled window keeps the whole area to itself
+        assert_eq!(
+            effects
+                .iter()
+                .filter(|effect| matches!(effect, Effect::Configure { .. }))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_remap_of_known_window_applies_floating() {
+        // Window 2 is tracked but unmapped, e.g. withdrawn by its client
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, false)], 25);
+        let window = Window::new(2);
+
+        let _ = state.on_map_request(window, WindowType::Floating);
+
+        assert!(state.is_window_floating(window));
+        assert!(state.current_workspace().is_window_mapped(&window));
+    }
+
+    #[test]
+    fn test_dialog_in_too_small_tile_falls_back_to_floating() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state
+            .current_workspace_mut()
+            .set_layout(LayoutType::MasterLayout);
+        let dialog = Window::new(3);
+        state.set_min_size(dialog, (400, 300));
+
+        // The third window would share the 400px wide stack column with window 2.
+        let _ = state.on_map_request(dialog, WindowType::Managed);
+
+        assert!(state.is_window_floating(dialog));
+        let rect = state.floating_geometry[&dialog];
+        assert_eq!((rect.x, rect.y, rect.w, rect.h), (199, 149, 400, 300));
+        assert!(state.current_geometry().iter().all(|(w, _)| *w != dialog));
+    }
+
+    #[test]
+    fn test_floated_dialog_tiles_again_once_its_tile_fits() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state
+            .current_workspace_mut()
+            .set_layout(LayoutType::MasterLayout);
+        let dialog = Window::new(3);
+        state.set_min_size(dialog, (300, 400));
+        // Sharing the stack column with window 2 leaves it 300px high.
+        let _ = state.on_map_request(dialog, WindowType::Managed);
+        assert!(state.is_window_floating(dialog));
+
+        // Without window 2, the dialog gets the whole stack column.
+        let _ = state.on_destroy(Window::new(2));
+
+        assert!(!state.is_window_floating(dialog));
+        assert!(state.current_geometry().iter().any(|(w, _)| *w == dialog));
+    }
+
+    #[test]
+    fn test_dialog_that_fits_its_tile_stays_tiled() {
+        let mut state = make_state_with_windows(&[], 0);
+        let dialog = Window::new(1);
+        state.set_min_size(dialog, (400, 300));
+
+        let _ = state.on_map_request(dialog, WindowType::Managed);
+
+        assert!(!state.is_window_floating(dialog));
+        assert!(fits_min_size(state.current_geometry()[0].1, (400, 300)));
+        assert!(!fits_min_size(
+            Rect {
+                x: 0,
+                y: 0,
+                w: 398,
+                h: 600
+            },
+            (400, 300)
+        ));
+    }
+
+    #[test]
+    fn test_transient_destroy_refocuses_owner() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 25);
+        let owner = Window::new(1);
+        let transient = Window::new(4);
+
+        let map_effects = state.on_map_request_transient(transient, owner);
+        assert!(state.is_window_floating(transient));
+        assert_eq!(state.transient_owners.get(&transient), Some(&owner));
+        assert!(map_effects.contains(&Effect::Focus(transient)));
+        assert!(map_effects.contains(&Effect::Raise(transient)));
+
+        let destroy_effects = state.on_destroy(transient);
+
+        assert_eq!(state.focused_window(), Some(owner));
+        assert!(destroy_effects.contains(&Effect::Focus(owner)));
+        assert_eq!(state.transient_owners.get(&transient), None);
+    }
+
+    #[test]
+    fn test_transient_with_unmanaged_owner_floats_without_link() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let transient = Window::new(4);
+
+        let _ = state.on_map_request_transient(transient, Window::new(99));
+
+        assert!(state.is_window_floating(transient));
+        assert_eq!(state.transient_owners.get(&transient), None);
+    }
+
+    #[test]
+    fn test_reserved_top_strip_shrinks_layout_area() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        state.reserved_regions = vec![Rect {
+            x: 600,
+            y: 0,
+            w: 200,
+            h: 30,
+        }];
+
+        let effects = state.configure_windows(0);
+
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(1),
+            x: 0,
+            y: 30,
+            w: 798,
+            h: 568,
+            border: 1,
+        }));
+    }
+
+    #[test]
+    fn test_rotate_layout_stacks_horizontal_windows() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+
+        let effects = state.apply_action(ActionEvent::RotateLayout);
+
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(1),
+            x: 0,
+            y: 0,
+            w: 798,
+            h: 298,
+            border: 1,
+        }));
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(2),
+            x: 0,
+            y: 300,
+            w: 798,
+            h: 298,
+            border: 1,
+        }));
+    }
+
+    fn tiled_order(state: &State) -> Vec<Window> {
+        state
+            .current_geometry()
+            .into_iter()
+            .map(|(window, _)| window)
+            .collect()
+    }
+
+    #[test]
+    fn test_zoom_in_place_keeps_focus_on_the_same_spot() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let _ = state.set_focus(Window::new(3));
+        let spot = state.current_geometry()[2].1;
+        let middle = state.current_geometry()[1].1;
+
+        let _ = state.apply_action(ActionEvent::ZoomInPlace);
+
+        let order: Vec<Window> = state
+            .current_geometry()
+            .into_iter()
+            .map(|(window, _)| window)
+            .collect();
+        assert_eq!(order, vec![Window::new(3), Window::new(2), Window::new(1)]);
+        // The old master now sits where the focused window was, and has focus.
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+        let focused_rect = state.current_geometry()[2].1;
+        assert_eq!((focused_rect.x, focused_rect.w), (spot.x, spot.w));
+        let moved = state.current_geometry()[1].1;
+        assert_eq!((moved.x, moved.w), (middle.x, middle.w));
+    }
+
+    #[test]
+    fn test_zoom_in_place_from_master_swaps_with_next() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        let _ = state.apply_action(ActionEvent::ZoomInPlace);
+
+        let order: Vec<Window> = state
+            .current_geometry()
+            .into_iter()
+            .map(|(window, _)| window)
+            .collect();
+        assert_eq!(order, vec![Window::new(2), Window::new(1), Window::new(3)]);
+        assert_eq!(state.focused_window(), Some(Window::new(2)));
+    }
+
+    #[test]
+    fn test_zoom_in_place_needs_two_tiled_windows() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        assert!(state.apply_action(ActionEvent::ZoomInPlace).is_empty());
+    }
+
+    #[test]
+    fn test_zoom_promotes_stack_window_to_master() {
+        let mut state = make_grid_state();
+        let _ = state.set_focus(Window::new(3));
+
+        let effects = state.apply_action(ActionEvent::Zoom);
+
+        assert_eq!(
+            tiled_order(&state),
+            vec![
+                Window::new(3),
+                Window::new(1),
+                Window::new(2),
+                Window::new(4)
+            ]
+        );
+        assert_eq!(state.focused_window(), Some(Window::new(3)));
+        assert!(effects.contains(&Effect::Focus(Window::new(3))));
+    }
+
+    #[test]
+    fn test_zoom_on_master_restores_previous_master() {
+        let mut state = make_grid_state();
+        let _ = state.set_focus(Window::new(3));
+        let _ = state.zoom();
+
+        let _ = state.zoom();
+
+        assert_eq!(
+            tiled_order(&state),
+            vec![
+                Window::new(1),
+                Window::new(3),
+                Window::new(2),
+                Window::new(4)
+            ]
+        );
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+    }
+
+    #[test]
+    fn test_zoom_with_single_window_is_noop() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+
+        assert!(state.zoom().is_empty());
+    }
+
+    #[test]
+    fn test_swap_last_two_keeps_focus_on_same_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        let _ = state.set_focus(Window::new(3));
+
+        let effects = state.apply_action(ActionEvent::SwapLastTwo);
+
+        assert!(!effects.is_empty());
+        assert_eq!(
+            tiled_order(&state),
+            vec![Window::new(3), Window::new(2), Window::new(1)]
+        );
+        assert_eq!(state.focused_window(), Some(Window::new(3)));
+
+        // Swapping again restores the original order.
+        let _ = state.swap_last_two();
+        assert_eq!(
+            tiled_order(&state),
+            vec![Window::new(1), Window::new(2), Window::new(3)]
+        );
+        assert_eq!(state.focused_window(), Some(Window::new(3)));
+    }
+
+    #[test]
+    fn test_swap_last_two_without_previous_focus_is_noop() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(2));
+        let _ = state.on_destroy(Window::new(1));
+
+        assert!(state.swap_last_two().is_empty());
+    }
+
+    fn make_switch_state(mode: FocusOnSwitch) -> State {
+        let mut state =
+            make_state_with_windows(&[(0, 1, true), (1, 2, true), (1, 3, true), (1, 4, true)], 0);
+        state.focus_on_switch = mode;
+        // Workspace 1 last had window 4 focused.
+        state
+            .get_workspace_mut(1)
+            .unwrap()
+            .set_focus(Window::new(4));
+        state
+    }
+
+    #[test]
+    fn test_focus_on_switch_last_focused() {
+        let mut state = make_switch_state(FocusOnSwitch::LastFocused);
+
+        let _ = state.go_to_workspace(1);
+
+        assert_eq!(state.focused_window(), Some(Window::new(4)));
+    }
+
+    #[test]
+    fn test_focus_on_switch_first_window() {
+        let mut state = make_switch_state(FocusOnSwitch::FirstWindow);
+
+        let effects = state.go_to_workspace(1);
+
+        assert_eq!(state.focused_window(), Some(Window::new(2)));
+        assert!(effects.contains(&Effect::Focus(Window::new(2))));
+    }
+
+    #[test]
+    fn test_focus_on_switch_under_pointer() {
+        let mut state = make_switch_state(FocusOnSwitch::UnderPointer);
+        // Three horizontal tiles of ~266px: x = 400 is over the middle one.
+        state.set_pointer(400, 300);
+
+        let _ = state.go_to_workspace(1);
+
+        assert_eq!(state.focused_window(), Some(Window::new(3)));
+    }
+
+    #[test]
+    fn test_focus_on_switch_under_pointer_falls_back_to_last_focused() {
+        let mut state = make_switch_state(FocusOnSwitch::UnderPointer);
+
+        let _ = state.go_to_workspace(1);
+
+        assert_eq!(state.focused_window(), Some(Window::new(4)));
+    }
+
+    #[test]
+    fn test_reparent_away_evicts_managed_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let root = Window::new(100);
+
+        let effects = state.on_reparent(Window::new(2), Window::new(500), root);
+
+        assert_eq!(state.window_workspace(Window::new(2)), None);
+        assert!(
+            !state
+                .get_workspace(0)
+                .unwrap()
+                .iter_windows()
+                .any(|w| *w == Window::new(2))
+        );
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(1),
+            x: 0,
+            y: 0,
+            w: 798,
+            h: 598,
+            border: 1,
+        }));
+    }
+
+    #[test]
+    fn test_reparent_to_root_or_own_frame_keeps_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        let root = Window::new(100);
+        let frame = Window::new(200);
+        let _ = state.attach_frame(Window::new(1), frame);
+
+        assert!(state.on_reparent(Window::new(1), root, root).is_empty());
+        assert!(state.on_reparent(Window::new(1), frame, root).is_empty());
+        assert_eq!(state.window_workspace(Window::new(1)), Some(0));
+    }
+
+    #[test]
+    fn test_reparent_of_unmanaged_window_is_ignored() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+
+        let effects = state.on_reparent(Window::new(9), Window::new(500), Window::new(100));
+
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn test_modal_child_is_floated_and_focused_on_map() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        let modal = Window::new(4);
+
+        let effects = state.on_map_request_modal(modal, Some(Window::new(1)));
+
+        assert!(state.is_window_floating(modal));
+        assert_eq!(state.focused_window(), Some(modal));
+        assert!(effects.contains(&Effect::Focus(modal)));
+        assert!(effects.contains(&Effect::Raise(modal)));
+    }
+
+    #[test]
+    fn test_strict_modal_blocks_focusing_owner() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state.strict_modal = true;
+        let owner = Window::new(1);
+        let modal = Window::new(4);
+        let _ = state.on_map_request_modal(modal, Some(owner));
+
+        let effects = state.set_focus(owner);
+        assert_eq!(state.focused_window(), Some(modal));
+        assert!(!effects.contains(&Effect::Focus(owner)));
+
+        // Unrelated windows can still be focused.
+        let _ = state.set_focus(Window::new(2));
+        assert_eq!(state.focused_window(), Some(Window::new(2)));
+
+        let _ = state.on_destroy(modal);
+        let _ = state.set_focus(owner);
+        assert_eq!(state.focused_window(), Some(owner));
+    }
+
+    #[test]
+    fn test_modal_does_not_block_owner_outside_strict_mode() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        state.strict_modal = false;
+        let owner = Window::new(1);
+        let _ = state.on_map_request_modal(Window::new(4), Some(owner));
+
+        let _ = state.set_focus(owner);
+
+        assert_eq!(state.focused_window(), Some(owner));
+    }
+
+    #[test]
+    fn test_destroying_owner_drops_transient_link() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 25);
+        let owner = Window::new(1);
+        let transient = Window::new(4);
+        let _ = state.on_map_request_transient(transient, owner);
+
+        let _ = state.on_destroy(owner);
+
+        assert_eq!(state.transient_owners.get(&transient), None);
+    }
+
+    #[test]
+    fn test_toggle_borders_off_retiles_without_border() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 2, false)], 25);
+        let window = Window::new(1);
+
+        let effects = state.toggle_borders();
+
+        assert!(effects.contains(&Effect::SetBorder {
+            window,
+            pixel: state.screen.focused_border_pixel,
+            width: 0,
+        }));
+        assert!(effects.contains(&Effect::SetBorder {
+            window: Window::new(2),
+            pixel: state.screen.normal_border_pixel,
+            width: 0,
+        }));
+        assert!(effects.contains(&Effect::Configure {
+            window,
+            x: 0,
+            y: 0,
+            w: 800,
+            h: 600,
+            border: 0,
+        }));
+    }
+
+    #[test]
+    fn test_border_override_applies_to_set_border() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state.border_width = 3;
+        state.set_border_override(Window::new(1), 1);
+
+        let effects = state.set_focus(Window::new(1));
+        assert!(effects.contains(&Effect::SetBorder {
+            window: Window::new(1),
+            pixel: state.screen.focused_border_pixel,
+            width: 1,
+        }));
+
+        let effects = state.set_focus(Window::new(2));
+        assert!(effects.contains(&Effect::SetBorder {
+            window: Window::new(1),
+            pixel: state.screen.normal_border_pixel,
+            width: 1,
+        }));
+        assert!(effects.contains(&Effect::SetBorder {
+            window: Window::new(2),
+            pixel: state.screen.focused_border_pixel,
+            width: 3,
+        }));
+    }
+
+    #[test]
+    fn test_border_override_keeps_outer_tile_size() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state.border_width = 3;
+        state.set_border_override(Window::new(1), 1);
+
+        let effects = state.configure_windows(0);
+
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(1),
+            x: 0,
+            y: 0,
+            w: 398,
+            h: 598,
+            border: 1,
+        }));
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(2),
+            x: 400,
+            y: 0,
+            w: 394,
+            h: 594,
+            border: 3,
+        }));
+    }
+
+    #[test]
+    fn test_toggle_borders_twice_restores_border_width() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let _ = state.toggle_borders();
+
+        let effects = state.toggle_borders();
+
+        assert_eq!(find_configure_height(&effects, Window::new(1)), Some(598));
+        assert!(effects.contains(&Effect::SetBorder {
+            window: Window::new(1),
+            pixel: state.screen.focused_border_pixel,
+            width: state.border_width,
+        }));
+    }
+
+    #[test]
+    fn test_toggle_docks_hides_docks_and_reclaims_space() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let dock = Window::new(50);
+        let _ = state.on_map_request(dock, WindowType::Dock);
+        assert_eq!(state.usable_screen_height(), 575);
+
+        let effects = state.apply_action(ActionEvent::ToggleDocks);
+
+        assert!(effects.contains(&Effect::Unmap(dock)));
+        assert_eq!(state.usable_screen_height(), 600);
+        assert_eq!(find_configure_height(&effects, Window::new(1)), Some(598));
+        assert!(
+            !effects
+                .iter()
+                .any(|e| matches!(e, Effect::ConfigurePositionSize { .. }))
+        );
+    }
+
+    #[test]
+    fn test_toggle_docks_twice_restores_dock_area() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let dock = Window::new(50);
+        let _ = state.on_map_request(dock, WindowType::Dock);
+        let _ = state.toggle_docks();
+
+        let effects = state.toggle_docks();
+
+        assert!(effects.contains(&Effect::Map(dock)));
+        assert_eq!(state.usable_screen_height(), 575);
+        assert_eq!(find_configure_height(&effects, Window::new(1)), Some(573));
+        assert!(effects.contains(&Effect::ConfigurePositionSize {
+            window: dock,
+            x: 0,
+            y: 575,
+            w: 800,
+            h: 25,
+        }));
+    }
+
+    #[test]
+    fn test_dock_mapped_while_hidden_stays_hidden() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
+        let _ = state.toggle_docks();
+
+        let effects = state.on_map_request(Window::new(50), WindowType::Dock);
+
+        assert!(effects.is_empty());
+        assert_eq!(state.usable_screen_height(), 600);
+    }
+
+    #[test]
+    fn test_focus_window_ignores_untracked_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 11, true)], 25);
+
+        let effects = state.focus_window(Window::new(99));
+
+        assert!(effects.is_empty());
+        assert_eq!(state.current_workspace_id(), 0);
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+    }
+
+    #[test]
+    fn test_initial_workspaces_use_configured_layouts() {
+        let workspaces = initial_workspaces(&[(1, LayoutType::MasterLayout)], &[], &[]);
+
+        assert_eq!(workspaces[0].layout(), LayoutType::HorizontalLayout);
+        assert_eq!(workspaces[1].layout(), LayoutType::MasterLayout);
+    }
+
+    #[test]
+    fn test_initial_workspaces_ignore_out_of_range_entries() {
+        let workspaces = initial_workspaces(
+            &[(NUM_WORKSPACES, LayoutType::MasterLayout)],
+            &[(NUM_WORKSPACES, 0)],
+            &[(NUM_WORKSPACES, 0)],
+        );
+
+        assert!(workspaces.iter().all(|workspace| {
+            workspace.layout() == LayoutType::default()
+                && workspace.gap().is_none()
+                && workspace.border_width().is_none()
+        }));
+    }
+
+    #[test]
+    fn test_initial_workspaces_use_configured_gaps_and_borders() {
+        let workspaces = initial_workspaces(&[], &[(8, 0)], &[(8, 0), (2, 5)]);
+
+        assert_eq!(workspaces[8].gap(), Some(0));
+        assert_eq!(workspaces[8].border_width(), Some(0));
+        assert_eq!(workspaces[2].border_width(), Some(5));
+        assert_eq!(workspaces[0].gap(), None);
+    }
+
+    #[test]
+    fn test_workspace_gap_and_border_overrides_follow_workspace_switches() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (8, 2, false)], 0);
+        state.window_gap = 10;
+        state.workspaces[8].set_gap(Some(0));
+        state.workspaces[8].set_border_width(Some(0));
+
+        let effects = state.go_to_workspace(8);
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(2),
+            x: 0,
+            y: 0,
+            w: 800,
+            h: 600,
+            border: 0,
+        }));
+        assert!(effects.contains(&Effect::SetBorder {
+            window: Window::new(2),
+            pixel: state.screen.focused_border_pixel,
+            width: 0,
+        }));
+
+        let effects = state.go_to_workspace(0);
+        assert!(effects.iter().any(|effect| matches!(
+            effect,
+            Effect::Configure { window, x: 10, y: 10, border: 1, .. } if *window == Window::new(1)
+        )));
+    }
+
+    #[test]
+    fn test_gap_keys_change_the_workspace_override() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        state.workspaces[0].set_gap(Some(4));
+
+        let _ = state.increase_window_gap(2);
+
+        assert_eq!(state.workspaces[0].gap(), Some(6));
+        assert_eq!(state.window_gap, 0);
+    }
+
+    #[test]
+    fn test_cycle_layout_only_affects_current_workspace() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 2, false)], 25);
+
+        let _ = state.cycle_layout();
+
+        assert_eq!(state.current_workspace().layout(), LayoutType::MasterLayout);
+        assert_eq!(
+            state.get_workspace(1).unwrap().layout(),
+            LayoutType::HorizontalLayout
+        );
+    }
+
+    #[test]
+    fn test_cycle_layout_keeps_window_gap() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.increase_window_gap(8);
+
+        for _ in 0..3 {
+            let _ = state.cycle_layout();
+
+            // MasterLayout and BspLayout place two windows alike, so the
+            // configure cache may skip the effect; check the geometry instead.
+            assert_eq!(state.window_gap, 8);
+            let (window, rect) = state.tiled_geometry(0)[0];
+            assert_eq!((window, rect.x, rect.y), (Window::new(1), 8, 8));
+        }
+        assert_eq!(
+            state.current_workspace().layout(),
+            LayoutType::HorizontalLayout
+        );
+    }
+
+    #[test]
+    fn test_cycle_layout_keeps_window_weights() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        let _ = state.increase_window_weight(3);
+        let before = state.tiled_geometry(0);
+
+        for _ in 0..3 {
+            let _ = state.cycle_layout();
+        }
+
+        let weights: Vec<u32> = state
+            .current_workspace()
+            .iter_clients()
+            .map(|client| client.size())
+            .collect();
+        assert_eq!(weights, vec![DEFAULT_WEIGHT + 3, DEFAULT_WEIGHT]);
+        let after = state.tiled_geometry(0);
+        assert_eq!(before[0].1.w, after[0].1.w);
+    }
+
+    #[test]
+    fn test_window_infos_report_flags_per_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 2, false)], 25);
+        let _ = state.set_focus(Window::new(1));
+        let _ = state.toggle_fullscreen();
+
+        let infos = state.window_infos();
+
+        assert_eq!(
+            infos,
+            vec![
+                WindowInfo {
+                    window: Window::new(1),
+                    workspace: 0,
+                    focused: true,
+                    floating: false,
+                    fullscreen: true,
+                    hidden: false,
+                    border_width: 0,
+                },
+                WindowInfo {
+                    window: Window::new(2),
+                    workspace: 1,
+                    focused: false,
+                    floating: false,
+                    fullscreen: false,
+                    hidden: false,
+                    border_width: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_focus_transition_ignores_unmanaged_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        assert_eq!(state.transition_focus(Window::new(99)), None);
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+        assert!(state.set_focus(Window::new(99)).is_empty());
+        assert!(state.focus_window(Window::new(99)).is_empty());
+    }
+
+    #[test]
+    fn test_focus_transition_records_previous_focus() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        assert_eq!(
+            state.transition_focus(Window::new(2)),
+            Some(FocusChange {
+                previous: Some(Window::new(1)),
+                focused: Window::new(2),
+            })
+        );
+        assert_eq!(
+            state.current_workspace().get_previous_focus_window(),
+            Some(Window::new(1))
+        );
+    }
+
+    #[test]
+    fn test_focus_transition_skips_windows_on_other_workspaces() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 2, false)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        assert_eq!(state.transition_focus(Window::new(2)), None);
+        assert_eq!(state.current_workspace_id(), 0);
+    }
+
+    #[test]
+    fn test_focus_window_across_workspaces_switches_workspace() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 2, false)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        let effects = state.focus_window(Window::new(2));
+
+        assert_eq!(state.current_workspace_id(), 1);
+        assert_eq!(state.focused_window(), Some(Window::new(2)));
+        assert!(effects.contains(&Effect::Unmap(Window::new(1))));
+        assert!(effects.contains(&Effect::Focus(Window::new(2))));
+    }
+
+    #[test]
+    fn test_refocusing_focused_window_keeps_bookkeeping_and_resends_focus() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        let _ = state.set_focus(Window::new(2));
+
+        let effects = state.set_focus(Window::new(2));
+
+        assert_eq!(
+            state.current_workspace().get_previous_focus_window(),
+            Some(Window::new(1))
+        );
+        assert!(effects.contains(&Effect::Focus(Window::new(2))));
+        assert!(!effects.contains(&Effect::SetBorder {
+            window: Window::new(2),
+            pixel: state.screen.normal_border_pixel,
+            width: 1,
+        }));
+    }
+
+    #[test]
+    fn test_minimize_removes_window_from_layout_but_keeps_it_managed() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        let effects = state.apply_action(ActionEvent::Minimize);
+
+        assert!(effects.contains(&Effect::Unmap(Window::new(1))));
+        assert!(effects.contains(&Effect::Focus(Window::new(2))));
+        let tiled: Vec<Window> = state
+            .current_geometry()
+            .into_iter()
+            .map(|(window, _)| window)
+            .collect();
+        assert_eq!(tiled, vec![Window::new(2)]);
+        assert!(state.client_list_windows().contains(&Window::new(1)));
+        assert!(state.is_window_hidden(Window::new(1)));
+    }
+
+    #[test]
+    fn test_minimize_last_window_clears_focus() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        let _ = state.minimize();
+
+        assert_eq!(state.focused_window(), None);
+        assert!(state.current_geometry().is_empty());
+    }
+
+    #[test]
+    fn test_restore_last_minimized_maps_retiles_and_focuses() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        let _ = state.minimize();
+
+        let effects = state.apply_action(ActionEvent::RestoreLastMinimized);
+
+        assert!(effects.contains(&Effect::Map(Window::new(1))));
+        assert!(effects.contains(&Effect::Focus(Window::new(1))));
+        assert_eq!(state.current_geometry().len(), 2);
+        assert!(!state.is_window_hidden(Window::new(1)));
+        assert!(
+            state
+                .apply_action(ActionEvent::RestoreLastMinimized)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_unminimize_returns_window_to_its_tiling_position() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let _ = state.configure_windows(0);
+        let before = state.current_geometry();
+        let _ = state.set_focus(Window::new(2));
+        let _ = state.minimize();
+
+        let effects = state.unminimize(Window::new(2));
+
+        let after = state.current_geometry();
+        let order: Vec<Window> = after.iter().map(|(window, _)| *window).collect();
+        assert_eq!(order, vec![Window::new(1), Window::new(2), Window::new(3)]);
+        assert_eq!((after[1].1.x, after[1].1.w), (before[1].1.x, before[1].1.w));
+        assert!(effects.contains(&Effect::Focus(Window::new(2))));
+        assert_eq!(state.focused_window(), Some(Window::new(2)));
+    }
+
+    #[test]
+    fn test_unminimize_switches_to_the_windows_workspace() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        let _ = state.minimize();
+        let _ = state.go_to_workspace(1);
+
+        let effects = state.unminimize(Window::new(1));
+
+        assert_eq!(state.current_workspace_id(), 0);
+        assert!(effects.contains(&Effect::Map(Window::new(1))));
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+        assert_eq!(state.current_geometry().len(), 2);
+    }
+
+    #[test]
+    fn test_restore_last_minimized_goes_in_reverse_order() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        let _ = state.minimize();
+        let _ = state.set_focus(Window::new(3));
+        let _ = state.minimize();
+
+        let _ = state.restore_last_minimized();
+        assert_eq!(state.focused_window(), Some(Window::new(3)));
+        let _ = state.restore_last_minimized();
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+    }
+
+    #[test]
+    fn test_hidden_window_stays_unmapped_across_workspace_switch() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        let _ = state.minimize();
+
+        let _ = state.go_to_workspace(1);
+        let effects = state.go_to_workspace(0);
+
+        assert!(!effects.contains(&Effect::Map(Window::new(1))));
+        assert_eq!(state.current_geometry().len(), 1);
+    }
+
+    #[test]
+    fn test_move_window_to_workspace_from_hidden_to_current_maps_it() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 2, false)], 0);
+        let win1 = Window::new(1);
+        let win2 = Window::new(2);
+
+        let effects = state.move_window_to_workspace(win2, 0);
+
+        assert_eq!(state.window_workspace(win2), Some(0));
+        assert!(effects.contains(&Effect::Map(win2)));
+        assert!(find_configure_height(&effects, win1).is_some());
+        assert!(find_configure_height(&effects, win2).is_some());
+    }
+
+    #[test]
+    fn test_move_window_to_workspace_stays_on_the_windows_monitor() {
+        let mut state = make_dual_monitor_state();
+        let win2 = Window::new(2);
+
+        // Monitor 0 is active, window 2 lives on monitor 1
+        let _ = state.move_window_to_workspace(win2, 1);
+
+        assert_eq!(state.window_workspace(win2), Some(NUM_WORKSPACES + 1));
+    }
+
+    #[test]
+    fn test_move_window_to_workspace_rejects_invalid_targets() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        let win1 = Window::new(1);
+
+        assert!(
+            state
+                .move_window_to_workspace(win1, NUM_WORKSPACES)
+                .is_empty()
+        );
+        assert!(state.move_window_to_workspace(win1, 0).is_empty());
+        assert!(
+            state
+                .move_window_to_workspace(Window::new(99), 1)
+                .is_empty()
+        );
+        assert_eq!(state.window_workspace(win1), Some(0));
+    }
+
+    #[test]
+    fn test_focus_stack_next_cycles_stack_and_skips_master() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let master = Window::new(1);
+        let s1 = Window::new(2);
+        let s2 = Window::new(3);
+        let _ = state.set_focus(master);
+
+        let mut focused = Vec::new();
+        for _ in 0..4 {
+            state.apply_action(ActionEvent::FocusStackNext);
+            focused.push(state.focused_window().unwrap());
+        }
+
+        assert_eq!(focused, vec![s1, s2, s1, s2]);
+        assert!(!focused.contains(&master));
+    }
+
+    #[test]
+    fn test_focus_stack_prev_from_master_focuses_last_stack_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        state.apply_action(ActionEvent::FocusStackPrev);
+
+        assert_eq!(state.focused_window(), Some(Window::new(3)));
+    }
+
+    #[test]
+    fn test_focus_stack_noop_without_stack_windows() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        assert!(state.focus_stack(1).is_empty());
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+    }
+
+    #[test]
+    fn test_client_for_toplevel_maps_clients_and_frames() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 2, false)], 0);
+        let _ = state.attach_frame(Window::new(1), Window::new(100));
+
+        assert_eq!(
+            state.client_for_toplevel(Window::new(100)),
+            Some(Window::new(1))
+        );
+        assert_eq!(
+            state.client_for_toplevel(Window::new(2)),
+            Some(Window::new(2))
+        );
+        // The root, a dock or any other unmanaged window is not a client.
+        assert_eq!(state.client_for_toplevel(Window::new(999)), None);
+    }
+
+    #[test]
+    fn test_release_frames_puts_floating_clients_where_they_float() {
+        let mut state = make_floating_state();
+        let root = Window::new(1000);
+        let _ = state.attach_frame(Window::new(2), Window::new(52));
+
+        assert_eq!(
+            state.release_frames(root),
+            vec![
+                Effect::Reparent {
+                    window: Window::new(2),
+                    parent: root,
+                    x: 100,
+                    y: 150,
+                },
+                Effect::DestroyWindow(Window::new(52)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attach_frame_tracks_frame_and_reparents() {
+        let mut state = make_state_with_windows(&[], 0);
+        let win = Window::new(1);
+        let frame = Window::new(100);
+
+        let effects = state.attach_frame(win, frame);
+
+        assert!(state.is_framed(win));
+        assert_eq!(state.frames.get(&win), Some(&frame));
+        assert_eq!(
+            effects,
+            vec![
+                Effect::CreateFrame(frame),
+                Effect::AddToSaveSet(win),
+                Effect::Reparent {
+                    window: win,
+                    parent: frame,
+                    x: 0,
+                    y: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_framed_window_positions_frame_and_pins_client() {
+        let mut state = make_state_with_windows(&[], 0);
+        let win = Window::new(1);
+        let frame = Window::new(100);
+        let _ = state.attach_frame(win, frame);
+
+        let effects = state.on_map_request(win, WindowType::Managed);
+
+        assert!(effects.contains(&Effect::Map(frame)));
+        assert!(effects.contains(&Effect::Map(win)));
+        assert!(effects.contains(&Effect::Configure {
+            window: frame,
+            x: 0,
+            y: 0,
+            w: 800,
+            h: 600,
+            border: 0,
+        }));
+        assert!(effects.contains(&Effect::Configure {
+            window: win,
+            x: 0,
+            y: 0,
+            w: 798,
+            h: 598,
+            border: 1,
+        }));
+    }
+
+    #[test]
+    fn test_destroying_framed_window_destroys_frame() {
+        let mut state = make_state_with_windows(&[], 0);
+        let win = Window::new(1);
+        let frame = Window::new(100);
+        let _ = state.attach_frame(win, frame);
+        let _ = state.on_map_request(win, WindowType::Managed);
+
+        let effects = state.on_destroy(win);
+
+        assert!(effects.contains(&Effect::DestroyWindow(frame)));
+        assert!(!state.is_framed(win));
+    }
+
+    #[test]
+    fn test_go_to_workspace_unmaps_frames() {
+        let mut state = make_state_with_windows(&[], 0);
+        let win = Window::new(1);
+        let frame = Window::new(100);
+        let _ = state.attach_frame(win, frame);
+        let _ = state.on_map_request(win, WindowType::Managed);
+
+        let effects = state.go_to_workspace(1);
+
+        assert!(effects.contains(&Effect::Unmap(win)));
+        assert!(effects.contains(&Effect::Unmap(frame)));
+    }
+
+    #[test]
+    fn test_window_infos_report_configured_border_width() {
+        let screen = ScreenConfig {
+            width: 800,
+            height: 600,
+            focused_border_pixel: 0,
+            normal_border_pixel: 1,
+        };
+        let mut state = State::new(screen, 3, 0, 0);
+        state.track_startup_managed(Window::new(1), 0);
+
+        assert_eq!(state.window_infos()[0].border_width, 3);
+
+        let _ = state.toggle_borders();
+        assert_eq!(state.window_infos()[0].border_width, 0);
+    }
+
+    fn make_dual_monitor_state() -> State {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        state.set_monitors(&[
+            Rect {
+                x: 0,
+                y: 0,
+                w: 800,
+                h: 600,
+            },
+            Rect {
+                x: 800,
+                y: 0,
+                w: 800,
+                h: 600,
+            },
+        ]);
+        state.track_startup_managed(Window::new(1), 0);
+
+        // Windows 2 and 3 live on the second monitor's first workspace.
+        state.active_monitor = 1;
+        state.track_startup_managed(Window::new(2), 0);
+        state.track_startup_managed(Window::new(3), 0);
+        state.active_monitor = 0;
+        state
+    }
+
+    #[test]
+    fn test_spread_across_monitors_splits_windows_evenly() {
+        let mut state = make_state_with_windows(&[], 0);
+        state.set_monitors(&[
+            Rect {
+                x: 0,
+                y: 0,
+                w: 800,
+                h: 600,
+            },
+            Rect {
+                x: 800,
+                y: 0,
+                w: 800,
+                h: 600,
+            },
+        ]);
+        for id in 1..=4 {
+            state.track_startup_managed(Window::new(id), 0);
+        }
+        let (left, right) = (state.monitors[0].workspace, state.monitors[1].workspace);
+
+        let effects = state.apply_action(ActionEvent::SpreadAcrossMonitors);
+
+        let workspaces: Vec<Option<usize>> = (1..=4)
+            .map(|id| state.window_workspace(Window::new(id)))
+            .collect();
+        assert_eq!(
+            workspaces,
+            vec![Some(left), Some(right), Some(left), Some(right)]
+        );
+        for (id, min_x) in [(1, 0), (3, 0), (2, 800), (4, 800)] {
+            let last_configure = effects.iter().rev().find_map(|effect| match effect {
+                Effect::Configure { window, x, w, .. } if *window == Window::new(id) => {
+                    Some((*x, *w))
+                }
+                _ => None,
+            });
+            let (x, w) = last_configure.expect("every window is re-tiled");
+            assert!(x >= min_x && x < min_x + 800);
+            assert_eq!(w, 400 - 2);
+        }
+    }
+
+    #[test]
+    fn test_empty_workspace_stay_keeps_workspace_without_focus() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (3, 2, false)], 0);
+        let _ = state.go_to_workspace(3);
+
+        let _ = state.on_destroy(Window::new(2));
+
+        assert_eq!(state.current_workspace_id(), 3);
+        assert_eq!(state.focused_window(), None);
+    }
+
+    #[test]
+    fn test_empty_workspace_switches_to_previous_non_empty_on_destroy() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (3, 2, false)], 0);
+        state.empty_workspace = EmptyWorkspace::SwitchToPrevious;
+        let _ = state.go_to_workspace(3);
+
+        let effects = state.on_destroy(Window::new(2));
+
+        assert_eq!(state.current_workspace_id(), 0);
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+        assert!(effects.contains(&Effect::Map(Window::new(1))));
+        assert!(effects.contains(&Effect::Focus(Window::new(1))));
+    }
+
+    #[test]
+    fn test_empty_workspace_switches_after_sending_last_window() {
+        let mut state = make_state_with_windows(&[(1, 1, false), (3, 2, false)], 0);
+        state.empty_workspace = EmptyWorkspace::SwitchToPrevious;
+        let _ = state.go_to_workspace(3);
+
+        let _ = state.send_to_workspace(5);
+
+        assert_eq!(state.current_workspace_id(), 1);
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+    }
+
+    #[test]
+    fn test_empty_workspace_switch_ignores_windows_on_other_workspaces() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (3, 3, false)], 0);
+        state.empty_workspace = EmptyWorkspace::SwitchToPrevious;
+
+        let _ = state.on_destroy(Window::new(3));
+        let _ = state.on_destroy(Window::new(1));
+
+        assert_eq!(state.current_workspace_id(), 0);
+        assert_eq!(state.focused_window(), Some(Window::new(2)));
+    }
+
+    #[test]
+    fn test_gather_all_brings_every_window_to_the_current_workspace() {
+        let mut state = make_state_with_windows(
+            &[(0, 1, true), (0, 2, true), (3, 3, false), (3, 4, false)],
+            0,
+        );
+
+        let effects = state.apply_action(ActionEvent::GatherAll);
+
+        assert!(effects.contains(&Effect::Map(Window::new(3))));
+        assert!(effects.contains(&Effect::Map(Window::new(4))));
+        assert_eq!(state.current_workspace().iter_windows().count(), 4);
+        assert_eq!(state.workspaces[3].iter_windows().count(), 0);
+        assert_eq!(state.current_geometry().len(), 4);
+        assert!(state.window_infos().iter().all(|info| info.workspace == 0));
+    }
+
+    #[test]
+    fn test_gather_all_leaves_minimized_windows() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (3, 2, true), (3, 3, true)], 0);
+        let _ = state.go_to_workspace(3);
+        let _ = state.set_focus(Window::new(3));
+        let _ = state.minimize();
+        let _ = state.go_to_workspace(0);
+
+        let _ = state.gather_all();
+
+        assert_eq!(state.window_workspace(Window::new(2)), Some(0));
+        assert_eq!(state.window_workspace(Window::new(3)), Some(3));
+        assert!(state.gather_all().is_empty());
+    }
+
+    #[test]
+    fn test_spread_across_monitors_needs_two_monitors() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+
+        assert!(
+            state
+                .apply_action(ActionEvent::SpreadAcrossMonitors)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_focus_monitor_next_picks_that_monitors_focused_window() {
+        let mut state = make_dual_monitor_state();
+        let _ = state.set_focus(Window::new(1));
+        state
+            .get_workspace_mut(NUM_WORKSPACES)
+            .unwrap()
+            .set_focus(Window::new(3));
+
+        let effects = state.apply_action(ActionEvent::FocusMonitorNext);
+
+        assert_eq!(state.active_monitor, 1);
+        assert_eq!(state.current_workspace_id(), 0);
+        assert_eq!(state.focused_window(), Some(Window::new(3)));
+        assert!(effects.contains(&Effect::Focus(Window::new(3))));
+        assert!(effects.contains(&Effect::WarpPointer { x: 1200, y: 300 }));
+
+        let _ = state.apply_action(ActionEvent::FocusMonitorNext);
+        assert_eq!(state.active_monitor, 0);
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+    }
+
+    #[test]
+    fn test_sticky_window_stays_put_when_focusing_the_other_monitor() {
+        let mut state = make_dual_monitor_state();
+        let sticky = Window::new(9);
+        state.track_startup_sticky(sticky, false);
+        assert_eq!(state.window_workspace(sticky), Some(0));
+
+        let effects = state.apply_action(ActionEvent::FocusMonitorNext);
+
+        assert_eq!(state.window_workspace(sticky), Some(0));
+        assert!(state.get_workspace(0).unwrap().is_window_mapped(&sticky));
+        assert!(!effects.iter().any(|effect| matches!(
+            effect,
+            Effect::Configure { window, .. } if *window == sticky
+        )));
+
+        // Switching workspaces on its own monitor still carries it.
+        let _ = state.apply_action(ActionEvent::FocusMonitorNext);
+        let _ = state.go_to_workspace(2);
+        assert_eq!(state.window_workspace(sticky), Some(2));
+    }
+
+    #[test]
+    fn test_focus_monitor_with_empty_workspace_focuses_root() {
+        let mut state = make_dual_monitor_state();
+        let _ = state.set_focus(Window::new(1));
+        state.active_monitor = 1;
+        state.current_workspace = NUM_WORKSPACES;
+        let _ = state.go_to_workspace(1);
+        state.active_monitor = 0;
+        state.current_workspace = 0;
+
+        let effects = state.apply_action(ActionEvent::FocusMonitorNext);
+
+        assert_eq!(state.focused_window(), None);
+        assert!(effects.contains(&Effect::FocusRoot));
+        assert!(
+            !effects
+                .iter()
+                .any(|effect| matches!(effect, Effect::Focus(_)))
+        );
+    }
+
+    #[test]
+    fn test_configure_windows_offsets_by_monitor_origin() {
+        let mut state = make_dual_monitor_state();
+
+        let effects = state.configure_windows(NUM_WORKSPACES);
+
+        assert!(find_configure_height(&effects, Window::new(2)).is_some());
+        assert!(effects.iter().all(|e| match e {
+            Effect::Configure { x, .. } => *x >= 800,
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn test_each_monitor_has_its_own_workspace_set() {
+        let state = make_dual_monitor_state();
+
+        assert_eq!(state.workspaces.len(), 2 * NUM_WORKSPACES);
+        assert_eq!(state.window_workspace(Window::new(1)), Some(0));
+        assert_eq!(state.window_workspace(Window::new(2)), Some(NUM_WORKSPACES));
+        assert_eq!(state.monitors[1].workspace, NUM_WORKSPACES);
+    }
+
+    #[test]
+    fn test_go_to_workspace_only_switches_focused_monitor() {
+        let mut state = make_dual_monitor_state();
+        let _ = state.focus_monitor(1);
+
+        let effects = state.go_to_workspace(3);
+
+        assert_eq!(state.monitors[0].workspace, 0);
+        assert_eq!(state.monitors[1].workspace, NUM_WORKSPACES + 3);
+        assert_eq!(state.current_workspace_id(), 3);
+        assert!(effects.contains(&Effect::Unmap(Window::new(2))));
+        assert!(!effects.contains(&Effect::Unmap(Window::new(1))));
+        assert!(
+            state
+                .get_workspace(0)
+                .unwrap()
+                .is_window_mapped(&Window::new(1))
+        );
+    }
+
+    #[test]
+    fn test_send_to_workspace_stays_on_focused_monitor() {
+        let mut state = make_dual_monitor_state();
+        let _ = state.focus_monitor(1);
+        let _ = state.set_focus(Window::new(2));
+
+        let _ = state.send_to_workspace(4);
+
+        assert_eq!(
+            state.window_workspace(Window::new(2)),
+            Some(NUM_WORKSPACES + 4)
+        );
+        let info = state
+            .window_infos()
+            .into_iter()
+            .find(|info| info.window == Window::new(2))
+            .unwrap();
+        assert_eq!(info.workspace, 4);
+    }
+
+    #[test]
+    fn test_send_to_monitor_next_moves_window_to_target_workspace() {
+        let mut state = make_dual_monitor_state();
+        state.track_startup_managed(Window::new(4), 0);
+        let _ = state.set_focus(Window::new(1));
+
+        let effects = state.apply_action(ActionEvent::SendToMonitorNext);
+
+        assert_eq!(state.window_workspace(Window::new(1)), Some(NUM_WORKSPACES));
+        assert!(
+            !state
+                .get_workspace(0)
+                .unwrap()
+                .iter_windows()
+                .any(|w| *w == Window::new(1))
+        );
+        assert!(
+            state
+                .get_workspace(NUM_WORKSPACES)
+                .unwrap()
+                .is_window_mapped(&Window::new(1))
+        );
+        assert!(!effects.contains(&Effect::Unmap(Window::new(1))));
+        // Both monitors are re-tiled: window 4 takes over monitor 0 and window 1
+        // is placed on monitor 1.
+        assert!(effects.iter().any(|e| matches!(
+            e,
+            Effect::Configure { window, x: 0, w: 798, .. } if *window == Window::new(4)
+        )));
+        assert!(effects.iter().any(|e| matches!(
+            e,
+            Effect::Configure { window, x, .. } if *window == Window::new(1) && *x >= 800
+        )));
+        assert_eq!(state.active_monitor, 0);
+        assert_eq!(state.focused_window(), Some(Window::new(4)));
+    }
+
+    #[test]
+    fn test_send_to_monitor_prev_wraps_around() {
+        let mut state = make_dual_monitor_state();
+        let _ = state.focus_monitor(1);
+        let _ = state.set_focus(Window::new(3));
+
+        let _ = state.apply_action(ActionEvent::SendToMonitorPrev);
+
+        assert_eq!(state.window_workspace(Window::new(3)), Some(0));
+        assert_eq!(state.focused_window(), Some(Window::new(2)));
+    }
+
+    #[test]
+    fn test_send_to_monitor_with_single_monitor_is_noop() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        let effects = state.send_to_monitor(1);
+
+        assert!(effects.is_empty());
+        assert_eq!(state.window_workspace(Window::new(1)), Some(0));
+    }
+
+    fn make_floating_state() -> State {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state.reserved_regions = vec![Rect {
+            x: 0,
+            y: 0,
+            w: 800,
+            h: 20,
+        }];
+        let _ = state.set_focus(Window::new(2));
+        let _ = state.toggle_floating();
+        state.floating_geometry.insert(
+            Window::new(2),
+            Rect {
+                x: 100,
+                y: 150,
+                w: 200,
+                h: 100,
+            },
+        );
+        state
+    }
+
+    #[test]
+    fn test_seed_floating_geometry_only_fills_missing_floating_rects() {
+        let mut state = make_floating_state();
+        let rect = Rect {
+            x: 5,
+            y: 5,
+            w: 50,
+            h: 50,
+        };
+
+        // Window 1 is tiled and window 2 already has a rect.
+        state.seed_floating_geometry(Window::new(1), rect);
+        state.seed_floating_geometry(Window::new(2), rect);
+        assert!(!state.floating_geometry.contains_key(&Window::new(1)));
+        assert_eq!(state.floating_geometry[&Window::new(2)].x, 100);
+
+        let dialog = Window::new(3);
+        let _ = state.on_map_request(dialog, WindowType::Floating);
+        assert!(state.needs_floating_geometry(dialog));
+        state.seed_floating_geometry(dialog, rect);
+        assert!(!state.needs_floating_geometry(dialog));
+        assert_eq!(state.floating_geometry[&dialog].w, 50);
+    }
+
+    #[test]
+    fn test_move_resize_floating_changes_only_requested_fields() {
+        let mut state = make_floating_state();
+
+        let effects = state.move_resize_floating(Window::new(2), |rect, _| Rect {
+            x: 40,
+            w: 300,
+            ..rect
+        });
+
+        assert_eq!(
+            effects,
+            vec![Effect::ConfigurePositionSize {
+                window: Window::new(2),
+                x: 40,
+                y: 150,
+                w: 300,
+                h: 100,
+            }]
+        );
+        assert!(
+            state
+                .move_resize_floating(Window::new(1), |rect, _| rect)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_focus_master_and_last_stack_jump_to_the_ends() {
+        let mut state =
+            make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true), (0, 4, true)], 0);
+        state
+            .current_workspace_mut()
+            .set_layout(LayoutType::MasterLayout);
+        let _ = state.set_focus(Window::new(2));
+
+        let effects = state.apply_action(ActionEvent::FocusLastStack);
+        assert!(effects.contains(&Effect::Focus(Window::new(4))));
+        assert_eq!(state.focused_window(), Some(Window::new(4)));
+
+        let effects = state.apply_action(ActionEvent::FocusMaster);
+        assert!(effects.contains(&Effect::Focus(Window::new(1))));
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
+
+        assert!(state.apply_action(ActionEvent::FocusMaster).is_empty());
+    }
+
+    #[test]
+    fn test_cycle_floating_skips_tiled_windows() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        for window in [1, 3] {
+            let _ = state.set_focus(Window::new(window));
+            let _ = state.toggle_floating();
+        }
+        let touches_tiled = |effects: &Effects| {
+            effects.iter().any(|effect| {
+                matches!(effect, Effect::Focus(w) | Effect::Raise(w) if *w == Window::new(2))
+            })
+        };
+
+        let effects = state.apply_action(ActionEvent::CycleFloating);
+        assert!(effects.contains(&Effect::Focus(Window::new(1))));
+        assert!(effects.contains(&Effect::Raise(Window::new(1))));
+        assert!(!touches_tiled(&effects));
+
+        let effects = state.apply_action(ActionEvent::CycleFloating);
+        assert!(effects.contains(&Effect::Focus(Window::new(3))));
+        assert!(effects.contains(&Effect::Raise(Window::new(3))));
+        assert!(!touches_tiled(&effects));
+    }
+
+    #[test]
+    fn test_cycle_floating_from_tiled_focus_picks_first_floating() {
+        let mut state = make_floating_state();
+        let _ = state.set_focus(Window::new(1));
+
+        let effects = state.apply_action(ActionEvent::CycleFloating);
+
+        assert!(effects.contains(&Effect::Focus(Window::new(2))));
+        assert!(effects.contains(&Effect::Raise(Window::new(2))));
+        assert_eq!(state.focused_window(), Some(Window::new(2)));
+    }
+
+    #[test]
+    fn test_cycle_floating_without_floating_windows_is_noop() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        assert!(state.apply_action(ActionEvent::CycleFloating).is_empty());
+    }
+
+    #[test]
+    fn test_max_vertical_spans_usable_height_and_keeps_x_w() {
+        let mut state = make_floating_state();
+
+        let effects = state.apply_action(ActionEvent::MaxVertical);
+
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(2),
+            x: 100,
+            y: 20,
+            w: 200,
+            h: 578,
+            border: 1,
+        }));
+
+        let effects = state.apply_action(ActionEvent::MaxVertical);
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(2),
+            x: 100,
+            y: 150,
+            w: 200,
+            h: 100,
+            border: 1,
+        }));
+    }
+
+    #[test]
+    fn test_max_horizontal_spans_usable_width_and_keeps_y_h() {
+        let mut state = make_floating_state();
+        let _ = state.apply_action(ActionEvent::MaxVertical);
+
+        let effects = state.apply_action(ActionEvent::MaxHorizontal);
+
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(2),
+            x: 0,
+            y: 20,
+            w: 798,
+            h: 578,
+            border: 1,
+        }));
+
+        let _ = state.apply_action(ActionEvent::MaxHorizontal);
+        assert_eq!(
+            state
+                .floating_geometry
+                .get(&Window::new(2))
+                .map(|r| (r.x, r.w)),
+            Some((100, 200))
+        );
+    }
+
+    #[test]
+    fn test_drag_snaps_to_usable_area_edge() {
+        let mut state = make_floating_state();
+        assert!(state.begin_drag(Window::new(2), 110, 160));
+
+        let effects = state.drag_to(20, 300);
+
+        // x=10 snaps to the screen's left edge, the top stays where it was dragged.
+        assert_eq!(
+            effects,
+            vec![Effect::Configure {
+                window: Window::new(2),
+                x: 0,
+                y: 290,
+                w: 200,
+                h: 100,
+                border: 1,
+            }]
+        );
+        state.end_drag();
+        assert!(state.drag_to(50, 50).is_empty());
+    }
+
+    #[test]
+    fn test_placement_enters_on_floating_map() {
+        let mut state = make_state_with_windows(&[], 0);
+        let window = Window::new(5);
+        let _ = state.on_map_request(window, WindowType::Floating);
+
+        let effects = state.begin_placement(window, (200, 100), (400, 300));
+
+        assert!(state.is_placing());
+        assert!(effects.contains(&Effect::Configure {
+            window,
+            x: 299,
+            y: 249,
+            w: 200,
+            h: 100,
+            border: 1,
         }));
-        assert!(effects.contains(&Effect::SetBorder {
-            window: window_to_focus,
-            pixel: state.screen.focused_border_pixel,
-            width: state.border_width
+    }
+
+    #[test]
+    fn test_placement_follows_motion_until_click() {
+        let mut state = make_state_with_windows(&[], 0);
+        let window = Window::new(5);
+        let _ = state.on_map_request(window, WindowType::Floating);
+        let _ = state.begin_placement(window, (200, 100), (400, 300));
+
+        let effects = state.place_at(500, 200);
+        assert_eq!(
+            effects,
+            vec![Effect::Configure {
+                window,
+                x: 399,
+                y: 149,
+                w: 200,
+                h: 100,
+                border: 1,
+            }]
+        );
+
+        assert!(state.commit_placement());
+        assert!(!state.is_placing());
+        assert!(state.place_at(10, 10).is_empty());
+        assert!(!state.commit_placement());
+        // The placed rect sticks through later re-tiles.
+        assert!(state.configure_windows(0).is_empty());
+    }
+
+    #[test]
+    fn test_placement_skips_tiled_and_already_placed_windows() {
+        let mut state = make_floating_state();
+
+        assert!(
+            state
+                .begin_placement(Window::new(1), (200, 100), (400, 300))
+                .is_empty()
+        );
+        assert!(
+            state
+                .begin_placement(Window::new(2), (200, 100), (400, 300))
+                .is_empty()
+        );
+        assert!(!state.is_placing());
+    }
+
+    #[test]
+    fn test_placement_ends_when_window_goes_away() {
+        let mut state = make_state_with_windows(&[], 0);
+        let window = Window::new(5);
+        let _ = state.on_map_request(window, WindowType::Floating);
+        let _ = state.begin_placement(window, (200, 100), (400, 300));
+
+        let _ = state.on_destroy(window);
+
+        assert!(!state.is_placing());
+    }
+
+    #[test]
+    fn test_drag_keeps_window_partly_on_screen() {
+        let mut state = make_floating_state();
+        assert!(state.begin_drag(Window::new(2), 100, 150));
+
+        let effects = state.drag_to(-500, 150);
+
+        // The outer rect is 202px wide, so 20px stay visible at x=-182.
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(2),
+            x: -182,
+            y: 150,
+            w: 200,
+            h: 100,
+            border: 1,
         }));
-        assert!(effects.contains(&Effect::Focus(window_to_focus)));
     }
 
     #[test]
-    fn test_toggle_fullscreen() {
-        let mut state = make_state(10);
-        let window_to_fullsreen = Window::new(6);
-        let _ = state.set_focus(window_to_fullsreen);
-        let mut fullscreen_effects = state.toggle_fullscreen();
+    fn test_drag_ignores_tiled_window() {
+        let mut state = make_floating_state();
+
+        assert!(!state.begin_drag(Window::new(1), 10, 10));
+        assert!(state.drag_to(50, 50).is_empty());
+    }
+
+    #[test]
+    fn test_snap_half_left_fills_left_of_usable_area() {
+        let mut state = make_floating_state();
+
+        let effects = state.apply_action(ActionEvent::SnapHalfLeft);
+
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(2),
+            x: 0,
+            y: 20,
+            w: 398,
+            h: 578,
+            border: 1,
+        }));
+    }
+
+    #[test]
+    fn test_snap_bottom_right_fills_quarter_and_drops_maximize() {
+        let mut state = make_floating_state();
+        let _ = state.apply_action(ActionEvent::MaxVertical);
+
+        let effects = state.apply_action(ActionEvent::SnapBottomRight);
+
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(2),
+            x: 400,
+            y: 310,
+            w: 398,
+            h: 288,
+            border: 1,
+        }));
+        // Un-maximizing no longer restores the pre-snap height.
+        let effects = state.apply_action(ActionEvent::MaxVertical);
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(2),
+            x: 400,
+            y: 20,
+            w: 398,
+            h: 578,
+            border: 1,
+        }));
+    }
+
+    #[test]
+    fn test_center_window_keeps_size_in_usable_area() {
+        let screen = ScreenConfig {
+            width: 1920,
+            height: 1080,
+            focused_border_pixel: 0,
+            normal_border_pixel: 1,
+        };
+        let mut state = State::new(screen, 0, 0, 0);
+        let window = Window::new(1);
+        state.track_startup_floating(window, 0);
+        let _ = state.set_focus(window);
+        state.floating_geometry.insert(
+            window,
+            Rect {
+                x: 10,
+                y: 20,
+                w: 800,
+                h: 600,
+            },
+        );
+
+        let effects = state.apply_action(ActionEvent::CenterWindow);
 
-        // Test that we succesfully toggled window to fullscreen
-        assert_eq!(state.focused_window().unwrap(), window_to_fullsreen);
         assert_eq!(
-            state.current_workspace().get_fullscreen_window().unwrap(),
-            window_to_fullsreen
+            effects,
+            vec![Effect::ConfigurePositionSize {
+                window,
+                x: 560,
+                y: 240,
+                w: 800,
+                h: 600,
+            }]
         );
-        assert!(state.is_window_fullscreen(window_to_fullsreen));
-        assert!(fullscreen_effects.contains(&Effect::Raise(window_to_fullsreen)));
-        assert!(fullscreen_effects.contains(&Effect::Configure {
-            window: window_to_fullsreen,
+    }
+
+    #[test]
+    fn test_center_window_accounts_for_border_and_reserved_space() {
+        let mut state = make_floating_state();
+
+        let effects = state.apply_action(ActionEvent::CenterWindow);
+
+        // 800x580 usable below the 20px strip; the window is 202x102 with borders.
+        assert_eq!(
+            effects,
+            vec![Effect::ConfigurePositionSize {
+                window: Window::new(2),
+                x: 299,
+                y: 259,
+                w: 200,
+                h: 100,
+            }]
+        );
+        assert!(
+            make_state_with_windows(&[(0, 1, true)], 0)
+                .apply_action(ActionEvent::CenterWindow)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_snap_ignores_tiled_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+
+        assert!(state.apply_action(ActionEvent::SnapHalfRight).is_empty());
+    }
+
+    #[test]
+    fn test_max_vertical_ignores_tiled_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+
+        assert!(state.toggle_float_maximize(true).is_empty());
+    }
+
+    #[test]
+    fn test_toggle_floating_keeps_tiled_rect() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(2));
+        let (_, tiled) = state
+            .current_geometry()
+            .into_iter()
+            .find(|(window, _)| *window == Window::new(2))
+            .unwrap();
+
+        let effects = state.apply_action(ActionEvent::ToggleFloating);
+
+        assert!(state.is_window_floating(Window::new(2)));
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(2),
+            x: tiled.x,
+            y: tiled.y,
+            w: tiled.w,
+            h: tiled.h,
+            border: 1,
+        }));
+        assert!(effects.contains(&Effect::Raise(Window::new(2))));
+        // The remaining tiled window takes the whole screen.
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(1),
             x: 0,
             y: 0,
-            w: 800,
-            h: 600,
-            border: 0
+            w: 798,
+            h: 598,
+            border: 1,
         }));
+    }
 
-        fullscreen_effects = state.toggle_fullscreen();
+    #[test]
+    fn test_toggle_floating_twice_tiles_window_again() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(2));
+        let _ = state.toggle_floating();
 
-        assert_eq!(state.focused_window().unwrap(), window_to_fullsreen);
-        assert_eq!(state.current_workspace().get_fullscreen_window(), None);
-        assert!(!state.is_window_fullscreen(window_to_fullsreen));
-        assert!(fullscreen_effects.contains(&Effect::Focus(window_to_fullsreen)))
+        let effects = state.toggle_floating();
+
+        assert!(!state.is_window_floating(Window::new(2)));
+        assert!(state.floating_geometry.is_empty());
+        assert_eq!(state.current_geometry().len(), 2);
+        assert!(!effects.contains(&Effect::Raise(Window::new(2))));
     }
 
     #[test]
-    fn test_toggle_fullscreen_and_switch_focus() {
-        let mut state = make_state(10);
-        let window_to_fullsreen = Window::new(6);
-        let window_to_focus = Window::new(2);
-        let _ = state.set_focus(window_to_fullsreen);
-        let _fullscreen_effects = state.toggle_fullscreen();
-        let focus_effects = state.set_focus(window_to_focus);
-        // We assert that our focus has not been stolen
-        assert!(focus_effects.is_empty());
+    fn test_tiling_skip_excludes_window_from_layout() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let _ = state.set_focus(Window::new(2));
+
+        let effects = state.apply_action(ActionEvent::ToggleTilingSkip);
+
+        assert_eq!(tiled_order(&state), vec![Window::new(1), Window::new(3)]);
+        assert!(!effects.iter().any(|e| matches!(
+            e,
+            Effect::Configure { window, .. } if *window == Window::new(2)
+        )));
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(3),
+            x: 400,
+            y: 0,
+            w: 398,
+            h: 598,
+            border: 1,
+        }));
+        assert!(!effects.contains(&Effect::Raise(Window::new(2))));
+        assert!(state.client_list_windows().contains(&Window::new(2)));
+        assert!(state.current_workspace().is_window_mapped(&Window::new(2)));
     }
 
     #[test]
-    fn test_toggle_fullscreen_and_kill_window() {
-        let mut state = make_state(10);
-        let window_to_fullsreen = Window::new(6);
-        let expected_focus = Window::new(7);
-        let _ = state.set_focus(window_to_fullsreen);
-        let _fullscreen_effects = state.toggle_fullscreen();
-        let destroy_effects = state.on_destroy(window_to_fullsreen);
+    fn test_tiling_skip_toggled_twice_tiles_window_again() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(2));
+        let _ = state.toggle_tiling_skip();
 
-        assert!(!state.is_window_fullscreen(window_to_fullsreen));
-        assert_eq!(state.focused_window().unwrap(), expected_focus);
-        assert!(destroy_effects.contains(&Effect::Focus(expected_focus)));
-        assert_eq!(
-            destroy_effects
+        let _ = state.toggle_tiling_skip();
+
+        assert_eq!(tiled_order(&state), vec![Window::new(1), Window::new(2)]);
+    }
+
+    #[test]
+    fn test_only_focused_border_moves_border_with_focus() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state.only_focused_border = true;
+        state.border_width = 4;
+        let _ = state.set_focus(Window::new(1));
+
+        let effects = state.set_focus(Window::new(2));
+
+        assert!(effects.contains(&Effect::SetBorder {
+            window: Window::new(2),
+            pixel: state.screen.focused_border_pixel,
+            width: 4,
+        }));
+        assert!(effects.contains(&Effect::SetBorder {
+            window: Window::new(1),
+            pixel: state.screen.normal_border_pixel,
+            width: 0,
+        }));
+        // Both windows are re-tiled: the unfocused one grows into its old border.
+        assert!(effects.iter().any(|effect| matches!(
+            effect,
+            Effect::Configure { window, border: 0, w: 400, .. } if *window == Window::new(1)
+        )));
+        assert!(effects.iter().any(|effect| matches!(
+            effect,
+            Effect::Configure { window, border: 4, w: 392, .. } if *window == Window::new(2)
+        )));
+    }
+
+    #[test]
+    fn test_only_focused_border_off_keeps_every_border() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        let effects = state.set_focus(Window::new(2));
+
+        assert!(effects.contains(&Effect::SetBorder {
+            window: Window::new(1),
+            pixel: state.screen.normal_border_pixel,
+            width: 1,
+        }));
+        assert!(
+            !effects
                 .iter()
-                .filter(|effect| matches!(
-                    effect,
-                    Effect::Configure {
-                        window: _,
-                        x: _,
-                        y: _,
-                        w: _,
-                        h: _,
-                        border: _
-                    }
-                ))
-                .collect::<Vec<&Effect>>()
-                .len(),
-            9
-        )
+                .any(|effect| matches!(effect, Effect::Configure { .. }))
+        );
     }
 
     #[test]
-    fn test_toggle_fullscreen_and_send_to_workspace() {
-        let mut state = make_state(10);
-        let window_to_fullsreen = Window::new(6);
-        let expected_focus = Window::new(7);
-        let _ = state.set_focus(window_to_fullsreen);
-        let _fullscreen_effects = state.toggle_fullscreen();
-        let workspace_effects = state.send_to_workspace(1);
+    fn test_mapping_firefox_names_its_workspace() {
+        let mut state = make_state(2);
+        state.auto_name_workspaces = true;
+        let window = Window::new(99);
+
+        let _ = state.on_map_request(window, WindowType::Managed);
+        state.set_window_class(window, Some("Firefox".to_string()));
+
+        let names = state.desktop_names();
+        assert_eq!(names[0], "firefox");
+        assert_eq!(names[1], "2");
+        assert_eq!(names.len(), NUM_WORKSPACES);
+    }
 
-        assert!(!state.is_window_fullscreen(window_to_fullsreen));
-        assert_eq!(state.window_workspace(window_to_fullsreen).unwrap(), 1);
+    #[test]
+    fn test_auto_name_follows_focused_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state.auto_name_workspaces = true;
+        state.set_window_class(Window::new(1), Some("Firefox".to_string()));
+        state.set_window_class(Window::new(2), Some("Alacritty".to_string()));
+
+        let _ = state.set_focus(Window::new(2));
+        assert_eq!(state.desktop_names()[0], "alacritty");
+
+        let _ = state.set_focus(Window::new(1));
+        assert_eq!(state.desktop_names()[0], "firefox");
+
+        let _ = state.on_destroy(Window::new(1));
+        assert_eq!(state.desktop_names()[0], "alacritty");
+    }
+
+    #[test]
+    fn test_auto_name_keeps_configured_names_and_is_off_by_default() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (1, 2, true)], 0);
+        state.set_window_class(Window::new(1), Some("Firefox".to_string()));
+        state.set_window_class(Window::new(2), Some("Alacritty".to_string()));
+        state.workspace_names.insert(0, "web");
+
+        assert_eq!(state.desktop_names()[..2], ["web", "2"]);
+
+        state.auto_name_workspaces = true;
+        assert_eq!(state.desktop_names()[..2], ["web", "alacritty"]);
+    }
+
+    #[test]
+    fn test_reconcile_maps_present_but_unviewable_window() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+
+        let effects = state.reconcile_current_workspace_maps(&[Window::new(2)]);
+
+        assert!(effects.contains(&Effect::Map(Window::new(2))));
+        assert!(!effects.contains(&Effect::Map(Window::new(1))));
+        assert_eq!(state.current_geometry().len(), 2);
+        assert!(state.reconcile_current_workspace_maps(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_leaves_withdrawn_windows_unmapped() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.handle_unmap_event_managed(Window::new(2));
+
+        assert_eq!(state.current_mapped_windows(), vec![Window::new(1)]);
         assert!(
             state
-                .get_workspace(0)
-                .unwrap()
-                .index_of_window(&window_to_fullsreen)
-                .is_none()
+                .reconcile_current_workspace_maps(&[Window::new(2)])
+                .is_empty()
         );
-        assert!(workspace_effects.contains(&Effect::Unmap(window_to_fullsreen)));
-        assert!(workspace_effects.contains(&Effect::Focus(expected_focus)));
-        assert_eq!(
-            workspace_effects
-                .iter()
-                .filter(|effect| matches!(effect, Effect::Configure { .. }))
-                .collect::<Vec<&Effect>>()
-                .len(),
-            9
-        )
     }
 
     #[test]
-    fn test_toggle_fullscreen_and_go_to_workspace() {
-        let mut state = make_state(10);
-        let window_to_fullsreen = Window::new(6);
-        let _ = state.set_focus(window_to_fullsreen);
-        let _fullscreen_effects = state.toggle_fullscreen();
-        let workspace_effects = state.go_to_workspace(1);
+    fn test_reconcile_leaves_minimized_windows_unmapped() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        let _ = state.minimize();
 
-        assert!(!state.is_window_fullscreen(window_to_fullsreen));
-        assert_eq!(state.current_workspace_id(), 1);
-        assert_eq!(
-            workspace_effects
-                .iter()
-                .filter(|effect| matches!(effect, Effect::Configure { .. }))
-                .collect::<Vec<&Effect>>()
-                .len(),
-            10
+        assert!(
+            state
+                .reconcile_current_workspace_maps(&[Window::new(1)])
+                .is_empty()
         );
-        assert_eq!(
-            workspace_effects
-                .iter()
-                .filter(|effect| matches!(effect, Effect::Unmap(_)))
-                .collect::<Vec<&Effect>>()
-                .len(),
-            10
+        let _ = state.go_to_workspace(1);
+        let effects = state.go_to_workspace(0);
+        assert!(!effects.contains(&Effect::Map(Window::new(1))));
+    }
+
+    #[test]
+    fn test_title_and_urgency_show_in_dump() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state.set_window_title(Window::new(1), Some("vim".to_string()));
+        state.set_window_urgent(Window::new(2), true);
+        state.set_window_title(Window::new(99), Some("stray".to_string()));
+
+        let dump = state.dump();
+        assert!(dump.contains("  window 1 weight=10 title=\"vim\" mapped focused\n"));
+        assert!(dump.contains("  window 2 weight=10 mapped urgent\n"));
+        assert!(!dump.contains("stray"));
+
+        state.set_window_title(Window::new(1), None);
+        state.set_window_urgent(Window::new(2), false);
+        let dump = state.dump();
+        assert!(dump.contains("  window 1 weight=10 mapped focused\n"));
+        assert!(dump.contains("  window 2 weight=10 mapped\n"));
+    }
+
+    #[test]
+    fn test_focus_denied_window_stays_urgent_until_focused() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        state.deny_focus_on_map(Window::new(2));
+
+        // A WM_HINTS refresh without the urgency flag keeps it.
+        state.set_window_urgent(Window::new(2), false);
+        assert!(
+            state
+                .dump()
+                .contains("  window 2 weight=10 mapped urgent\n")
+        );
+
+        let _ = state.set_focus(Window::new(2));
+        assert!(
+            state
+                .dump()
+                .contains("  window 2 weight=10 mapped focused\n")
         );
-        assert_eq!(
-            workspace_effects
-                .iter()
-                .filter(|effect| matches!(effect, Effect::Map(_)))
-                .collect::<Vec<&Effect>>()
-                .len(),
-            10
-        )
     }
 
     #[test]
-    fn test_fullscreen_then_map_request_does_not_steal_focus() {
-        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
-        let fullscreen_window = Window::new(1);
-        let _ = state.set_focus(fullscreen_window);
+    fn test_dump_lists_windows_per_workspace() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (4, 7, false)], 0);
+        let _ = state.set_focus(Window::new(2));
         let _ = state.toggle_fullscreen();
 
-        let new_window = Window::new(2);
-        let effects = state.on_map_request(new_window, WindowType::Managed);
+        let dump = state.dump();
 
-        assert_eq!(state.focused_window(), Some(fullscreen_window));
-        assert!(state.is_window_fullscreen(fullscreen_window));
-        assert!(effects.contains(&Effect::Map(new_window)));
-        assert!(!effects.contains(&Effect::Focus(new_window)));
-        assert!(state.current_workspace().is_window_mapped(&new_window));
+        assert!(dump.contains("monitor 0 800x600+0+0 showing workspace 0 (active)"));
+        assert!(dump.contains("monitor 0 workspace 0 layout=HorizontalLayout focus=Some(2)"));
+        assert!(dump.contains("  window 1 weight=10 mapped\n"));
+        assert!(dump.contains("  window 2 weight=10 mapped fullscreen focused\n"));
+        assert!(dump.contains("monitor 0 workspace 4 layout=HorizontalLayout focus=Some(7)"));
+        assert!(dump.contains("  window 7 weight=10\n"));
+        assert!(!dump.contains("workspace 3 "));
+        assert!(dump.contains("border_width=1 borders_enabled=true window_gap=0 nmaster=1"));
     }
 
-    #[test]
-    fn test_unmap_current_workspace_window_reconfigures() {
-        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 25);
-        let focus_window = Window::new(1);
-        let other_window = Window::new(2);
+    fn set_identity(state: &mut State, window: u32, class: &str, title: &str) {
+        let window = Window::new(window);
+        state.set_window_class(window, Some(class.to_string()));
+        state.set_window_instance(window, Some(class.to_lowercase()));
+        state.set_window_title(window, Some(title.to_string()));
+    }
 
-        let _ = state.set_focus(focus_window);
-        let effects = state.on_unmap(other_window);
+    fn make_session_state() -> State {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (3, 3, false)], 0);
+        set_identity(&mut state, 1, "Alacritty", "~");
+        set_identity(&mut state, 2, "Alacritty", "vim");
+        set_identity(&mut state, 3, "Firefox", "News");
+        let _ = state.set_focus(Window::new(2));
+        let _ = state.increase_window_weight(3);
+        let _ = state.swap_window(-1);
+        state
+            .get_workspace_mut(0)
+            .unwrap()
+            .get_client_mut(&Window::new(2))
+            .unwrap()
+            .set_floating(true);
+        state
+            .get_workspace_mut(3)
+            .unwrap()
+            .set_layout(LayoutType::MasterLayout);
+        let _ = state.go_to_workspace(3);
+        state
+    }
 
-        assert_eq!(state.focused_window(), Some(focus_window));
-        assert!(!state.current_workspace().is_window_mapped(&other_window));
+    #[test]
+    fn test_session_round_trip_restores_assignments() {
+        let original = make_session_state();
+        let data = original.serialize();
+
+        // After a window manager restart the clients keep their ids, and the
+        // startup scan found every window on workspace 0 with default settings.
+        let mut restored = make_state_with_windows(&[(0, 3, true), (0, 1, true), (0, 2, true)], 0);
+        set_identity(&mut restored, 1, "Alacritty", "~");
+        set_identity(&mut restored, 2, "Alacritty", "vim");
+        set_identity(&mut restored, 3, "Firefox", "News");
+        let current = restored.deserialize(&data);
+
+        assert_eq!(current, Some(3));
+        let _ = restored.startup_finalize(current);
+        assert_eq!(restored.window_workspace(Window::new(3)), Some(3));
+        assert!(restored.is_window_floating(Window::new(2)));
+        assert!(!restored.is_window_floating(Window::new(1)));
+        let order: Vec<Window> = restored
+            .get_workspace(0)
+            .unwrap()
+            .iter_windows()
+            .copied()
+            .collect();
+        assert_eq!(order, vec![Window::new(2), Window::new(1)]);
         assert_eq!(
-            effects
-                .iter()
-                .filter(|effect| matches!(effect, Effect::Configure { .. }))
-                .collect::<Vec<&Effect>>()
-                .len(),
-            1
+            restored.get_workspace(3).unwrap().layout(),
+            LayoutType::MasterLayout
         );
     }
 
     #[test]
-    fn test_dock_reduces_configured_height() {
-        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
-        let window = Window::new(1);
-
-        let effects_no_dock = state.configure_windows(0);
-        let height_no_dock = find_configure_height(&effects_no_dock, window).unwrap();
+    fn test_session_matches_windows_by_id_whatever_their_titles() {
+        let data = make_session_state().serialize();
 
-        state.track_startup_dock(Window::new(99));
-        let effects_with_dock = state.configure_windows(0);
-        let height_with_dock = find_configure_height(&effects_with_dock, window).unwrap();
+        // Both terminals changed their titles, the second to the first's old one.
+        let mut restored = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        set_identity(&mut restored, 1, "Alacritty", "vim");
+        set_identity(&mut restored, 2, "Alacritty", "htop");
+        let _ = restored.deserialize(&data);
 
-        assert_eq!(height_no_dock, 598);
-        assert_eq!(height_with_dock, 573);
-        assert!(height_with_dock < height_no_dock);
+        assert!(!restored.is_window_floating(Window::new(1)));
+        assert!(restored.is_window_floating(Window::new(2)));
     }
 
     #[test]
-    fn test_managed_windows_sorted_by_workspace_then_id() {
-        let state = make_state_with_windows(&[(1, 3, false), (0, 2, true), (0, 1, true)], 25);
-        // Ensure all are tracked
-        assert_eq!(state.window_workspace(Window::new(1)), Some(0));
-        assert_eq!(state.window_workspace(Window::new(2)), Some(0));
-        assert_eq!(state.window_workspace(Window::new(3)), Some(1));
-
-        let sorted = state.managed_windows_sorted();
-        assert_eq!(sorted, vec![Window::new(1), Window::new(2), Window::new(3)]);
+    fn test_session_falls_back_to_class_for_ids_that_are_gone() {
+        let data = make_session_state().serialize();
+
+        // Window 1 is still there; the clients of 2 and 3 were restarted.
+        let mut restored =
+            make_state_with_windows(&[(0, 1, true), (0, 12, true), (0, 13, true)], 0);
+        set_identity(&mut restored, 1, "Alacritty", "~");
+        set_identity(&mut restored, 12, "Alacritty", "vim");
+        set_identity(&mut restored, 13, "Firefox", "News");
+        let _ = restored.deserialize(&data);
+
+        assert!(!restored.is_window_floating(Window::new(1)));
+        assert!(restored.is_window_floating(Window::new(12)));
+        assert_eq!(restored.window_workspace(Window::new(13)), Some(3));
     }
 
     #[test]
-    fn test_client_list_includes_docks_after_managed() {
-        let mut state = make_state_with_windows(&[(0, 5, true), (0, 2, true)], 25);
-        state.track_startup_dock(Window::new(20));
-        state.track_startup_dock(Window::new(10));
+    fn test_session_skips_unknown_windows_and_malformed_data() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        set_identity(&mut state, 1, "Alacritty", "~");
 
-        let list = state.client_list_windows();
+        assert_eq!(state.deserialize("current 2\nwindow 1 5 3 0\n"), None);
+        assert_eq!(state.window_workspace(Window::new(1)), Some(0));
+
+        let current = state.deserialize(
+            r#"{"current": 2, "layouts": ["NoSuchLayout"], "windows": [
+                {"id": 9, "class": "Firefox", "instance": "firefox", "title": "News",
+                 "workspace": 4, "weight": 2, "floating": false},
+                {"id": 1, "class": "Alacritty", "instance": "alacritty", "title": "~",
+                 "workspace": 5, "weight": 3, "floating": false}
+            ]}"#,
+        );
+
+        assert_eq!(current, Some(2));
+        assert_eq!(state.window_workspace(Window::new(1)), Some(5));
         assert_eq!(
-            list,
-            vec![
-                Window::new(2),
-                Window::new(5),
-                Window::new(10),
-                Window::new(20)
-            ]
+            state.get_workspace(0).unwrap().layout(),
+            LayoutType::default()
         );
     }
 
     #[test]
-    fn test_focus_window_uses_desktop_hint_when_untracked() {
-        let mut state = make_state_with_windows(&[(0, 1, true), (1, 11, true)], 25);
-        let effects = state.focus_window(Window::new(11), Some(1));
+    fn test_gap_binding_uses_configured_step() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        let action = bound_action(|a| matches!(a, ActionEvent::IncreaseWindowGap(_)));
+
+        let effects = state.apply_action(action);
+
+        assert!(effects.contains(&Effect::Configure {
+            window: Window::new(1),
+            x: GAP_STEP as i32,
+            y: GAP_STEP as i32,
+            w: 800 - 2 * (GAP_STEP + 1),
+            h: 600 - 2 * (GAP_STEP + 1),
+            border: 1,
+        }));
+    }
 
-        assert_eq!(state.current_workspace_id(), 1);
-        assert_eq!(state.focused_window(), Some(Window::new(11)));
-        assert!(effects.iter().any(|e| matches!(e, Effect::Map(_))));
-        assert!(
-            effects
-                .iter()
-                .any(|e| matches!(e, Effect::Configure { .. }))
-        );
+    #[test]
+    fn test_weight_binding_uses_configured_step() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+        let action = bound_action(|a| matches!(a, ActionEvent::IncreaseWindowWeight(_)));
+
+        let effects = state.apply_action(action);
+
+        let expected_cell =
+            800 * (DEFAULT_WEIGHT + WEIGHT_STEP) / (2 * DEFAULT_WEIGHT + WEIGHT_STEP);
+        assert!(effects.iter().any(|e| matches!(
+            e,
+            Effect::Configure { window, w, .. }
+                if *window == Window::new(1) && *w == expected_cell - 2
+        )));
     }
 
     #[test]
-    fn test_go_to_workspace_invalid_or_same_is_noop() {
-        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
-        let effects_same = state.go_to_workspace(0);
-        let effects_invalid = state.go_to_workspace(NUM_WORKSPACES + 1);
+    fn test_retile_without_change_emits_no_configure() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let first = state.configure_windows(0);
+        assert_eq!(
+            first
+                .iter()
+                .filter(|e| matches!(e, Effect::Configure { .. }))
+                .count(),
+            3
+        );
 
-        assert!(effects_same.is_empty());
-        assert!(effects_invalid.is_empty());
-        assert_eq!(state.current_workspace_id(), 0);
+        assert!(state.configure_windows(0).is_empty());
     }
 
     #[test]
-    fn test_send_to_workspace_invalid_or_same_is_noop() {
-        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
-        let effects_same = state.send_to_workspace(0);
-        let effects_invalid = state.send_to_workspace(NUM_WORKSPACES + 1);
+    fn test_retile_only_configures_moved_windows() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let _ = state.configure_windows(0);
+        let _ = state.set_focus(Window::new(3));
+
+        let effects = state.handle_unmap_event_managed(Window::new(2));
+
+        assert!(effects.iter().all(|e| !matches!(
+            e,
+            Effect::Configure { window, .. } if *window == Window::new(2)
+        )));
+        assert!(effects.iter().any(|e| matches!(
+            e,
+            Effect::Configure { window, .. } if *window == Window::new(3)
+        )));
+    }
 
-        assert!(effects_same.is_empty());
-        assert!(effects_invalid.is_empty());
-        assert_eq!(state.window_workspace(Window::new(1)), Some(0));
+    #[test]
+    fn test_retile_after_client_move_sends_configure_again() {
+        let mut state = make_floating_state();
+        let window = Window::new(2);
+        let _ = state.configure_windows(0);
+
+        // The client moves itself away and back to where the last pass put it.
+        let _ = state.move_resize_floating(window, |rect, _| Rect { x: 40, ..rect });
+        let _ = state.move_resize_floating(window, |rect, _| Rect { x: 100, ..rect });
+
+        assert!(state.configure_windows(0).iter().any(|e| matches!(
+            e,
+            Effect::Configure { window: w, x: 100, y: 150, .. } if *w == window
+        )));
     }
 
     #[test]
-    fn test_increase_decrease_window_gap_reconfigures() {
-        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 25);
+    fn test_grow_active_shrinks_only_its_neighbor() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let _ = state.set_focus(Window::new(2));
+        let before = state.current_geometry();
 
-        let effects_increase = state.increase_window_gap(1);
+        let _ = state.apply_action(ActionEvent::GrowActive(10));
+
+        let weights: Vec<u32> = state
+            .current_workspace()
+            .iter_clients()
+            .map(|client| client.size())
+            .collect();
         assert_eq!(
-            effects_increase
-                .iter()
-                .filter(|effect| matches!(effect, Effect::Configure { .. }))
-                .count(),
-            2
+            weights,
+            vec![DEFAULT_WEIGHT, DEFAULT_WEIGHT + 2, DEFAULT_WEIGHT - 2]
         );
+        let after = state.current_geometry();
+        assert_eq!((after[0].1.x, after[0].1.w), (before[0].1.x, before[0].1.w));
+        assert!(after[1].1.w > before[1].1.w);
+        assert!(after[2].1.w < before[2].1.w);
+    }
 
-        let effects_decrease = state.decrease_window_gap(1);
+    #[test]
+    fn test_shrink_active_on_last_window_grows_previous() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
+        let _ = state.set_focus(Window::new(3));
+
+        let _ = state.apply_action(ActionEvent::ShrinkActive(10));
+
+        let weights: Vec<u32> = state
+            .current_workspace()
+            .iter_clients()
+            .map(|client| client.size())
+            .collect();
         assert_eq!(
-            effects_decrease
-                .iter()
-                .filter(|effect| matches!(effect, Effect::Configure { .. }))
-                .count(),
-            2
+            weights,
+            vec![DEFAULT_WEIGHT, DEFAULT_WEIGHT + 2, DEFAULT_WEIGHT - 2]
         );
+    }
 
-        let effects_noop = state.decrease_window_gap(1000);
-        assert!(effects_noop.is_empty());
+    #[test]
+    fn test_grow_active_needs_a_neighbor() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
+        let _ = state.set_focus(Window::new(1));
+
+        assert!(state.apply_action(ActionEvent::GrowActive(10)).is_empty());
     }
 
     #[test]
-    fn test_increase_decrease_window_weight_reconfigures() {
-        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 25);
+    fn test_equalize_weights_gives_even_horizontal_split() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true)], 0);
         let _ = state.set_focus(Window::new(1));
+        let _ = state.increase_window_weight(2);
+        let _ = state.set_focus(Window::new(3));
+        let _ = state.increase_window_weight(1);
 
-        let effects_inc = state.increase_window_weight(2);
-        assert_eq!(
-            effects_inc
-                .iter()
-                .filter(|effect| matches!(effect, Effect::Configure { .. }))
-                .count(),
-            2
-        );
+        let effects = state.apply_action(ActionEvent::EqualizeWeights);
 
-        let effects_dec = state.decrease_window_weight(1);
+        // Window 3 already sat in the last third, so only the other two move.
         assert_eq!(
-            effects_dec
+            effects
                 .iter()
-                .filter(|effect| matches!(effect, Effect::Configure { .. }))
+                .filter(|e| matches!(e, Effect::Configure { .. }))
                 .count(),
             2
         );
+        let widths: Vec<u32> = state
+            .current_geometry()
+            .into_iter()
+            .map(|(_, rect)| rect.w)
+            .collect();
+        assert_eq!(widths.len(), 3);
+        assert!(widths.iter().all(|w| *w == widths[0]));
+
+        assert!(state.apply_action(ActionEvent::EqualizeWeights).is_empty());
+    }
+
+    fn make_grid_state() -> State {
+        // MasterLayout with two masters tiles four windows as a 2x2 grid:
+        // 1 | 3
+        // --+--
+        // 2 | 4
+        let mut state =
+            make_state_with_windows(&[(0, 1, true), (0, 2, true), (0, 3, true), (0, 4, true)], 0);
+        state
+            .current_workspace_mut()
+            .set_layout(LayoutType::MasterLayout);
+        state.nmaster = 2;
+        state
     }
 
     #[test]
-    fn test_map_request_unmanaged_is_simple_map() {
-        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
-        let effects = state.on_map_request(Window::new(99), WindowType::Unmanaged);
+    fn test_move_window_right_swaps_with_right_neighbor() {
+        let mut state = make_grid_state();
+        let _ = state.set_focus(Window::new(1));
+        let before: HashMap<Window, (i32, i32)> = state
+            .current_geometry()
+            .into_iter()
+            .map(|(window, rect)| (window, (rect.x, rect.y)))
+            .collect();
 
-        assert_eq!(effects, vec![Effect::Map(Window::new(99))]);
-        assert!(state.window_workspace(Window::new(99)).is_none());
+        let effects = state.apply_action(ActionEvent::MoveWindowRight);
+        assert!(!effects.is_empty());
+
+        let after: HashMap<Window, (i32, i32)> = state
+            .current_geometry()
+            .into_iter()
+            .map(|(window, rect)| (window, (rect.x, rect.y)))
+            .collect();
+        assert_eq!(after[&Window::new(1)], before[&Window::new(3)]);
+        assert_eq!(after[&Window::new(3)], before[&Window::new(1)]);
+        assert_eq!(after[&Window::new(2)], before[&Window::new(2)]);
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
     }
 
     #[test]
-    fn test_dock_map_and_destroy_updates_layout() {
-        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
-        let dock = Window::new(50);
+    fn test_move_window_down_swaps_with_window_below() {
+        let mut state = make_grid_state();
+        let _ = state.set_focus(Window::new(1));
 
-        let map_effects = state.on_map_request(dock, WindowType::Dock);
-        assert!(map_effects.contains(&Effect::Map(dock)));
-        assert!(!state.dock_windows.is_empty());
+        let _ = state.apply_action(ActionEvent::MoveWindowDown);
 
-        let destroy_effects = state.on_destroy(dock);
-        assert!(
-            !destroy_effects
-                .iter()
-                .any(|e| matches!(e, Effect::ConfigurePositionSize { .. }))
+        let order: Vec<Window> = state
+            .current_geometry()
+            .into_iter()
+            .map(|(window, _)| window)
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                Window::new(2),
+                Window::new(1),
+                Window::new(3),
+                Window::new(4)
+            ]
         );
-        assert!(state.dock_windows.is_empty());
     }
 
     #[test]
-    fn test_on_unmap_ignored_for_dock_and_unmanaged() {
-        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
-        let dock = Window::new(77);
-        state.track_startup_dock(dock);
-
-        let effects_dock = state.on_unmap(dock);
-        let effects_unmanaged = state.on_unmap(Window::new(88));
+    fn test_move_window_without_neighbor_is_noop() {
+        let mut state = make_grid_state();
+        let _ = state.set_focus(Window::new(1));
 
-        assert!(effects_dock.is_empty());
-        assert!(effects_unmanaged.is_empty());
+        assert!(state.apply_action(ActionEvent::MoveWindowLeft).is_empty());
+        assert!(state.apply_action(ActionEvent::MoveWindowUp).is_empty());
     }
 
     #[test]
-    fn test_startup_finalize_switches_workspace_when_hint_provided() {
-        let mut state = make_state_with_windows(&[(0, 1, true), (1, 11, false)], 25);
-        let effects = state.startup_finalize(Some(1));
+    fn test_right_preselection_opens_next_window_right_of_focused() {
+        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, true)], 0);
+        state
+            .current_workspace_mut()
+            .set_layout(LayoutType::BspLayout);
+        let _ = state.set_focus(Window::new(1));
 
-        assert_eq!(state.current_workspace_id(), 1);
-        assert!(effects.iter().any(|e| matches!(e, Effect::Map(_))));
-        assert!(
-            effects
-                .iter()
-                .any(|e| matches!(e, Effect::Configure { .. }))
+        let _ = state.apply_action(ActionEvent::PreselectRight);
+        let _ = state.on_map_request(Window::new(3), WindowType::Managed);
+
+        let cells: Vec<_> = state
+            .tiled_geometry(0)
+            .into_iter()
+            .map(|(window, r)| (window.resource_id(), r.x, r.y, r.w, r.h))
+            .collect();
+        // Window 1's half is split; window 2 keeps the right half.
+        assert_eq!(
+            cells,
+            vec![
+                (1, 0, 0, 198, 598),
+                (2, 400, 0, 398, 598),
+                (3, 200, 0, 198, 598)
+            ]
         );
+        assert_eq!(state.preselection, None);
     }
 
     #[test]
-    fn test_shift_focus_wraps_and_skips_unmapped() {
-        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, false), (0, 3, true)], 25);
-
+    fn test_preselecting_same_side_twice_cancels() {
+        let mut state = make_state_with_windows(&[(0, 1, true)], 0);
         let _ = state.set_focus(Window::new(1));
-        let effects_forward = state.shift_focus(1);
 
-        assert_eq!(state.focused_window(), Some(Window::new(3)));
-        assert!(effects_forward.contains(&Effect::Focus(Window::new(3))));
+        let _ = state.preselect(Direction::Down);
+        assert_eq!(state.preselection, Some((Window::new(1), Direction::Down)));
+        let _ = state.preselect(Direction::Down);
+        assert_eq!(state.preselection, None);
 
-        let effects_backward = state.shift_focus(-1);
-        assert_eq!(state.focused_window(), Some(Window::new(1)));
-        assert!(effects_backward.contains(&Effect::Focus(Window::new(1))));
+        let _ = state.preselect(Direction::Left);
+        let _ = state.on_destroy(Window::new(1));
+        assert_eq!(state.preselection, None);
     }
 
     #[test]
-    fn test_shift_focus_noop_when_only_one_mapped() {
-        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, false)], 25);
+    fn test_focus_direction_moves_to_neighbor() {
+        let mut state = make_grid_state();
         let _ = state.set_focus(Window::new(1));
 
-        let effects = state.shift_focus(1);
+        let effects = state.apply_action(ActionEvent::FocusRight);
+        assert!(effects.contains(&Effect::Focus(Window::new(3))));
+        assert_eq!(state.focused_window(), Some(Window::new(3)));
 
-        assert!(effects.is_empty());
-        assert_eq!(state.focused_window(), Some(Window::new(1)));
+        let _ = state.apply_action(ActionEvent::FocusDown);
+        assert_eq!(state.focused_window(), Some(Window::new(4)));
     }
 
     #[test]
-    fn test_swap_window_swaps_with_next_mapped() {
-        let mut state = make_state_with_windows(&[(0, 1, true), (0, 2, false), (0, 3, true)], 25);
-        let _ = state.set_focus(Window::new(1));
+    fn test_focus_direction_stops_at_edge_without_wrap() {
+        let mut state = make_grid_state();
+        state.focus_wrap = false;
+        let _ = state.set_focus(Window::new(3));
 
-        let effects = state.swap_window(1);
-
-        let order: Vec<Window> = state.current_workspace().iter_windows().copied().collect();
-        assert_eq!(order, vec![Window::new(3), Window::new(2), Window::new(1)]);
-        assert_eq!(
-            effects
-                .iter()
-                .filter(|effect| matches!(effect, Effect::Configure { .. }))
-                .count(),
-            2
-        );
+        assert!(state.apply_action(ActionEvent::FocusRight).is_empty());
+        assert!(state.apply_action(ActionEvent::FocusUp).is_empty());
+        assert_eq!(state.focused_window(), Some(Window::new(3)));
     }
 
     #[test]
-    fn test_swap_window_noop_when_no_other_mapped() {
-        let mut state = make_state_with_windows(&[(0, 1, true)], 25);
-        let _ = state.set_focus(Window::new(1));
+    fn test_focus_direction_wraps_at_edge() {
+        let mut state = make_grid_state();
+        state.focus_wrap = true;
+        let _ = state.set_focus(Window::new(3));
 
-        let effects = state.swap_window(1);
+        let effects = state.apply_action(ActionEvent::FocusRight);
+        assert!(effects.contains(&Effect::Focus(Window::new(1))));
+        assert_eq!(state.focused_window(), Some(Window::new(1)));
 
-        assert!(effects.is_empty());
-        let order: Vec<Window> = state.current_workspace().iter_windows().copied().collect();
-        assert_eq!(order, vec![Window::new(1)]);
+        let _ = state.apply_action(ActionEvent::FocusUp);
+        assert_eq!(state.focused_window(), Some(Window::new(2)));
     }
 }
@@ -0,0 +1,137 @@
+use std::{fs::File, io::Read, path::Path};
+
+/// A decoded wallpaper image, one `0x00RRGGBB` value per pixel in row-major order.
+#[derive(Debug)]
+pub struct Wallpaper {
+    width: u16,
+    height: u16,
+    pixels: Vec<u32>,
+}
+
+impl Wallpaper {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::decode_png(File::open(path)?)
+    }
+
+    /// Decodes any 8/16-bit PNG, dropping alpha. Images must fit X's 16-bit sizes.
+    pub fn decode_png(data: impl Read) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+
+        let (Ok(width), Ok(height)) = (u16::try_from(info.width), u16::try_from(info.height))
+        else {
+            return Err(format!("image is too large ({}x{})", info.width, info.height).into());
+        };
+
+        let channels = info.color_type.samples();
+        let pixels = buf
+            .chunks(info.line_size)
+            .take(info.height as usize)
+            .flat_map(|line| line.chunks(channels).take(info.width as usize))
+            .map(|px| match px {
+                [r, g, b, ..] => u32::from_be_bytes([0, *r, *g, *b]),
+                [grey, ..] => u32::from_be_bytes([0, *grey, *grey, *grey]),
+                [] => 0,
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Pixels as 32 bits-per-pixel ZPixmap data in the server's byte order.
+    pub fn to_zpixmap(&self, msb_first: bool) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|px| {
+                if msb_first {
+                    px.to_be_bytes()
+                } else {
+                    px.to_le_bytes()
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod wallpaper_tests {
+    use super::*;
+
+    fn encode_png(width: u32, height: u32, color: png::ColorType, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+        writer.finish().unwrap();
+        out
+    }
+
+    #[test]
+    fn test_decode_png_reports_dimensions_and_pixels() {
+        let png = encode_png(
+            2,
+            3,
+            png::ColorType::Rgb,
+            &[
+                255, 0, 0, 0, 255, 0, //
+                0, 0, 255, 1, 2, 3, //
+                10, 20, 30, 255, 255, 255,
+            ],
+        );
+
+        let wallpaper = Wallpaper::decode_png(png.as_slice()).unwrap();
+
+        assert_eq!(wallpaper.width(), 2);
+        assert_eq!(wallpaper.height(), 3);
+        assert_eq!(
+            wallpaper.pixels,
+            vec![0xFF0000, 0x00FF00, 0x0000FF, 0x010203, 0x0A141E, 0xFFFFFF]
+        );
+    }
+
+    #[test]
+    fn test_decode_png_drops_alpha_and_expands_grey() {
+        let rgba = encode_png(1, 1, png::ColorType::Rgba, &[1, 2, 3, 4]);
+        let grey = encode_png(1, 1, png::ColorType::Grayscale, &[7]);
+
+        assert_eq!(
+            Wallpaper::decode_png(rgba.as_slice()).unwrap().pixels,
+            vec![0x010203]
+        );
+        assert_eq!(
+            Wallpaper::decode_png(grey.as_slice()).unwrap().pixels,
+            vec![0x070707]
+        );
+    }
+
+    #[test]
+    fn test_decode_png_rejects_garbage() {
+        assert!(Wallpaper::decode_png(&b"not a png"[..]).is_err());
+    }
+
+    #[test]
+    fn test_to_zpixmap_follows_byte_order() {
+        let png = encode_png(1, 1, png::ColorType::Rgb, &[1, 2, 3]);
+        let wallpaper = Wallpaper::decode_png(png.as_slice()).unwrap();
+
+        assert_eq!(wallpaper.to_zpixmap(false), vec![3, 2, 1, 0]);
+        assert_eq!(wallpaper.to_zpixmap(true), vec![0, 1, 2, 3]);
+    }
+}
@@ -1,64 +1,189 @@
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
+use std::path::PathBuf;
 use std::process::Command;
-use std::{collections::HashMap, process::Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    process::Stdio,
+};
 
 use xcb::{
-    Connection,
+    Connection, screensaver,
     x::{self, ModMask, Window},
 };
 
 use crate::atoms::Atoms;
 use crate::config::{
-    DEFAULT_BORDER_WIDTH, DEFAULT_DOCK_HEIGHT, DEFAULT_WINDOW_GAP, NUM_WORKSPACES,
+    AUTOSTART, DEFAULT_BORDER_WIDTH, DEFAULT_DOCK_HEIGHT, DEFAULT_WINDOW_GAP,
+    EWMH_STRICT_SOURCE_INDICATION, FOCUS_FLASH, FOCUS_ON_SWITCH, IDLE_TIMEOUT_SECS,
+    INTERACTIVE_PLACEMENT, KEY_GRAB_OWNER_EVENTS, LOCK_COMMAND, MOUSE_MODIFIER, NUM_WORKSPACES,
+    REPARENT_WINDOWS, ROOT_SCROLL_SWITCHES_WORKSPACE, SESSION_FILE_STEM, SUPPRESSED_HINTS,
+    TILE_DIALOGS, WALLPAPER_PATH,
 };
 use crate::effect::{Effect, Effects};
-use crate::ewmh_manager::EwmhManager;
+use crate::effective_config::Config;
+use crate::ewmh_manager::{
+    ALL_DESKTOPS, EwmhManager, accepts_source_indication, allows_focus_on_map, decode_moveresize,
+};
+use crate::focus_flash::FocusFlash;
+use crate::idle::IdleMonitor;
 use crate::key_mapping::ActionEvent;
-use crate::keyboard::{fetch_keyboard_mapping, populate_key_bindings};
-use crate::state::{ScreenConfig, State};
-use crate::x11::{WindowType, X11};
+use crate::keyboard::{
+    GrabDelta, fetch_keyboard_mapping, format_grab_failure, grab_delta, keysym_for_keycode,
+    populate_key_bindings,
+};
+use crate::options::FocusOnSwitch;
+use crate::session::session_file_name;
+use crate::state::{DockEdge, ScreenConfig, State};
+use crate::wallpaper::Wallpaper;
+use crate::x11::{PropertyUpdate, WindowType, X11, XConn, is_benign_error, property_update};
+
+/// Set from the signal handler when the WM is asked to terminate; `run` returns
+/// on the next loop iteration so the WM is dropped and cleans up after itself.
+static TERMINATE: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn request_termination(_signal: libc::c_int) {
+    TERMINATE.store(true, Ordering::Relaxed);
+}
+
+/// Routes SIGTERM and SIGINT (e.g. the `pkill` reload binding) to
+/// `request_termination`, leaving `SA_RESTART` unset so a blocked wait for
+/// events is interrupted.
+fn install_termination_handler() {
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        // SAFETY: the handler only stores to an atomic, which is async-signal-safe,
+        // and the zeroed sigaction is a valid value with an empty mask.
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = request_termination as *const () as libc::sighandler_t;
+            if libc::sigaction(signal, &raw const action, std::ptr::null_mut()) != 0 {
+                warn!("Failed to install handler for signal {signal}");
+            }
+        }
+    }
+}
 
 pub struct WindowManager {
-    x11: X11,
+    x11: Box<dyn XConn>,
     ewmh: EwmhManager,
     key_bindings: HashMap<(u8, ModMask), ActionEvent>,
+    /// Key grabs currently held on the root window.
+    grabbed_keys: HashSet<(u8, ModMask)>,
     state: State,
+    idle: Option<IdleMonitor>,
+    /// Puts back the server screensaver settings `IDLE_TIMEOUT_SECS` replaced.
+    screensaver_restore: Option<Effect>,
+    focus_flash: Option<FocusFlash>,
+    last_user_time: u32,
+    session_path: Option<PathBuf>,
+    last_session: String,
+    /// Set by `ActionEvent::Quit`; the event loop returns once it sees it.
+    quit_requested: bool,
+    /// Events read off the connection early by `reconcile_maps`, handled before
+    /// waiting for new ones.
+    queued_events: VecDeque<xcb::Result<xcb::Event>>,
 }
 
 impl WindowManager {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let (conn, _) = Connection::connect(None)?;
+        let (conn, screen_num) = Connection::connect_with_extensions(
+            None,
+            &[],
+            &[xcb::Extension::RandR, xcb::Extension::ScreenSaver],
+        )?;
         info!("Connected to X.");
+        info!("Effective config: {}", Config::resolve());
 
-        let key_bindings = Self::setup_key_bindings(&conn);
+        let (keysyms, keysyms_per_keycode) = fetch_keyboard_mapping(&conn);
+        let min_keycode = conn.get_setup().min_keycode();
+        let key_bindings = populate_key_bindings(&conn, &keysyms, keysyms_per_keycode);
         let (screen, root_window) = Self::setup_root(&conn);
         let atoms = Atoms::intern_all(&conn).expect("Failed to intern Atoms");
 
         // Create WM check window
         let wm_check_window = Self::create_wm_check_window(&conn, root_window);
         let x11 = X11::new(conn, root_window, atoms);
-        let ewmh = EwmhManager::new(atoms, root_window, wm_check_window);
+        let ewmh = EwmhManager::new(
+            atoms,
+            root_window,
+            wm_check_window,
+            x11.existing_atoms(SUPPRESSED_HINTS),
+        );
 
-        let state = State::new(
+        let mut state = State::new(
             screen,
             DEFAULT_BORDER_WIDTH,
             DEFAULT_WINDOW_GAP,
             DEFAULT_DOCK_HEIGHT,
         );
+        if x11.compositor_running(screen_num) {
+            info!("Compositor detected, leaving compositing to it.");
+        } else {
+            debug!("No compositor detected.");
+        }
 
-        let wm = Self {
-            x11,
+        let monitors = x11.get_monitor_rects();
+        debug!("Detected monitors: {monitors:?}");
+        state.set_monitors(&monitors);
+
+        if let Some(path) = WALLPAPER_PATH {
+            match Wallpaper::load(path.as_ref()) {
+                Ok(wallpaper) => {
+                    if x11.set_root_background(&wallpaper) {
+                        info!("Wallpaper set from {path}");
+                    }
+                }
+                Err(e) => error!("Failed to load wallpaper {path}: {e}"),
+            }
+        }
+
+        let screensaver_restore =
+            LOCK_COMMAND.and_then(|_| x11.enable_idle_notify(IDLE_TIMEOUT_SECS));
+        let idle = match LOCK_COMMAND {
+            Some(_) if screensaver_restore.is_some() => Some(IdleMonitor::new(
+                Duration::from_secs(IDLE_TIMEOUT_SECS.into()),
+            )),
+            Some(_) => {
+                warn!("SCREENSAVER extension unavailable, idle command disabled");
+                None
+            }
+            None => None,
+        };
+
+        x11.set_root_event_mask()?;
+        info!("Successfully set substructure redirect");
+        if ROOT_SCROLL_SWITCHES_WORKSPACE && let Err(e) = x11.grab_root_scroll() {
+            warn!(
+                "Failed to grab scrolling on the root window, workspace scrolling disabled: {e:?}"
+            );
+        }
+
+        let mut wm = Self {
+            x11: Box::new(x11),
             ewmh,
             key_bindings,
+            grabbed_keys: HashSet::new(),
             state,
+            idle,
+            screensaver_restore,
+            focus_flash: FOCUS_FLASH.map(|(_, duration)| FocusFlash::new(duration)),
+            last_user_time: 0,
+            session_path: std::env::var_os("XDG_RUNTIME_DIR")
+                .zip(std::env::var("DISPLAY").ok())
+                .map(|(dir, display)| {
+                    PathBuf::from(dir).join(session_file_name(SESSION_FILE_STEM, &display))
+                }),
+            last_session: String::new(),
+            quit_requested: false,
+            queued_events: VecDeque::new(),
         };
 
-        wm.x11.set_root_event_mask()?;
-        info!("Successfully set substructure redirect");
-
         // Key grabs
-        let keygrab_effects = wm.keygrab_effects();
-        wm.x11.apply_effects_checked(&keygrab_effects);
+        for (keycode, modifiers, e) in wm.grab_keys() {
+            let keysym = keysym_for_keycode(&keysyms, keysyms_per_keycode, min_keycode, keycode);
+            error!("{}", format_grab_failure(keysym, keycode, modifiers, &e));
+        }
 
         // EWMH hints
         let ewmh_effects = wm.ewmh.publish_hints();
@@ -66,59 +191,164 @@ impl WindowManager {
 
         // Publish geometry now that we know screen size, then sync full EWMH state.
         let screen = wm.state.screen();
-        let mut ewmh_runtime_effects =
-            vec![wm.ewmh.desktop_geometry_effect(screen.width, screen.height)];
+        let mut ewmh_runtime_effects = wm.ewmh.without_suppressed(vec![
+            wm.ewmh.desktop_geometry_effect(screen.width, screen.height),
+        ]);
         ewmh_runtime_effects.extend(wm.ewmh_sync_effects());
         wm.x11.apply_effects_unchecked(&ewmh_runtime_effects);
 
         Ok(wm)
     }
 
-    fn ewmh_sync_effects(&self) -> Effects {
+    fn ewmh_sync_effects(&mut self) -> Effects {
+        let active_window = self.ewmh.active_window_effect(self.state.focused_window());
+        let desktop_names = self.ewmh.desktop_names_effect(self.state.desktop_names());
         let ewmh = &self.ewmh;
         let screen = self.state.screen();
 
         let client_list = self.state.client_list_windows();
-        let managed = self.state.managed_windows_sorted();
 
         let mut effects = Vec::new();
         effects.extend(ewmh.client_list_effects(&client_list));
         effects.push(ewmh.current_desktop_effect(self.state.current_workspace_id()));
-        effects.push(ewmh.active_window_effect(self.state.focused_window()));
-        effects.push(ewmh.workarea_effect(0, 0, screen.width, self.state.usable_screen_height()));
-
-        for window in managed {
-            if let Some(workspace) = self.state.window_workspace(window) {
-                effects.push(ewmh.window_desktop_effect(window, workspace as u32));
-            }
-            effects.push(
-                ewmh.window_fullscreen_state_effect(
-                    window,
-                    self.state.is_window_fullscreen(window),
-                ),
-            );
+        effects.extend(active_window);
+        effects.extend(desktop_names);
+        effects.push(ewmh.workarea_effect(
+            0,
+            self.state.usable_screen_top(),
+            screen.width,
+            self.state.usable_screen_height(),
+        ));
+
+        for info in self.state.window_infos() {
+            let desktop = if self.state.is_window_sticky(info.window) {
+                ALL_DESKTOPS
+            } else {
+                info.workspace as u32
+            };
+            effects.push(ewmh.window_desktop_effect(info.window, desktop));
+            effects.push(ewmh.window_state_effect(info.window, info.fullscreen, info.hidden));
+            let border = info.border_width;
+            effects.push(ewmh.frame_extents_effect(info.window, border, border, border, border));
         }
 
-        effects
+        ewmh.without_suppressed(effects)
     }
 
-    fn setup_key_bindings(conn: &Connection) -> HashMap<(u8, ModMask), ActionEvent> {
-        let (keysyms, keysyms_per_keycode) = fetch_keyboard_mapping(conn);
-        populate_key_bindings(conn, &keysyms, keysyms_per_keycode)
+    fn pending_grabs(&self) -> GrabDelta {
+        let wanted = self.key_bindings.keys().copied().collect();
+        grab_delta(&self.grabbed_keys, &wanted)
     }
 
+    /// Grabs for bindings that are not grabbed yet.
     fn keygrab_effects(&self) -> Effects {
-        let mut effects = Vec::with_capacity(self.key_bindings.len());
-        for &(keycode, modifiers) in self.key_bindings.keys() {
+        let pending = self.pending_grabs().grab;
+        let mut effects = Vec::with_capacity(pending.len());
+        for (keycode, modifiers) in pending {
             effects.push(Effect::GrabKey {
                 keycode,
                 modifiers,
                 grab_window: self.x11.root(),
+                owner_events: KEY_GRAB_OWNER_EVENTS,
             });
         }
         effects
     }
 
+    /// Reconciles the held grabs with the bindings: stale grabs are released
+    /// and only bindings not grabbed yet are grabbed. Grabs are checked one at a
+    /// time so a failure can be traced back to its binding, and a rejected grab
+    /// is retried once with the opposite `owner_events`. Returns the errors of
+    /// grabs that failed both ways; those are tried again on the next call.
+    fn grab_keys(&mut self) -> Vec<(u8, ModMask, xcb::Error)> {
+        let root = self.x11.root();
+        let stale = self.pending_grabs().ungrab;
+        if !stale.is_empty() {
+            let ungrabs: Effects = stale
+                .iter()
+                .map(|&(keycode, modifiers)| Effect::UngrabKey {
+                    keycode,
+                    modifiers,
+                    grab_window: root,
+                })
+                .collect();
+            self.x11.apply_effects_unchecked(&ungrabs);
+            for key in &stale {
+                self.grabbed_keys.remove(key);
+            }
+        }
+
+        let mut failed = Vec::new();
+        for effect in self.keygrab_effects() {
+            let Effect::GrabKey {
+                keycode,
+                modifiers,
+                grab_window,
+                owner_events,
+            } = effect
+            else {
+                continue;
+            };
+            if self
+                .x11
+                .apply_effects_checked_result(std::slice::from_ref(&effect))
+                .is_ok()
+            {
+                self.grabbed_keys.insert((keycode, modifiers));
+                continue;
+            }
+
+            let retry = Effect::GrabKey {
+                keycode,
+                modifiers,
+                grab_window,
+                owner_events: !owner_events,
+            };
+            match self
+                .x11
+                .apply_effects_checked_result(std::slice::from_ref(&retry))
+            {
+                Ok(()) => {
+                    self.grabbed_keys.insert((keycode, modifiers));
+                    debug!(
+                        "Grabbed keycode {keycode} with owner_events={} after a failed grab",
+                        !owner_events
+                    );
+                }
+                Err(failures) => failed.extend(
+                    failures
+                        .into_iter()
+                        .map(|(_, error)| (keycode, modifiers, error)),
+                ),
+            }
+        }
+        failed
+    }
+
+    /// Releases what the WM holds so the next WM starts clean: the key grabs,
+    /// the frames and check window it created, the root event mask (including
+    /// substructure redirect) and the screensaver settings it changed.
+    fn cleanup_effects(&self) -> Effects {
+        let root = self.x11.root();
+        let mut effects: Effects = self
+            .grabbed_keys
+            .iter()
+            .map(|&(keycode, modifiers)| Effect::UngrabKey {
+                keycode,
+                modifiers,
+                grab_window: root,
+            })
+            .collect();
+        effects.extend(self.state.release_frames(root));
+        effects.push(self.ewmh.destroy_check_window_effect());
+        effects.push(Effect::SetEventMask {
+            window: root,
+            mask: x::EventMask::NO_EVENT,
+        });
+        effects.extend(self.screensaver_restore.clone());
+        effects
+    }
+
     fn setup_root(conn: &Connection) -> (ScreenConfig, Window) {
         let root = conn.get_setup().roots().next().expect("Cannot find root");
         let screen = ScreenConfig {
@@ -151,16 +381,10 @@ impl WindowManager {
 
     fn spawn_client(&self, cmd: &str) {
         info!("Spawning command: {cmd}");
-        let parts: Vec<&str> = cmd.split_whitespace().collect();
-        if parts.is_empty() {
+        let Some(mut command) = parse_command(cmd) else {
             error!("Empty command provided");
             return;
-        }
-
-        let mut command = Command::new(parts[0]);
-        for arg in &parts[1..] {
-            command.arg(arg);
-        }
+        };
 
         match command.spawn() {
             Ok(_) => info!("Successfully spawned: {cmd}"),
@@ -169,22 +393,40 @@ impl WindowManager {
     }
 
     fn spawn_autostart() {
-        match Command::new("sh")
-            .arg("-c")
-            .arg("exec ~/.config/ferriswm/autostart.sh")
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
+        if AUTOSTART.is_empty() {
+            match autostart_script().spawn() {
+                Ok(_) => debug!("Ran autostart succesfully!"),
+                Err(e) => debug!("Failed to run autostart: {e:?}"),
+            }
+            return;
+        }
+
+        for mut command in autostart_commands(AUTOSTART) {
+            match command.spawn() {
+                Ok(_) => info!("Autostarted {command:?}"),
+                Err(e) => error!("Failed to autostart {command:?}: {e:?}"),
+            }
+        }
+    }
+
+    fn handle_screensaver_notify(&mut self, ev: &screensaver::NotifyEvent) {
+        let Some(idle) = self.idle.as_mut() else {
+            return;
+        };
+        let idle_time = match ev.state() {
+            screensaver::State::Off => Duration::ZERO,
+            _ => self.x11.idle_time().unwrap_or_default(),
+        };
+        if idle.should_fire(idle_time)
+            && let Some(cmd) = LOCK_COMMAND
         {
-            Ok(_) => debug!("Ran autostart succesfully!"),
-            Err(e) => debug!("Failed to run autostart: {e:?}"),
+            self.spawn_client(cmd);
         }
     }
 
-    fn close_window(&self, window: Window) -> Effects {
+    fn close_window(&self, window: Window, time: u32) -> Effects {
         match self.x11.supports_wm_delete(window) {
-            Ok(true) => vec![Effect::SendWmDelete(window)],
+            Ok(true) => vec![Effect::SendWmDelete { window, time }],
             Ok(false) => vec![Effect::KillClient(window)],
             Err(e) => {
                 error!(
@@ -199,7 +441,7 @@ impl WindowManager {
         let keycode = ev.detail();
         let modifiers = ModMask::from_bits_truncate(ev.state().bits());
 
-        let Some(action) = self.key_bindings.get(&(keycode, modifiers)) else {
+        let Some(&action) = self.key_bindings.get(&(keycode, modifiers)) else {
             error!("No binding found for keycode: {keycode} with modifiers: {modifiers:?}");
             return vec![];
         };
@@ -214,13 +456,209 @@ impl WindowManager {
                     return vec![];
                 };
 
-                self.close_window(window)
+                self.close_window(window, x::CURRENT_TIME)
             }
-            _ => {
-                let mut effects = self.state.apply_action(*action);
-                effects.extend(self.ewmh_sync_effects());
-                effects
+            ActionEvent::KillUnderPointer => {
+                let Some(window) = self
+                    .x11
+                    .window_under_pointer()
+                    .and_then(|toplevel| self.state.client_for_toplevel(toplevel))
+                else {
+                    return vec![];
+                };
+
+                self.close_window(window, x::CURRENT_TIME)
+            }
+            ActionEvent::DumpState => {
+                info!("State dump:\n{}", self.state.dump());
+                vec![]
+            }
+            ActionEvent::Quit => {
+                // Cleanup happens when the WM is dropped after `run` returns.
+                self.quit_requested = true;
+                vec![]
+            }
+            _ => self.dispatch_action(action),
+        }
+    }
+
+    /// Applies a state action and syncs EWMH hints afterwards.
+    fn dispatch_action(&mut self, action: ActionEvent) -> Effects {
+        if matches!(
+            action,
+            ActionEvent::GoToWorkspace(_)
+                | ActionEvent::GoToNextWorkspace
+                | ActionEvent::GoToPrevWorkspace
+        ) {
+            self.refresh_pointer();
+        }
+        let mut effects = self.state.apply_action(action);
+        effects.extend(self.ewmh_sync_effects());
+        effects
+    }
+
+    fn handle_button_press(&mut self, ev: &x::ButtonPressEvent) -> Effects {
+        if self.state.commit_placement() {
+            return vec![];
+        }
+        if ev.event() != self.x11.root() {
+            if let Some(grow) = border_scroll_grows(ev.detail()) {
+                let scrolled = self.state.scroll_border_weight(
+                    ev.event(),
+                    ev.event_x().into(),
+                    ev.event_y().into(),
+                    grow,
+                );
+                return match scrolled {
+                    Some(effects) => {
+                        self.x11.consume_pointer_event();
+                        effects
+                    }
+                    None => {
+                        self.x11.allow_events();
+                        vec![]
+                    }
+                };
+            }
+            let modifiers = ModMask::from_bits_truncate(ev.state().bits());
+            if ev.detail() == 1
+                && modifiers.contains(MOUSE_MODIFIER)
+                && self
+                    .state
+                    .begin_drag(ev.event(), ev.root_x().into(), ev.root_y().into())
+            {
+                self.x11.start_drag();
+            } else {
+                self.x11.allow_events();
+            }
+            let mut effects = self.state.set_focus(ev.event());
+            effects.extend(self.ewmh_sync_effects());
+            return effects;
+        }
+
+        // The root grab sees scrolls over every window; only ones on the bare
+        // root switch workspaces, the rest are replayed to the window below.
+        match root_scroll_action(ev.detail()) {
+            Some(action) if ev.child() == x::WINDOW_NONE => {
+                self.x11.consume_pointer_event();
+                self.dispatch_action(action)
+            }
+            Some(_) => {
+                self.x11.allow_events();
+                vec![]
+            }
+            None => vec![],
+        }
+    }
+
+    /// Draws the newly focused window's border in the `FOCUS_FLASH` color.
+    fn start_focus_flash(&mut self, window: Window) {
+        let (Some(flash), Some((pixel, _))) = (self.focus_flash.as_mut(), FOCUS_FLASH) else {
+            return;
+        };
+        if let Some(effect) = self.state.focus_border_effect(window, pixel) {
+            flash.start(window, Instant::now());
+            self.x11.apply_effects_unchecked(&[effect]);
+        }
+    }
+
+    /// Returns a flashed border to the focused color once the flash is over.
+    fn settle_focus_flash(&mut self) {
+        let Some(window) = self
+            .focus_flash
+            .as_mut()
+            .and_then(|flash| flash.take_due(Instant::now()))
+        else {
+            return;
+        };
+        let pixel = self.state.screen().focused_border_pixel;
+        if let Some(effect) = self.state.focus_border_effect(window, pixel) {
+            self.x11.apply_effects_unchecked(&[effect]);
+        }
+    }
+
+    /// Records which screen edge a dock sits on from its current geometry.
+    fn detect_dock_edge(&mut self, window: Window) {
+        if let Some(rect) = self.x11.get_window_rect(window) {
+            let edge = DockEdge::of(rect, self.state.screen().height);
+            debug!("Dock {window:?} at {rect:?} is on the {edge:?} edge");
+            self.state.set_dock_edge(window, edge);
+        }
+    }
+
+    /// Seeds the floating rect of a floating `window` that has none from its
+    /// geometry on the server. Framed windows must be seeded before they are
+    /// reparented, while their geometry is still relative to the root.
+    fn seed_floating_geometry(&mut self, window: Window) {
+        if !self.state.needs_floating_geometry(window) {
+            return;
+        }
+        let Some(mut rect) = self.x11.get_window_rect(window) else {
+            return;
+        };
+        // A framed client sits at its frame's origin, so the frame has the position.
+        let toplevel = self.state.toplevel(window);
+        if toplevel != window
+            && let Some(frame) = self.x11.get_window_rect(toplevel)
+        {
+            rect.x = frame.x;
+            rect.y = frame.y;
+        }
+        self.state.seed_floating_geometry(window, rect);
+    }
+
+    /// Re-reads the property behind `update` and stores it in `State`.
+    fn refresh_window_property(&mut self, window: Window, update: PropertyUpdate) {
+        match update {
+            PropertyUpdate::Title => {
+                let title = self.x11.get_window_title(window);
+                self.state.set_window_title(window, title);
+            }
+            PropertyUpdate::Urgency => {
+                let urgent = self.x11.is_urgent(window);
+                self.state.set_window_urgent(window, urgent);
             }
+            PropertyUpdate::Class => {
+                let class = self.x11.get_window_class(window);
+                self.state.set_window_class(window, class);
+                let instance = self.x11.get_window_instance(window);
+                self.state.set_window_instance(window, instance);
+            }
+        }
+    }
+
+    /// Records `time` as the latest user interaction, unless an earlier one is
+    /// already newer. Timestamps wrap, so "newer" is a wrapping comparison.
+    fn note_user_time(&mut self, time: u32) {
+        if self.last_user_time == 0 || time.wrapping_sub(self.last_user_time).cast_signed() > 0 {
+            self.last_user_time = time;
+        }
+    }
+
+    /// Asks the server which windows of the current workspace are not actually
+    /// visible, and maps again the ones `State` believes are mapped. The effects
+    /// of the switch are already sent, so the reply reflects them. A client may
+    /// have withdrawn its window before the reply, with the UnmapNotify only
+    /// queued behind it; such windows are left for that event to handle.
+    fn reconcile_maps(&mut self) {
+        let windows = self.state.current_mapped_windows();
+        let mut unviewable = self.x11.unviewable_windows(&windows);
+        self.queued_events.extend(self.x11.take_queued_events());
+        unviewable.retain(|&window| {
+            !self.queued_events.iter().any(|event| {
+                matches!(event, Ok(xcb::Event::X(x::Event::UnmapNotify(ev))) if ev.window() == window)
+            })
+        });
+        let effects = self.state.reconcile_current_workspace_maps(&unviewable);
+        self.x11.apply_effects_unchecked(&effects);
+    }
+
+    /// Hands the pointer position to `State` when focus follows it on workspace switches.
+    fn refresh_pointer(&mut self) {
+        if FOCUS_ON_SWITCH == FocusOnSwitch::UnderPointer
+            && let Some((x, y)) = self.x11.query_pointer()
+        {
+            self.state.set_pointer(x, y);
         }
     }
 
@@ -233,141 +671,1317 @@ impl WindowManager {
             _ => return vec![],
         };
 
-        if msg_type == atoms.current_desktop {
-            let mut effects = self.state.go_to_workspace(data32[0] as usize);
-            effects.extend(self.ewmh_sync_effects());
-            return effects;
-        }
-
-        if msg_type == atoms.active_window {
-            let target = ev.window();
-            let desktop_hint = self
-                .ewmh
-                .get_window_desktop(&self.x11, target)
-                .map(|d| d as usize);
-            let mut effects = self.state.focus_window(target, desktop_hint);
-            effects.extend(self.ewmh_sync_effects());
-            return effects;
-        }
+        if msg_type == atoms.current_desktop {
+            let workspace_id = data32[0] as usize;
+            if workspace_id >= NUM_WORKSPACES {
+                warn!(
+                    "Ignoring _NET_CURRENT_DESKTOP request for out of range desktop {workspace_id}"
+                );
+                return vec![];
+            }
+            self.refresh_pointer();
+            let mut effects = self.state.go_to_workspace(workspace_id);
+            effects.extend(self.ewmh_sync_effects());
+            return effects;
+        }
+
+        if msg_type == atoms.active_window {
+            let target = ev.window();
+            if self.state.window_workspace(target).is_none() {
+                debug!("Ignoring _NET_ACTIVE_WINDOW request for unmanaged window {target:?}");
+                return vec![];
+            }
+            let mut effects = self.state.focus_window_by_id(target);
+            effects.extend(self.ewmh_sync_effects());
+            return effects;
+        }
+
+        if msg_type == atoms.pull_window {
+            let target = ev.window();
+            if self.state.window_workspace(target).is_none() {
+                debug!("Ignoring _FERRISWM_PULL_WINDOW request for unmanaged window {target:?}");
+                return vec![];
+            }
+            let mut effects = self.state.pull_window(target);
+            effects.extend(self.ewmh_sync_effects());
+            return effects;
+        }
+
+        if msg_type == atoms.wm_desktop {
+            let target = ev.window();
+            let workspace_id = data32[0] as usize;
+            if workspace_id >= NUM_WORKSPACES {
+                warn!("Ignoring _NET_WM_DESKTOP request for out of range desktop {workspace_id}");
+                return vec![];
+            }
+            let mut effects = self.state.move_window_to_workspace(target, workspace_id);
+            effects.extend(self.ewmh_sync_effects());
+            return effects;
+        }
+
+        if msg_type == atoms.close_window {
+            let [time, source, ..] = data32;
+            if !accepts_source_indication(source, EWMH_STRICT_SOURCE_INDICATION) {
+                debug!("Ignoring _NET_CLOSE_WINDOW with source indication {source}");
+                return vec![];
+            }
+            return self.close_window(ev.window(), time);
+        }
+
+        if msg_type == atoms.moveresize_window {
+            let request = decode_moveresize(data32);
+            if !accepts_source_indication(request.source, EWMH_STRICT_SOURCE_INDICATION) {
+                debug!(
+                    "Ignoring _NET_MOVERESIZE_WINDOW with source indication {}",
+                    request.source
+                );
+                return vec![];
+            }
+            // Windows floated before their geometry was known start from the real one.
+            self.seed_floating_geometry(ev.window());
+            return self
+                .state
+                .move_resize_floating(ev.window(), |rect, border| request.apply(rect, border));
+        }
+
+        vec![]
+    }
+
+    fn grab_windows(&mut self) -> Effects {
+        let mut effects = Vec::new();
+
+        match self.x11.get_root_window_children() {
+            Ok(children) => {
+                debug!("Startup scan: {} root children", children.len());
+                let types = self.x11.classify_windows(&children);
+                for (window, wt) in children.into_iter().zip(types) {
+                    let floating = wt == WindowType::Floating;
+                    match wt {
+                        WindowType::Dock => {
+                            self.detect_dock_edge(window);
+                            self.state.track_startup_dock(window);
+                        }
+                        WindowType::Managed | WindowType::Floating => {
+                            match self.ewmh.get_window_desktop(self.x11.as_ref(), window) {
+                                Some(ALL_DESKTOPS) => {
+                                    self.state.track_startup_sticky(window, floating);
+                                }
+                                Some(workspace_id) if (workspace_id as usize) < NUM_WORKSPACES => {
+                                    if floating {
+                                        self.state
+                                            .track_startup_floating(window, workspace_id as usize);
+                                    } else {
+                                        self.state
+                                            .track_startup_managed(window, workspace_id as usize);
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                        WindowType::Unmanaged => {
+                            continue;
+                        }
+                    }
+                }
+            }
+            Err(e) => error!("Failed to grab children of root at startup: {e:?}"),
+        }
+
+        for window in self.state.managed_windows_sorted() {
+            self.refresh_window_property(window, PropertyUpdate::Title);
+            self.refresh_window_property(window, PropertyUpdate::Urgency);
+            self.refresh_window_property(window, PropertyUpdate::Class);
+        }
+
+        let current_desktop = self.load_session().or_else(|| {
+            self.ewmh
+                .get_current_desktop(self.x11.as_ref())
+                .map(|d| d as usize)
+        });
+        for window in self.state.managed_windows_sorted() {
+            self.seed_floating_geometry(window);
+        }
+        effects.extend(self.state.startup_finalize(current_desktop));
+        effects.extend(self.ewmh_sync_effects());
+        effects
+    }
+
+    /// Restores weights, floating flags, layouts and workspace assignments from
+    /// the session file, returning the saved current workspace.
+    fn load_session(&mut self) -> Option<usize> {
+        let path = self.session_path.as_ref()?;
+        match std::fs::read_to_string(path) {
+            Ok(data) => {
+                info!("Restoring session from {}", path.display());
+                self.state.deserialize(&data)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                error!("Failed to read session file {}: {e:?}", path.display());
+                None
+            }
+        }
+    }
+
+    fn save_session(&mut self) {
+        let Some(path) = self.session_path.as_ref() else {
+            return;
+        };
+        let session = self.state.serialize();
+        if session == self.last_session {
+            return;
+        }
+        match std::fs::write(path, &session) {
+            Ok(()) => self.last_session = session,
+            Err(e) => error!("Failed to write session file {}: {e:?}", path.display()),
+        }
+    }
+
+    /// Removes the session file once the server it describes is gone, since
+    /// its windows can't come back.
+    fn discard_session(&self) {
+        if let Some(path) = self.session_path.as_ref()
+            && let Err(e) = std::fs::remove_file(path)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            error!("Failed to remove session file {}: {e:?}", path.display());
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), RunError> {
+        install_termination_handler();
+        Self::spawn_autostart();
+        let startup_effects = self.grab_windows();
+        self.x11.apply_effects_unchecked(&startup_effects);
+        self.save_session();
+
+        loop {
+            if TERMINATE.load(Ordering::Relaxed) || self.quit_requested {
+                info!("Termination requested, shutting down.");
+                return Ok(());
+            }
+
+            let timeout = self
+                .focus_flash
+                .as_ref()
+                .and_then(|flash| flash.time_left(Instant::now()));
+            let event = match self.queued_events.pop_front() {
+                Some(event) => event.map(Some),
+                None => self.x11.wait_for_event(timeout),
+            };
+            let event = match event {
+                Ok(Some(ev)) => ev,
+                Ok(None) => {
+                    self.settle_focus_flash();
+                    continue;
+                }
+                Err(e) => {
+                    let e = match classify_event_error(e) {
+                        Ok(e) => e,
+                        Err(e) => {
+                            self.discard_session();
+                            return Err(e);
+                        }
+                    };
+                    if is_benign_error(&e) {
+                        debug!("Ignoring benign X11 protocol error: {e:?}");
+                    } else {
+                        error!("X11 protocol error: {e:?}");
+                    }
+                    continue;
+                }
+            };
+
+            self.handle_event(event);
+            self.settle_focus_flash();
+            self.save_session();
+        }
+    }
+
+    /// Handles one event from the server and applies the resulting effects.
+    fn handle_event(&mut self, event: xcb::Event) {
+        let was_placing = self.state.is_placing();
+        let was_focused = self.state.focused_window();
+        let was_showing = self.state.current_global_workspace();
+        self.dispatch_event(event);
+        if self.state.current_global_workspace() != was_showing {
+            self.reconcile_maps();
+        }
+        if let Some(window) = self.state.focused_window()
+            && was_focused != Some(window)
+        {
+            self.start_focus_flash(window);
+        }
+        match (was_placing, self.state.is_placing()) {
+            (false, true) if !self.x11.grab_pointer() => {
+                self.state.commit_placement();
+            }
+            (true, false) => self.x11.ungrab_pointer(),
+            _ => {}
+        }
+    }
+
+    fn dispatch_event(&mut self, event: xcb::Event) {
+        match event {
+            xcb::Event::X(x::Event::KeyPress(ev)) => {
+                debug!("Received KeyPress event: {ev:?}");
+                self.note_user_time(ev.time());
+                let effects = self.handle_key_press(&ev);
+                self.x11.apply_effects_unchecked(&effects);
+            }
+            xcb::Event::X(x::Event::MapRequest(ev)) => {
+                debug!("Received MapRequest event for {:?}", ev.window());
+                let (wt, dialog) = self.x11.classify_window_and_dialog(ev.window());
+                debug!("Window type {wt:?} for window {:?}", ev.window());
+                if wt == WindowType::Dock {
+                    self.detect_dock_edge(ev.window());
+                }
+                let transient_for = match wt {
+                    WindowType::Managed | WindowType::Floating => {
+                        self.x11.get_transient_for(ev.window())
+                    }
+                    WindowType::Dock | WindowType::Unmanaged => None,
+                };
+                let mut effects = Vec::new();
+                if REPARENT_WINDOWS
+                    && matches!(wt, WindowType::Managed | WindowType::Floating)
+                    && !self.state.is_framed(ev.window())
+                {
+                    let frame = self.x11.generate_window_id();
+                    effects.extend(self.state.attach_frame(ev.window(), frame));
+                }
+                if matches!(wt, WindowType::Managed | WindowType::Floating)
+                    && let Some(width) = self.x11.border_width_override(ev.window())
+                {
+                    self.state.set_border_override(ev.window(), width);
+                }
+                if TILE_DIALOGS
+                    && wt == WindowType::Managed
+                    && dialog
+                    && let Some(min_size) = self.x11.get_min_size(ev.window())
+                {
+                    self.state.set_min_size(ev.window(), min_size);
+                }
+                let modal = matches!(wt, WindowType::Managed | WindowType::Floating)
+                    && self.x11.is_modal(ev.window());
+                let user_time = self
+                    .x11
+                    .get_cardinal32(ev.window(), self.x11.atoms().wm_user_time);
+                let deny_focus = !allows_focus_on_map(user_time, self.last_user_time);
+                effects.extend(match (modal, transient_for) {
+                    (true, owner) => self.state.on_map_request_modal(ev.window(), owner),
+                    (false, Some(owner)) => self.state.on_map_request_transient(ev.window(), owner),
+                    (false, None) if deny_focus => {
+                        self.state.on_map_request_unfocused(ev.window(), wt)
+                    }
+                    (false, None) => self.state.on_map_request(ev.window(), wt),
+                });
+                if matches!(wt, WindowType::Managed | WindowType::Floating) {
+                    self.refresh_window_property(ev.window(), PropertyUpdate::Title);
+                    self.refresh_window_property(ev.window(), PropertyUpdate::Urgency);
+                    self.refresh_window_property(ev.window(), PropertyUpdate::Class);
+                    if deny_focus {
+                        self.state.deny_focus_on_map(ev.window());
+                    }
+                }
+                if INTERACTIVE_PLACEMENT
+                    && !self.state.is_placing()
+                    && self.state.is_window_floating(ev.window())
+                    && let Some(rect) = self.x11.get_window_rect(ev.window())
+                    && let Some(pointer) = self.x11.query_pointer()
+                {
+                    effects.extend(self.state.begin_placement(
+                        ev.window(),
+                        (rect.w, rect.h),
+                        pointer,
+                    ));
+                }
+                // The frame effects are not applied yet, so this still reads the
+                // client's geometry on the root.
+                self.seed_floating_geometry(ev.window());
+                effects.extend(self.ewmh_sync_effects());
+                self.x11.apply_effects_unchecked(&effects);
+            }
+            xcb::Event::X(x::Event::DestroyNotify(ev)) => {
+                debug!("Received DestroyNotify event for  {:?}", ev.window());
+                let mut effects = self.state.on_destroy(ev.window());
+                effects.extend(self.ewmh_sync_effects());
+                self.x11.apply_effects_unchecked(&effects);
+            }
+            xcb::Event::X(x::Event::UnmapNotify(ev)) => {
+                debug!("Received UnmapNotify event for {:?}", ev.window());
+                let mut effects = self.state.on_unmap(ev.window());
+                effects.extend(self.ewmh_sync_effects());
+                self.x11.apply_effects_unchecked(&effects);
+            }
+            xcb::Event::X(x::Event::ReparentNotify(ev)) => {
+                debug!(
+                    "Received ReparentNotify event for {:?} (new parent {:?})",
+                    ev.window(),
+                    ev.parent()
+                );
+                let mut effects = self
+                    .state
+                    .on_reparent(ev.window(), ev.parent(), self.x11.root());
+                effects.extend(self.ewmh_sync_effects());
+                self.x11.apply_effects_unchecked(&effects);
+            }
+            xcb::Event::X(x::Event::ClientMessage(ev)) => {
+                debug!("Received ClientMessage event: {ev:?}");
+                let effects = self.handle_client_message(&ev);
+                self.x11.apply_effects_unchecked(&effects);
+            }
+            xcb::Event::X(x::Event::ButtonPress(ev)) => {
+                debug!("Received ButtonPress event for {:?}", ev.event());
+                self.note_user_time(ev.time());
+                let effects = self.handle_button_press(&ev);
+                self.x11.apply_effects_unchecked(&effects);
+            }
+            xcb::Event::X(x::Event::MotionNotify(ev)) => {
+                let (x, y) = (ev.root_x().into(), ev.root_y().into());
+                let effects = if self.state.is_placing() {
+                    self.state.place_at(x, y)
+                } else {
+                    self.state.drag_to(x, y)
+                };
+                self.x11.apply_effects_unchecked(&effects);
+            }
+            xcb::Event::X(x::Event::ButtonRelease(_)) => {
+                self.state.end_drag();
+            }
+            xcb::Event::X(x::Event::EnterNotify(ev)) => {
+                debug!("Received EnterNotify event for {:?}", ev.event());
+                // TODO Enable in config later
+                // let mut effects = self.state.set_focus(ev.event());
+                // effects.extend(self.ewmh_sync_effects());
+                // self.x11.apply_effects_unchecked(&effects);
+            }
+            xcb::Event::X(x::Event::MapNotify(ev)) => {
+                debug!("Window mapped: {:?}", ev.window());
+                // Override-redirect windows never send a MapRequest, so whitelisted
+                // ones are picked up once the server has mapped them.
+                if ev.override_redirect() && self.state.window_workspace(ev.window()).is_none() {
+                    let wt = self.x11.classify_window(ev.window());
+                    if matches!(wt, WindowType::Managed | WindowType::Floating) {
+                        let mut effects = self.state.on_map_request(ev.window(), wt);
+                        effects.extend(self.ewmh_sync_effects());
+                        self.x11.apply_effects_unchecked(&effects);
+                    }
+                }
+            }
+            xcb::Event::X(x::Event::ConfigureNotify(ev)) if ev.window() == self.x11.root() => {
+                let (width, height) = (ev.width().into(), ev.height().into());
+                debug!("Root resized to {width}x{height}");
+                let mut effects = self.state.resize_screen(width, height);
+                effects
+                    .extend(self.ewmh.without_suppressed(vec![
+                        self.ewmh.desktop_geometry_effect(width, height),
+                    ]));
+                effects.extend(self.ewmh_sync_effects());
+                self.x11.apply_effects_unchecked(&effects);
+            }
+            xcb::Event::X(x::Event::PropertyNotify(ev)) => {
+                let atoms = *self.x11.atoms();
+                // Input the client handled itself, which the WM never sees.
+                if ev.atom() == atoms.wm_user_time
+                    && self.state.focused_window() == Some(ev.window())
+                    && let Some(time) = self.x11.get_cardinal32(ev.window(), atoms.wm_user_time)
+                {
+                    self.note_user_time(time);
+                }
+                if self.state.window_workspace(ev.window()).is_some()
+                    && let Some(update) = property_update(ev.atom(), atoms.wm_name, atoms.wm_state)
+                {
+                    debug!("Property {update:?} changed on {:?}", ev.window());
+                    self.refresh_window_property(ev.window(), update);
+                }
+            }
+            xcb::Event::ScreenSaver(screensaver::Event::Notify(ev)) => {
+                debug!("Received ScreenSaverNotify event: {:?}", ev.state());
+                self.handle_screensaver_notify(&ev);
+            }
+            ev => {
+                debug!("Ignoring event: {ev:?}");
+            }
+        }
+    }
+}
+
+impl Drop for WindowManager {
+    /// Best effort: errors are ignored, the connection may already be gone.
+    fn drop(&mut self) {
+        self.x11.apply_effects_unchecked(&self.cleanup_effects());
+    }
+}
+
+/// Why the event loop stopped.
+#[derive(Debug)]
+pub enum RunError {
+    /// The connection to the X server was lost, usually because the server exited.
+    ServerGone(xcb::ConnError),
+}
+
+impl RunError {
+    /// Process exit code, so session scripts can tell a dead X server from a crash.
+    pub const fn exit_code(&self) -> i32 {
+        match self {
+            RunError::ServerGone(_) => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::ServerGone(e) => write!(f, "Lost connection to the X server: {e}"),
+        }
+    }
+}
+
+/// Protocol errors are per-request and the loop carries on; a connection error
+/// means the server is gone.
+fn classify_event_error(error: xcb::Error) -> Result<xcb::ProtocolError, RunError> {
+    match error {
+        xcb::Error::Protocol(e) => Ok(e),
+        xcb::Error::Connection(e) => Err(RunError::ServerGone(e)),
+    }
+}
+
+/// `cmd` split on whitespace into a program and its arguments, or `None` if it
+/// is blank.
+fn parse_command(cmd: &str) -> Option<Command> {
+    let mut parts = cmd.split_whitespace();
+    let mut command = Command::new(parts.next()?);
+    command.args(parts);
+    Some(command)
+}
+
+/// One command per non-blank `AUTOSTART` entry.
+fn autostart_commands(entries: &[&str]) -> Vec<Command> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let command = parse_command(entry);
+            if command.is_none() {
+                warn!("Skipping empty autostart entry");
+            }
+            command
+        })
+        .collect()
+}
+
+/// Fallback when `AUTOSTART` is empty: the user's autostart script.
+fn autostart_script() -> Command {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg("exec ~/.config/ferriswm/autostart.sh")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    command
+}
+
+/// Workspace action for a scroll button pressed on the root window.
+const fn root_scroll_action(button: x::Button) -> Option<ActionEvent> {
+    match button {
+        4 => Some(ActionEvent::GoToNextWorkspace),
+        5 => Some(ActionEvent::GoToPrevWorkspace),
+        _ => None,
+    }
+}
+
+/// Whether a scroll button grabbed for `BORDER_SCROLL_WEIGHT` grows the window.
+const fn border_scroll_grows(button: x::Button) -> Option<bool> {
+    match button {
+        4 => Some(true),
+        5 => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod window_manager_tests {
+    use super::*;
+    use crate::layout::Rect;
+    use std::{cell::RefCell, rc::Rc};
+    use xcb::{Xid, XidNew};
+
+    /// Stand-in for the server: queries get empty answers and every applied
+    /// effect is recorded in `sent`.
+    struct MockConn {
+        root: Window,
+        atoms: Atoms,
+        sent: Rc<RefCell<Vec<Effect>>>,
+        /// Checked key grabs with this `owner_events` fail.
+        rejected_owner_events: Option<bool>,
+        /// Windows classified as floating, with the geometry the server reports.
+        floating_rects: HashMap<Window, Rect>,
+        /// `_NET_WM_USER_TIME` every window reports.
+        user_time: Option<u32>,
+        /// Window its client withdrew: reported unviewable, with the UnmapNotify
+        /// already read off the connection.
+        withdrawn: Option<Window>,
+        queued: RefCell<Vec<xcb::Event>>,
+    }
+
+    fn fake_atoms() -> Atoms {
+        Atoms {
+            number_of_desktops: x::Atom::new(1),
+            current_desktop: x::Atom::new(2),
+            desktop_names: x::Atom::new(3),
+            desktop_viewport: x::Atom::new(4),
+            desktop_geometry: x::Atom::new(5),
+            workarea: x::Atom::new(6),
+            showing_desktop: x::Atom::new(7),
+            active_window: x::Atom::new(8),
+            client_list: x::Atom::new(9),
+            client_list_stacking: x::Atom::new(10),
+            supported: x::Atom::new(11),
+            supporting_wm_check: x::Atom::new(12),
+            wm_name: x::Atom::new(13),
+            wm_pid: x::Atom::new(14),
+            utf8_string: x::Atom::new(15),
+            wm_window_type: x::Atom::new(16),
+            wm_window_type_dock: x::Atom::new(17),
+            wm_window_type_dialog: x::Atom::new(18),
+            wm_window_type_utility: x::Atom::new(19),
+            wm_window_type_splash: x::Atom::new(20),
+            wm_window_type_toolbar: x::Atom::new(21),
+            wm_window_type_menu: x::Atom::new(22),
+            wm_strut_partial: x::Atom::new(23),
+            wm_state: x::Atom::new(24),
+            wm_state_fullscreen: x::Atom::new(25),
+            wm_state_modal: x::Atom::new(26),
+            wm_state_demands_attention: x::Atom::new(27),
+            close_window: x::Atom::new(28),
+            wm_protocols: x::Atom::new(29),
+            wm_delete_window: x::Atom::new(30),
+            wm_desktop: x::Atom::new(31),
+            frame_extents: x::Atom::new(32),
+            pull_window: x::Atom::new(33),
+            wm_user_time: x::Atom::new(34),
+            wm_state_hidden: x::Atom::new(35),
+            moveresize_window: x::Atom::new(36),
+        }
+    }
+
+    impl XConn for MockConn {
+        fn root(&self) -> Window {
+            self.root
+        }
+
+        fn atoms(&self) -> &Atoms {
+            &self.atoms
+        }
+
+        fn wait_for_event(&self, _timeout: Option<Duration>) -> xcb::Result<Option<xcb::Event>> {
+            Err(xcb::Error::Connection(xcb::ConnError::Connection))
+        }
+
+        fn apply_effects_unchecked(&self, effects: &[Effect]) {
+            self.sent.borrow_mut().extend_from_slice(effects);
+        }
+
+        fn apply_effects_checked_result(
+            &self,
+            effects: &[Effect],
+        ) -> Result<(), Vec<(String, xcb::Error)>> {
+            self.apply_effects_unchecked(effects);
+            let failures: Vec<_> = effects
+                .iter()
+                .filter(|effect| {
+                    matches!(effect, Effect::GrabKey { owner_events, .. }
+                        if Some(*owner_events) == self.rejected_owner_events)
+                })
+                .map(|effect| {
+                    (
+                        format!("{effect:?}"),
+                        xcb::Error::Connection(xcb::ConnError::Connection),
+                    )
+                })
+                .collect();
+            if failures.is_empty() {
+                Ok(())
+            } else {
+                Err(failures)
+            }
+        }
+
+        fn idle_time(&self) -> Option<Duration> {
+            None
+        }
+
+        fn generate_window_id(&self) -> Window {
+            Window::new(0x1000)
+        }
+
+        fn query_pointer(&self) -> Option<(i32, i32)> {
+            None
+        }
+
+        fn window_under_pointer(&self) -> Option<Window> {
+            None
+        }
+
+        fn start_drag(&self) {}
+
+        fn allow_events(&self) {}
+
+        fn consume_pointer_event(&self) {}
+
+        fn grab_pointer(&self) -> bool {
+            true
+        }
+
+        fn ungrab_pointer(&self) {}
+
+        fn get_window_rect(&self, window: Window) -> Option<Rect> {
+            self.floating_rects.get(&window).copied()
+        }
+
+        fn unviewable_windows(&self, windows: &[Window]) -> Vec<Window> {
+            windows
+                .iter()
+                .filter(|&&window| self.withdrawn == Some(window))
+                .copied()
+                .collect()
+        }
+
+        fn take_queued_events(&self) -> Vec<xcb::Result<xcb::Event>> {
+            self.queued.take().into_iter().map(Ok).collect()
+        }
+
+        fn get_root_window_children(&self) -> Result<Vec<Window>, xcb::Error> {
+            Ok(vec![])
+        }
+
+        fn classify_window(&self, window: Window) -> WindowType {
+            if self.floating_rects.contains_key(&window) {
+                WindowType::Floating
+            } else {
+                WindowType::Managed
+            }
+        }
+
+        fn classify_window_and_dialog(&self, window: Window) -> (WindowType, bool) {
+            (self.classify_window(window), false)
+        }
+
+        fn classify_windows(&self, windows: &[Window]) -> Vec<WindowType> {
+            windows
+                .iter()
+                .map(|window| self.classify_window(*window))
+                .collect()
+        }
+
+        fn border_width_override(&self, _window: Window) -> Option<u32> {
+            None
+        }
+
+        fn is_modal(&self, _window: Window) -> bool {
+            false
+        }
+
+        fn get_min_size(&self, _window: Window) -> Option<(u32, u32)> {
+            None
+        }
+
+        fn get_window_title(&self, _window: Window) -> Option<String> {
+            None
+        }
+
+        fn get_window_class(&self, _window: Window) -> Option<String> {
+            None
+        }
+
+        fn get_window_instance(&self, _window: Window) -> Option<String> {
+            None
+        }
+
+        fn is_urgent(&self, _window: Window) -> bool {
+            false
+        }
+
+        fn supports_wm_delete(&self, _window: Window) -> Result<bool, xcb::Error> {
+            Ok(true)
+        }
+
+        fn get_transient_for(&self, _window: Window) -> Option<Window> {
+            None
+        }
+
+        fn get_cardinal32(&self, _window: x::Window, prop: x::Atom) -> Option<u32> {
+            self.user_time.filter(|_| prop == self.atoms.wm_user_time)
+        }
+    }
+
+    /// A window manager on an 800x600 screen backed by `MockConn`, along with
+    /// the log of effects it sends.
+    fn make_mock_wm() -> (WindowManager, Rc<RefCell<Vec<Effect>>>) {
+        make_mock_wm_rejecting_grabs(None)
+    }
+
+    fn make_mock_wm_rejecting_grabs(
+        rejected_owner_events: Option<bool>,
+    ) -> (WindowManager, Rc<RefCell<Vec<Effect>>>) {
+        make_mock_wm_from(rejected_owner_events, HashMap::new(), None, None)
+    }
+
+    fn make_mock_wm_from(
+        rejected_owner_events: Option<bool>,
+        floating_rects: HashMap<Window, Rect>,
+        user_time: Option<u32>,
+        withdrawn: Option<Window>,
+    ) -> (WindowManager, Rc<RefCell<Vec<Effect>>>) {
+        let root = Window::new(1000);
+        let atoms = fake_atoms();
+        let sent = Rc::new(RefCell::new(vec![]));
+        let screen = ScreenConfig {
+            width: 800,
+            height: 600,
+            focused_border_pixel: 0,
+            normal_border_pixel: 1,
+        };
+        let wm = WindowManager {
+            x11: Box::new(MockConn {
+                root,
+                atoms,
+                sent: Rc::clone(&sent),
+                rejected_owner_events,
+                floating_rects,
+                user_time,
+                withdrawn,
+                queued: RefCell::new(
+                    withdrawn
+                        .map(|window| {
+                            xcb::Event::X(x::Event::UnmapNotify(x::UnmapNotifyEvent::new(
+                                root, window, false,
+                            )))
+                        })
+                        .into_iter()
+                        .collect(),
+                ),
+            }),
+            ewmh: EwmhManager::new(atoms, root, Window::new(1001), vec![]),
+            key_bindings: HashMap::new(),
+            grabbed_keys: HashSet::new(),
+            state: State::new(screen, 1, 0, 0),
+            idle: None,
+            screensaver_restore: None,
+            focus_flash: None,
+            last_user_time: 0,
+            session_path: None,
+            last_session: String::new(),
+            quit_requested: false,
+            queued_events: VecDeque::new(),
+        };
+        (wm, sent)
+    }
+
+    /// A window manager whose server reports `window` as a floating window at `rect`.
+    fn make_mock_wm_with_floating(
+        window: Window,
+        rect: Rect,
+    ) -> (WindowManager, Rc<RefCell<Vec<Effect>>>) {
+        make_mock_wm_from(None, HashMap::from([(window, rect)]), None, None)
+    }
+
+    fn map_request(window: Window) -> xcb::Event {
+        xcb::Event::X(x::Event::MapRequest(x::MapRequestEvent::new(
+            Window::new(1000),
+            window,
+        )))
+    }
+
+    fn key_press(keycode: u8, modifiers: ModMask) -> xcb::Event {
+        xcb::Event::X(x::Event::KeyPress(x::KeyPressEvent::new(
+            keycode,
+            x::CURRENT_TIME,
+            Window::new(1000),
+            Window::new(1000),
+            x::WINDOW_NONE,
+            0,
+            0,
+            0,
+            0,
+            x::KeyButMask::from_bits_truncate(modifiers.bits()),
+            true,
+        )))
+    }
+
+    #[test]
+    fn test_key_press_sends_bound_action_effects() {
+        let (mut wm, sent) = make_mock_wm();
+        let (win1, win2) = (Window::new(1), Window::new(2));
+        wm.state.track_startup_managed(win1, 0);
+        wm.state.track_startup_managed(win2, 0);
+        let _ = wm.state.set_focus(win1);
+        wm.key_bindings
+            .insert((10, ModMask::N4), ActionEvent::NextWindow);
+
+        wm.handle_event(key_press(10, ModMask::N4));
+
+        let sent = sent.borrow();
+        assert!(sent.contains(&Effect::Focus(win2)));
+        assert!(sent.contains(&Effect::SetWindowProperty {
+            window: Window::new(1000),
+            atom: wm.x11.atoms().active_window,
+            values: vec![win2.resource_id()],
+        }));
+    }
+
+    #[test]
+    fn test_click_commits_interactive_placement() {
+        let (mut wm, sent) = make_mock_wm();
+        let win = Window::new(1);
+        let _ = wm.state.on_map_request(win, WindowType::Floating);
+        let _ = wm.state.begin_placement(win, (200, 100), (400, 300));
+
+        wm.handle_event(xcb::Event::X(x::Event::ButtonPress(
+            x::ButtonPressEvent::new(
+                1,
+                x::CURRENT_TIME,
+                Window::new(1000),
+                Window::new(1000),
+                x::WINDOW_NONE,
+                10,
+                10,
+                10,
+                10,
+                x::KeyButMask::empty(),
+                true,
+            ),
+        )));
+
+        assert!(!wm.state.is_placing());
+        assert!(sent.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_root_configure_notify_resizes_and_retiles() {
+        let (mut wm, sent) = make_mock_wm();
+        let win = Window::new(1);
+        wm.state.track_startup_managed(win, 0);
+        let root = wm.x11.root();
+
+        wm.handle_event(xcb::Event::X(x::Event::ConfigureNotify(
+            x::ConfigureNotifyEvent::new(root, root, x::WINDOW_NONE, 0, 0, 1024, 768, 0, false),
+        )));
+
+        let screen = wm.state.screen();
+        assert_eq!((screen.width, screen.height), (1024, 768));
+        let sent = sent.borrow();
+        assert!(sent.contains(&Effect::Configure {
+            window: win,
+            x: 0,
+            y: 0,
+            w: 1022,
+            h: 766,
+            border: 1,
+        }));
+        assert!(sent.contains(&Effect::SetCardinal32List {
+            window: root,
+            atom: wm.x11.atoms().desktop_geometry,
+            values: vec![1024, 768],
+        }));
+    }
+
+    #[test]
+    fn test_drop_ungrabs_keys_destroys_check_window_and_clears_root_mask() {
+        let (mut wm, sent) = make_mock_wm();
+        wm.grabbed_keys.insert((10, ModMask::N4));
+        wm.grabbed_keys.insert((24, ModMask::N4 | ModMask::SHIFT));
+        let root = wm.x11.root();
+
+        drop(wm);
+
+        let sent = sent.borrow();
+        assert_eq!(sent.len(), 4);
+        assert!(sent.contains(&Effect::DestroyWindow(Window::new(1001))));
+        for (keycode, modifiers) in [(10, ModMask::N4), (24, ModMask::N4 | ModMask::SHIFT)] {
+            assert!(sent.contains(&Effect::UngrabKey {
+                keycode,
+                modifiers,
+                grab_window: root,
+            }));
+        }
+        assert_eq!(
+            sent.last(),
+            Some(&Effect::SetEventMask {
+                window: root,
+                mask: x::EventMask::NO_EVENT,
+            })
+        );
+    }
+
+    #[test]
+    fn test_quit_key_press_stops_the_event_loop() {
+        let (mut wm, _) = make_mock_wm();
+        wm.key_bindings.insert((26, ModMask::N4), ActionEvent::Quit);
+
+        wm.handle_event(key_press(26, ModMask::N4));
+
+        assert!(wm.quit_requested);
+    }
+
+    #[test]
+    fn test_withdraw_racing_a_switch_is_not_mapped_again() {
+        let window = Window::new(1);
+        let (mut wm, sent) = make_mock_wm_from(None, HashMap::new(), None, Some(window));
+        wm.state.track_startup_managed(window, 1);
+        wm.key_bindings
+            .insert((11, ModMask::N4), ActionEvent::GoToWorkspace(1));
+
+        wm.handle_event(key_press(11, ModMask::N4));
+
+        // Mapped once by the switch, not again by the reconcile pass.
+        let maps = sent
+            .borrow()
+            .iter()
+            .filter(|effect| **effect == Effect::Map(window))
+            .count();
+        assert_eq!(maps, 1);
+
+        // The queued UnmapNotify is handled next and takes the window off screen.
+        let event = wm.queued_events.pop_front().unwrap().unwrap();
+        wm.handle_event(event);
+        assert!(wm.state.current_mapped_windows().is_empty());
+    }
+
+    #[test]
+    fn test_drop_restores_screensaver_settings() {
+        let (mut wm, sent) = make_mock_wm();
+        let restore = Effect::SetScreenSaver {
+            timeout: 600,
+            interval: 600,
+            prefer_blanking: x::Blanking::Default,
+            allow_exposures: x::Exposures::Default,
+        };
+        wm.screensaver_restore = Some(restore.clone());
+
+        drop(wm);
+
+        assert_eq!(sent.borrow().last(), Some(&restore));
+    }
+
+    #[test]
+    fn test_unbound_key_press_sends_nothing() {
+        let (mut wm, sent) = make_mock_wm();
+        wm.state.track_startup_managed(Window::new(1), 0);
+
+        wm.handle_event(key_press(10, ModMask::N4));
+
+        assert!(sent.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_active_window_is_published_only_when_focus_changes() {
+        let (mut wm, _) = make_mock_wm();
+        let (win1, win2) = (Window::new(1), Window::new(2));
+        wm.state.track_startup_managed(win1, 0);
+        wm.state.track_startup_managed(win2, 0);
+        let active_window = wm.x11.atoms().active_window;
+        let publishes_active = |effects: &Effects| {
+            effects.iter().any(|effect| {
+                matches!(effect, Effect::SetWindowProperty { atom, .. } if *atom == active_window)
+            })
+        };
+
+        let _ = wm.state.set_focus(win1);
+        assert!(publishes_active(&wm.ewmh_sync_effects()));
+
+        let _ = wm.state.set_focus(win1);
+        assert!(!publishes_active(&wm.ewmh_sync_effects()));
+
+        let _ = wm.state.set_focus(win2);
+        assert!(publishes_active(&wm.ewmh_sync_effects()));
+    }
+
+    fn program_and_args(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|part| part.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_autostart_list_spawns_each_entry() {
+        let commands =
+            autostart_commands(&["picom --daemon", "  ", "nm-applet", "xset r rate 200 30"]);
+
+        let commands: Vec<Vec<String>> = commands.iter().map(program_and_args).collect();
+        assert_eq!(
+            commands,
+            vec![
+                vec!["picom", "--daemon"],
+                vec!["nm-applet"],
+                vec!["xset", "r", "rate", "200", "30"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_autostart_script_is_the_fallback() {
+        assert!(autostart_commands(&[]).is_empty());
+        assert_eq!(
+            program_and_args(&autostart_script()),
+            vec!["sh", "-c", "exec ~/.config/ferriswm/autostart.sh"]
+        );
+    }
+
+    #[test]
+    fn test_drop_hands_framed_clients_back_to_root() {
+        let (mut wm, sent) = make_mock_wm();
+        let (window, frame) = (Window::new(2), Window::new(50));
+        wm.state.track_startup_managed(Window::new(1), 0);
+        wm.state.track_startup_managed(window, 0);
+        let _ = wm.state.attach_frame(window, frame);
+        let root = wm.x11.root();
+
+        drop(wm);
+
+        // Tiled in the right half, so it goes back there rather than to 0,0.
+        let sent = sent.borrow();
+        let reparent = sent.iter().position(|effect| {
+            *effect
+                == Effect::Reparent {
+                    window,
+                    parent: root,
+                    x: 400,
+                    y: 0,
+                }
+        });
+        let destroy = sent
+            .iter()
+            .position(|effect| *effect == Effect::DestroyWindow(frame));
+        assert!(reparent.is_some() && destroy.is_some());
+        assert!(reparent < destroy);
+    }
+
+    #[test]
+    fn test_initial_desktop_names_come_from_the_first_sync_only() {
+        let (mut wm, _) = make_mock_wm();
+        let desktop_names = wm.x11.atoms().desktop_names;
+        let writes_names = |effects: &Effects| {
+            effects.iter().any(|effect| {
+                matches!(effect, Effect::SetUtf8String { atom, .. } if *atom == desktop_names)
+            })
+        };
+
+        assert!(!writes_names(&wm.ewmh.publish_hints()));
+        assert!(writes_names(&wm.ewmh_sync_effects()));
+    }
+
+    #[test]
+    fn test_desktop_names_are_published_only_when_they_change() {
+        let (mut wm, _) = make_mock_wm();
+        let desktop_names = wm.x11.atoms().desktop_names;
+        let published_names = |effects: &Effects| {
+            effects.iter().find_map(|effect| match effect {
+                Effect::SetUtf8String { atom, value, .. } if *atom == desktop_names => {
+                    Some(value.clone())
+                }
+                _ => None,
+            })
+        };
+
+        let names = published_names(&wm.ewmh_sync_effects()).unwrap();
+        assert!(names.starts_with("1\u{0}2\u{0}"));
+        assert_eq!(published_names(&wm.ewmh_sync_effects()), None);
+    }
+
+    #[test]
+    fn test_minimized_window_stays_in_client_list_with_hidden_state() {
+        let (mut wm, _) = make_mock_wm();
+        let (win1, win2) = (Window::new(1), Window::new(2));
+        wm.state.track_startup_managed(win1, 0);
+        wm.state.track_startup_managed(win2, 0);
+        let _ = wm.state.set_focus(win1);
+
+        let _ = wm.state.apply_action(ActionEvent::Minimize);
+        let effects = wm.ewmh_sync_effects();
+        let atoms = *wm.x11.atoms();
+
+        assert!(effects.iter().any(|effect| matches!(
+            effect,
+            Effect::SetWindowProperty { atom, values, .. }
+                if *atom == atoms.client_list && values.contains(&win1.resource_id())
+        )));
+        assert!(effects.contains(&Effect::SetAtomList {
+            window: win1,
+            atom: atoms.wm_state,
+            values: vec![atoms.wm_state_hidden.resource_id()],
+        }));
+        assert!(effects.contains(&Effect::SetAtomList {
+            window: win2,
+            atom: atoms.wm_state,
+            values: vec![],
+        }));
+    }
+
+    #[test]
+    fn test_active_window_message_restores_minimized_window() {
+        let (mut wm, _) = make_mock_wm();
+        let (win1, win2) = (Window::new(1), Window::new(2));
+        wm.state.track_startup_managed(win1, 0);
+        wm.state.track_startup_managed(win2, 0);
+        let _ = wm.state.set_focus(win1);
+        let _ = wm.state.apply_action(ActionEvent::Minimize);
+
+        let ev = x::ClientMessageEvent::new(
+            win1,
+            wm.x11.atoms().active_window,
+            x::ClientMessageData::Data32([2, 0, 0, 0, 0]),
+        );
+        let effects = wm.handle_client_message(&ev);
+
+        assert!(!wm.state.is_window_hidden(win1));
+        assert_eq!(wm.state.focused_window(), Some(win1));
+        assert!(effects.contains(&Effect::Map(win1)));
+        assert_eq!(wm.state.current_geometry().len(), 2);
+    }
+
+    #[test]
+    fn test_active_window_message_on_other_workspace_publishes_its_desktop() {
+        let (mut wm, _) = make_mock_wm();
+        let (win1, win2) = (Window::new(1), Window::new(2));
+        wm.state.track_startup_managed(win1, 0);
+        wm.state.track_startup_managed(win2, 1);
+        let _ = wm.state.set_focus(win1);
+
+        let atoms = *wm.x11.atoms();
+        let ev = x::ClientMessageEvent::new(
+            win2,
+            atoms.active_window,
+            x::ClientMessageData::Data32([2, 0, 0, 0, 0]),
+        );
+        let effects = wm.handle_client_message(&ev);
+
+        assert_eq!(wm.state.focused_window(), Some(win2));
+        assert!(effects.contains(&Effect::SetCardinal32 {
+            window: wm.x11.root(),
+            atom: atoms.current_desktop,
+            value: 1,
+        }));
+    }
+
+    #[test]
+    fn test_suppressed_workarea_is_left_out_of_sync() {
+        let (mut wm, _) = make_mock_wm();
+        let atoms = *wm.x11.atoms();
+        let publishes_workarea = |effects: &Effects| {
+            effects.iter().any(|effect| {
+                matches!(effect, Effect::SetCardinal32List { atom, .. } if *atom == atoms.workarea)
+            })
+        };
+        assert!(publishes_workarea(&wm.ewmh_sync_effects()));
+
+        wm.ewmh = EwmhManager::new(
+            atoms,
+            wm.x11.root(),
+            Window::new(1001),
+            vec![atoms.workarea],
+        );
 
-        if msg_type == atoms.close_window {
-            let target = ev.window();
-            return self.close_window(target);
-        }
+        let effects = wm.ewmh_sync_effects();
+        assert!(!publishes_workarea(&effects));
+        assert!(effects.iter().any(|effect| matches!(
+            effect,
+            Effect::SetCardinal32 { atom, .. } if *atom == atoms.current_desktop
+        )));
+        assert!(!publishes_workarea(&wm.ewmh.publish_hints()));
+    }
 
-        vec![]
+    #[test]
+    fn test_rejected_key_grab_is_retried_with_other_owner_events() {
+        let (mut wm, sent) = make_mock_wm_rejecting_grabs(Some(KEY_GRAB_OWNER_EVENTS));
+        wm.key_bindings.insert((24, ModMask::N4), ActionEvent::Kill);
+
+        let failed = wm.grab_keys();
+
+        assert!(failed.is_empty());
+        let grab = |owner_events| Effect::GrabKey {
+            keycode: 24,
+            modifiers: ModMask::N4,
+            grab_window: Window::new(1000),
+            owner_events,
+        };
+        assert_eq!(
+            *sent.borrow(),
+            vec![grab(KEY_GRAB_OWNER_EVENTS), grab(!KEY_GRAB_OWNER_EVENTS)]
+        );
     }
 
-    fn grab_windows(&mut self) -> Effects {
-        let mut effects = Vec::new();
+    #[test]
+    fn test_accepted_key_grab_is_not_retried() {
+        let (mut wm, sent) = make_mock_wm();
+        wm.key_bindings.insert((24, ModMask::N4), ActionEvent::Kill);
 
-        match self.x11.get_root_window_children() {
-            Ok(children) => {
-                debug!("Startup scan: {} root children", children.len());
-                for window in children {
-                    match self.x11.classify_window(window) {
-                        WindowType::Dock => {
-                            self.state.track_startup_dock(window);
-                        }
-                        WindowType::Managed => {
-                            if let Some(workspace_id) =
-                                self.ewmh.get_window_desktop(&self.x11, window)
-                                && (workspace_id as usize) < NUM_WORKSPACES
-                            {
-                                self.state
-                                    .track_startup_managed(window, workspace_id as usize);
-                            }
-                        }
-                        WindowType::Unmanaged => {
-                            continue;
-                        }
-                    }
-                }
-            }
-            Err(e) => error!("Failed to grab children of root at startup: {e:?}"),
-        }
+        assert!(wm.grab_keys().is_empty());
+        assert_eq!(sent.borrow().len(), 1);
+    }
 
-        let current_desktop = self.ewmh.get_current_desktop(&self.x11).map(|d| d as usize);
-        effects.extend(self.state.startup_finalize(current_desktop));
-        effects.extend(self.ewmh_sync_effects());
-        effects
+    #[test]
+    fn test_regrab_only_applies_changed_bindings() {
+        let (mut wm, sent) = make_mock_wm();
+        wm.key_bindings.insert((24, ModMask::N4), ActionEvent::Kill);
+        wm.key_bindings
+            .insert((36, ModMask::N4), ActionEvent::Spawn("xterm"));
+        assert!(wm.grab_keys().is_empty());
+
+        sent.borrow_mut().clear();
+        assert!(wm.grab_keys().is_empty());
+        assert!(
+            sent.borrow().is_empty(),
+            "held grabs must not be grabbed again"
+        );
+
+        wm.key_bindings.remove(&(24, ModMask::N4));
+        wm.key_bindings.insert((25, ModMask::N4), ActionEvent::Kill);
+        assert!(wm.grab_keys().is_empty());
+
+        let root = Window::new(1000);
+        assert_eq!(
+            *sent.borrow(),
+            vec![
+                Effect::UngrabKey {
+                    keycode: 24,
+                    modifiers: ModMask::N4,
+                    grab_window: root,
+                },
+                Effect::GrabKey {
+                    keycode: 25,
+                    modifiers: ModMask::N4,
+                    grab_window: root,
+                    owner_events: KEY_GRAB_OWNER_EVENTS,
+                },
+            ]
+        );
+        assert_eq!(
+            wm.grabbed_keys,
+            HashSet::from([(25, ModMask::N4), (36, ModMask::N4)])
+        );
     }
 
-    pub fn run(&mut self) -> xcb::Result<()> {
-        Self::spawn_autostart();
-        let startup_effects = self.grab_windows();
-        self.x11.apply_effects_unchecked(&startup_effects);
+    #[test]
+    fn test_kill_key_press_asks_client_to_close() {
+        let (mut wm, sent) = make_mock_wm();
+        let win = Window::new(1);
+        wm.state.track_startup_managed(win, 0);
+        let _ = wm.state.set_focus(win);
+        wm.key_bindings.insert((24, ModMask::N4), ActionEvent::Kill);
 
-        loop {
-            let event = match self.x11.wait_for_event() {
-                Ok(ev) => ev,
-                Err(xcb::Error::Protocol(e)) => {
-                    error!("X11 protocol error: {e:?}");
-                    continue;
-                }
-                Err(e) => return Err(e),
-            };
+        wm.handle_event(key_press(24, ModMask::N4));
 
-            match event {
-                xcb::Event::X(x::Event::KeyPress(ev)) => {
-                    debug!("Received KeyPress event: {ev:?}");
-                    let effects = self.handle_key_press(&ev);
-                    self.x11.apply_effects_unchecked(&effects);
-                }
-                xcb::Event::X(x::Event::MapRequest(ev)) => {
-                    debug!("Received MapRequest event for {:?}", ev.window());
-                    let wt = self.x11.classify_window(ev.window());
-                    debug!("Window type {wt:?} for window {:?}", ev.window());
-                    let mut effects = self.state.on_map_request(ev.window(), wt);
-                    effects.extend(self.ewmh_sync_effects());
-                    self.x11.apply_effects_unchecked(&effects);
-                }
-                xcb::Event::X(x::Event::DestroyNotify(ev)) => {
-                    debug!("Received DestroyNotify event for  {:?}", ev.window());
-                    let mut effects = self.state.on_destroy(ev.window());
-                    effects.extend(self.ewmh_sync_effects());
-                    self.x11.apply_effects_unchecked(&effects);
-                }
-                xcb::Event::X(x::Event::UnmapNotify(ev)) => {
-                    debug!("Received UnmapNotify event for {:?}", ev.window());
-                    let mut effects = self.state.on_unmap(ev.window());
-                    effects.extend(self.ewmh_sync_effects());
-                    self.x11.apply_effects_unchecked(&effects);
-                }
-                xcb::Event::X(x::Event::ClientMessage(ev)) => {
-                    debug!("Received ClientMessage event: {ev:?}");
-                    let effects = self.handle_client_message(&ev);
-                    self.x11.apply_effects_unchecked(&effects);
-                }
-                xcb::Event::X(x::Event::ButtonPress(ev)) => {
-                    debug!("Received ButtonPress event for {:?}", ev.event());
-                    self.x11.allow_events();
-                    let mut effects = self.state.set_focus(ev.event());
-                    effects.extend(self.ewmh_sync_effects());
-                    self.x11.apply_effects_unchecked(&effects);
-                }
-                xcb::Event::X(x::Event::EnterNotify(ev)) => {
-                    debug!("Received EnterNotify event for {:?}", ev.event());
-                    // TODO Enable in config later
-                    // let mut effects = self.state.set_focus(ev.event());
-                    // effects.extend(self.ewmh_sync_effects());
-                    // self.x11.apply_effects_unchecked(&effects);
-                }
-                xcb::Event::X(x::Event::MapNotify(ev)) => {
-                    debug!("Window mapped: {:?}", ev.window());
-                }
-                ev => {
-                    debug!("Ignoring event: {ev:?}");
-                }
-            }
-        }
+        assert_eq!(
+            *sent.borrow(),
+            vec![Effect::SendWmDelete {
+                window: win,
+                time: x::CURRENT_TIME,
+            }]
+        );
     }
-}
-
-#[cfg(test)]
-mod window_manager_tests {
-    use super::*;
-    use xcb::{Xid, XidNew};
 
     fn try_make_wm() -> Option<WindowManager> {
         let (conn, _) = Connection::connect(None).ok()?;
@@ -375,8 +1989,8 @@ mod window_manager_tests {
         let atoms = Atoms::intern_all(&conn).ok()?;
         let wm_check_window = WindowManager::create_wm_check_window(&conn, root);
 
-        let x11 = X11::new(conn, root, atoms);
-        let ewmh = EwmhManager::new(atoms, root, wm_check_window);
+        let x11 = Box::new(X11::new(conn, root, atoms));
+        let ewmh = EwmhManager::new(atoms, root, wm_check_window, vec![]);
         let state = State::new(
             screen,
             DEFAULT_BORDER_WIDTH,
@@ -388,10 +2002,103 @@ mod window_manager_tests {
             x11,
             ewmh,
             key_bindings: HashMap::new(),
+            grabbed_keys: HashSet::new(),
             state,
+            idle: None,
+            screensaver_restore: None,
+            focus_flash: None,
+            last_user_time: 0,
+            session_path: None,
+            last_session: String::new(),
+            quit_requested: false,
+            queued_events: VecDeque::new(),
         })
     }
 
+    #[test]
+    fn test_connection_error_means_server_gone() {
+        let error = classify_event_error(xcb::Error::Connection(xcb::ConnError::Connection))
+            .expect_err("a connection error must stop the loop");
+
+        assert!(matches!(
+            error,
+            RunError::ServerGone(xcb::ConnError::Connection)
+        ));
+        assert_eq!(error.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_border_scroll_buttons_grow_and_shrink() {
+        assert_eq!(border_scroll_grows(4), Some(true));
+        assert_eq!(border_scroll_grows(5), Some(false));
+        assert_eq!(border_scroll_grows(1), None);
+    }
+
+    #[test]
+    fn test_button_press_on_client_without_border_scroll_is_replayed() {
+        let (mut wm, sent) = make_mock_wm();
+        let win = Window::new(1);
+        wm.state.track_startup_managed(win, 0);
+
+        let effects = wm.handle_button_press(&x::ButtonPressEvent::new(
+            4,
+            x::CURRENT_TIME,
+            Window::new(1000),
+            win,
+            x::WINDOW_NONE,
+            10,
+            10,
+            -1,
+            10,
+            x::KeyButMask::empty(),
+            true,
+        ));
+
+        assert!(effects.is_empty());
+        assert!(sent.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_root_scroll_buttons_switch_workspace() {
+        assert!(matches!(
+            root_scroll_action(4),
+            Some(ActionEvent::GoToNextWorkspace)
+        ));
+        assert!(matches!(
+            root_scroll_action(5),
+            Some(ActionEvent::GoToPrevWorkspace)
+        ));
+        assert!(root_scroll_action(1).is_none());
+    }
+
+    #[test]
+    fn test_root_grab_switches_workspace_only_on_the_bare_root() {
+        let (mut wm, _sent) = make_mock_wm();
+        let root = wm.x11.root();
+        let scroll = |child| {
+            x::ButtonPressEvent::new(
+                4,
+                x::CURRENT_TIME,
+                root,
+                root,
+                child,
+                10,
+                10,
+                10,
+                10,
+                x::KeyButMask::empty(),
+                true,
+            )
+        };
+
+        let effects = wm.handle_button_press(&scroll(Window::new(1)));
+        assert!(effects.is_empty());
+        assert_eq!(wm.state.current_workspace_id(), 0);
+
+        let _ = wm.handle_button_press(&scroll(x::WINDOW_NONE));
+        assert_eq!(wm.state.current_workspace_id(), 1);
+    }
+
     #[test]
     fn test_keygrab_effects_match_bindings() {
         let mut wm = match try_make_wm() {
@@ -411,11 +2118,13 @@ mod window_manager_tests {
             keycode: 10,
             modifiers: ModMask::SHIFT,
             grab_window: wm.x11.root(),
+            owner_events: KEY_GRAB_OWNER_EVENTS,
         }));
         assert!(effects.contains(&Effect::GrabKey {
             keycode: 20,
             modifiers: ModMask::CONTROL,
             grab_window: wm.x11.root(),
+            owner_events: KEY_GRAB_OWNER_EVENTS,
         }));
     }
 
@@ -494,6 +2203,26 @@ mod window_manager_tests {
         }));
     }
 
+    #[test]
+    fn test_handle_client_message_current_desktop_out_of_range_is_ignored() {
+        let mut wm = match try_make_wm() {
+            Some(wm) => wm,
+            None => return,
+        };
+
+        let atoms = *wm.x11.atoms();
+        let ev = x::ClientMessageEvent::new(
+            wm.x11.root(),
+            atoms.current_desktop,
+            x::ClientMessageData::Data32([999, 0, 0, 0, 0]),
+        );
+
+        let effects = wm.handle_client_message(&ev);
+
+        assert!(effects.is_empty());
+        assert_eq!(wm.state.current_workspace_id(), 0);
+    }
+
     #[test]
     fn test_handle_client_message_ignores_unhandled_type() {
         let mut wm = match try_make_wm() {
@@ -537,6 +2266,125 @@ mod window_manager_tests {
         assert!(effects.is_empty());
     }
 
+    #[test]
+    fn test_mapped_floating_window_gets_its_geometry_as_floating_rect() {
+        let window = Window::new(7);
+        let rect = Rect {
+            x: 120,
+            y: 80,
+            w: 300,
+            h: 200,
+        };
+        let (mut wm, _) = make_mock_wm_with_floating(window, rect);
+
+        wm.handle_event(map_request(window));
+        let effects = wm.state.apply_action(ActionEvent::MaxVertical);
+
+        assert!(wm.state.is_window_floating(window));
+        assert!(effects.contains(&Effect::Configure {
+            window,
+            x: 120,
+            y: 0,
+            w: 300,
+            h: 598,
+            border: 1,
+        }));
+    }
+
+    fn user_time_changed(window: Window) -> xcb::Event {
+        xcb::Event::X(x::Event::PropertyNotify(x::PropertyNotifyEvent::new(
+            window,
+            fake_atoms().wm_user_time,
+            x::CURRENT_TIME,
+            x::Property::NewValue,
+        )))
+    }
+
+    #[test]
+    fn test_user_time_of_focused_window_counts_as_interaction() {
+        let (mut wm, _) = make_mock_wm_from(None, HashMap::new(), Some(5000), None);
+        wm.handle_event(map_request(Window::new(1)));
+        assert_eq!(wm.state.focused_window(), Some(Window::new(1)));
+
+        wm.handle_event(user_time_changed(Window::new(2)));
+        assert_eq!(wm.last_user_time, 0);
+
+        wm.handle_event(user_time_changed(Window::new(1)));
+        assert_eq!(wm.last_user_time, 5000);
+
+        // A window whose last interaction is older than that maps without focus.
+        wm.note_user_time(6000);
+        wm.handle_event(map_request(Window::new(2)));
+        assert_eq!(wm.state.focused_window(), Some(Window::new(1)));
+        assert!(
+            wm.state
+                .dump()
+                .lines()
+                .any(|line| line.starts_with("  window 2 ") && line.ends_with(" urgent"))
+        );
+    }
+
+    #[test]
+    fn test_center_window_centers_a_natively_floating_window() {
+        let window = Window::new(7);
+        let rect = Rect {
+            x: 10,
+            y: 20,
+            w: 200,
+            h: 100,
+        };
+        let (mut wm, _) = make_mock_wm_with_floating(window, rect);
+
+        wm.handle_event(map_request(window));
+        let effects = wm.state.apply_action(ActionEvent::CenterWindow);
+
+        // Centered on the 800x600 screen, 1px border included.
+        assert_eq!(
+            effects,
+            vec![Effect::ConfigurePositionSize {
+                window,
+                x: 299,
+                y: 249,
+                w: 200,
+                h: 100,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_moveresize_message_sets_only_flagged_fields() {
+        let window = Window::new(1);
+        let rect = Rect {
+            x: 120,
+            y: 80,
+            w: 200,
+            h: 100,
+        };
+        let (mut wm, _) = make_mock_wm_with_floating(window, rect);
+        // Tracked without ever being placed, so only the server knows its geometry.
+        wm.state.track_startup_floating(window, 0);
+
+        // NorthWest gravity with only the x and width flags set.
+        let flags = 1 | (1 << 8) | (1 << 10);
+        let ev = x::ClientMessageEvent::new(
+            window,
+            wm.x11.atoms().moveresize_window,
+            x::ClientMessageData::Data32([flags, 40, 999, 300, 999]),
+        );
+        let effects = wm.handle_client_message(&ev);
+
+        assert_eq!(
+            effects,
+            vec![Effect::ConfigurePositionSize {
+                window,
+                x: 40,
+                y: 80,
+                w: 300,
+                h: 100,
+            }]
+        );
+    }
+
     #[test]
     fn test_handle_client_message_active_window_focuses() {
         let mut wm = match try_make_wm() {
@@ -563,6 +2411,59 @@ mod window_manager_tests {
         assert!(effects.contains(&Effect::Focus(win2)));
     }
 
+    #[test]
+    fn test_handle_client_message_active_window_ignores_untracked() {
+        let mut wm = match try_make_wm() {
+            Some(wm) => wm,
+            None => return,
+        };
+
+        let win = Window::new(1);
+        wm.state.track_startup_managed(win, 0);
+        let _ = wm.state.set_focus(win);
+
+        let atoms = *wm.x11.atoms();
+        let untracked = Window::new(77);
+        let ev = x::ClientMessageEvent::new(
+            untracked,
+            atoms.active_window,
+            x::ClientMessageData::Data32([0, 0, 0, 0, 0]),
+        );
+
+        let effects = wm.handle_client_message(&ev);
+
+        assert!(!effects.iter().any(|e| matches!(e, Effect::Focus(_))));
+        assert_eq!(wm.state.focused_window(), Some(win));
+    }
+
+    #[test]
+    fn test_handle_client_message_wm_desktop_moves_window() {
+        let mut wm = match try_make_wm() {
+            Some(wm) => wm,
+            None => return,
+        };
+
+        let win = Window::new(1);
+        wm.state.track_startup_managed(win, 0);
+
+        let atoms = *wm.x11.atoms();
+        let ev = x::ClientMessageEvent::new(
+            win,
+            atoms.wm_desktop,
+            x::ClientMessageData::Data32([2, 0, 0, 0, 0]),
+        );
+
+        let effects = wm.handle_client_message(&ev);
+
+        assert_eq!(wm.state.window_workspace(win), Some(2));
+        assert!(effects.contains(&Effect::Unmap(win)));
+        assert!(effects.contains(&Effect::SetCardinal32 {
+            window: win,
+            atom: atoms.wm_desktop,
+            value: 2,
+        }));
+    }
+
     #[test]
     fn test_handle_client_message_close_window_kills_client() {
         let mut wm = match try_make_wm() {
@@ -584,6 +2485,43 @@ mod window_manager_tests {
         assert!(effects.contains(&Effect::KillClient(target)));
     }
 
+    #[test]
+    fn test_handle_client_message_close_window_with_timestamp() {
+        let mut wm = match try_make_wm() {
+            Some(wm) => wm,
+            None => return,
+        };
+
+        let target = Window::new(42);
+        let atoms = *wm.x11.atoms();
+        let ev = x::ClientMessageEvent::new(
+            target,
+            atoms.close_window,
+            x::ClientMessageData::Data32([1234, 2, 0, 0, 0]),
+        );
+
+        let effects = wm.handle_client_message(&ev);
+        assert!(effects.contains(&Effect::KillClient(target)));
+    }
+
+    #[test]
+    fn test_handle_client_message_close_window_non_data32_ignored() {
+        let mut wm = match try_make_wm() {
+            Some(wm) => wm,
+            None => return,
+        };
+
+        let atoms = *wm.x11.atoms();
+        let ev = x::ClientMessageEvent::new(
+            Window::new(42),
+            atoms.close_window,
+            x::ClientMessageData::Data8([0; 20]),
+        );
+
+        let effects = wm.handle_client_message(&ev);
+        assert!(effects.is_empty());
+    }
+
     #[test]
     fn test_close_window_fallback_to_kill_on_error() {
         let wm = match try_make_wm() {
@@ -593,10 +2531,51 @@ mod window_manager_tests {
 
         // A non-existent window triggers the Err branch in close_window.
         let fake = Window::new(999);
-        let effects = wm.close_window(fake);
+        let effects = wm.close_window(fake, x::CURRENT_TIME);
         assert_eq!(effects, vec![Effect::KillClient(fake)]);
     }
 
+    #[test]
+    fn test_apply_effects_checked_result_skips_benign_failures() {
+        let wm = match try_make_wm() {
+            Some(wm) => wm,
+            None => return,
+        };
+
+        // Mapping a window that doesn't exist fails with BadWindow, which
+        // `BENIGN_X_ERRORS` lists as an expected race.
+        let missing = Window::new(0xdead);
+        let result = wm.x11.apply_effects_checked_result(&[Effect::Map(missing)]);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_ewmh_sync_effects_publish_frame_extents() {
+        let mut wm = match try_make_wm() {
+            Some(wm) => wm,
+            None => return,
+        };
+
+        wm.state = State::new(
+            wm.state.screen(),
+            3,
+            DEFAULT_WINDOW_GAP,
+            DEFAULT_DOCK_HEIGHT,
+        );
+        let win = Window::new(1);
+        wm.state.track_startup_managed(win, 0);
+
+        let atoms = *wm.x11.atoms();
+        let effects = wm.ewmh_sync_effects();
+
+        assert!(effects.contains(&Effect::SetCardinal32List {
+            window: win,
+            atom: atoms.frame_extents,
+            values: vec![3, 3, 3, 3],
+        }));
+    }
+
     #[test]
     fn test_keygrab_effects_empty_bindings() {
         let wm = match try_make_wm() {
@@ -611,7 +2590,7 @@ mod window_manager_tests {
 
     #[test]
     fn test_ewmh_sync_effects_no_windows() {
-        let wm = match try_make_wm() {
+        let mut wm = match try_make_wm() {
             Some(wm) => wm,
             None => return,
         };
@@ -1,19 +1,27 @@
 use indexmap::IndexMap;
 use xcb::x::Window;
 
+use crate::config::DEFAULT_WEIGHT;
+use crate::layout::{Direction, LayoutType};
+
 #[derive(Debug)]
 pub struct Client {
     window: Window,
     size: u32,
     is_mapped: bool,
+    is_floating: bool,
+    /// The window this one was preselected to open next to, and on which side.
+    split_from: Option<(Window, Direction)>,
 }
 
 impl Client {
     pub fn new(window: Window) -> Self {
         Client {
             window,
-            size: 1,
+            size: DEFAULT_WEIGHT,
             is_mapped: true,
+            is_floating: false,
+            split_from: None,
         }
     }
     pub fn window(&self) -> Window {
@@ -32,6 +40,14 @@ impl Client {
         self.size = self.size.saturating_sub(decrement).max(1);
     }
 
+    pub fn set_window_size(&mut self, size: u32) {
+        self.size = size.max(1);
+    }
+
+    pub fn reset_window_size(&mut self) {
+        self.size = DEFAULT_WEIGHT;
+    }
+
     pub fn is_mapped(&self) -> bool {
         self.is_mapped
     }
@@ -39,19 +55,76 @@ impl Client {
     pub fn set_mapped(&mut self, mapped: bool) {
         self.is_mapped = mapped;
     }
+
+    pub fn is_floating(&self) -> bool {
+        self.is_floating
+    }
+
+    pub fn set_floating(&mut self, floating: bool) {
+        self.is_floating = floating;
+    }
+
+    pub fn split_from(&self) -> Option<(Window, Direction)> {
+        self.split_from
+    }
+
+    pub fn set_split_from(&mut self, split_from: Option<(Window, Direction)>) {
+        self.split_from = split_from;
+    }
 }
 
 #[derive(Default, Debug)]
 pub struct Workspace {
     clients: IndexMap<Window, Client>,
     focus: Option<Window>,
+    /// Window that held focus before the current one, for `SwapLastTwo`.
+    previous_focus: Option<Window>,
     fullscreen: Option<Window>,
+    layout: LayoutType,
+    /// Window gap and border width used here instead of the global ones.
+    gap: Option<u32>,
+    border_width: Option<u32>,
+    /// Number of master windows used here instead of `DEFAULT_NMASTER`.
+    nmaster: Option<usize>,
 }
 
 impl Workspace {
     fn number_of_clients(&self) -> usize {
         self.clients.len()
     }
+
+    pub fn layout(&self) -> LayoutType {
+        self.layout
+    }
+
+    pub fn set_layout(&mut self, layout: LayoutType) {
+        self.layout = layout;
+    }
+
+    pub fn gap(&self) -> Option<u32> {
+        self.gap
+    }
+
+    pub fn set_gap(&mut self, gap: Option<u32>) {
+        self.gap = gap;
+    }
+
+    pub fn border_width(&self) -> Option<u32> {
+        self.border_width
+    }
+
+    pub fn set_border_width(&mut self, border_width: Option<u32>) {
+        self.border_width = border_width;
+    }
+
+    pub fn nmaster(&self) -> Option<usize> {
+        self.nmaster
+    }
+
+    pub fn set_nmaster(&mut self, nmaster: Option<usize>) {
+        self.nmaster = nmaster;
+    }
+
     pub fn get_fullscreen_window(&self) -> Option<Window> {
         self.fullscreen
     }
@@ -72,6 +145,12 @@ impl Workspace {
         self.focus
     }
 
+    /// The previously focused window, if it is still mapped here.
+    pub fn get_previous_focus_window(&self) -> Option<Window> {
+        self.previous_focus
+            .filter(|window| self.is_window_mapped(window) && self.focus != Some(*window))
+    }
+
     pub fn get_focused_client_mut(&mut self) -> Option<&mut Client> {
         self.focus.and_then(|win| self.clients.get_mut(&win))
     }
@@ -91,14 +170,27 @@ impl Workspace {
         self.clients.get(window).is_some_and(|c| c.is_mapped())
     }
 
+    pub fn is_window_floating(&self, window: &Window) -> bool {
+        self.clients.get(window).is_some_and(|c| c.is_floating())
+    }
+
     pub fn set_focus(&mut self, window: Window) -> bool {
         if self.clients.contains_key(&window) && self.is_window_mapped(&window) {
+            if self.focus != Some(window) {
+                self.previous_focus = self.focus;
+            }
             self.focus = Some(window);
             return true;
         }
         false
     }
 
+    /// Leaves the workspace without a focused window, e.g. when the last mapped
+    /// one is minimized.
+    pub fn clear_focus(&mut self) {
+        self.focus = None;
+    }
+
     pub fn push_window(&mut self, window: Window) {
         self.clients.insert(window, Client::new(window));
         if self.focus.is_none() {
@@ -110,6 +202,9 @@ impl Workspace {
     pub fn remove_client(&mut self, window: Window) -> Option<Client> {
         let idx_to_remove = self.index_of_window(&window);
         let client = self.clients.shift_remove(&window);
+        if self.previous_focus == Some(window) {
+            self.previous_focus = None;
+        }
         if let Some(index) = idx_to_remove {
             let new_index = if index < self.number_of_clients() {
                 index
@@ -176,6 +271,34 @@ impl Workspace {
         self.clients.values()
     }
 
+    /// Resets every client's weight to `DEFAULT_WEIGHT`. Returns whether any weight changed.
+    pub fn equalize_weights(&mut self) -> bool {
+        let mut changed = false;
+        for client in self.clients.values_mut() {
+            changed |= client.size() != DEFAULT_WEIGHT;
+            client.reset_window_size();
+        }
+        changed
+    }
+
+    /// Moves up to `amount` weight from `from` to `to`, never taking `from` below 1,
+    /// so their combined weight stays the same. Returns whether any weight moved.
+    pub fn transfer_weight(&mut self, from: &Window, to: &Window, amount: u32) -> bool {
+        let (Some(from_size), Some(to_size)) = (
+            self.clients.get(from).map(Client::size),
+            self.clients.get(to).map(Client::size),
+        ) else {
+            return false;
+        };
+        let moved = amount.min(from_size - 1);
+        if moved == 0 || from == to {
+            return false;
+        }
+        self.clients[from].set_window_size(from_size - moved);
+        self.clients[to].set_window_size(to_size + moved);
+        true
+    }
+
     pub fn index_of_window(&self, window: &Window) -> Option<usize> {
         self.clients.get_index_of(window)
     }
@@ -207,6 +330,24 @@ impl Workspace {
         None
     }
 
+    /// Moves `window` to `anchor`'s position, shifting `anchor` and the windows
+    /// between them one place back.
+    pub fn move_window_before(&mut self, window: &Window, anchor: &Window) {
+        if let Some(from) = self.index_of_window(window)
+            && let Some(to) = self.index_of_window(anchor)
+        {
+            self.clients.move_index(from, to);
+        }
+    }
+
+    /// Moves `window` to `index`, or to the end if `index` is past it.
+    pub fn move_window_to_index(&mut self, window: &Window, index: usize) {
+        if let Some(from) = self.index_of_window(window) {
+            let to = index.min(self.clients.len() - 1);
+            self.clients.move_index(from, to);
+        }
+    }
+
     pub fn swap_windows(&mut self, window_a: &Window, window_b: &Window) {
         if let Some(idx_a) = self.index_of_window(window_a)
             && let Some(idx_b) = self.index_of_window(window_b)
@@ -227,7 +368,7 @@ mod client_tests {
         let window = Window::new(0);
         let mut client = Client::new(window);
 
-        client.decrease_window_size(2);
+        client.decrease_window_size(DEFAULT_WEIGHT + 2);
         assert_eq!(client.size(), 1);
     }
 
@@ -238,6 +379,8 @@ mod client_tests {
             window,
             size: 5,
             is_mapped: true,
+            is_floating: false,
+            split_from: None,
         };
 
         client.decrease_window_size(2);
@@ -250,7 +393,28 @@ mod client_tests {
         let mut client = Client::new(window);
 
         client.increase_window_size(1);
-        assert_eq!(client.size(), 2);
+        assert_eq!(client.size(), DEFAULT_WEIGHT + 1);
+    }
+
+    #[test]
+    fn test_set_weight_clamps_to_one() {
+        let window = Window::new(0);
+        let mut client = Client::new(window);
+
+        client.set_window_size(4);
+        assert_eq!(client.size(), 4);
+        client.set_window_size(0);
+        assert_eq!(client.size(), 1);
+    }
+
+    #[test]
+    fn test_reset_weight() {
+        let window = Window::new(0);
+        let mut client = Client::new(window);
+
+        client.increase_window_size(4);
+        client.reset_window_size();
+        assert_eq!(client.size(), DEFAULT_WEIGHT);
     }
 }
 
@@ -387,6 +551,24 @@ mod workspace_tests {
         assert_eq!(workspace.next_mapped_window(-1), Some(Window::new(2)));
     }
 
+    #[test]
+    fn test_move_window_before_shifts_others_back() {
+        let mut workspace = make_workspace(4);
+
+        workspace.move_window_before(&Window::new(2), &Window::new(0));
+
+        let windows: Vec<Window> = workspace.iter_windows().copied().collect();
+        assert_eq!(
+            windows,
+            vec![
+                Window::new(2),
+                Window::new(0),
+                Window::new(1),
+                Window::new(3)
+            ]
+        );
+    }
+
     #[test]
     fn test_swap_windows_changes_order() {
         let mut workspace = make_workspace(3);
@@ -398,4 +580,28 @@ mod workspace_tests {
         let windows: Vec<Window> = workspace.iter_windows().copied().collect();
         assert_eq!(windows, vec![window_b, Window::new(1), window_a]);
     }
+
+    #[test]
+    fn test_transfer_weight_keeps_total() {
+        let mut workspace = make_workspace(3);
+
+        assert!(workspace.transfer_weight(&Window::new(1), &Window::new(0), 4));
+
+        let weights: Vec<u32> = workspace.iter_clients().map(Client::size).collect();
+        assert_eq!(
+            weights,
+            vec![DEFAULT_WEIGHT + 4, DEFAULT_WEIGHT - 4, DEFAULT_WEIGHT]
+        );
+    }
+
+    #[test]
+    fn test_transfer_weight_leaves_donor_at_least_one() {
+        let mut workspace = make_workspace(2);
+
+        assert!(workspace.transfer_weight(&Window::new(0), &Window::new(1), 100));
+        assert!(!workspace.transfer_weight(&Window::new(0), &Window::new(1), 1));
+
+        let weights: Vec<u32> = workspace.iter_clients().map(Client::size).collect();
+        assert_eq!(weights, vec![1, 2 * DEFAULT_WEIGHT - 1]);
+    }
 }
@@ -1,7 +1,14 @@
-use crate::{atoms::Atoms, effect::Effect};
-use log::error;
+use crate::{
+    atoms::Atoms,
+    config::{BENIGN_X_ERRORS, BORDER_WIDTH_RULES, MANAGE_OVERRIDE_REDIRECT_CLASSES, TILE_DIALOGS},
+    effect::Effect,
+    layout::Rect,
+    wallpaper::Wallpaper,
+};
+use log::{debug, error};
+use std::{os::fd::AsRawFd, time::Duration};
 use xcb::{
-    Connection, ProtocolError, VoidCookieChecked, Xid,
+    Connection, ProtocolError, VoidCookieChecked, Xid, randr, screensaver,
     x::{self, EventMask, Window},
 };
 
@@ -11,6 +18,50 @@ pub struct X11 {
     atoms: Atoms,
 }
 
+/// The requests and queries `WindowManager` sends once it is running. `X11`
+/// talks to the server; tests can substitute a recording implementation.
+pub trait XConn {
+    fn root(&self) -> Window;
+    fn atoms(&self) -> &Atoms;
+    /// Next event from the server, or `None` once `timeout` (if any) runs out.
+    fn wait_for_event(&self, timeout: Option<Duration>) -> xcb::Result<Option<xcb::Event>>;
+    fn apply_effects_unchecked(&self, effects: &[Effect]);
+    fn apply_effects_checked_result(
+        &self,
+        effects: &[Effect],
+    ) -> Result<(), Vec<(String, xcb::Error)>>;
+    fn idle_time(&self) -> Option<Duration>;
+    fn generate_window_id(&self) -> Window;
+    fn query_pointer(&self) -> Option<(i32, i32)>;
+    fn window_under_pointer(&self) -> Option<Window>;
+    fn start_drag(&self);
+    fn grab_pointer(&self) -> bool;
+    fn ungrab_pointer(&self);
+    fn allow_events(&self);
+    fn consume_pointer_event(&self);
+    fn get_root_window_children(&self) -> Result<Vec<Window>, xcb::Error>;
+    fn classify_window(&self, window: Window) -> WindowType;
+    /// Like `classify_window`, along with whether `_NET_WM_WINDOW_TYPE` lists
+    /// dialog, read from the same reply.
+    fn classify_window_and_dialog(&self, window: Window) -> (WindowType, bool);
+    fn classify_windows(&self, windows: &[Window]) -> Vec<WindowType>;
+    fn border_width_override(&self, window: Window) -> Option<u32>;
+    fn is_modal(&self, window: Window) -> bool;
+    fn get_min_size(&self, window: Window) -> Option<(u32, u32)>;
+    fn get_window_title(&self, window: Window) -> Option<String>;
+    fn get_window_class(&self, window: Window) -> Option<String>;
+    fn get_window_instance(&self, window: Window) -> Option<String>;
+    fn is_urgent(&self, window: Window) -> bool;
+    fn supports_wm_delete(&self, window: Window) -> Result<bool, xcb::Error>;
+    fn get_transient_for(&self, window: Window) -> Option<Window>;
+    fn get_window_rect(&self, window: Window) -> Option<Rect>;
+    fn unviewable_windows(&self, windows: &[Window]) -> Vec<Window>;
+    /// Events and errors already read off the connection, e.g. along with a
+    /// reply, without reading any more.
+    fn take_queued_events(&self) -> Vec<xcb::Result<xcb::Event>>;
+    fn get_cardinal32(&self, window: x::Window, prop: x::Atom) -> Option<u32>;
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum WindowType {
     /// Normal client windows the WM should manage (tile/focus/workspace).
@@ -19,6 +70,153 @@ pub enum WindowType {
     Unmanaged,
     /// Dock/panel windows (EWMH _NET_WM_WINDOW_TYPE_DOCK).
     Dock,
+    /// Managed windows that float above the tiling layout (dialogs, utilities, splash screens, etc).
+    Floating,
+}
+
+/// Core X error kinds that can be listed in `BENIGN_X_ERRORS`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum XErrorKind {
+    Window,
+    Drawable,
+    Match,
+}
+
+/// Whether an X error is an expected race (e.g. the window vanished mid-operation)
+/// as configured by `BENIGN_X_ERRORS`, rather than something worth reporting.
+pub fn is_benign_error(err: &ProtocolError) -> bool {
+    let ProtocolError::X(error, request) = err else {
+        return false;
+    };
+
+    let kind = match error {
+        x::Error::Window(_) => XErrorKind::Window,
+        x::Error::Drawable(_) => XErrorKind::Drawable,
+        x::Error::Match(_) => XErrorKind::Match,
+        _ => return false,
+    };
+
+    is_benign_kind(kind, *request)
+}
+
+/// Whether `BENIGN_X_ERRORS` lists an error of `kind` raised by `request`.
+fn is_benign_kind(kind: XErrorKind, request: Option<&str>) -> bool {
+    BENIGN_X_ERRORS
+        .iter()
+        .any(|&(benign_kind, benign_request)| {
+            benign_kind == kind && benign_request.is_none_or(|name| request == Some(name))
+        })
+}
+
+/// Maps a window's `_NET_WM_WINDOW_TYPE` atoms to the type they imply, if any.
+pub fn window_type_from_net_wm_types(
+    window_types: &[x::Atom],
+    dock_type: x::Atom,
+    floating_types: &[x::Atom],
+) -> Option<WindowType> {
+    if window_types.contains(&dock_type) {
+        return Some(WindowType::Dock);
+    }
+
+    if window_types.iter().any(|t| floating_types.contains(t)) {
+        return Some(WindowType::Floating);
+    }
+
+    None
+}
+
+/// What a `PropertyNotify` on a managed window means for `State`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PropertyUpdate {
+    /// `_NET_WM_NAME` or `WM_NAME` changed.
+    Title,
+    /// `WM_HINTS` or `_NET_WM_STATE` changed, either of which can carry urgency.
+    Urgency,
+    /// `WM_CLASS`; only read when a window is first managed.
+    Class,
+}
+
+/// Maps a changed property to the state it feeds, if any.
+pub fn property_update(
+    property: x::Atom,
+    net_wm_name: x::Atom,
+    net_wm_state: x::Atom,
+) -> Option<PropertyUpdate> {
+    if property == net_wm_name || property == x::ATOM_WM_NAME {
+        Some(PropertyUpdate::Title)
+    } else if property == x::ATOM_WM_HINTS || property == net_wm_state {
+        Some(PropertyUpdate::Urgency)
+    } else {
+        None
+    }
+}
+
+/// Whether ICCCM `WM_HINTS` data has the urgency flag set.
+pub fn wm_hints_urgent(hints: &[u32]) -> bool {
+    const URGENCY_HINT: u32 = 1 << 8;
+    hints.first().is_some_and(|flags| flags & URGENCY_HINT != 0)
+}
+
+/// Minimum client size from ICCCM `WM_NORMAL_HINTS` data, if the program set one.
+pub fn normal_hints_min_size(hints: &[u32]) -> Option<(u32, u32)> {
+    const P_MIN_SIZE: u32 = 1 << 4;
+    let (&flags, &min_width, &min_height) = (hints.first()?, hints.get(5)?, hints.get(6)?);
+    (flags & P_MIN_SIZE != 0 && (min_width, min_height) != (0, 0))
+        .then_some((min_width, min_height))
+}
+
+/// Sends one request per item through `send`, and only then collects every reply
+/// through `wait`, so a batch costs a single round-trip instead of one per item.
+pub fn pipelined<T, C, R>(
+    items: &[T],
+    send: impl FnMut(&T) -> C,
+    wait: impl FnMut(C) -> R,
+) -> Vec<R> {
+    let cookies: Vec<C> = items.iter().map(send).collect();
+    cookies.into_iter().map(wait).collect()
+}
+
+/// Type of an override-redirect window: left alone unless one of the
+/// null-separated `WM_CLASS` strings (instance or class) is in `managed_classes`.
+pub fn override_redirect_window_type(
+    wm_class: &[u8],
+    type_hint: Option<WindowType>,
+    managed_classes: &[&str],
+) -> WindowType {
+    let whitelisted =
+        wm_class_names(wm_class).any(|name| managed_classes.iter().any(|c| c.as_bytes() == name));
+
+    if whitelisted {
+        type_hint.unwrap_or(WindowType::Managed)
+    } else {
+        WindowType::Unmanaged
+    }
+}
+
+/// Border width of the first `(class, width)` rule naming the window's WM_CLASS
+/// instance or class.
+pub fn border_width_for_class(wm_class: &[u8], rules: &[(&str, u32)]) -> Option<u32> {
+    rules
+        .iter()
+        .find(|(class, _)| wm_class_names(wm_class).any(|name| class.as_bytes() == name))
+        .map(|&(_, width)| width)
+}
+
+/// Request for a window's WM_CLASS property.
+fn wm_class_request(window: Window) -> x::GetProperty {
+    x::GetProperty {
+        delete: false,
+        window,
+        property: x::ATOM_WM_CLASS,
+        r#type: x::ATOM_STRING,
+        long_offset: 0,
+        long_length: 64,
+    }
+}
+
+/// The NUL-separated instance and class names of a WM_CLASS value.
+fn wm_class_names(wm_class: &[u8]) -> impl Iterator<Item = &[u8]> {
+    wm_class.split(|b| *b == 0).filter(|name| !name.is_empty())
 }
 
 /// Generates `_unchecked` and `_checked` method pairs for X11 requests.
@@ -82,49 +280,6 @@ impl X11 {
         Self { conn, root, atoms }
     }
 
-    pub const fn root(&self) -> Window {
-        self.root
-    }
-
-    pub const fn atoms(&self) -> &Atoms {
-        &self.atoms
-    }
-
-    pub fn wait_for_event(&self) -> xcb::Result<xcb::Event> {
-        self.conn.wait_for_event()
-    }
-
-    pub fn apply_effects_unchecked(&self, effects: &[Effect]) {
-        for effect in effects {
-            self.send_effect_unchecked(effect);
-        }
-
-        if let Err(e) = self.flush() {
-            error!("Failed to flush X connection: {e:?}");
-        }
-    }
-
-    pub fn apply_effects_checked(&self, effects: &[Effect]) {
-        let mut pending_checks: Vec<(VoidCookieChecked, String)> = Vec::new();
-
-        for effect in effects {
-            let effect_dbg = format!("{effect:?}");
-            for cookie in self.send_effect_checked(effect) {
-                pending_checks.push((cookie, effect_dbg.clone()));
-            }
-        }
-
-        if let Err(e) = self.flush() {
-            error!("Failed to flush X connection: {e:?}");
-        }
-
-        for (cookie, effect_dbg) in pending_checks {
-            if let Err(e) = self.check_cookie(cookie) {
-                error!("X error applying {effect_dbg}: {e:?}");
-            }
-        }
-    }
-
     // ── Effect dispatch ─────────────────────────────────────────────────
     // Generates both `send_effect_unchecked` and `send_effect_checked`
     // from a single table of Effect variant → method name mappings.
@@ -135,6 +290,8 @@ impl X11 {
             => unmap_window(*window),
         Effect::Focus(window)
             => focus_window(*window),
+        Effect::FocusRoot
+            => focus_root(),
         Effect::Raise(window)
             => raise_window(*window),
         Effect::Configure { window, x, y, w, h, border }
@@ -155,14 +312,30 @@ impl X11 {
             => set_window_property(*window, *atom, values),
         Effect::KillClient(window)
             => kill_client(*window),
-        Effect::SendWmDelete(window)
-            => send_wm_delete(*window),
-        Effect::GrabKey { keycode, modifiers, grab_window }
-            => grab_key(*keycode, *modifiers, *grab_window),
+        Effect::SendWmDelete { window, time }
+            => send_wm_delete(*window, *time),
+        Effect::GrabKey { keycode, modifiers, grab_window, owner_events }
+            => grab_key(*keycode, *modifiers, *grab_window, *owner_events),
+        Effect::UngrabKey { keycode, modifiers, grab_window }
+            => ungrab_key(*keycode, *modifiers, *grab_window),
         Effect::GrabButton(window)
             => grab_button(*window),
-        Effect::SubscribeEnterNotify(window)
-            => subscribe_enter_notify(*window),
+        Effect::GrabScroll(window)
+            => grab_scroll(*window),
+        Effect::SetEventMask { window, mask }
+            => set_event_mask(*window, *mask),
+        Effect::CreateFrame(frame)
+            => create_frame(*frame),
+        Effect::AddToSaveSet(window)
+            => add_to_save_set(*window),
+        Effect::Reparent { window, parent, x, y }
+            => reparent_window(*window, *parent, *x, *y),
+        Effect::DestroyWindow(window)
+            => destroy_window(*window),
+        Effect::SetScreenSaver { timeout, interval, prefer_blanking, allow_exposures }
+            => set_screen_saver(*timeout, *interval, *prefer_blanking, *allow_exposures),
+        Effect::WarpPointer { x, y }
+            => warp_pointer(*x, *y),
     }
 
     // ── X11 request pairs ───────────────────────────────────────────────
@@ -188,6 +361,16 @@ impl X11 {
         }]
     }
 
+    x11_request! {
+        fn focus_root_unchecked / focus_root_checked(&self)
+        let focus = self.root;
+        => [x::SetInputFocus {
+            revert_to: x::InputFocus::PointerRoot,
+            focus,
+            time: x::CURRENT_TIME,
+        }]
+    }
+
     x11_request! {
         fn raise_window_unchecked / raise_window_checked(&self, window: Window)
         let config_values = [x::ConfigWindow::StackMode(x::StackMode::Above)];
@@ -304,8 +487,8 @@ impl X11 {
     }
 
     x11_request! {
-        fn send_wm_delete_unchecked / send_wm_delete_checked(&self, window: Window)
-        let ev = self.wm_delete_client_message(window);
+        fn send_wm_delete_unchecked / send_wm_delete_checked(&self, window: Window, time: u32)
+        let ev = self.wm_delete_client_message(window, time);
         => [x::SendEvent {
             propagate: false,
             destination: x::SendEventDest::Window(window),
@@ -315,9 +498,9 @@ impl X11 {
     }
 
     x11_request! {
-        fn grab_key_unchecked / grab_key_checked(&self, keycode: u8, modifiers: x::ModMask, grab_window: Window)
+        fn grab_key_unchecked / grab_key_checked(&self, keycode: u8, modifiers: x::ModMask, grab_window: Window, owner_events: bool)
         => [x::GrabKey {
-            owner_events: false,
+            owner_events,
             grab_window,
             modifiers,
             key: keycode,
@@ -326,6 +509,15 @@ impl X11 {
         }]
     }
 
+    x11_request! {
+        fn ungrab_key_unchecked / ungrab_key_checked(&self, keycode: u8, modifiers: x::ModMask, grab_window: Window)
+        => [x::UngrabKey {
+            key: keycode,
+            grab_window,
+            modifiers,
+        }]
+    }
+
     x11_request! {
         fn grab_button_unchecked / grab_button_checked(&self, window: Window)
         => [x::GrabButton {
@@ -342,22 +534,257 @@ impl X11 {
     }
 
     x11_request! {
-        fn subscribe_enter_notify_unchecked / subscribe_enter_notify_checked(&self, window: Window)
+        fn grab_scroll_unchecked / grab_scroll_checked(&self, window: Window)
+        let grab = |button| x::GrabButton {
+            owner_events: false,
+            grab_window: window,
+            event_mask: x::EventMask::BUTTON_PRESS,
+            pointer_mode: x::GrabMode::Sync,
+            keyboard_mode: x::GrabMode::Async,
+            confine_to: x::WINDOW_NONE,
+            cursor: x::CURSOR_NONE,
+            button,
+            modifiers: x::ModMask::ANY,
+        };
+        => [grab(x::ButtonIndex::N4), grab(x::ButtonIndex::N5)]
+    }
+
+    x11_request! {
+        fn set_event_mask_unchecked / set_event_mask_checked(&self, window: Window, mask: EventMask)
         => [x::ChangeWindowAttributes {
             window,
-            value_list: &[x::Cw::EventMask(EventMask::ENTER_WINDOW)],
+            value_list: &[x::Cw::EventMask(mask)],
+        }]
+    }
+
+    x11_request! {
+        fn create_frame_unchecked / create_frame_checked(&self, frame: Window)
+        let parent = self.root;
+        let values = [x::Cw::EventMask(
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+        )];
+        => [x::CreateWindow {
+            depth: x::COPY_FROM_PARENT as u8,
+            wid: frame,
+            parent,
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+            border_width: 0,
+            class: x::WindowClass::InputOutput,
+            visual: x::COPY_FROM_PARENT,
+            value_list: &values,
+        }]
+    }
+
+    x11_request! {
+        fn reparent_window_unchecked / reparent_window_checked(&self, window: Window, parent: Window, x: i32, y: i32)
+        => [x::ReparentWindow {
+            window,
+            parent,
+            x: x as i16,
+            y: y as i16,
+        }]
+    }
+
+    x11_request! {
+        fn add_to_save_set_unchecked / add_to_save_set_checked(&self, window: Window)
+        => [x::ChangeSaveSet { mode: x::SetMode::Insert, window }]
+    }
+
+    x11_request! {
+        fn set_screen_saver_unchecked / set_screen_saver_checked(
+            &self,
+            timeout: i16,
+            interval: i16,
+            prefer_blanking: x::Blanking,
+            allow_exposures: x::Exposures
+        )
+        => [x::SetScreenSaver { timeout, interval, prefer_blanking, allow_exposures }]
+    }
+
+    x11_request! {
+        fn destroy_window_unchecked / destroy_window_checked(&self, window: Window)
+        => [x::DestroyWindow { window }]
+    }
+
+    x11_request! {
+        fn warp_pointer_unchecked / warp_pointer_checked(&self, x: i32, y: i32)
+        let dst_window = self.root;
+        => [x::WarpPointer {
+            src_window: x::WINDOW_NONE,
+            dst_window,
+            src_x: 0,
+            src_y: 0,
+            src_width: 0,
+            src_height: 0,
+            dst_x: x as i16,
+            dst_y: y as i16,
         }]
     }
 
     // ── Helpers (not macro-generated) ───────────────────────────────────
 
-    fn wm_delete_client_message(&self, window: Window) -> x::ClientMessageEvent {
+    /// Rects of the active RandR monitors, or an empty list if RandR is unavailable.
+    pub fn get_monitor_rects(&self) -> Vec<Rect> {
+        if !self
+            .conn
+            .active_extensions()
+            .any(|ext| ext == xcb::Extension::RandR)
+        {
+            return Vec::new();
+        }
+
+        let cookie = self.conn.send_request(&randr::GetMonitors {
+            window: self.root,
+            get_active: true,
+        });
+        match self.conn.wait_for_reply(cookie) {
+            Ok(reply) => reply
+                .monitors()
+                .map(|monitor| Rect {
+                    x: monitor.x() as i32,
+                    y: monitor.y() as i32,
+                    w: monitor.width() as u32,
+                    h: monitor.height() as u32,
+                })
+                .collect(),
+            Err(e) => {
+                error!("Failed to query RandR monitors: {e:?}");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Sets the server screensaver timeout and asks for screensaver notifications on
+    /// the root window. Returns the effect that puts back the server's previous
+    /// screensaver settings, or `None` (leaving them alone) if the SCREENSAVER
+    /// extension is unavailable or they can't be read.
+    pub fn enable_idle_notify(&self, timeout_secs: u32) -> Option<Effect> {
+        if !self
+            .conn
+            .active_extensions()
+            .any(|ext| ext == xcb::Extension::ScreenSaver)
+        {
+            return None;
+        }
+
+        let cookie = self.conn.send_request(&x::GetScreenSaver {});
+        let previous = match self.conn.wait_for_reply(cookie) {
+            Ok(reply) => Effect::SetScreenSaver {
+                timeout: reply.timeout().min(i16::MAX as u16) as i16,
+                interval: reply.interval().min(i16::MAX as u16) as i16,
+                prefer_blanking: reply.prefer_blanking(),
+                allow_exposures: reply.allow_exposures(),
+            },
+            Err(e) => {
+                error!("Failed to query screensaver settings: {e:?}");
+                return None;
+            }
+        };
+
+        self.conn.send_request(&x::SetScreenSaver {
+            timeout: timeout_secs.min(i16::MAX as u32) as i16,
+            interval: 0,
+            prefer_blanking: x::Blanking::Default,
+            allow_exposures: x::Exposures::Default,
+        });
+        self.conn.send_request(&screensaver::SelectInput {
+            drawable: x::Drawable::Window(self.root),
+            event_mask: screensaver::EventFlags::NOTIFY_MASK,
+        });
+        if let Err(e) = self.flush() {
+            error!("Failed to enable screensaver notifications: {e:?}");
+            return None;
+        }
+        Some(previous)
+    }
+
+    /// Uploads `wallpaper` to a pixmap and makes it the root window background.
+    /// Only 24/32-bit TrueColor roots are supported.
+    pub fn set_root_background(&self, wallpaper: &Wallpaper) -> bool {
+        let cookie = self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(self.root),
+        });
+        let depth = match self.conn.wait_for_reply(cookie) {
+            Ok(reply) => reply.depth(),
+            Err(e) => {
+                error!("Failed to query root geometry: {e:?}");
+                return false;
+            }
+        };
+        if depth != 24 && depth != 32 {
+            error!("Unsupported root depth {depth} for wallpaper");
+            return false;
+        }
+
+        let (width, height) = (wallpaper.width(), wallpaper.height());
+        let pixmap: x::Pixmap = self.conn.generate_id();
+        self.conn.send_request(&x::CreatePixmap {
+            depth,
+            pid: pixmap,
+            drawable: x::Drawable::Window(self.root),
+            width,
+            height,
+        });
+        let gc: x::Gcontext = self.conn.generate_id();
+        self.conn.send_request(&x::CreateGc {
+            cid: gc,
+            drawable: x::Drawable::Pixmap(pixmap),
+            value_list: &[],
+        });
+
+        // Split the upload into row bands that fit in a single request.
+        let msb_first = self.conn.get_setup().image_byte_order() == x::ImageOrder::MsbFirst;
+        let data = wallpaper.to_zpixmap(msb_first);
+        let stride = usize::from(width) * 4;
+        let max_request = self.conn.get_maximum_request_length() as usize * 4;
+        let rows_per_request = (max_request.saturating_sub(24) / stride.max(1)).max(1);
+        for (band, chunk) in data.chunks(stride * rows_per_request).enumerate() {
+            self.conn.send_request(&x::PutImage {
+                format: x::ImageFormat::ZPixmap,
+                drawable: x::Drawable::Pixmap(pixmap),
+                gc,
+                width,
+                height: (chunk.len() / stride) as u16,
+                dst_x: 0,
+                dst_y: (band * rows_per_request) as i16,
+                left_pad: 0,
+                depth,
+                data: chunk,
+            });
+        }
+
+        self.conn.send_request(&x::ChangeWindowAttributes {
+            window: self.root,
+            value_list: &[x::Cw::BackPixmap(pixmap)],
+        });
+        self.conn.send_request(&x::ClearArea {
+            exposures: false,
+            window: self.root,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        });
+        // The server keeps its own reference for the background.
+        self.conn.send_request(&x::FreeGc { gc });
+        self.conn.send_request(&x::FreePixmap { pixmap });
+        if let Err(e) = self.flush() {
+            error!("Failed to set wallpaper: {e:?}");
+            return false;
+        }
+        true
+    }
+
+    fn wm_delete_client_message(&self, window: Window, time: u32) -> x::ClientMessageEvent {
         x::ClientMessageEvent::new(
             window,
             self.atoms.wm_protocols,
             x::ClientMessageData::Data32([
                 self.atoms.wm_delete_window.resource_id(),
-                x::CURRENT_TIME,
+                time,
                 0,
                 0,
                 0,
@@ -373,19 +800,12 @@ impl X11 {
         self.conn.check_request(cookie).map_err(Into::into)
     }
 
-    pub fn allow_events(&self) {
-        self.conn.send_request(&x::AllowEvents {
-            mode: x::Allow::ReplayPointer,
-            time: x::CURRENT_TIME,
-        });
-    }
-
     pub fn set_root_event_mask(&self) -> Result<(), ProtocolError> {
-        let values = [x::Cw::EventMask(
-            EventMask::SUBSTRUCTURE_REDIRECT
-                | EventMask::SUBSTRUCTURE_NOTIFY
-                | EventMask::KEY_PRESS,
-        )];
+        let mask = EventMask::SUBSTRUCTURE_REDIRECT
+            | EventMask::SUBSTRUCTURE_NOTIFY
+            | EventMask::STRUCTURE_NOTIFY
+            | EventMask::KEY_PRESS;
+        let values = [x::Cw::EventMask(mask)];
         self.conn
             .send_and_check_request(&x::ChangeWindowAttributes {
                 window: self.root,
@@ -393,53 +813,475 @@ impl X11 {
             })
     }
 
-    pub fn get_root_window_children(&self) -> Result<Vec<Window>, xcb::Error> {
-        let cookie = self.conn.send_request(&x::QueryTree { window: self.root });
-        let reply = self.conn.wait_for_reply(cookie)?;
-        Ok(reply.children().to_vec())
+    /// Grabs the scroll buttons on the root window for
+    /// `ROOT_SCROLL_SWITCHES_WORKSPACE`. Kept apart from the root event mask so
+    /// that another client's grab only costs this feature.
+    pub fn grab_root_scroll(&self) -> xcb::Result<()> {
+        self.grab_scroll_checked(self.root)
+            .into_iter()
+            .try_for_each(|cookie| self.check_cookie(cookie))
     }
 
-    pub fn classify_window(&self, window: Window) -> WindowType {
+    /// Classifies every window and tells whether it is a dialog, with its type,
+    /// attributes and class requests all in flight at once, rather than a few
+    /// round-trips per window.
+    fn classify_pipelined(&self, windows: &[Window]) -> Vec<(WindowType, bool)> {
+        pipelined(
+            windows,
+            |&window| {
+                (
+                    self.conn.send_request(&self.window_types_request(window)),
+                    self.conn.send_request(&x::GetWindowAttributes { window }),
+                    self.conn.send_request(&wm_class_request(window)),
+                )
+            },
+            |(types, attributes, wm_class)| {
+                let types = self
+                    .conn
+                    .wait_for_reply(types)
+                    .map(|reply| reply.value::<x::Atom>().to_vec())
+                    .unwrap_or_default();
+                let override_redirect = self
+                    .conn
+                    .wait_for_reply(attributes)
+                    .map(|reply| reply.override_redirect());
+                let wm_class = self
+                    .conn
+                    .wait_for_reply(wm_class)
+                    .map(|reply| reply.value::<u8>().to_vec())
+                    .unwrap_or_default();
+                let dialog = types.contains(&self.atoms.wm_window_type_dialog);
+                (
+                    self.window_type(&types, override_redirect, &wm_class),
+                    dialog,
+                )
+            },
+        )
+    }
+
+    fn window_type(
+        &self,
+        window_types: &[x::Atom],
+        override_redirect: Result<bool, xcb::Error>,
+        wm_class: &[u8],
+    ) -> WindowType {
+        let mut floating_types = self.atoms.floating_window_types().to_vec();
+        if TILE_DIALOGS {
+            floating_types.retain(|t| *t != self.atoms.wm_window_type_dialog);
+        }
+        let type_hint = window_type_from_net_wm_types(
+            window_types,
+            self.atoms.wm_window_type_dock,
+            &floating_types,
+        );
+
         // Docks are special-cased: even if override-redirect is set, we want to treat them as docks.
-        if self.is_dock_window(window) {
+        if type_hint == Some(WindowType::Dock) {
             return WindowType::Dock;
         }
 
-        match self.is_override_redirect(window) {
-            Ok(true) => WindowType::Unmanaged,
-            Ok(false) => WindowType::Managed,
+        match override_redirect {
+            Ok(true) => {
+                override_redirect_window_type(wm_class, type_hint, MANAGE_OVERRIDE_REDIRECT_CLASSES)
+            }
+            Ok(false) => type_hint.unwrap_or(WindowType::Managed),
             // Preserve existing behavior: on query failure, treat as manageable.
-            Err(_e) => WindowType::Managed,
+            Err(_e) => type_hint.unwrap_or(WindowType::Managed),
         }
     }
 
-    fn is_override_redirect(&self, window: Window) -> Result<bool, xcb::Error> {
-        let cookie = self.conn.send_request(&x::GetWindowAttributes { window });
-        let reply = self.conn.wait_for_reply(cookie)?;
-        Ok(reply.override_redirect())
+    fn get_wm_class(&self, window: Window) -> Vec<u8> {
+        let cookie = self.conn.send_request(&wm_class_request(window));
+
+        match self.conn.wait_for_reply(cookie) {
+            Ok(reply) => reply.value::<u8>().to_vec(),
+            Err(_) => vec![],
+        }
     }
 
-    fn is_dock_window(&self, window: Window) -> bool {
-        let cookie = self.conn.send_request(&x::GetProperty {
+    fn window_types_request(&self, window: Window) -> x::GetProperty {
+        x::GetProperty {
             delete: false,
             window,
             property: self.atoms.wm_window_type,
             r#type: x::ATOM_ATOM,
             long_offset: 0,
             long_length: 32,
+        }
+    }
+
+    /// Atoms for `names` that exist on the server; unknown names are skipped.
+    pub fn existing_atoms(&self, names: &[&str]) -> Vec<x::Atom> {
+        pipelined(
+            names,
+            |name| {
+                self.conn.send_request(&x::InternAtom {
+                    only_if_exists: true,
+                    name: name.as_bytes(),
+                })
+            },
+            |cookie| self.conn.wait_for_reply(cookie).map(|reply| reply.atom()),
+        )
+        .into_iter()
+        .zip(names)
+        .filter_map(|(reply, name)| match reply {
+            Ok(atom) if atom != x::ATOM_NONE => Some(atom),
+            Ok(_) => None,
+            Err(e) => {
+                error!("Failed to intern {name}: {e:?}");
+                None
+            }
+        })
+        .collect()
+    }
+
+    /// Whether a compositing manager owns the `_NET_WM_CM_Sn` selection for `screen`.
+    pub fn compositor_running(&self, screen: i32) -> bool {
+        let name = compositor_selection_name(screen);
+        let cookie = self.conn.send_request(&x::InternAtom {
+            only_if_exists: true,
+            name: name.as_bytes(),
         });
+        let atom = match self.conn.wait_for_reply(cookie) {
+            Ok(reply) if reply.atom() != x::ATOM_NONE => reply.atom(),
+            Ok(_) => return false,
+            Err(e) => {
+                error!("Failed to intern {name}: {e:?}");
+                return false;
+            }
+        };
 
-        if let Ok(reply) = self.conn.wait_for_reply(cookie) {
-            let atoms_vec: &[x::Atom] = reply.value();
-            atoms_vec
-                .iter()
-                .any(|a| a.resource_id() == self.atoms.wm_window_type_dock.resource_id())
+        let cookie = self
+            .conn
+            .send_request(&x::GetSelectionOwner { selection: atom });
+        self.conn
+            .wait_for_reply(cookie)
+            .is_ok_and(|reply| !reply.owner().is_none())
+    }
+}
+
+impl XConn for X11 {
+    fn root(&self) -> Window {
+        self.root
+    }
+
+    fn atoms(&self) -> &Atoms {
+        &self.atoms
+    }
+
+    fn wait_for_event(&self, timeout: Option<Duration>) -> xcb::Result<Option<xcb::Event>> {
+        if let Some(event) = self.conn.poll_for_event()? {
+            return Ok(Some(event));
+        }
+
+        let mut fd = libc::pollfd {
+            fd: self.conn.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // Waiting in poll rather than xcb lets a termination signal interrupt it.
+        let millis = timeout.map_or(-1, |timeout| {
+            i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX)
+        });
+        // SAFETY: `fd` is a single valid pollfd that outlives the call.
+        if unsafe { libc::poll(&raw mut fd, 1, millis) } <= 0 {
+            return Ok(None);
+        }
+        self.conn.poll_for_event()
+    }
+
+    fn apply_effects_unchecked(&self, effects: &[Effect]) {
+        for effect in effects {
+            self.send_effect_unchecked(effect);
+        }
+
+        if let Err(e) = self.flush() {
+            error!("Failed to flush X connection: {e:?}");
+        }
+    }
+
+    /// Applies effects with checked requests and hands every failed effect
+    /// (and a failed flush) back to the caller instead of logging it.
+    fn apply_effects_checked_result(
+        &self,
+        effects: &[Effect],
+    ) -> Result<(), Vec<(String, xcb::Error)>> {
+        let mut pending_checks: Vec<(VoidCookieChecked, String)> = Vec::new();
+
+        for effect in effects {
+            let effect_dbg = format!("{effect:?}");
+            for cookie in self.send_effect_checked(effect) {
+                pending_checks.push((cookie, effect_dbg.clone()));
+            }
+        }
+
+        let mut failures = Vec::new();
+        if let Err(e) = self.flush() {
+            failures.push(("flush".to_string(), e));
+        }
+
+        for (cookie, effect_dbg) in pending_checks {
+            match self.check_cookie(cookie) {
+                Ok(()) => {}
+                Err(xcb::Error::Protocol(e)) if is_benign_error(&e) => {
+                    debug!("Ignoring benign X11 protocol error from {effect_dbg}: {e:?}");
+                }
+                Err(e) => failures.push((effect_dbg, e)),
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
         } else {
-            false
+            Err(failures)
+        }
+    }
+
+    /// Time since the last user input, as reported by the SCREENSAVER extension.
+    fn idle_time(&self) -> Option<Duration> {
+        let cookie = self.conn.send_request(&screensaver::QueryInfo {
+            drawable: x::Drawable::Window(self.root),
+        });
+        match self.conn.wait_for_reply(cookie) {
+            Ok(reply) => Some(Duration::from_millis(reply.ms_since_user_input().into())),
+            Err(e) => {
+                error!("Failed to query screensaver info: {e:?}");
+                None
+            }
+        }
+    }
+
+    fn generate_window_id(&self) -> Window {
+        self.conn.generate_id()
+    }
+
+    /// Pointer position in root coordinates.
+    fn query_pointer(&self) -> Option<(i32, i32)> {
+        let cookie = self
+            .conn
+            .send_request(&x::QueryPointer { window: self.root });
+        match self.conn.wait_for_reply(cookie) {
+            Ok(reply) => Some((reply.root_x().into(), reply.root_y().into())),
+            Err(e) => {
+                error!("Failed to query pointer: {e:?}");
+                None
+            }
+        }
+    }
+
+    /// The direct child of the root under the pointer, if the pointer is not over
+    /// the bare root window.
+    fn window_under_pointer(&self) -> Option<Window> {
+        let cookie = self
+            .conn
+            .send_request(&x::QueryPointer { window: self.root });
+        match self.conn.wait_for_reply(cookie) {
+            Ok(reply) if reply.child() != x::WINDOW_NONE => Some(reply.child()),
+            Ok(_) => None,
+            Err(e) => {
+                error!("Failed to query pointer: {e:?}");
+                None
+            }
+        }
+    }
+
+    /// Turns the frozen click-to-focus grab into a drag: report motion and release
+    /// for the rest of the grab, then let the pointer move again.
+    fn start_drag(&self) {
+        self.conn.send_request(&x::ChangeActivePointerGrab {
+            cursor: x::CURSOR_NONE,
+            time: x::CURRENT_TIME,
+            event_mask: EventMask::BUTTON_PRESS
+                | EventMask::BUTTON_RELEASE
+                | EventMask::BUTTON1_MOTION,
+        });
+        self.conn.send_request(&x::AllowEvents {
+            mode: x::Allow::AsyncPointer,
+            time: x::CURRENT_TIME,
+        });
+    }
+
+    /// Routes all pointer motion and clicks to the root until `ungrab_pointer`.
+    fn grab_pointer(&self) -> bool {
+        let cookie = self.conn.send_request(&x::GrabPointer {
+            owner_events: false,
+            grab_window: self.root,
+            event_mask: EventMask::POINTER_MOTION | EventMask::BUTTON_PRESS,
+            pointer_mode: x::GrabMode::Async,
+            keyboard_mode: x::GrabMode::Async,
+            confine_to: x::WINDOW_NONE,
+            cursor: x::CURSOR_NONE,
+            time: x::CURRENT_TIME,
+        });
+        match self.conn.wait_for_reply(cookie) {
+            Ok(reply) if reply.status() == x::GrabStatus::Success => true,
+            Ok(reply) => {
+                error!("Failed to grab pointer: {:?}", reply.status());
+                false
+            }
+            Err(e) => {
+                error!("Failed to grab pointer: {e:?}");
+                false
+            }
+        }
+    }
+
+    fn ungrab_pointer(&self) {
+        self.conn.send_request(&x::UngrabPointer {
+            time: x::CURRENT_TIME,
+        });
+        if let Err(e) = self.flush() {
+            error!("Failed to flush X connection: {e:?}");
+        }
+    }
+
+    fn allow_events(&self) {
+        self.conn.send_request(&x::AllowEvents {
+            mode: x::Allow::ReplayPointer,
+            time: x::CURRENT_TIME,
+        });
+    }
+
+    /// Releases a pointer frozen by a sync button grab without replaying the
+    /// press to the client.
+    fn consume_pointer_event(&self) {
+        self.conn.send_request(&x::AllowEvents {
+            mode: x::Allow::AsyncPointer,
+            time: x::CURRENT_TIME,
+        });
+    }
+
+    fn get_root_window_children(&self) -> Result<Vec<Window>, xcb::Error> {
+        let cookie = self.conn.send_request(&x::QueryTree { window: self.root });
+        let reply = self.conn.wait_for_reply(cookie)?;
+        Ok(reply.children().to_vec())
+    }
+
+    fn classify_window(&self, window: Window) -> WindowType {
+        self.classify_window_and_dialog(window).0
+    }
+
+    fn classify_window_and_dialog(&self, window: Window) -> (WindowType, bool) {
+        self.classify_pipelined(&[window])[0]
+    }
+
+    fn classify_windows(&self, windows: &[Window]) -> Vec<WindowType> {
+        self.classify_pipelined(windows)
+            .into_iter()
+            .map(|(window_type, _)| window_type)
+            .collect()
+    }
+
+    /// Border width configured for the window's class in `BORDER_WIDTH_RULES`.
+    fn border_width_override(&self, window: Window) -> Option<u32> {
+        if BORDER_WIDTH_RULES.is_empty() {
+            return None;
+        }
+        border_width_for_class(&self.get_wm_class(window), BORDER_WIDTH_RULES)
+    }
+
+    /// Whether the window asks to be modal through `_NET_WM_STATE_MODAL`.
+    fn is_modal(&self, window: Window) -> bool {
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: self.atoms.wm_state,
+            r#type: x::ATOM_ATOM,
+            long_offset: 0,
+            long_length: 32,
+        });
+
+        self.conn.wait_for_reply(cookie).is_ok_and(|reply| {
+            reply
+                .value::<x::Atom>()
+                .contains(&self.atoms.wm_state_modal)
+        })
+    }
+
+    /// Class name, the second string of `WM_CLASS`.
+    fn get_window_class(&self, window: Window) -> Option<String> {
+        wm_class_names(&self.get_wm_class(window))
+            .nth(1)
+            .map(|class| String::from_utf8_lossy(class).into_owned())
+    }
+
+    fn get_window_instance(&self, window: Window) -> Option<String> {
+        wm_class_names(&self.get_wm_class(window))
+            .next()
+            .map(|instance| String::from_utf8_lossy(instance).into_owned())
+    }
+
+    /// Window title from `_NET_WM_NAME`, falling back to `WM_NAME`.
+    fn get_window_title(&self, window: Window) -> Option<String> {
+        [
+            (self.atoms.wm_name, self.atoms.utf8_string),
+            (x::ATOM_WM_NAME, x::ATOM_ANY),
+        ]
+        .into_iter()
+        .find_map(|(property, r#type)| {
+            let cookie = self.conn.send_request(&x::GetProperty {
+                delete: false,
+                window,
+                property,
+                r#type,
+                long_offset: 0,
+                long_length: 256,
+            });
+            let reply = self.conn.wait_for_reply(cookie).ok()?;
+            let value = reply.value::<u8>();
+            (!value.is_empty()).then(|| String::from_utf8_lossy(value).into_owned())
+        })
+    }
+
+    /// Minimum size from the window's `WM_NORMAL_HINTS`.
+    fn get_min_size(&self, window: Window) -> Option<(u32, u32)> {
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: x::ATOM_WM_NORMAL_HINTS,
+            r#type: x::ATOM_WM_SIZE_HINTS,
+            long_offset: 0,
+            long_length: 18,
+        });
+        let reply = self.conn.wait_for_reply(cookie).ok()?;
+        normal_hints_min_size(reply.value::<u32>())
+    }
+
+    /// Whether the window asks for attention through `WM_HINTS` or
+    /// `_NET_WM_STATE_DEMANDS_ATTENTION`.
+    fn is_urgent(&self, window: Window) -> bool {
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: x::ATOM_WM_HINTS,
+            r#type: x::ATOM_WM_HINTS,
+            long_offset: 0,
+            long_length: 9,
+        });
+        if self
+            .conn
+            .wait_for_reply(cookie)
+            .is_ok_and(|reply| wm_hints_urgent(reply.value::<u32>()))
+        {
+            return true;
         }
+
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: self.atoms.wm_state,
+            r#type: x::ATOM_ATOM,
+            long_offset: 0,
+            long_length: 32,
+        });
+        self.conn.wait_for_reply(cookie).is_ok_and(|reply| {
+            reply
+                .value::<x::Atom>()
+                .contains(&self.atoms.wm_state_demands_attention)
+        })
     }
 
-    pub fn supports_wm_delete(&self, window: Window) -> Result<bool, xcb::Error> {
+    fn supports_wm_delete(&self, window: Window) -> Result<bool, xcb::Error> {
         let cookie = self.conn.send_request(&x::GetProperty {
             delete: false,
             window,
@@ -454,7 +1296,71 @@ impl X11 {
         Ok(atoms_list.contains(&self.atoms.wm_delete_window))
     }
 
-    pub fn get_cardinal32(&self, window: x::Window, prop: x::Atom) -> Option<u32> {
+    fn get_transient_for(&self, window: Window) -> Option<Window> {
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: x::ATOM_WM_TRANSIENT_FOR,
+            r#type: x::ATOM_WINDOW,
+            long_offset: 0,
+            long_length: 1,
+        });
+
+        let reply = self.conn.wait_for_reply(cookie).ok()?;
+        reply
+            .value::<Window>()
+            .first()
+            .copied()
+            .filter(|owner| !owner.is_none())
+    }
+
+    /// The windows among `windows` the server doesn't report as viewable, i.e.
+    /// unmapped or inside an unmapped frame. Windows that are gone are skipped.
+    fn unviewable_windows(&self, windows: &[Window]) -> Vec<Window> {
+        pipelined(
+            windows,
+            |&window| self.conn.send_request(&x::GetWindowAttributes { window }),
+            |cookie| self.conn.wait_for_reply(cookie).ok(),
+        )
+        .into_iter()
+        .zip(windows)
+        .filter_map(|(reply, &window)| {
+            reply
+                .is_some_and(|reply| reply.map_state() != x::MapState::Viewable)
+                .then_some(window)
+        })
+        .collect()
+    }
+
+    fn take_queued_events(&self) -> Vec<xcb::Result<xcb::Event>> {
+        std::iter::from_fn(|| {
+            self.conn
+                .poll_for_queued_event()
+                .map_err(xcb::Error::Protocol)
+                .transpose()
+        })
+        .collect()
+    }
+
+    fn get_window_rect(&self, window: Window) -> Option<Rect> {
+        let cookie = self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        });
+        match self.conn.wait_for_reply(cookie) {
+            Ok(reply) => Some(Rect {
+                x: reply.x().into(),
+                y: reply.y().into(),
+                w: reply.width().into(),
+                h: reply.height().into(),
+            }),
+            Err(e) => {
+                error!("Failed to query geometry of {window:?}: {e:?}");
+                None
+            }
+        }
+    }
+
+    fn get_cardinal32(&self, window: x::Window, prop: x::Atom) -> Option<u32> {
         let cookie = self.conn.send_request(&x::GetProperty {
             delete: false,
             window,
@@ -474,3 +1380,197 @@ impl X11 {
         None
     }
 }
+
+/// Name of the selection a compositing manager owns for `screen` (EWMH `_NET_WM_CM_Sn`).
+pub fn compositor_selection_name(screen: i32) -> String {
+    format!("_NET_WM_CM_S{screen}")
+}
+
+#[cfg(test)]
+mod x11_tests {
+    use super::*;
+    use std::cell::RefCell;
+    use xcb::XidNew;
+
+    #[test]
+    fn pipelined_sends_every_request_before_waiting() {
+        let log = RefCell::new(vec![]);
+
+        let replies = pipelined(
+            &[1, 2, 3],
+            |item| {
+                log.borrow_mut().push(format!("send {item}"));
+                *item * 10
+            },
+            |cookie| {
+                log.borrow_mut().push(format!("wait {cookie}"));
+                cookie + 1
+            },
+        );
+
+        assert_eq!(replies, vec![11, 21, 31]);
+        assert_eq!(
+            log.into_inner(),
+            vec![
+                "send 1", "send 2", "send 3", "wait 10", "wait 20", "wait 30"
+            ]
+        );
+    }
+
+    #[test]
+    fn pipelined_empty_batch_does_nothing() {
+        let replies: Vec<u32> =
+            pipelined(&[] as &[u32], |_| panic!("send"), |_: u32| panic!("wait"));
+
+        assert!(replies.is_empty());
+    }
+
+    const DOCK: u32 = 10;
+    const DIALOG: u32 = 11;
+    const UTILITY: u32 = 12;
+    const NORMAL: u32 = 20;
+
+    fn atom(id: u32) -> x::Atom {
+        x::Atom::new(id)
+    }
+
+    fn classify(types: &[u32]) -> Option<WindowType> {
+        let types: Vec<x::Atom> = types.iter().map(|id| atom(*id)).collect();
+        window_type_from_net_wm_types(&types, atom(DOCK), &[atom(DIALOG), atom(UTILITY)])
+    }
+
+    #[test]
+    fn dialog_type_is_floating() {
+        assert_eq!(classify(&[DIALOG]), Some(WindowType::Floating));
+    }
+
+    #[test]
+    fn any_floating_type_in_list_is_floating() {
+        assert_eq!(classify(&[NORMAL, UTILITY]), Some(WindowType::Floating));
+    }
+
+    #[test]
+    fn dock_type_wins_over_floating() {
+        assert_eq!(classify(&[DIALOG, DOCK]), Some(WindowType::Dock));
+    }
+
+    #[test]
+    fn whitelisted_override_redirect_class_is_managed() {
+        let wm_class = b"kitty\0Kitty\0";
+        assert_eq!(
+            override_redirect_window_type(wm_class, None, &["Kitty"]),
+            WindowType::Managed
+        );
+        assert_eq!(
+            override_redirect_window_type(wm_class, Some(WindowType::Floating), &["kitty"]),
+            WindowType::Floating
+        );
+    }
+
+    #[test]
+    fn other_override_redirect_windows_stay_unmanaged() {
+        assert_eq!(
+            override_redirect_window_type(b"rofi\0Rofi\0", None, &["Kitty"]),
+            WindowType::Unmanaged
+        );
+        assert_eq!(
+            override_redirect_window_type(b"", None, &[""]),
+            WindowType::Unmanaged
+        );
+    }
+
+    #[test]
+    fn border_width_rule_matches_instance_or_class() {
+        let rules = [("Alacritty", 1), ("firefox", 4)];
+
+        assert_eq!(
+            border_width_for_class(b"Alacritty\0Alacritty\0", &rules),
+            Some(1)
+        );
+        assert_eq!(
+            border_width_for_class(b"firefox\0Firefox\0", &rules),
+            Some(4)
+        );
+        assert_eq!(border_width_for_class(b"xterm\0XTerm\0", &rules), None);
+    }
+
+    #[test]
+    fn title_properties_update_title() {
+        let (net_wm_name, net_wm_state) = (atom(30), atom(31));
+
+        assert_eq!(
+            property_update(net_wm_name, net_wm_name, net_wm_state),
+            Some(PropertyUpdate::Title)
+        );
+        assert_eq!(
+            property_update(x::ATOM_WM_NAME, net_wm_name, net_wm_state),
+            Some(PropertyUpdate::Title)
+        );
+    }
+
+    #[test]
+    fn hints_and_state_properties_update_urgency() {
+        let (net_wm_name, net_wm_state) = (atom(30), atom(31));
+
+        assert_eq!(
+            property_update(x::ATOM_WM_HINTS, net_wm_name, net_wm_state),
+            Some(PropertyUpdate::Urgency)
+        );
+        assert_eq!(
+            property_update(net_wm_state, net_wm_name, net_wm_state),
+            Some(PropertyUpdate::Urgency)
+        );
+    }
+
+    #[test]
+    fn other_properties_are_ignored() {
+        assert_eq!(property_update(x::ATOM_WM_CLASS, atom(30), atom(31)), None);
+    }
+
+    #[test]
+    fn urgency_hint_flag_is_detected() {
+        assert!(wm_hints_urgent(&[1 << 8, 0, 0]));
+        assert!(!wm_hints_urgent(&[1, 1, 0]));
+        assert!(!wm_hints_urgent(&[]));
+    }
+
+    #[test]
+    fn min_size_needs_its_flag_in_normal_hints() {
+        let mut hints = [0u32; 18];
+        hints[5] = 400;
+        hints[6] = 300;
+        assert_eq!(normal_hints_min_size(&hints), None);
+        hints[0] = 1 << 4;
+        assert_eq!(normal_hints_min_size(&hints), Some((400, 300)));
+        assert_eq!(normal_hints_min_size(&hints[..6]), None);
+        assert_eq!(normal_hints_min_size(&[1 << 4, 0, 0, 0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn normal_or_missing_type_has_no_hint() {
+        assert_eq!(classify(&[NORMAL]), None);
+        assert_eq!(classify(&[]), None);
+    }
+
+    #[test]
+    fn bad_window_is_benign_for_any_request() {
+        assert!(is_benign_kind(XErrorKind::Window, Some("x::MapWindow")));
+        assert!(is_benign_kind(XErrorKind::Window, None));
+    }
+
+    #[test]
+    fn bad_match_is_benign_only_on_configure() {
+        assert!(is_benign_kind(
+            XErrorKind::Match,
+            Some("x::ConfigureWindow")
+        ));
+        assert!(!is_benign_kind(XErrorKind::Match, Some("x::SetInputFocus")));
+        assert!(!is_benign_kind(XErrorKind::Match, None));
+    }
+
+    #[test]
+    fn compositor_selection_name_includes_screen_number() {
+        assert_eq!(compositor_selection_name(0), "_NET_WM_CM_S0");
+        assert_eq!(compositor_selection_name(2), "_NET_WM_CM_S2");
+    }
+}